@@ -2,6 +2,7 @@ use crate::certificate::PoolId;
 use crate::date::Epoch;
 use crate::value::*;
 use imhamt::HamtIter;
+use std::collections::HashSet;
 
 use super::{LastRewards, LedgerError};
 
@@ -17,6 +18,43 @@ pub enum DelegationType {
     Ratio(DelegationRatio),
 }
 
+impl DelegationType {
+    /// Return the delegation without the given pools, or None if the
+    /// delegation doesn't reference any of these pools.
+    ///
+    /// A ratio delegation keeps the parts of the remaining pools, and
+    /// collapses into a full delegation if only one pool is left.
+    pub fn without_pools(&self, pool_ids: &HashSet<PoolId>) -> Option<DelegationType> {
+        match self {
+            DelegationType::NonDelegated => None,
+            DelegationType::Full(id) if pool_ids.contains(id) => Some(DelegationType::NonDelegated),
+            DelegationType::Full(_) => None,
+            DelegationType::Ratio(ratio) => {
+                if !ratio.pools.iter().any(|(id, _)| pool_ids.contains(id)) {
+                    return None;
+                }
+                let pools: Vec<(PoolId, u8)> = ratio
+                    .pools
+                    .iter()
+                    .filter(|(id, _)| !pool_ids.contains(id))
+                    .cloned()
+                    .collect();
+                match pools.len() {
+                    0 => Some(DelegationType::NonDelegated),
+                    1 => Some(DelegationType::Full(pools[0].0.clone())),
+                    _ => {
+                        let parts = pools.iter().map(|(_, p)| *p).sum();
+                        Some(DelegationType::Ratio(DelegationRatio {
+                            parts,
+                            pools: pools.into(),
+                        }))
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// Delegation Ratio type express a number of parts
 /// and a list of pools and their individual parts
 ///
@@ -81,6 +119,9 @@ pub struct AccountState<Extra> {
     pub delegation: DelegationType,
     pub value: Value,
     pub last_rewards: LastRewards,
+    /// Refundable key deposit locked when the account first delegated,
+    /// kept aside from `value` and returned on deregistration
+    pub deposit: Value,
     pub extra: Extra,
 }
 
@@ -92,6 +133,7 @@ impl<Extra> AccountState<Extra> {
             delegation: DelegationType::NonDelegated,
             value: v,
             last_rewards: LastRewards::default(),
+            deposit: Value::zero(),
             extra: e,
         }
     }
@@ -181,6 +223,14 @@ impl<Extra: Clone> AccountState<Extra> {
         st.delegation = delegation;
         st
     }
+
+    /// Lock an additional key deposit on this account
+    pub fn add_deposit(&self, v: Value) -> Result<Self, ValueError> {
+        let new_deposit = (self.deposit + v)?;
+        let mut st = self.clone();
+        st.deposit = new_deposit;
+        Ok(st)
+    }
 }
 
 /// Spending counter associated to an account.
@@ -241,6 +291,7 @@ mod tests {
     };
     use quickcheck::{Arbitrary, Gen, TestResult};
     use quickcheck_macros::quickcheck;
+    use std::collections::HashSet;
     use std::iter;
 
     #[quickcheck]
@@ -376,6 +427,7 @@ mod tests {
                 delegation,
                 value: result_value,
                 last_rewards: LastRewards::default(),
+                deposit: Value::zero(),
                 extra: (),
             }
         }
@@ -539,6 +591,42 @@ mod tests {
         assert!(DelegationRatio::new(parts, pools).is_none());
     }
 
+    #[test]
+    pub fn delegation_without_pools() {
+        let first = StakePoolBuilder::new().build().id();
+        let second = StakePoolBuilder::new().build().id();
+        let third = StakePoolBuilder::new().build().id();
+        let retired = |ids: &[&PoolId]| ids.iter().map(|&id| id.clone()).collect::<HashSet<_>>();
+
+        let full = DelegationType::Full(first.clone());
+        assert_eq!(full.without_pools(&retired(&[&second])), None);
+        assert_eq!(
+            full.without_pools(&retired(&[&first])),
+            Some(DelegationType::NonDelegated)
+        );
+
+        let two = DelegationRatio::new(3, vec![(first.clone(), 1), (second.clone(), 2)]).unwrap();
+        assert_eq!(
+            DelegationType::Ratio(two).without_pools(&retired(&[&first])),
+            Some(DelegationType::Full(second.clone()))
+        );
+
+        let three = DelegationRatio::new(
+            6,
+            vec![(first.clone(), 1), (second.clone(), 2), (third.clone(), 3)],
+        )
+        .unwrap();
+        let expected = DelegationRatio::new(5, vec![(second.clone(), 2), (third.clone(), 3)]).unwrap();
+        assert_eq!(
+            DelegationType::Ratio(three.clone()).without_pools(&retired(&[&first])),
+            Some(DelegationType::Ratio(expected))
+        );
+        assert_eq!(
+            DelegationType::Ratio(three).without_pools(&retired(&[&first, &third])),
+            Some(DelegationType::Full(second))
+        );
+    }
+
     #[quickcheck]
     pub fn add_rewards(account_state_no_reward: AccountState<()>, value: Value) -> TestResult {
         let initial_value = account_state_no_reward.value();
@@ -6,9 +6,11 @@
 
 pub mod account_state;
 pub mod last_rewards;
-use crate::{date::Epoch, value::*};
+use crate::{certificate::PoolId, date::Epoch, value::*};
 use imhamt::{Hamt, InsertError, UpdateError};
+use rayon::iter::ParallelIterator;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use std::fmt::{self, Debug};
 use std::hash::Hash;
 use thiserror::Error;
@@ -51,8 +53,16 @@ impl From<InsertError> for LedgerError {
 }
 
 /// The public ledger of all accounts associated with their current state
+///
+/// Along with the accounts, the ledger keeps a tombstone with the last
+/// spending counter of every deregistered account: an account credited
+/// again after its deregistration resumes from that counter, so that the
+/// transactions it signed before cannot be replayed.
 #[derive(Clone, PartialEq, Eq)]
-pub struct Ledger<ID: Hash + Eq, Extra>(Hamt<DefaultHasher, ID, AccountState<Extra>>);
+pub struct Ledger<ID: Hash + Eq, Extra>(
+    Hamt<DefaultHasher, ID, AccountState<Extra>>,
+    Hamt<DefaultHasher, ID, SpendingCounter>,
+);
 
 impl<ID: Clone + Eq + Hash, Extra: Clone> Default for Ledger<ID, Extra> {
     fn default() -> Self {
@@ -63,7 +73,50 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> Default for Ledger<ID, Extra> {
 impl<ID: Clone + Eq + Hash, Extra: Clone> Ledger<ID, Extra> {
     /// Create a new empty account ledger
     pub fn new() -> Self {
-        Ledger(Hamt::new())
+        Ledger(Hamt::new(), Hamt::new())
+    }
+
+    /// Add the tombstones of deregistered accounts, as recovered along
+    /// with the accounts
+    pub fn with_tombstones<I>(self, tombstones: I) -> Result<Self, LedgerError>
+    where
+        I: IntoIterator<Item = (ID, SpendingCounter)>,
+    {
+        let mut ledger = self;
+        for (identifier, counter) in tombstones {
+            if ledger.exists(&identifier) {
+                return Err(LedgerError::AlreadyExists);
+            }
+            ledger.1 = ledger.1.insert(identifier, counter)?;
+        }
+        Ok(ledger)
+    }
+
+    /// The last spending counter of the deregistered accounts that were not
+    /// created again since
+    pub fn iter_tombstones(&self) -> imhamt::HamtIter<'_, ID, SpendingCounter> {
+        self.1.iter()
+    }
+
+    /// State of an account created in this ledger, resuming from the
+    /// spending counter of the account if it was deregistered before,
+    /// along with the tombstones left
+    fn new_state(
+        &self,
+        identifier: &ID,
+        mut state: AccountState<Extra>,
+    ) -> (
+        AccountState<Extra>,
+        Hamt<DefaultHasher, ID, SpendingCounter>,
+    ) {
+        match self.1.lookup(identifier) {
+            None => (state, self.1.clone()),
+            Some(counter) => {
+                state.counter = *counter;
+                let tombstones = self.1.remove(identifier).unwrap_or_else(|_| self.1.clone());
+                (state, tombstones)
+            }
+        }
     }
 
     /// Add a new account into this ledger.
@@ -75,9 +128,11 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> Ledger<ID, Extra> {
         initial_value: Value,
         extra: Extra,
     ) -> Result<Self, LedgerError> {
+        let (state, tombstones) =
+            self.new_state(identifier, AccountState::new(initial_value, extra));
         self.0
-            .insert(identifier.clone(), AccountState::new(initial_value, extra))
-            .map(Ledger)
+            .insert(identifier.clone(), state)
+            .map(|accounts| Ledger(accounts, tombstones))
             .map_err(|e| e.into())
     }
 
@@ -91,10 +146,33 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> Ledger<ID, Extra> {
             .update(identifier, |st| {
                 Ok(Some(st.set_delegation(delegation.clone())))
             })
-            .map(Ledger)
+            .map(|accounts| Ledger(accounts, self.1.clone()))
             .map_err(|e| e.into())
     }
 
+    /// Remove the given pools from the delegation of every account
+    /// delegating to them.
+    ///
+    /// There is no index from pools to their delegators, so this walks
+    /// every account of the ledger: the pools retiring together should
+    /// be removed in a single call, which walks the accounts only once.
+    pub fn remove_pools_delegation(&self, pool_ids: &HashSet<PoolId>) -> Result<Self, LedgerError> {
+        let affected: Vec<(ID, DelegationType)> = self
+            .0
+            .iter()
+            .filter_map(|(id, st)| {
+                st.delegation
+                    .without_pools(pool_ids)
+                    .map(|delegation| (id.clone(), delegation))
+            })
+            .collect();
+        let mut ledger = self.clone();
+        for (id, delegation) in affected {
+            ledger = ledger.set_delegation(&id, &delegation)?;
+        }
+        Ok(ledger)
+    }
+
     /// check if an account already exist
     #[inline]
     pub fn exists(&self, identifier: &ID) -> bool {
@@ -120,7 +198,40 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> Ledger<ID, Extra> {
                     Err(LedgerError::NonZero)
                 }
             })
-            .map(Ledger)
+            .map(|accounts| Ledger(accounts, self.1.clone()))
+            .map_err(|e| e.into())
+    }
+
+    /// Remove an account from this ledger regardless of its remaining value,
+    /// returning its last state so the caller can sweep the value and deposit.
+    ///
+    /// A tombstone keeps the spending counter of the removed account, which
+    /// resumes from it if it is created again, so that the transactions it
+    /// signed before cannot be replayed.
+    ///
+    /// If the account doesn't exist, error out.
+    pub fn deregister_account(
+        &self,
+        identifier: &ID,
+    ) -> Result<(Self, AccountState<Extra>), LedgerError> {
+        let state = self.get_state(identifier)?.clone();
+        let accounts = self
+            .0
+            .remove(identifier)
+            .map_err(|_| LedgerError::NonExistent)?;
+        let tombstones = self.1.insert(identifier.clone(), state.counter)?;
+        Ok((Ledger(accounts, tombstones), state))
+    }
+
+    /// Lock a key deposit on an existing account.
+    ///
+    /// If the account doesn't exist, error out.
+    pub fn add_deposit(&self, identifier: &ID, deposit: Value) -> Result<Self, LedgerError> {
+        self.0
+            .update(identifier, |st| {
+                st.add_deposit(deposit).map(Some).map_err(|e| e.into())
+            })
+            .map(|accounts| Ledger(accounts, self.1.clone()))
             .map_err(|e| e.into())
     }
 
@@ -130,7 +241,7 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> Ledger<ID, Extra> {
     pub fn add_value(&self, identifier: &ID, value: Value) -> Result<Self, LedgerError> {
         self.0
             .update(identifier, |st| st.add(value).map(Some))
-            .map(Ledger)
+            .map(|accounts| Ledger(accounts, self.1.clone()))
             .map_err(|e| e.into())
     }
 
@@ -143,11 +254,12 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> Ledger<ID, Extra> {
         value: Value,
         extra: Extra,
     ) -> Result<Self, ValueError> {
+        let (state, tombstones) = self.new_state(identifier, AccountState::new(value, extra));
         self.0
-            .insert_or_update(identifier.clone(), AccountState::new(value, extra), |st| {
+            .insert_or_update(identifier.clone(), state, |st| {
                 st.add_value(value).map(Some)
             })
-            .map(Ledger)
+            .map(|accounts| Ledger(accounts, tombstones))
     }
 
     /// Add rewards to an existing account.
@@ -160,13 +272,13 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> Ledger<ID, Extra> {
         value: Value,
         extra: Extra,
     ) -> Result<Self, ValueError> {
+        let (state, tombstones) =
+            self.new_state(identifier, AccountState::new_reward(epoch, value, extra));
         self.0
-            .insert_or_update(
-                identifier.clone(),
-                AccountState::new_reward(epoch, value, extra),
-                |st| st.add_rewards(epoch, value).map(Some),
-            )
-            .map(Ledger)
+            .insert_or_update(identifier.clone(), state, |st| {
+                st.add_rewards(epoch, value).map(Some)
+            })
+            .map(|accounts| Ledger(accounts, tombstones))
     }
 
     /// Subtract value to an existing account.
@@ -184,7 +296,7 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> Ledger<ID, Extra> {
             .map_or(Err(LedgerError::NonExistent), |st| Ok(st.counter))?;
         self.0
             .update(identifier, |st| st.sub(value))
-            .map(|ledger| (Ledger(ledger), counter))
+            .map(|accounts| (Ledger(accounts, self.1.clone()), counter))
             .map_err(|e| e.into())
     }
 
@@ -196,9 +308,27 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> Ledger<ID, Extra> {
         Value::sum(values)
     }
 
+    /// Get the sum of all the key deposits locked in accounts
+    pub fn get_total_deposits(&self) -> Result<Value, ValueError> {
+        Value::sum(
+            self.0
+                .iter()
+                .map(|(_, account_state)| account_state.deposit),
+        )
+    }
+
     pub fn iter(&self) -> Iter<'_, ID, Extra> {
         Iter(self.0.iter())
     }
+
+    /// Parallel iterator over the accounts, in no particular order
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (&ID, &AccountState<Extra>)>
+    where
+        ID: Send + Sync,
+        Extra: Send + Sync,
+    {
+        self.0.par_iter()
+    }
 }
 
 impl<ID: Clone + Eq + Hash + Debug, Extra: Clone + Debug> Debug for Ledger<ID, Extra> {
@@ -218,7 +348,7 @@ impl<ID: Clone + Eq + Hash, Extra: Clone> std::iter::FromIterator<(ID, AccountSt
     for Ledger<ID, Extra>
 {
     fn from_iter<I: IntoIterator<Item = (ID, AccountState<Extra>)>>(iter: I) -> Self {
-        Ledger(Hamt::from_iter(iter))
+        Ledger(Hamt::from_iter_bulk(iter), Hamt::new())
     }
 }
 
@@ -400,6 +530,7 @@ mod tests {
                     },
                     delegation: DelegationType::Full(stake_pool_id),
                     value: value_after_reward,
+                    deposit: Value::zero(),
                     extra: (),
                 };
 
@@ -565,6 +696,55 @@ mod tests {
         }
     }
 
+    #[test]
+    pub fn deregistered_account_resumes_from_its_counter() {
+        let (alice, bob) = (TestGen::identifier(), TestGen::identifier());
+        let mut ledger = Ledger::new()
+            .add_account(&alice, Value(100), ())
+            .unwrap()
+            .add_account(&bob, Value(100), ())
+            .unwrap();
+        for _ in 0..3 {
+            ledger = ledger.remove_value(&alice, Value(1)).unwrap().0;
+        }
+        let (ledger, state) = ledger.deregister_account(&alice).unwrap();
+        assert_eq!(state.counter, SpendingCounter::from(3));
+        assert!(!ledger.exists(&alice));
+        assert_eq!(
+            ledger.iter_tombstones().collect::<Vec<_>>(),
+            vec![(&alice, &SpendingCounter::from(3))]
+        );
+
+        // other accounts are not affected by the deregistration
+        let carol = TestGen::identifier();
+        let ledger = ledger.add_account(&carol, Value(1), ()).unwrap();
+        assert_eq!(
+            ledger.get_state(&carol).unwrap().counter,
+            SpendingCounter::zero()
+        );
+        assert_eq!(
+            ledger.get_state(&bob).unwrap().counter,
+            SpendingCounter::zero()
+        );
+
+        let ledger = ledger.add_value_or_account(&alice, Value(10), ()).unwrap();
+        assert_eq!(
+            ledger.get_state(&alice).unwrap().counter,
+            SpendingCounter::from(3)
+        );
+        assert_eq!(ledger.iter_tombstones().count(), 0);
+    }
+
+    #[test]
+    pub fn tombstones_of_existing_accounts_are_rejected() {
+        let id = TestGen::identifier();
+        let ledger = Ledger::new().add_account(&id, Value(1), ()).unwrap();
+        assert_eq!(
+            ledger.with_tombstones(vec![(id, SpendingCounter::from(1))]),
+            Err(LedgerError::AlreadyExists)
+        );
+    }
+
     #[test]
     pub fn add_value_or_account_test() {
         let ledger = Ledger::new();
@@ -1,5 +1,6 @@
 #[warn(unused_imports)]
 use super::{AccountState, DelegationType, LastRewards, SpendingCounter};
+use crate::value::Value;
 use quickcheck::{Arbitrary, Gen};
 
 impl Arbitrary for SpendingCounter {
@@ -15,6 +16,7 @@ impl Arbitrary for AccountState<()> {
             delegation: DelegationType::Full(Arbitrary::arbitrary(gen)),
             value: Arbitrary::arbitrary(gen),
             last_rewards: LastRewards::default(),
+            deposit: Value::zero(),
             extra: (),
         }
     }
@@ -45,9 +45,14 @@ impl Eq for Block {}
 impl Block {
     pub fn is_consistent(&self) -> bool {
         let (content_hash, content_size) = self.contents.compute_hash_size();
+        let fragments_root_matches = match self.header.fragments_root() {
+            Some(root) => root == self.contents.compute_fragments_root(),
+            None => true,
+        };
 
         content_hash == self.header.block_content_hash()
             && content_size == self.header.block_content_size()
+            && fragments_root_matches
     }
 
     pub fn fragments(&self) -> impl Iterator<Item = &Fragment> {
@@ -67,6 +67,33 @@ quickcheck! {
 
         TestResult::from_bool(header.chain_length() == block.chain_length())
     }
+
+    fn fragments_root_is_committed_in_header(block: Block) -> TestResult {
+        let root = match block.header.fragments_root() {
+            Some(root) => root,
+            None => return TestResult::discard(),
+        };
+        TestResult::from_bool(
+            block.is_consistent()
+                && block.header.block_content_hash() == block.contents.compute_hash()
+                && root == block.contents.compute_fragments_root(),
+        )
+    }
+
+    fn fragment_proofs_verify_against_header(block: Block) -> TestResult {
+        let root = match block.header.fragments_root() {
+            Some(root) => root,
+            None => return TestResult::discard(),
+        };
+        for fragment in block.contents.iter() {
+            let id = fragment.hash();
+            let proof = block.contents.merkle_proof(&id).unwrap();
+            if !proof.verify(&id, &root) {
+                return TestResult::failed();
+            }
+        }
+        TestResult::passed()
+    }
 }
 
 #[cfg(test)]
@@ -104,7 +131,11 @@ impl Arbitrary for Block {
         let parent_hash = Arbitrary::arbitrary(g);
         let chain_length = Arbitrary::arbitrary(g);
         let date = Arbitrary::arbitrary(g);
-        let hdrbuilder = HeaderBuilderNew::new(ver, &content)
+        let mut hdrbuilder = HeaderBuilderNew::new(ver, &content);
+        if bool::arbitrary(g) {
+            hdrbuilder = hdrbuilder.set_fragments_root(&content.compute_fragments_root());
+        }
+        let hdrbuilder = hdrbuilder
             .set_parent(&parent_hash, chain_length)
             .set_date(date);
         let header = match ver {
@@ -5,6 +5,7 @@ use crate::transaction::{
     UnspecifiedAccountIdentifier,
 };
 
+use chain_addr::Address;
 use chain_core::{
     mempack::{ReadBuf, ReadError, Readable},
     property,
@@ -138,6 +139,64 @@ impl Payload for StakeDelegation {
     }
 }
 
+/// Deregister an account, sweeping its remaining value, rewards and key
+/// deposit to the destination address and removing it from the ledger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountDeregistration {
+    pub account_id: UnspecifiedAccountIdentifier,
+    pub destination: Address,
+}
+
+impl AccountDeregistration {
+    pub fn serialize_in(&self, bb: ByteBuilder<Self>) -> ByteBuilder<Self> {
+        bb.bytes(self.account_id.as_ref())
+            .bytes(&self.destination.to_bytes())
+    }
+    pub fn serialize(&self) -> ByteArray<Self> {
+        self.serialize_in(ByteBuilder::new()).finalize()
+    }
+}
+
+impl property::Serialize for AccountDeregistration {
+    type Error = std::io::Error;
+    fn serialize<W: std::io::Write>(&self, mut writer: W) -> Result<(), Self::Error> {
+        writer.write_all(self.serialize().as_slice())
+    }
+}
+
+impl Readable for AccountDeregistration {
+    fn read(buf: &mut ReadBuf) -> Result<Self, ReadError> {
+        let account_identifier = <[u8; 32]>::read(buf)?;
+        let destination = Address::read(buf)?;
+        Ok(AccountDeregistration {
+            account_id: account_identifier.into(),
+            destination,
+        })
+    }
+}
+
+impl Payload for AccountDeregistration {
+    const HAS_DATA: bool = true;
+    const HAS_AUTH: bool = true;
+    type Auth = AccountBindingSignature;
+    fn payload_data(&self) -> PayloadData<Self> {
+        PayloadData(
+            self.serialize_in(ByteBuilder::new())
+                .finalize_as_vec()
+                .into(),
+            PhantomData,
+        )
+    }
+
+    fn payload_auth_data(auth: &Self::Auth) -> PayloadAuthData<Self> {
+        let bb = auth.serialize_in(ByteBuilder::new()).finalize_as_vec();
+        PayloadAuthData(bb.into(), PhantomData)
+    }
+    fn to_certificate_slice(p: PayloadSlice<'_, Self>) -> Option<CertificateSlice<'_>> {
+        Some(CertificateSlice::from(p))
+    }
+}
+
 // Format is either:
 // 0 (byte)
 // 1 (byte)     POOL_ID (32 bytes)
@@ -4,11 +4,9 @@ use crate::{
     certificate::{CertificateSlice, VotePlanId},
     transaction::{Payload, PayloadAuthData, PayloadData, PayloadSlice},
 };
-use chain_core::{
-    mempack::{ReadBuf, ReadError, Readable},
-    property,
-};
+use chain_core::mempack::{ReadBuf, ReadError, Readable};
 use chain_crypto::Verification;
+use chain_ser_derive::{ByteBuilder, Readable, Serialize};
 use typed_bytes::{ByteArray, ByteBuilder};
 
 #[derive(Debug, Clone)]
@@ -17,8 +15,9 @@ pub struct EncryptedVoteTallyProof {
     pub signature: SingleAccountBindingSignature,
 }
 
-#[derive(Debug, Eq, PartialEq, Hash, Clone)]
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Readable, Serialize, ByteBuilder)]
 pub struct EncryptedVoteTally {
+    #[codec(bytes = 32)]
     id: VotePlanId,
 }
 
@@ -42,10 +41,6 @@ impl EncryptedVoteTally {
         &self.id
     }
 
-    pub fn serialize_in(&self, bb: ByteBuilder<Self>) -> ByteBuilder<Self> {
-        bb.bytes(self.id().as_ref())
-    }
-
     pub fn serialize(&self) -> ByteArray<Self> {
         self.serialize_in(ByteBuilder::new()).finalize()
     }
@@ -83,14 +78,6 @@ impl Payload for EncryptedVoteTally {
 
 /* Ser/De ******************************************************************* */
 
-impl property::Serialize for EncryptedVoteTally {
-    type Error = std::io::Error;
-    fn serialize<W: std::io::Write>(&self, mut writer: W) -> Result<(), Self::Error> {
-        writer.write_all(self.serialize().as_slice())?;
-        Ok(())
-    }
-}
-
 impl Readable for EncryptedVoteTallyProof {
     fn read(buf: &mut ReadBuf) -> Result<Self, ReadError> {
         let id = CommitteeId::read(buf)?;
@@ -98,10 +85,3 @@ impl Readable for EncryptedVoteTallyProof {
         Ok(Self { id, signature })
     }
 }
-
-impl Readable for EncryptedVoteTally {
-    fn read(buf: &mut ReadBuf) -> Result<Self, ReadError> {
-        let id = <[u8; 32]>::read(buf)?.into();
-        Ok(Self { id })
-    }
-}
@@ -1,5 +1,6 @@
 mod delegation;
 mod encrypted_vote_tally;
+mod multisig;
 mod pool;
 mod vote_cast;
 mod vote_plan;
@@ -17,7 +18,8 @@ pub use self::vote_plan::{
     VotePlan, VotePlanId, VotePlanProof,
 };
 pub use self::vote_tally::{TallyDecryptShares, TallyProof, VoteTally, VoteTallyPayload};
-pub use delegation::{OwnerStakeDelegation, StakeDelegation};
+pub use delegation::{AccountDeregistration, OwnerStakeDelegation, StakeDelegation};
+pub use multisig::MultisigRegistration;
 pub use pool::{
     GenesisPraosLeaderHash, IndexSignatures, ManagementThreshold, PoolId, PoolOwnersSigned,
     PoolPermissions, PoolRegistration, PoolRegistrationHash, PoolRetirement, PoolSignature,
@@ -34,6 +36,8 @@ pub enum CertificateSlice<'a> {
     VoteCast(PayloadSlice<'a, VoteCast>),
     VoteTally(PayloadSlice<'a, VoteTally>),
     EncryptedVoteTally(PayloadSlice<'a, EncryptedVoteTally>),
+    AccountDeregistration(PayloadSlice<'a, AccountDeregistration>),
+    MultisigRegistration(PayloadSlice<'a, MultisigRegistration>),
 }
 
 impl<'a> From<PayloadSlice<'a, StakeDelegation>> for CertificateSlice<'a> {
@@ -89,6 +93,18 @@ impl<'a> From<PayloadSlice<'a, EncryptedVoteTally>> for CertificateSlice<'a> {
     }
 }
 
+impl<'a> From<PayloadSlice<'a, AccountDeregistration>> for CertificateSlice<'a> {
+    fn from(payload: PayloadSlice<'a, AccountDeregistration>) -> CertificateSlice<'a> {
+        CertificateSlice::AccountDeregistration(payload)
+    }
+}
+
+impl<'a> From<PayloadSlice<'a, MultisigRegistration>> for CertificateSlice<'a> {
+    fn from(payload: PayloadSlice<'a, MultisigRegistration>) -> CertificateSlice<'a> {
+        CertificateSlice::MultisigRegistration(payload)
+    }
+}
+
 impl<'a> CertificateSlice<'a> {
     pub fn into_owned(self) -> Certificate {
         match self {
@@ -107,6 +123,12 @@ impl<'a> CertificateSlice<'a> {
             CertificateSlice::EncryptedVoteTally(c) => {
                 Certificate::EncryptedVoteTally(c.into_payload())
             }
+            CertificateSlice::AccountDeregistration(c) => {
+                Certificate::AccountDeregistration(c.into_payload())
+            }
+            CertificateSlice::MultisigRegistration(c) => {
+                Certificate::MultisigRegistration(c.into_payload())
+            }
         }
     }
 }
@@ -122,6 +144,8 @@ pub enum CertificatePayload {
     VoteCast(PayloadData<VoteCast>),
     VoteTally(PayloadData<VoteTally>),
     EncryptedVoteTally(PayloadData<EncryptedVoteTally>),
+    AccountDeregistration(PayloadData<AccountDeregistration>),
+    MultisigRegistration(PayloadData<MultisigRegistration>),
 }
 
 impl CertificatePayload {
@@ -136,6 +160,8 @@ impl CertificatePayload {
             CertificatePayload::VoteCast(payload) => payload.borrow().into(),
             CertificatePayload::VoteTally(payload) => payload.borrow().into(),
             CertificatePayload::EncryptedVoteTally(payload) => payload.borrow().into(),
+            CertificatePayload::AccountDeregistration(payload) => payload.borrow().into(),
+            CertificatePayload::MultisigRegistration(payload) => payload.borrow().into(),
         }
     }
 }
@@ -166,6 +192,12 @@ impl<'a> From<&'a Certificate> for CertificatePayload {
             Certificate::EncryptedVoteTally(payload) => {
                 CertificatePayload::EncryptedVoteTally(payload.payload_data())
             }
+            Certificate::AccountDeregistration(payload) => {
+                CertificatePayload::AccountDeregistration(payload.payload_data())
+            }
+            Certificate::MultisigRegistration(payload) => {
+                CertificatePayload::MultisigRegistration(payload.payload_data())
+            }
         }
     }
 }
@@ -182,6 +214,8 @@ pub enum Certificate {
     VoteCast(VoteCast),
     VoteTally(VoteTally),
     EncryptedVoteTally(EncryptedVoteTally),
+    AccountDeregistration(AccountDeregistration),
+    MultisigRegistration(MultisigRegistration),
 }
 
 impl From<StakeDelegation> for Certificate {
@@ -238,6 +272,18 @@ impl From<EncryptedVoteTally> for Certificate {
     }
 }
 
+impl From<AccountDeregistration> for Certificate {
+    fn from(cert: AccountDeregistration) -> Certificate {
+        Certificate::AccountDeregistration(cert)
+    }
+}
+
+impl From<MultisigRegistration> for Certificate {
+    fn from(cert: MultisigRegistration) -> Certificate {
+        Certificate::MultisigRegistration(cert)
+    }
+}
+
 impl Certificate {
     pub fn need_auth(&self) -> bool {
         match self {
@@ -250,6 +296,8 @@ impl Certificate {
             Certificate::VoteCast(_) => <VoteCast as Payload>::HAS_AUTH,
             Certificate::VoteTally(_) => <VoteTally as Payload>::HAS_AUTH,
             Certificate::EncryptedVoteTally(_) => <EncryptedVoteTally as Payload>::HAS_AUTH,
+            Certificate::AccountDeregistration(_) => <AccountDeregistration as Payload>::HAS_AUTH,
+            Certificate::MultisigRegistration(_) => <MultisigRegistration as Payload>::HAS_AUTH,
         }
     }
 }
@@ -268,6 +316,14 @@ pub enum SignedCertificate {
     VotePlan(VotePlan, <VotePlan as Payload>::Auth),
     VoteTally(VoteTally, <VoteTally as Payload>::Auth),
     EncryptedVoteTally(EncryptedVoteTally, <EncryptedVoteTally as Payload>::Auth),
+    AccountDeregistration(
+        AccountDeregistration,
+        <AccountDeregistration as Payload>::Auth,
+    ),
+    MultisigRegistration(
+        MultisigRegistration,
+        <MultisigRegistration as Payload>::Auth,
+    ),
 }
 
 #[cfg(test)]
@@ -288,6 +344,8 @@ mod tests {
             Certificate::VoteCast(_) => false,
             Certificate::VoteTally(_) => true,
             Certificate::EncryptedVoteTally(_) => true,
+            Certificate::AccountDeregistration(_) => true,
+            Certificate::MultisigRegistration(_) => false,
         };
         TestResult::from_bool(certificate.need_auth() == expected_result)
     }
@@ -0,0 +1,108 @@
+use crate::certificate::CertificateSlice;
+use crate::key::Hash;
+use crate::multisig::{DeclElement, Declaration, DEPTH_MAXLIMIT};
+use crate::transaction::{Payload, PayloadAuthData, PayloadData, PayloadSlice};
+
+use chain_core::{
+    mempack::{ReadBuf, ReadError, Readable},
+    property,
+};
+use std::marker::PhantomData;
+use typed_bytes::{ByteArray, ByteBuilder};
+
+/// Register a multisig declaration on chain, creating the associated
+/// multisig account.
+///
+/// The declaration is not authenticated: its identifier is the hash of its
+/// content, so registering it only makes the account available for funding
+/// and spending by its owners.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigRegistration {
+    pub declaration: Declaration,
+}
+
+impl MultisigRegistration {
+    pub fn serialize_in(&self, bb: ByteBuilder<Self>) -> ByteBuilder<Self> {
+        bb.sub(|sb| serialize_declaration(&self.declaration, sb))
+    }
+    pub fn serialize(&self) -> ByteArray<Self> {
+        self.serialize_in(ByteBuilder::new()).finalize()
+    }
+}
+
+impl property::Serialize for MultisigRegistration {
+    type Error = std::io::Error;
+    fn serialize<W: std::io::Write>(&self, mut writer: W) -> Result<(), Self::Error> {
+        writer.write_all(self.serialize().as_slice())
+    }
+}
+
+impl Readable for MultisigRegistration {
+    fn read(buf: &mut ReadBuf) -> Result<Self, ReadError> {
+        let declaration = deserialize_declaration(buf, 1)?;
+        Ok(MultisigRegistration { declaration })
+    }
+}
+
+impl Payload for MultisigRegistration {
+    const HAS_DATA: bool = true;
+    const HAS_AUTH: bool = false;
+    type Auth = ();
+    fn payload_data(&self) -> PayloadData<Self> {
+        PayloadData(
+            self.serialize_in(ByteBuilder::new())
+                .finalize_as_vec()
+                .into(),
+            PhantomData,
+        )
+    }
+    fn payload_auth_data(_: &Self::Auth) -> PayloadAuthData<Self> {
+        PayloadAuthData(Vec::with_capacity(0).into(), PhantomData)
+    }
+    fn to_certificate_slice(p: PayloadSlice<'_, Self>) -> Option<CertificateSlice<'_>> {
+        Some(CertificateSlice::from(p))
+    }
+}
+
+const DECL_ELEMENT_SUB: u8 = 0;
+const DECL_ELEMENT_OWNER: u8 = 1;
+
+// Format is:
+// THRESHOLD (byte) #OWNERS (byte) [ OWNER ] (repeated #OWNERS time)
+// where OWNER is either:
+// 0 (byte) DECLARATION
+// 1 (byte) KEY_HASH (32 bytes)
+//
+// Sub declarations are nested at most DEPTH_MAXLIMIT levels deep.
+fn serialize_declaration(
+    declaration: &Declaration,
+    bb: ByteBuilder<Declaration>,
+) -> ByteBuilder<Declaration> {
+    bb.u8(declaration.threshold)
+        .iter8(declaration.owners.iter(), |bb, owner| match owner {
+            DeclElement::Sub(sub) => bb
+                .u8(DECL_ELEMENT_SUB)
+                .sub(|sb| serialize_declaration(sub, sb)),
+            DeclElement::Owner(hash) => bb.u8(DECL_ELEMENT_OWNER).bytes(hash.as_ref()),
+        })
+}
+
+fn deserialize_declaration(buf: &mut ReadBuf, level: usize) -> Result<Declaration, ReadError> {
+    let threshold = buf.get_u8()?;
+    let nb_owners = buf.get_u8()?;
+    let mut owners = Vec::with_capacity(nb_owners as usize);
+    for _ in 0..nb_owners {
+        let owner = match buf.get_u8()? {
+            DECL_ELEMENT_SUB if level >= DEPTH_MAXLIMIT => {
+                return Err(ReadError::StructureInvalid(
+                    "multisig declaration is nested too deep".to_string(),
+                ))
+            }
+            DECL_ELEMENT_SUB => DeclElement::Sub(deserialize_declaration(buf, level + 1)?),
+            DECL_ELEMENT_OWNER => DeclElement::Owner(Hash::read(buf)?),
+            code => return Err(ReadError::UnknownTag(code as u32)),
+        };
+        owners.push(owner);
+    }
+    Ok(Declaration { threshold, owners })
+}
@@ -5,7 +5,10 @@ use crate::ledger::governance::TreasuryGovernanceAction;
 use crate::rewards::TaxType;
 use crate::vote;
 #[cfg(test)]
-use chain_core::mempack::{ReadBuf, Readable};
+use chain_core::{
+    mempack::{ReadBuf, Readable},
+    property,
+};
 use chain_crypto::{testing, Ed25519};
 use chain_time::DurationSeconds;
 #[cfg(test)]
@@ -84,6 +87,23 @@ impl Arbitrary for StakeDelegation {
     }
 }
 
+impl Arbitrary for AccountDeregistration {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        AccountDeregistration {
+            account_id: Arbitrary::arbitrary(g),
+            destination: Arbitrary::arbitrary(g),
+        }
+    }
+}
+
+impl Arbitrary for MultisigRegistration {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        MultisigRegistration {
+            declaration: Arbitrary::arbitrary(g),
+        }
+    }
+}
+
 impl Arbitrary for OwnerStakeDelegation {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
         Self {
@@ -257,7 +277,7 @@ impl Arbitrary for EncryptedVoteTallyProof {
 
 impl Arbitrary for Certificate {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        let option = u8::arbitrary(g) % 9;
+        let option = u8::arbitrary(g) % 11;
         match option {
             0 => Certificate::StakeDelegation(Arbitrary::arbitrary(g)),
             1 => Certificate::OwnerStakeDelegation(Arbitrary::arbitrary(g)),
@@ -268,6 +288,8 @@ impl Arbitrary for Certificate {
             6 => Certificate::VoteCast(Arbitrary::arbitrary(g)),
             7 => Certificate::VoteTally(Arbitrary::arbitrary(g)),
             8 => Certificate::EncryptedVoteTally(Arbitrary::arbitrary(g)),
+            9 => Certificate::AccountDeregistration(Arbitrary::arbitrary(g)),
+            10 => Certificate::MultisigRegistration(Arbitrary::arbitrary(g)),
             _ => panic!("unimplemented"),
         }
     }
@@ -283,3 +305,54 @@ fn pool_reg_serialization_bijection(b: PoolRegistration) -> TestResult {
     assert_eq!(buf.get_slice_end(), &[]);
     TestResult::from_bool(left == result)
 }
+
+#[quickcheck]
+fn account_dereg_serialization_bijection(b: AccountDeregistration) -> TestResult {
+    let b_got = b.serialize();
+    let mut buf = ReadBuf::from(b_got.as_ref());
+    let result = AccountDeregistration::read(&mut buf);
+    let left = Ok(b);
+    assert_eq!(left, result);
+    assert_eq!(buf.get_slice_end(), &[]);
+    TestResult::from_bool(left == result)
+}
+
+#[quickcheck]
+fn encrypted_vote_tally_derived_encoding(b: EncryptedVoteTally) -> TestResult {
+    // the encoding written by hand before the codec was derived
+    let expected = b.id().as_ref().to_vec();
+    assert_eq!(b.serialize().as_slice(), &expected[..]);
+    assert_eq!(property::Serialize::serialize_as_vec(&b).unwrap(), expected);
+    let mut buf = ReadBuf::from(&expected);
+    let result = EncryptedVoteTally::read(&mut buf);
+    assert_eq!(buf.get_slice_end(), &[]);
+    TestResult::from_bool(result == Ok(b))
+}
+
+#[test]
+fn multisig_reg_nested_too_deep_is_rejected() {
+    use crate::multisig::{DeclElement, Declaration};
+
+    let owner = || DeclElement::Owner(crate::key::Hash::hash_bytes(&[0]));
+    let mut declaration = Declaration::new(1, vec![owner(), owner()]).unwrap();
+    for _ in 0..crate::multisig::DEPTH_MAXLIMIT {
+        declaration = Declaration {
+            threshold: 1,
+            owners: vec![owner(), DeclElement::Sub(declaration)],
+        };
+    }
+    let bytes = MultisigRegistration { declaration }.serialize();
+    let mut buf = ReadBuf::from(bytes.as_ref());
+    assert!(MultisigRegistration::read(&mut buf).is_err());
+}
+
+#[quickcheck]
+fn multisig_reg_serialization_bijection(b: MultisigRegistration) -> TestResult {
+    let b_got = b.serialize();
+    let mut buf = ReadBuf::from(b_got.as_ref());
+    let result = MultisigRegistration::read(&mut buf);
+    let left = Ok(b);
+    assert_eq!(left, result);
+    assert_eq!(buf.get_slice_end(), &[]);
+    TestResult::from_bool(left == result)
+}
@@ -1,7 +1,7 @@
 use crate::certificate::PoolId;
 use crate::chaintypes::ChainLength;
 use crate::date::BlockDate;
-use crate::fragment::BlockContentHash;
+use crate::fragment::{BlockContentHash, BlockFragmentsRoot};
 
 use crate::key::Hash;
 
@@ -49,6 +49,7 @@ pub struct HeaderContentEvalContext {
     pub(crate) block_date: BlockDate,
     pub(crate) chain_length: ChainLength,
     pub(crate) content_hash: BlockContentHash,
+    pub(crate) fragments_root: Option<BlockFragmentsRoot>,
     pub(crate) gp_content: Option<HeaderGPContentEvalContext>,
 }
 
@@ -83,6 +84,7 @@ mod test {
                 chain_length: Arbitrary::arbitrary(g),
                 gp_content: Arbitrary::arbitrary(g),
                 content_hash: Arbitrary::arbitrary(g),
+                fragments_root: None,
             }
         }
     }
@@ -5,7 +5,10 @@ use crate::rewards::{Ratio, TaxType};
 use crate::value::Value;
 use crate::{
     chaintypes::ConsensusType,
-    fee::{LinearFee, PerCertificateFee, PerVoteCertificateFee},
+    fee::{
+        DynamicFeeParams, DynamicFeePeriod, ExtendedFee, LinearFee, PerCertificateFee,
+        PerVoteCertificateFee,
+    },
     vote::CommitteeId,
 };
 use chain_addr::Discrimination;
@@ -82,6 +85,13 @@ pub enum ConfigParam {
     AddCommitteeId(CommitteeId),
     RemoveCommitteeId(CommitteeId),
     PerVoteCertificateFees(PerVoteCertificateFee),
+    PoolRegistrationDeposit(Value),
+    StakeKeyDeposit(Value),
+    /// Enables the dynamic fee rule with the given parameters, or
+    /// disables it with `None`, encoded as an empty payload.
+    DynamicFees(Option<DynamicFeeParams>),
+    ExtendedFees(ExtendedFee),
+    SlotDurationMillis(u32),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -153,6 +163,16 @@ pub enum Tag {
     RemoveCommitteeId = 27,
     #[strum(to_string = "per-vote-certificate-fees")]
     PerVoteCertificateFees = 28,
+    #[strum(to_string = "pool-registration-deposit")]
+    PoolRegistrationDeposit = 29,
+    #[strum(to_string = "stake-key-deposit")]
+    StakeKeyDeposit = 30,
+    #[strum(to_string = "dynamic-fees")]
+    DynamicFees = 31,
+    #[strum(to_string = "extended-fees")]
+    ExtendedFees = 32,
+    #[strum(to_string = "slot-duration-millis")]
+    SlotDurationMillis = 33,
 }
 
 impl Tag {
@@ -183,6 +203,11 @@ impl Tag {
             26 => Some(Tag::AddCommitteeId),
             27 => Some(Tag::RemoveCommitteeId),
             28 => Some(Tag::PerVoteCertificateFees),
+            29 => Some(Tag::PoolRegistrationDeposit),
+            30 => Some(Tag::StakeKeyDeposit),
+            31 => Some(Tag::DynamicFees),
+            32 => Some(Tag::ExtendedFees),
+            33 => Some(Tag::SlotDurationMillis),
             _ => None,
         }
     }
@@ -218,6 +243,11 @@ impl<'a> From<&'a ConfigParam> for Tag {
             ConfigParam::AddCommitteeId(..) => Tag::AddCommitteeId,
             ConfigParam::RemoveCommitteeId(..) => Tag::RemoveCommitteeId,
             ConfigParam::PerVoteCertificateFees(..) => Tag::PerVoteCertificateFees,
+            ConfigParam::PoolRegistrationDeposit(..) => Tag::PoolRegistrationDeposit,
+            ConfigParam::StakeKeyDeposit(..) => Tag::StakeKeyDeposit,
+            ConfigParam::DynamicFees(..) => Tag::DynamicFees,
+            ConfigParam::ExtendedFees(..) => Tag::ExtendedFees,
+            ConfigParam::SlotDurationMillis(_) => Tag::SlotDurationMillis,
         }
     }
 }
@@ -298,6 +328,21 @@ impl Readable for ConfigParam {
             Tag::PerVoteCertificateFees => {
                 ConfigParamVariant::from_payload(bytes).map(ConfigParam::PerVoteCertificateFees)
             }
+            Tag::PoolRegistrationDeposit => {
+                ConfigParamVariant::from_payload(bytes).map(ConfigParam::PoolRegistrationDeposit)
+            }
+            Tag::StakeKeyDeposit => {
+                ConfigParamVariant::from_payload(bytes).map(ConfigParam::StakeKeyDeposit)
+            }
+            Tag::DynamicFees => {
+                ConfigParamVariant::from_payload(bytes).map(ConfigParam::DynamicFees)
+            }
+            Tag::ExtendedFees => {
+                ConfigParamVariant::from_payload(bytes).map(ConfigParam::ExtendedFees)
+            }
+            Tag::SlotDurationMillis => {
+                ConfigParamVariant::from_payload(bytes).map(ConfigParam::SlotDurationMillis)
+            }
         }
         .map_err(Into::into)
     }
@@ -334,6 +379,11 @@ impl property::Serialize for ConfigParam {
             ConfigParam::AddCommitteeId(data) => data.to_payload(),
             ConfigParam::RemoveCommitteeId(data) => data.to_payload(),
             ConfigParam::PerVoteCertificateFees(data) => data.to_payload(),
+            ConfigParam::PoolRegistrationDeposit(data) => data.to_payload(),
+            ConfigParam::StakeKeyDeposit(data) => data.to_payload(),
+            ConfigParam::DynamicFees(data) => data.to_payload(),
+            ConfigParam::ExtendedFees(data) => data.to_payload(),
+            ConfigParam::SlotDurationMillis(data) => data.to_payload(),
         };
         let taglen = TagLen::new(tag, bytes.len()).ok_or_else(|| {
             io::Error::new(
@@ -661,6 +711,7 @@ impl ConfigParamVariant for LinearFee {
             certificate: u64::from_payload(&payload[16..24])?,
             per_certificate_fees: PerCertificateFee::default(),
             per_vote_certificate_fees: PerVoteCertificateFee::default(),
+            extended_fees: ExtendedFee::default(),
         })
     }
 }
@@ -728,6 +779,88 @@ impl ConfigParamVariant for PerVoteCertificateFee {
     }
 }
 
+impl ConfigParamVariant for ExtendedFee {
+    fn to_payload(&self) -> Vec<u8> {
+        let mut v = self.per_byte.to_payload();
+        v.extend(self.per_utxo_witness.to_payload());
+        v.extend(self.per_account_witness.to_payload());
+        v.extend(self.per_multisig_witness.to_payload());
+        v
+    }
+
+    fn from_payload(payload: &[u8]) -> Result<Self, Error> {
+        if payload.len() != 4 * 8 {
+            return Err(Error::SizeInvalid);
+        }
+        Ok(ExtendedFee {
+            per_byte: u64::from_payload(&payload[0..8])?,
+            per_utxo_witness: u64::from_payload(&payload[8..16])?,
+            per_account_witness: u64::from_payload(&payload[16..24])?,
+            per_multisig_witness: u64::from_payload(&payload[24..32])?,
+        })
+    }
+}
+
+impl ConfigParamVariant for DynamicFeeParams {
+    fn to_payload(&self) -> Vec<u8> {
+        let period: u8 = match self.period {
+            DynamicFeePeriod::Epoch => 0,
+            DynamicFeePeriod::Block => 1,
+        };
+        let mut v = vec![period, self.target_utilisation];
+        v.extend(self.max_change_denominator.get().to_payload());
+        v.extend(self.min_constant.to_payload());
+        v.extend(self.max_constant.to_payload());
+        v
+    }
+
+    fn from_payload(payload: &[u8]) -> Result<Self, Error> {
+        if payload.len() != 2 + 3 * 8 {
+            return Err(Error::SizeInvalid);
+        }
+        let period = match payload[0] {
+            0 => DynamicFeePeriod::Epoch,
+            1 => DynamicFeePeriod::Block,
+            _ => return Err(Error::StructureInvalid),
+        };
+        let target_utilisation = payload[1];
+        if target_utilisation == 0 || target_utilisation > 100 {
+            return Err(Error::StructureInvalid);
+        }
+        let max_change_denominator =
+            NonZeroU64::new(u64::from_payload(&payload[2..10])?).ok_or(Error::StructureInvalid)?;
+        let min_constant = u64::from_payload(&payload[10..18])?;
+        let max_constant = u64::from_payload(&payload[18..26])?;
+        if min_constant > max_constant {
+            return Err(Error::StructureInvalid);
+        }
+        Ok(DynamicFeeParams {
+            period,
+            target_utilisation,
+            max_change_denominator,
+            min_constant,
+            max_constant,
+        })
+    }
+}
+
+impl ConfigParamVariant for Option<DynamicFeeParams> {
+    fn to_payload(&self) -> Vec<u8> {
+        match self {
+            None => Vec::new(),
+            Some(params) => params.to_payload(),
+        }
+    }
+
+    fn from_payload(payload: &[u8]) -> Result<Self, Error> {
+        if payload.is_empty() {
+            Ok(None)
+        } else {
+            DynamicFeeParams::from_payload(payload).map(Some)
+        }
+    }
+}
+
 impl ConfigParamVariant for CommitteeId {
     fn to_payload(&self) -> Vec<u8> {
         self.as_ref().to_vec()
@@ -794,6 +927,20 @@ mod test {
             TestResult::from_bool(fee == decoded)
         }
 
+        fn extended_fee_to_payload_from_payload(fee: ExtendedFee) -> TestResult {
+            let payload = fee.to_payload();
+            let decoded = ExtendedFee::from_payload(&payload).unwrap();
+
+            TestResult::from_bool(fee == decoded)
+        }
+
+        fn dynamic_fees_to_payload_from_payload(params: Option<DynamicFeeParams>) -> TestResult {
+            let payload = params.to_payload();
+            let decoded = Option::<DynamicFeeParams>::from_payload(&payload).unwrap();
+
+            TestResult::from_bool(params == decoded)
+        }
+
         fn config_param_serialize_correct(param: ConfigParam) -> bool {
             use chain_core::property::{Serialize as _, Deserialize as _};
             let bytes = param.serialize_as_vec().unwrap();
@@ -813,6 +960,16 @@ mod test {
         }
     }
 
+    #[test]
+    fn dynamic_fees_can_be_disabled() {
+        use chain_core::property::Serialize as _;
+        let param = ConfigParam::DynamicFees(None);
+        let bytes = param.serialize_as_vec().unwrap();
+        assert_eq!(bytes, ((Tag::DynamicFees as u16) << 6).to_be_bytes());
+        let decoded = ConfigParam::read(&mut ReadBuf::from(&bytes)).unwrap();
+        assert_eq!(decoded, param);
+    }
+
     impl Arbitrary for Tag {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             let idx = usize::arbitrary(g) % Tag::iter().count();
@@ -858,7 +1015,7 @@ mod test {
 
     impl Arbitrary for ConfigParam {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
-            match u8::arbitrary(g) % 29 {
+            match u8::arbitrary(g) % 34 {
                 0 => ConfigParam::Block0Date(Arbitrary::arbitrary(g)),
                 1 => ConfigParam::Discrimination(Arbitrary::arbitrary(g)),
                 2 => ConfigParam::ConsensusVersion(Arbitrary::arbitrary(g)),
@@ -888,6 +1045,11 @@ mod test {
                 26 => ConfigParam::AddCommitteeId(Arbitrary::arbitrary(g)),
                 27 => ConfigParam::RemoveCommitteeId(Arbitrary::arbitrary(g)),
                 28 => ConfigParam::PerCertificateFees(Arbitrary::arbitrary(g)),
+                29 => ConfigParam::PoolRegistrationDeposit(Arbitrary::arbitrary(g)),
+                30 => ConfigParam::StakeKeyDeposit(Arbitrary::arbitrary(g)),
+                31 => ConfigParam::DynamicFees(Arbitrary::arbitrary(g)),
+                32 => ConfigParam::ExtendedFees(Arbitrary::arbitrary(g)),
+                33 => ConfigParam::SlotDurationMillis(Arbitrary::arbitrary(g)),
                 _ => unreachable!(),
             }
         }
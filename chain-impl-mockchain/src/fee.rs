@@ -1,5 +1,6 @@
 use crate::certificate::CertificateSlice;
 use crate::transaction as tx;
+use crate::transaction::Witness;
 use crate::value::Value;
 use std::num::NonZeroU64;
 
@@ -12,6 +13,7 @@ pub struct LinearFee {
     pub certificate: u64,
     pub per_certificate_fees: PerCertificateFee,
     pub per_vote_certificate_fees: PerVoteCertificateFee,
+    pub extended_fees: ExtendedFee,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Debug, Clone, Copy, Default)]
@@ -35,6 +37,7 @@ impl LinearFee {
             certificate,
             per_certificate_fees: PerCertificateFee::default(),
             per_vote_certificate_fees: PerVoteCertificateFee::default(),
+            extended_fees: ExtendedFee::default(),
         }
     }
 
@@ -45,6 +48,10 @@ impl LinearFee {
     pub fn per_vote_certificate_fees(&mut self, per_vote_certificate_fees: PerVoteCertificateFee) {
         self.per_vote_certificate_fees = per_vote_certificate_fees;
     }
+
+    pub fn extended_fees(&mut self, extended_fees: ExtendedFee) {
+        self.extended_fees = extended_fees;
+    }
 }
 
 impl PerCertificateFee {
@@ -96,10 +103,165 @@ impl PerVoteCertificateFee {
     }
 }
 
+/// Fee components charged on the size of a transaction and on the number and
+/// type of its witnesses, on top of the linear fee.
+///
+/// The size taken into account is the one of the transaction body (payload,
+/// inputs and outputs), which is known before the transaction is signed;
+/// the witnesses are paid through the per-witness components instead.
+/// Multisig witnesses vary in size with the number of owners signing them,
+/// so they are charged per signature rather than per witness.
+#[derive(PartialEq, Eq, PartialOrd, Debug, Clone, Copy, Default)]
+pub struct ExtendedFee {
+    pub per_byte: u64,
+    pub per_utxo_witness: u64,
+    pub per_account_witness: u64,
+    /// charged for each signature of a multisig witness
+    pub per_multisig_witness: u64,
+}
+
+/// Number of witnesses of each type in a transaction
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct WitnessCount {
+    pub utxo: u8,
+    pub account: u8,
+    /// total number of signatures in the multisig witnesses
+    pub multisig_signatures: u32,
+}
+
+impl ExtendedFee {
+    pub fn new(
+        per_byte: u64,
+        per_utxo_witness: u64,
+        per_account_witness: u64,
+        per_multisig_witness: u64,
+    ) -> Self {
+        Self {
+            per_byte,
+            per_utxo_witness,
+            per_account_witness,
+            per_multisig_witness,
+        }
+    }
+
+    fn fees_for_size_and_witnesses(&self, size: usize, witnesses: WitnessCount) -> Value {
+        Value(
+            self.per_byte
+                .saturating_mul(size as u64)
+                .saturating_add(self.per_utxo_witness.saturating_mul(witnesses.utxo as u64))
+                .saturating_add(
+                    self.per_account_witness
+                        .saturating_mul(witnesses.account as u64),
+                )
+                .saturating_add(
+                    self.per_multisig_witness
+                        .saturating_mul(witnesses.multisig_signatures as u64),
+                ),
+        )
+    }
+}
+
+impl WitnessCount {
+    pub fn add(&mut self, witness: &Witness) {
+        match witness {
+            Witness::Utxo(_) | Witness::OldUtxo(..) => self.utxo = self.utxo.saturating_add(1),
+            Witness::Account(_) => self.account = self.account.saturating_add(1),
+            Witness::Multisig(msig) => {
+                self.multisig_signatures = self
+                    .multisig_signatures
+                    .saturating_add(msig.signatures() as u32)
+            }
+        }
+    }
+
+    pub fn from_witnesses<I: Iterator<Item = Witness>>(witnesses: I) -> Self {
+        let mut count = Self::default();
+        for witness in witnesses {
+            count.add(&witness);
+        }
+        count
+    }
+}
+
+/// When the dynamic fee rule re-evaluates the fee constant
+#[derive(PartialEq, Eq, PartialOrd, Debug, Clone, Copy)]
+pub enum DynamicFeePeriod {
+    /// once per epoch, from the average utilisation of the previous epoch's blocks
+    Epoch,
+    /// after every block, from the utilisation of that block
+    Block,
+}
+
+/// Rule adjusting the `LinearFee` constant according to how full the blocks
+/// are relative to the maximum block content size.
+///
+/// When the utilisation is above the target the constant increases, when it
+/// is below it decreases, by at most `constant / max_change_denominator` per
+/// period, and always stays within `[min_constant, max_constant]`.
+#[derive(PartialEq, Eq, PartialOrd, Debug, Clone, Copy)]
+pub struct DynamicFeeParams {
+    pub period: DynamicFeePeriod,
+    /// target block utilisation, in percent of the block content max size (1 to 100)
+    pub target_utilisation: u8,
+    pub max_change_denominator: NonZeroU64,
+    pub min_constant: u64,
+    pub max_constant: u64,
+}
+
+impl DynamicFeeParams {
+    /// Compute the next fee constant given the average block content size
+    /// observed during the last period.
+    pub fn adjust_constant(&self, constant: u64, average_size: u64, max_size: u64) -> u64 {
+        let target = (max_size as u128) * (self.target_utilisation as u128) / 100;
+        let average = average_size as u128;
+        let adjusted = if target == 0 || average == target {
+            constant
+        } else {
+            let delta = (constant as u128) * average.abs_diff(target)
+                / target
+                / (self.max_change_denominator.get() as u128);
+            let delta = std::cmp::min(delta, u64::MAX as u128) as u64;
+            if average > target {
+                constant.saturating_add(std::cmp::max(delta, 1))
+            } else {
+                constant.saturating_sub(delta)
+            }
+        };
+        std::cmp::min(
+            std::cmp::max(adjusted, self.min_constant),
+            self.max_constant,
+        )
+    }
+}
+
+/// Running record of the block content sizes since the last dynamic fee adjustment
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct BlockUtilisation {
+    pub blocks: u32,
+    pub content_size: u64,
+}
+
+impl BlockUtilisation {
+    pub fn record(&mut self, content_size: u32) {
+        self.blocks = self.blocks.saturating_add(1);
+        self.content_size = self.content_size.saturating_add(content_size as u64);
+    }
+
+    /// Average content size of the recorded blocks, if any
+    pub fn average(&self) -> Option<u64> {
+        if self.blocks == 0 {
+            None
+        } else {
+            Some(self.content_size / self.blocks as u64)
+        }
+    }
+}
+
 pub trait FeeAlgorithm {
     fn baseline(&self) -> Value;
     fn fees_for_inputs_outputs(&self, inputs: u8, outputs: u8) -> Value;
     fn fees_for_certificate(&self, cert: CertificateSlice) -> Value;
+    fn fees_for_size_and_witnesses(&self, size: usize, witnesses: WitnessCount) -> Value;
 
     fn calculate(&self, cert: Option<CertificateSlice>, inputs: u8, outputs: u8) -> Value {
         self.baseline()
@@ -107,11 +269,27 @@ pub trait FeeAlgorithm {
             .saturating_add(cert.map_or(Value::zero(), |c| self.fees_for_certificate(c)))
     }
 
+    /// Calculate the fee of a transaction whose body is `size` bytes long
+    /// and which carries the given witnesses
+    fn calculate_extended(
+        &self,
+        cert: Option<CertificateSlice>,
+        inputs: u8,
+        outputs: u8,
+        size: usize,
+        witnesses: WitnessCount,
+    ) -> Value {
+        self.calculate(cert, inputs, outputs)
+            .saturating_add(self.fees_for_size_and_witnesses(size, witnesses))
+    }
+
     fn calculate_tx<P: tx::Payload>(&self, tx: &tx::TransactionSlice<P>) -> Value {
-        self.calculate(
+        self.calculate_extended(
             tx.payload().into_certificate_slice(),
             tx.nb_inputs(),
             tx.nb_outputs(),
+            tx.transaction_auth_data().0.len(),
+            WitnessCount::from_witnesses(tx.witnesses().iter()),
         )
     }
 }
@@ -135,6 +313,11 @@ impl FeeAlgorithm for LinearFee {
             .fees_for_certificate(&cert_slice);
         f1.or(f2).unwrap_or(Value(self.certificate))
     }
+
+    fn fees_for_size_and_witnesses(&self, size: usize, witnesses: WitnessCount) -> Value {
+        self.extended_fees
+            .fees_for_size_and_witnesses(size, witnesses)
+    }
 }
 
 #[cfg(any(test, feature = "property-test-api"))]
@@ -166,6 +349,36 @@ mod test {
         }
     }
 
+    impl Arbitrary for ExtendedFee {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            Self::new(
+                Arbitrary::arbitrary(g),
+                Arbitrary::arbitrary(g),
+                Arbitrary::arbitrary(g),
+                Arbitrary::arbitrary(g),
+            )
+        }
+    }
+
+    impl Arbitrary for DynamicFeeParams {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            let bound_a = u64::arbitrary(g);
+            let bound_b = u64::arbitrary(g);
+            Self {
+                period: if bool::arbitrary(g) {
+                    DynamicFeePeriod::Epoch
+                } else {
+                    DynamicFeePeriod::Block
+                },
+                target_utilisation: u8::arbitrary(g) % 100 + 1,
+                max_change_denominator: NonZeroU64::new(u64::arbitrary(g))
+                    .unwrap_or_else(|| NonZeroU64::new(8).unwrap()),
+                min_constant: std::cmp::min(bound_a, bound_b),
+                max_constant: std::cmp::max(bound_a, bound_b),
+            }
+        }
+    }
+
     impl Arbitrary for LinearFee {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             Self {
@@ -174,6 +387,7 @@ mod test {
                 certificate: Arbitrary::arbitrary(g),
                 per_certificate_fees: PerCertificateFee::new(None, None, None),
                 per_vote_certificate_fees: PerVoteCertificateFee::new(None, None),
+                extended_fees: ExtendedFee::default(),
             }
         }
     }
@@ -217,6 +431,33 @@ mod test {
         }
     }
 
+    #[quickcheck]
+    pub fn dynamic_fee_constant_stays_within_bounds(
+        params: DynamicFeeParams,
+        constant: u64,
+        average_size: u32,
+        max_size: u32,
+    ) -> TestResult {
+        let adjusted = params.adjust_constant(constant, average_size as u64, max_size as u64);
+        TestResult::from_bool(adjusted >= params.min_constant && adjusted <= params.max_constant)
+    }
+
+    #[test]
+    pub fn dynamic_fee_constant_follows_utilisation() {
+        let params = DynamicFeeParams {
+            period: DynamicFeePeriod::Block,
+            target_utilisation: 50,
+            max_change_denominator: NonZeroU64::new(8).unwrap(),
+            min_constant: 1,
+            max_constant: 1_000,
+        };
+        assert_eq!(params.adjust_constant(80, 1_000, 1_000), 90);
+        assert_eq!(params.adjust_constant(80, 500, 1_000), 80);
+        assert_eq!(params.adjust_constant(80, 0, 1_000), 70);
+        assert_eq!(params.adjust_constant(0, 1_000, 1_000), 1);
+        assert_eq!(params.adjust_constant(1_000, 1_000, 1_000), 1_000);
+    }
+
     #[cfg(test)]
     fn calculate_expected_cert_fee_value(certificate: &Certificate, fee: &LinearFee) -> u64 {
         let cert_fees = fee.per_certificate_fees;
@@ -1,10 +1,12 @@
-use crate::fragment::Fragment;
+use super::merkle::{merkle_root, MerkleProof};
+use crate::fragment::{Fragment, FragmentId};
 use crate::key::Hash;
 use chain_core::property::Serialize;
 use std::slice;
 
 pub type BlockContentHash = Hash;
 pub type BlockContentSize = u32;
+pub type BlockFragmentsRoot = Hash;
 
 /// Block Contents
 ///
@@ -54,6 +56,24 @@ impl Contents {
     pub fn compute_hash(&self) -> BlockContentHash {
         self.compute_hash_size().0
     }
+
+    /// Computes the Merkle root of the fragment IDs in the content,
+    /// which can be committed in the block header along with the
+    /// content hash.
+    pub fn compute_fragments_root(&self) -> BlockFragmentsRoot {
+        let ids: Vec<FragmentId> = self.iter().map(|fragment| fragment.hash()).collect();
+        merkle_root(&ids)
+    }
+
+    /// Generates a proof of inclusion of the identified fragment,
+    /// verifiable against the fragments root of the content.
+    ///
+    /// Returns `None` if the fragment is not in the content.
+    pub fn merkle_proof(&self, id: &FragmentId) -> Option<MerkleProof> {
+        let ids: Vec<FragmentId> = self.iter().map(|fragment| fragment.hash()).collect();
+        let index = ids.iter().position(|fragment_id| fragment_id == id)?;
+        MerkleProof::generate(&ids, index)
+    }
 }
 
 #[derive(Clone, Default)]
@@ -0,0 +1,236 @@
+//! Merkle tree over the fragment IDs of a block content.
+//!
+//! The root of the tree can be committed in the block header alongside the
+//! content hash, which allows a light client holding only the header to
+//! verify that a fragment is included in the block with a compact proof.
+//!
+//! Leaves and inner nodes are hashed with distinct prefixes. A node without
+//! a sibling on its level is carried over to the next level unchanged,
+//! rather than being paired with a copy of itself.
+
+use super::FragmentId;
+use crate::key::Hash;
+use chain_core::mempack::{ReadBuf, ReadError, Readable};
+use chain_core::property;
+use typed_bytes::{ByteArray, ByteBuilder};
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+// The tree depth is bounded by the bit size of the leaf count.
+const MAX_PATH_LEN: usize = 32;
+
+fn leaf_hash(id: &FragmentId) -> Hash {
+    let mut bytes = [0; 33];
+    bytes[0] = LEAF_PREFIX;
+    bytes[1..].copy_from_slice(id.as_bytes());
+    Hash::hash_bytes(&bytes)
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut bytes = [0; 65];
+    bytes[0] = NODE_PREFIX;
+    bytes[1..33].copy_from_slice(left.as_bytes());
+    bytes[33..].copy_from_slice(right.as_bytes());
+    Hash::hash_bytes(&bytes)
+}
+
+fn next_level(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Computes the Merkle root of the fragment IDs, in the order of
+/// the fragments in the block.
+///
+/// The root of an empty block is the hash of empty content.
+pub fn merkle_root<'a, I>(ids: I) -> Hash
+where
+    I: IntoIterator<Item = &'a FragmentId>,
+{
+    let mut level: Vec<Hash> = ids.into_iter().map(leaf_hash).collect();
+    if level.is_empty() {
+        return Hash::hash_bytes(&[]);
+    }
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Proof of inclusion of a fragment in the block content,
+/// verifiable against the Merkle root of the fragment IDs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    index: u32,
+    leaf_count: u32,
+    path: Vec<Hash>,
+}
+
+impl MerkleProof {
+    /// Generates the proof for the fragment at `index` among `ids`.
+    ///
+    /// Returns `None` if the index is out of range.
+    pub fn generate(ids: &[FragmentId], index: usize) -> Option<Self> {
+        if index >= ids.len() {
+            return None;
+        }
+        let mut level: Vec<Hash> = ids.iter().map(leaf_hash).collect();
+        let mut path = Vec::new();
+        let mut i = index;
+        while level.len() > 1 {
+            let sibling = i ^ 1;
+            if sibling < level.len() {
+                path.push(level[sibling]);
+            }
+            level = next_level(&level);
+            i /= 2;
+        }
+        Some(MerkleProof {
+            index: index as u32,
+            leaf_count: ids.len() as u32,
+            path,
+        })
+    }
+
+    /// Position of the fragment in the block.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Number of fragments in the block.
+    pub fn leaf_count(&self) -> u32 {
+        self.leaf_count
+    }
+
+    /// Computes the Merkle root implied by the proof for the given
+    /// fragment ID. Returns `None` if the proof is malformed.
+    pub fn compute_root(&self, id: &FragmentId) -> Option<Hash> {
+        if self.index >= self.leaf_count {
+            return None;
+        }
+        let mut path = self.path.iter();
+        let mut hash = leaf_hash(id);
+        let mut i = self.index;
+        let mut len = self.leaf_count;
+        while len > 1 {
+            let sibling = i ^ 1;
+            if sibling < len {
+                let sibling_hash = path.next()?;
+                hash = if i & 1 == 0 {
+                    node_hash(&hash, sibling_hash)
+                } else {
+                    node_hash(sibling_hash, &hash)
+                };
+            }
+            i /= 2;
+            len -= len / 2;
+        }
+        if path.next().is_some() {
+            return None;
+        }
+        Some(hash)
+    }
+
+    /// Verifies that the fragment is included in the block content
+    /// with the given Merkle root.
+    pub fn verify(&self, id: &FragmentId, root: &Hash) -> bool {
+        self.compute_root(id).as_ref() == Some(root)
+    }
+
+    pub fn serialize_in(&self, bb: ByteBuilder<Self>) -> ByteBuilder<Self> {
+        bb.u32(self.index)
+            .u32(self.leaf_count)
+            .iter8(&self.path, |bb, hash| bb.bytes(hash.as_bytes()))
+    }
+
+    pub fn serialize(&self) -> ByteArray<Self> {
+        self.serialize_in(ByteBuilder::new()).finalize()
+    }
+}
+
+impl property::Serialize for MerkleProof {
+    type Error = std::io::Error;
+    fn serialize<W: std::io::Write>(&self, mut writer: W) -> Result<(), Self::Error> {
+        writer.write_all(self.serialize().as_slice())?;
+        Ok(())
+    }
+}
+
+impl Readable for MerkleProof {
+    fn read(buf: &mut ReadBuf) -> Result<Self, ReadError> {
+        let index = buf.get_u32()?;
+        let leaf_count = buf.get_u32()?;
+        let path_len = buf.get_u8()? as usize;
+        if path_len > MAX_PATH_LEN {
+            return Err(ReadError::StructureInvalid(
+                "merkle proof path is too long".to_string(),
+            ));
+        }
+        let path = chain_core::mempack::read_vec(buf, path_len)?;
+        Ok(MerkleProof {
+            index,
+            leaf_count,
+            path,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chain_core::mempack::ReadBuf;
+
+    fn ids(n: usize) -> Vec<FragmentId> {
+        (0..n)
+            .map(|i| Hash::hash_bytes(&(i as u32).to_be_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn proofs_verify_for_all_positions() {
+        for n in 1..=17 {
+            let ids = ids(n);
+            let root = merkle_root(&ids);
+            for (index, id) in ids.iter().enumerate() {
+                let proof = MerkleProof::generate(&ids, index).unwrap();
+                assert!(proof.verify(id, &root), "n = {}, index = {}", n, index);
+            }
+            assert!(MerkleProof::generate(&ids, n).is_none());
+        }
+    }
+
+    #[test]
+    fn proof_does_not_verify_other_fragments() {
+        let ids = ids(5);
+        let root = merkle_root(&ids);
+        let proof = MerkleProof::generate(&ids, 2).unwrap();
+        assert!(!proof.verify(&ids[3], &root));
+        assert!(!proof.verify(&Hash::hash_bytes(b"other"), &root));
+    }
+
+    #[test]
+    fn root_depends_on_order() {
+        let mut ids = ids(4);
+        let root = merkle_root(&ids);
+        ids.swap(1, 2);
+        assert_ne!(merkle_root(&ids), root);
+    }
+
+    #[test]
+    fn serialization_roundtrip() {
+        let ids = ids(9);
+        let proof = MerkleProof::generate(&ids, 8).unwrap();
+        let bytes = proof.serialize();
+        let mut buf = ReadBuf::from(bytes.as_slice());
+        let decoded = MerkleProof::read(&mut buf).unwrap();
+        buf.expect_end().unwrap();
+        assert_eq!(decoded, proof);
+    }
+}
@@ -1,15 +1,21 @@
 pub mod config;
 mod content;
+mod merkle;
 mod raw;
+mod slice;
 
 use crate::legacy;
-use chain_core::mempack::{ReadBuf, ReadError, Readable};
+use chain_core::mempack::{ReadBuf, ReadError, Readable, ReadableRef};
 use chain_core::property;
 
 pub use config::ConfigParams;
 pub use raw::{FragmentId, FragmentRaw};
+pub use slice::{FragmentSlice, FragmentView};
 
-pub use content::{BlockContentHash, BlockContentSize, Contents, ContentsBuilder};
+pub use content::{
+    BlockContentHash, BlockContentSize, BlockFragmentsRoot, Contents, ContentsBuilder,
+};
+pub use merkle::{merkle_root, MerkleProof};
 
 use crate::{
     certificate,
@@ -43,6 +49,8 @@ pub enum Fragment {
     VoteCast(Transaction<certificate::VoteCast>),
     VoteTally(Transaction<certificate::VoteTally>),
     EncryptedVoteTally(Transaction<certificate::EncryptedVoteTally>),
+    AccountDeregistration(Transaction<certificate::AccountDeregistration>),
+    MultisigRegistration(Transaction<certificate::MultisigRegistration>),
 }
 
 impl PartialEq for Fragment {
@@ -69,6 +77,8 @@ pub(super) enum FragmentTag {
     VoteCast = 11,
     VoteTally = 12,
     EncryptedVoteTally = 13,
+    AccountDeregistration = 14,
+    MultisigRegistration = 15,
 }
 
 impl FragmentTag {
@@ -88,6 +98,8 @@ impl FragmentTag {
             11 => Some(FragmentTag::VoteCast),
             12 => Some(FragmentTag::VoteTally),
             13 => Some(FragmentTag::EncryptedVoteTally),
+            14 => Some(FragmentTag::AccountDeregistration),
+            15 => Some(FragmentTag::MultisigRegistration),
             _ => None,
         }
     }
@@ -111,6 +123,8 @@ impl Fragment {
             Fragment::VoteCast(_) => FragmentTag::VoteCast,
             Fragment::VoteTally(_) => FragmentTag::VoteTally,
             Fragment::EncryptedVoteTally(_) => FragmentTag::EncryptedVoteTally,
+            Fragment::AccountDeregistration(_) => FragmentTag::AccountDeregistration,
+            Fragment::MultisigRegistration(_) => FragmentTag::MultisigRegistration,
         }
     }
 
@@ -137,13 +151,17 @@ impl Fragment {
             Fragment::VoteCast(vote_plan) => vote_plan.serialize(&mut codec).unwrap(),
             Fragment::VoteTally(vote_tally) => vote_tally.serialize(&mut codec).unwrap(),
             Fragment::EncryptedVoteTally(vote_tally) => vote_tally.serialize(&mut codec).unwrap(),
+            Fragment::AccountDeregistration(dereg) => dereg.serialize(&mut codec).unwrap(),
+            Fragment::MultisigRegistration(reg) => reg.serialize(&mut codec).unwrap(),
         }
         FragmentRaw(codec.into_inner())
     }
 
     pub fn from_raw(raw: &FragmentRaw) -> Result<Self, ReadError> {
         let mut buf = ReadBuf::from(raw.as_ref());
-        Fragment::read(&mut buf)
+        let fragment = Fragment::read(&mut buf)?;
+        buf.expect_end()?;
+        Ok(fragment)
     }
 
     /// The ID of a message is a hash of its serialization *without* the size.
@@ -154,46 +172,7 @@ impl Fragment {
 
 impl Readable for Fragment {
     fn read(buf: &mut ReadBuf) -> Result<Self, ReadError> {
-        let padding_tag = buf.get_u8()?;
-        if padding_tag != 0 {
-            return Err(ReadError::StructureInvalid(format!(
-                "fragment padding tag expected at 0 but got {}",
-                padding_tag
-            )));
-        }
-
-        let tag = buf.get_u8()?;
-        match FragmentTag::from_u8(tag) {
-            Some(FragmentTag::Initial) => ConfigParams::read(buf).map(Fragment::Initial),
-            Some(FragmentTag::OldUtxoDeclaration) => {
-                legacy::UtxoDeclaration::read(buf).map(Fragment::OldUtxoDeclaration)
-            }
-            Some(FragmentTag::Transaction) => Transaction::read(buf).map(Fragment::Transaction),
-            Some(FragmentTag::OwnerStakeDelegation) => {
-                Transaction::read(buf).map(Fragment::OwnerStakeDelegation)
-            }
-            Some(FragmentTag::StakeDelegation) => {
-                Transaction::read(buf).map(Fragment::StakeDelegation)
-            }
-            Some(FragmentTag::PoolRegistration) => {
-                Transaction::read(buf).map(Fragment::PoolRegistration)
-            }
-            Some(FragmentTag::PoolRetirement) => {
-                Transaction::read(buf).map(Fragment::PoolRetirement)
-            }
-            Some(FragmentTag::PoolUpdate) => Transaction::read(buf).map(Fragment::PoolUpdate),
-            Some(FragmentTag::UpdateProposal) => {
-                SignedUpdateProposal::read(buf).map(Fragment::UpdateProposal)
-            }
-            Some(FragmentTag::UpdateVote) => SignedUpdateVote::read(buf).map(Fragment::UpdateVote),
-            Some(FragmentTag::VotePlan) => Transaction::read(buf).map(Fragment::VotePlan),
-            Some(FragmentTag::VoteCast) => Transaction::read(buf).map(Fragment::VoteCast),
-            Some(FragmentTag::VoteTally) => Transaction::read(buf).map(Fragment::VoteTally),
-            Some(FragmentTag::EncryptedVoteTally) => {
-                Transaction::read(buf).map(Fragment::EncryptedVoteTally)
-            }
-            None => Err(ReadError::UnknownTag(tag as u32)),
-        }
+        FragmentSlice::read_ref(buf).map(FragmentSlice::into_owned)
     }
 }
 
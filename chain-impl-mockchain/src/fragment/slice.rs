@@ -0,0 +1,171 @@
+use super::{ConfigParams, Fragment, FragmentId, FragmentTag};
+use crate::{
+    certificate, legacy,
+    transaction::{NoExtra, TransactionSlice},
+    update::{SignedUpdateProposal, SignedUpdateVote},
+};
+use chain_core::mempack::{ReadBuf, ReadError, Readable, ReadableRef};
+use std::fmt;
+
+/// A fragment decoded in place from the data of a buffer.
+///
+/// The transactions borrow their data from the buffer and are only
+/// checked for structure. The other fragments, which are rare, are
+/// decoded as owned values.
+#[derive(Clone)]
+pub struct FragmentSlice<'a> {
+    raw: &'a [u8],
+    view: FragmentView<'a>,
+}
+
+/// The content of a fragment slice
+#[derive(Clone)]
+pub enum FragmentView<'a> {
+    Initial(ConfigParams),
+    OldUtxoDeclaration(legacy::UtxoDeclaration),
+    Transaction(TransactionSlice<'a, NoExtra>),
+    OwnerStakeDelegation(TransactionSlice<'a, certificate::OwnerStakeDelegation>),
+    StakeDelegation(TransactionSlice<'a, certificate::StakeDelegation>),
+    PoolRegistration(TransactionSlice<'a, certificate::PoolRegistration>),
+    PoolRetirement(TransactionSlice<'a, certificate::PoolRetirement>),
+    PoolUpdate(TransactionSlice<'a, certificate::PoolUpdate>),
+    UpdateProposal(SignedUpdateProposal),
+    UpdateVote(SignedUpdateVote),
+    VotePlan(TransactionSlice<'a, certificate::VotePlan>),
+    VoteCast(TransactionSlice<'a, certificate::VoteCast>),
+    VoteTally(TransactionSlice<'a, certificate::VoteTally>),
+    EncryptedVoteTally(TransactionSlice<'a, certificate::EncryptedVoteTally>),
+    AccountDeregistration(TransactionSlice<'a, certificate::AccountDeregistration>),
+    MultisigRegistration(TransactionSlice<'a, certificate::MultisigRegistration>),
+}
+
+impl<'a> FragmentSlice<'a> {
+    /// The ID of the fragment, identical to the ID of the owned fragment
+    pub fn id(&self) -> FragmentId {
+        FragmentId::hash_bytes(self.raw)
+    }
+
+    /// The serialized representation of the fragment, without the size
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    pub fn view(&self) -> &FragmentView<'a> {
+        &self.view
+    }
+
+    pub fn into_view(self) -> FragmentView<'a> {
+        self.view
+    }
+
+    /// Copy the transaction data into an owned fragment
+    pub fn into_owned(self) -> Fragment {
+        self.view.into_owned()
+    }
+}
+
+impl<'a> FragmentView<'a> {
+    /// Copy the transaction data into an owned fragment
+    pub fn into_owned(self) -> Fragment {
+        match self {
+            FragmentView::Initial(i) => Fragment::Initial(i),
+            FragmentView::OldUtxoDeclaration(s) => Fragment::OldUtxoDeclaration(s),
+            FragmentView::Transaction(tx) => Fragment::Transaction(tx.to_owned()),
+            FragmentView::OwnerStakeDelegation(tx) => Fragment::OwnerStakeDelegation(tx.to_owned()),
+            FragmentView::StakeDelegation(tx) => Fragment::StakeDelegation(tx.to_owned()),
+            FragmentView::PoolRegistration(tx) => Fragment::PoolRegistration(tx.to_owned()),
+            FragmentView::PoolRetirement(tx) => Fragment::PoolRetirement(tx.to_owned()),
+            FragmentView::PoolUpdate(tx) => Fragment::PoolUpdate(tx.to_owned()),
+            FragmentView::UpdateProposal(p) => Fragment::UpdateProposal(p),
+            FragmentView::UpdateVote(v) => Fragment::UpdateVote(v),
+            FragmentView::VotePlan(tx) => Fragment::VotePlan(tx.to_owned()),
+            FragmentView::VoteCast(tx) => Fragment::VoteCast(tx.to_owned()),
+            FragmentView::VoteTally(tx) => Fragment::VoteTally(tx.to_owned()),
+            FragmentView::EncryptedVoteTally(tx) => Fragment::EncryptedVoteTally(tx.to_owned()),
+            FragmentView::AccountDeregistration(tx) => {
+                Fragment::AccountDeregistration(tx.to_owned())
+            }
+            FragmentView::MultisigRegistration(tx) => Fragment::MultisigRegistration(tx.to_owned()),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for FragmentSlice<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FragmentSlice")
+            .field("tag", &FragmentTag::from_u8(self.raw[1]))
+            .field("id", &self.id())
+            .finish()
+    }
+}
+
+impl<'a> ReadableRef<'a> for FragmentSlice<'a> {
+    fn read_ref(outer: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        let rest = outer.peek_slice_end();
+        let mut buf = ReadBuf::from(rest);
+
+        let padding_tag = buf.get_u8()?;
+        if padding_tag != 0 {
+            return Err(ReadError::StructureInvalid(format!(
+                "fragment padding tag expected at 0 but got {}",
+                padding_tag
+            )));
+        }
+
+        let tag = buf.get_u8()?;
+        let buf = &mut buf;
+        let view = match FragmentTag::from_u8(tag) {
+            Some(FragmentTag::Initial) => ConfigParams::read(buf).map(FragmentView::Initial),
+            Some(FragmentTag::OldUtxoDeclaration) => {
+                legacy::UtxoDeclaration::read(buf).map(FragmentView::OldUtxoDeclaration)
+            }
+            Some(FragmentTag::Transaction) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::Transaction)
+            }
+            Some(FragmentTag::OwnerStakeDelegation) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::OwnerStakeDelegation)
+            }
+            Some(FragmentTag::StakeDelegation) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::StakeDelegation)
+            }
+            Some(FragmentTag::PoolRegistration) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::PoolRegistration)
+            }
+            Some(FragmentTag::PoolRetirement) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::PoolRetirement)
+            }
+            Some(FragmentTag::PoolUpdate) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::PoolUpdate)
+            }
+            Some(FragmentTag::UpdateProposal) => {
+                SignedUpdateProposal::read(buf).map(FragmentView::UpdateProposal)
+            }
+            Some(FragmentTag::UpdateVote) => {
+                SignedUpdateVote::read(buf).map(FragmentView::UpdateVote)
+            }
+            Some(FragmentTag::VotePlan) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::VotePlan)
+            }
+            Some(FragmentTag::VoteCast) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::VoteCast)
+            }
+            Some(FragmentTag::VoteTally) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::VoteTally)
+            }
+            Some(FragmentTag::EncryptedVoteTally) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::EncryptedVoteTally)
+            }
+            Some(FragmentTag::AccountDeregistration) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::AccountDeregistration)
+            }
+            Some(FragmentTag::MultisigRegistration) => {
+                TransactionSlice::read_ref(buf).map(FragmentView::MultisigRegistration)
+            }
+            None => Err(ReadError::UnknownTag(tag as u32)),
+        }?;
+        // the slice only spans the bytes of the fragment, anything after it
+        // is left in the buffer for the caller
+        let raw = outer.get_slice(buf.position())?;
+        Ok(FragmentSlice { raw, view })
+    }
+}
@@ -7,7 +7,7 @@ use quickcheck_macros::quickcheck;
 
 impl Arbitrary for Fragment {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
-        match g.next_u32() % 14 {
+        match g.next_u32() % 16 {
             0 => Fragment::Initial(Arbitrary::arbitrary(g)),
             1 => Fragment::OldUtxoDeclaration(Arbitrary::arbitrary(g)),
             2 => Fragment::Transaction(Arbitrary::arbitrary(g)),
@@ -22,6 +22,8 @@ impl Arbitrary for Fragment {
             11 => Fragment::VoteCast(Arbitrary::arbitrary(g)),
             12 => Fragment::VoteTally(Arbitrary::arbitrary(g)),
             13 => Fragment::EncryptedVoteTally(Arbitrary::arbitrary(g)),
+            14 => Fragment::AccountDeregistration(Arbitrary::arbitrary(g)),
+            15 => Fragment::MultisigRegistration(Arbitrary::arbitrary(g)),
             _ => unreachable!(),
         }
     }
@@ -33,6 +35,35 @@ fn fragment_serialization_bijection(b: Fragment) -> TestResult {
     TestResult::from_bool(b == b_got)
 }
 
+#[quickcheck]
+fn fragment_slice_matches_owned(b: Fragment) -> TestResult {
+    use chain_core::mempack::read_ref_from_raw;
+    let raw = b.to_raw();
+    let slice: FragmentSlice = read_ref_from_raw(raw.as_ref()).unwrap();
+    if slice.id() != b.hash() || slice.as_slice() != raw.as_ref() {
+        return TestResult::error("fragment slice does not match the raw fragment");
+    }
+    TestResult::from_bool(slice.into_owned() == b)
+}
+
+#[quickcheck]
+fn fragment_slice_rejects_trailing_bytes(b: Fragment) -> TestResult {
+    use chain_core::mempack::read_ref_from_raw;
+    let mut raw = b.to_raw().as_ref().to_vec();
+    raw.push(0);
+    TestResult::from_bool(read_ref_from_raw::<FragmentSlice>(&raw).is_err())
+}
+
+#[quickcheck]
+fn fragment_read_leaves_trailing_bytes(config_params: ConfigParams) -> TestResult {
+    let fragment = Fragment::Initial(config_params);
+    let mut raw = fragment.to_raw().as_ref().to_vec();
+    raw.push(0);
+    let mut buf = ReadBuf::from(&raw);
+    let got = Fragment::read(&mut buf).unwrap();
+    TestResult::from_bool(got == fragment && buf.get_u8().unwrap() == 0 && buf.is_end())
+}
+
 quickcheck! {
     fn initial_ents_serialization_bijection(config_params: ConfigParams) -> TestResult {
         chain_test_utils::property::serialization_bijection_r(config_params)
@@ -8,7 +8,7 @@ use crate::{
     certificate::PoolId,
     chaintypes::{ChainLength, HeaderId},
     date::BlockDate,
-    fragment::{BlockContentHash, BlockContentSize, Contents},
+    fragment::{BlockContentHash, BlockContentSize, BlockFragmentsRoot, Contents},
     key::BftLeaderId,
 };
 
@@ -82,6 +82,17 @@ impl HeaderBuilderNew {
 }
 
 impl HeaderBuilder<HeaderSetParenting> {
+    /// Commit the Merkle root of the fragment IDs of the content
+    /// in the header, against which the proofs of inclusion of
+    /// fragments in the block can be verified.
+    ///
+    /// The root is typically computed with `Contents::compute_fragments_root`.
+    pub fn set_fragments_root(self, fragments_root: &BlockFragmentsRoot) -> Self {
+        let mut hdr = self.0;
+        hdr.set_fragments_root(fragments_root.into());
+        HeaderBuilder(hdr, PhantomData)
+    }
+
     /// Set the header as a genesis header:
     /// * the depth starts at 0
     /// * the parent is set to the "null hash" (hash all 0)
@@ -119,7 +130,7 @@ impl HeaderBuilder<HeaderSetDate> {
 impl HeaderBuilder<HeaderCommonDone> {
     /// Finalized to an unsigned header
     pub fn into_unsigned_header(self) -> Option<HeaderUnsigned> {
-        match self.0.version() {
+        match self.0.consensus_version() {
             cstruct::VERSION_UNSIGNED => Some(HeaderUnsigned(self.0)),
             _ => None,
         }
@@ -127,7 +138,7 @@ impl HeaderBuilder<HeaderCommonDone> {
 
     /// Tentatively transition to a BFT Header builder
     pub fn into_bft_builder(self) -> Option<HeaderBftBuilder<HeaderSetConsensusData>> {
-        match self.0.version() {
+        match self.0.consensus_version() {
             cstruct::VERSION_BFT => Some(HeaderBftBuilder(self.0, PhantomData)),
             _ => None,
         }
@@ -137,7 +148,7 @@ impl HeaderBuilder<HeaderCommonDone> {
     pub fn into_genesis_praos_builder(
         self,
     ) -> Option<HeaderGenesisPraosBuilder<HeaderSetConsensusData>> {
-        match self.0.version() {
+        match self.0.consensus_version() {
             cstruct::VERSION_GP => Some(HeaderGenesisPraosBuilder(self.0, PhantomData)),
             _ => None,
         }
@@ -14,6 +14,7 @@ pub(super) type DateSlotid = u32;
 pub(super) type Height = u32;
 pub(super) type ContentHash = [u8; 32];
 pub(super) type ParentHash = [u8; 32];
+pub(super) type FragmentsRoot = [u8; 32];
 
 pub(super) type BftLeaderId = [u8; 32];
 pub(super) type BftSignature = [u8; 64];
@@ -33,6 +34,13 @@ const HEADER_OFFSET_PARENT_HASH: usize = HEADER_OFFSET_CONTENT_HASH + size_of::<
 
 pub const HEADER_COMMON_SIZE: usize = HEADER_OFFSET_PARENT_HASH + size_of::<ParentHash>();
 
+// extension of the common parts, present when the version has
+// VERSION_FLAG_FRAGMENTS_ROOT set. The offsets of the consensus
+// parts below are shifted by the size of the extension.
+const HEADER_OFFSET_FRAGMENTS_ROOT: usize = HEADER_COMMON_SIZE;
+
+pub const HEADER_FRAGMENTS_ROOT_SIZE: usize = size_of::<FragmentsRoot>();
+
 // BFT
 const HEADER_OFFSET_BFT_LEADER_ID: usize = HEADER_COMMON_SIZE;
 const HEADER_OFFSET_BFT_SIGNATURE: usize = HEADER_OFFSET_BFT_LEADER_ID + size_of::<BftLeaderId>();
@@ -51,33 +59,31 @@ pub const HEADER_GP_SIZE: usize = HEADER_OFFSET_GP_KES_SIG + size_of::<GpKesSign
 pub const HEADER_GP_AUTHED_SIZE: usize = HEADER_OFFSET_GP_KES_SIG;
 
 pub const HEADER_MIN_KNOWN_SIZE: usize = HEADER_COMMON_SIZE;
-pub const HEADER_MAX_KNOWN_SIZE: usize = HEADER_GP_SIZE;
+pub const HEADER_MAX_KNOWN_SIZE: usize = HEADER_GP_SIZE + HEADER_FRAGMENTS_ROOT_SIZE;
 
 // ************************************************************************
 // Header union construction & accessors
 // ************************************************************************
 
 pub(super) type HeaderUnsigned = [u8; HEADER_COMMON_SIZE];
-pub(super) type HeaderBFT = [u8; HEADER_BFT_SIZE];
-pub(super) type HeaderGP = [u8; HEADER_GP_SIZE];
+pub(super) type HeaderMax = [u8; HEADER_MAX_KNOWN_SIZE];
 
 pub(super) union Header {
     unsigned: HeaderUnsigned,
-    bft: HeaderBFT,
-    gp: HeaderGP,
+    max: HeaderMax,
 }
 
 impl Clone for Header {
     fn clone(&self) -> Self {
-        let mut gp = [0u8; HEADER_GP_SIZE];
-        gp[..].copy_from_slice(unsafe { &self.gp[..] });
-        Header { gp }
+        let mut max = [0u8; HEADER_MAX_KNOWN_SIZE];
+        max[..].copy_from_slice(unsafe { &self.max[..] });
+        Header { max }
     }
 }
 
 impl PartialEq for Header {
     fn eq(&self, other: &Self) -> bool {
-        unsafe { self.gp[..] == other.gp[..] }
+        unsafe { self.max[..] == other.max[..] }
     }
 }
 impl Eq for Header {}
@@ -86,6 +92,33 @@ pub(super) const VERSION_UNSIGNED: Version = 0;
 pub(super) const VERSION_BFT: Version = 1;
 pub(super) const VERSION_GP: Version = 2;
 
+// set in the version of the headers extending the common parts with
+// the Merkle root of the fragment IDs of the content
+pub(super) const VERSION_FLAG_FRAGMENTS_ROOT: Version = 0x8000;
+
+fn consensus_version(version: Version) -> Version {
+    version & !VERSION_FLAG_FRAGMENTS_ROOT
+}
+
+fn extension_size(version: Version) -> usize {
+    if version & VERSION_FLAG_FRAGMENTS_ROOT != 0 {
+        HEADER_FRAGMENTS_ROOT_SIZE
+    } else {
+        0
+    }
+}
+
+fn header_size(version: Version) -> Option<usize> {
+    let size = match consensus_version(version) {
+        VERSION_UNSIGNED => HEADER_COMMON_SIZE,
+        VERSION_BFT => HEADER_BFT_SIZE,
+        VERSION_GP => HEADER_GP_SIZE,
+        _ => return None,
+    };
+    Some(size + extension_size(version))
+}
+
+#[derive(Clone, Copy)]
 pub struct HeaderSlice<'a>(&'a [u8]);
 
 impl Header {
@@ -96,27 +129,29 @@ impl Header {
         Version::from_be_bytes(buf)
     }
 
+    pub fn consensus_version(&self) -> Version {
+        consensus_version(self.version())
+    }
+
+    fn extension_size(&self) -> usize {
+        extension_size(self.version())
+    }
+
     pub fn as_slice(&self) -> HeaderSlice<'_> {
-        match self.version() {
-            VERSION_UNSIGNED => unsafe { HeaderSlice(&self.unsigned[..]) },
-            VERSION_BFT => unsafe { HeaderSlice(&self.bft[..]) },
-            VERSION_GP => unsafe { HeaderSlice(&self.gp[..]) },
-            _ => panic!("Header: cstruct: as slice with undefined version"),
-        }
+        let size =
+            header_size(self.version()).expect("Header: cstruct: as slice with undefined version");
+        unsafe { HeaderSlice(&self.max[..size]) }
     }
 
     pub(self) fn as_slice_mut(&mut self) -> &mut [u8] {
-        match self.version() {
-            VERSION_UNSIGNED => unsafe { &mut self.unsigned[..] },
-            VERSION_BFT => unsafe { &mut self.bft[..] },
-            VERSION_GP => unsafe { &mut self.gp[..] },
-            _ => panic!("Header: cstruct: as slice mut with undefined version"),
-        }
+        let size = header_size(self.version())
+            .expect("Header: cstruct: as slice mut with undefined version");
+        unsafe { &mut self.max[..size] }
     }
 
     pub fn new(version: Version) -> Header {
-        let gp = [0u8; HEADER_GP_SIZE];
-        let mut hdr = Header { gp };
+        let max = [0u8; HEADER_MAX_KNOWN_SIZE];
+        let mut hdr = Header { max };
         hdr.set_version(version);
         hdr
     }
@@ -174,71 +209,110 @@ impl Header {
         }
     }
 
+    /// Set the fragments root, extending the common parts.
+    ///
+    /// This moves the consensus parts, so it must be set before them.
+    pub fn set_fragments_root(&mut self, s: &FragmentsRoot) {
+        self.set_version(self.version() | VERSION_FLAG_FRAGMENTS_ROOT);
+        unsafe {
+            self.max[HEADER_OFFSET_FRAGMENTS_ROOT
+                ..HEADER_OFFSET_FRAGMENTS_ROOT + HEADER_FRAGMENTS_ROOT_SIZE]
+                .copy_from_slice(&s[..])
+        }
+    }
+
     #[allow(dead_code)]
     pub fn set_bft_leader_id(&mut self, s: &BftLeaderId) {
-        assert_eq!(self.version(), VERSION_BFT);
+        assert_eq!(self.consensus_version(), VERSION_BFT);
+        let ext = self.extension_size();
         unsafe {
-            self.bft[HEADER_OFFSET_BFT_LEADER_ID..HEADER_OFFSET_BFT_SIGNATURE]
+            self.max[ext + HEADER_OFFSET_BFT_LEADER_ID..ext + HEADER_OFFSET_BFT_SIGNATURE]
                 .copy_from_slice(&s[..])
         }
     }
 
     pub fn set_bft_leader_id_slice(&mut self, s: &[u8]) {
-        assert_eq!(self.version(), VERSION_BFT);
+        assert_eq!(self.consensus_version(), VERSION_BFT);
+        let ext = self.extension_size();
         assert_eq!(s.len(), size_of::<BftLeaderId>());
         unsafe {
-            self.bft[HEADER_OFFSET_BFT_LEADER_ID..HEADER_OFFSET_BFT_SIGNATURE].copy_from_slice(s)
+            self.max[ext + HEADER_OFFSET_BFT_LEADER_ID..ext + HEADER_OFFSET_BFT_SIGNATURE]
+                .copy_from_slice(s)
         }
     }
 
     #[allow(dead_code)]
     pub fn set_bft_signature(&mut self, s: &BftSignature) {
-        assert_eq!(self.version(), VERSION_BFT);
-        unsafe { self.bft[HEADER_OFFSET_BFT_SIGNATURE..HEADER_BFT_SIZE].copy_from_slice(&s[..]) }
+        assert_eq!(self.consensus_version(), VERSION_BFT);
+        let ext = self.extension_size();
+        unsafe {
+            self.max[ext + HEADER_OFFSET_BFT_SIGNATURE..ext + HEADER_BFT_SIZE]
+                .copy_from_slice(&s[..])
+        }
     }
 
     pub fn set_bft_signature_slice(&mut self, s: &[u8]) {
-        assert_eq!(self.version(), VERSION_BFT);
+        assert_eq!(self.consensus_version(), VERSION_BFT);
+        let ext = self.extension_size();
         assert_eq!(s.len(), size_of::<BftSignature>());
-        unsafe { self.bft[HEADER_OFFSET_BFT_SIGNATURE..HEADER_BFT_SIZE].copy_from_slice(s) }
+        unsafe {
+            self.max[ext + HEADER_OFFSET_BFT_SIGNATURE..ext + HEADER_BFT_SIZE].copy_from_slice(s)
+        }
     }
 
     pub fn set_gp_node_id(&mut self, s: &GpNodeId) {
-        assert_eq!(self.version(), VERSION_GP);
-        unsafe { self.gp[HEADER_OFFSET_GP_ID..HEADER_OFFSET_GP_VRF_PROOF].copy_from_slice(&s[..]) }
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
+        unsafe {
+            self.max[ext + HEADER_OFFSET_GP_ID..ext + HEADER_OFFSET_GP_VRF_PROOF]
+                .copy_from_slice(&s[..])
+        }
     }
 
     #[allow(dead_code)]
     pub fn set_gp_node_id_slice(&mut self, s: &[u8]) {
-        assert_eq!(self.version(), VERSION_GP);
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
         assert_eq!(s.len(), size_of::<GpNodeId>());
-        unsafe { self.gp[HEADER_OFFSET_GP_ID..HEADER_OFFSET_GP_VRF_PROOF].copy_from_slice(s) }
+        unsafe {
+            self.max[ext + HEADER_OFFSET_GP_ID..ext + HEADER_OFFSET_GP_VRF_PROOF].copy_from_slice(s)
+        }
     }
 
     pub fn set_gp_vrf_proof(&mut self, s: &GpVrfProof) {
-        assert_eq!(self.version(), VERSION_GP);
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
         unsafe {
-            self.gp[HEADER_OFFSET_GP_VRF_PROOF..HEADER_OFFSET_GP_KES_SIG].copy_from_slice(&s[..])
+            self.max[ext + HEADER_OFFSET_GP_VRF_PROOF..ext + HEADER_OFFSET_GP_KES_SIG]
+                .copy_from_slice(&s[..])
         }
     }
 
     #[allow(dead_code)]
     pub fn set_gp_vrf_proof_slice(&mut self, s: &[u8]) {
-        assert_eq!(self.version(), VERSION_GP);
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
         assert_eq!(s.len(), size_of::<GpVrfProof>());
-        unsafe { self.gp[HEADER_OFFSET_GP_VRF_PROOF..HEADER_OFFSET_GP_KES_SIG].copy_from_slice(s) }
+        unsafe {
+            self.max[ext + HEADER_OFFSET_GP_VRF_PROOF..ext + HEADER_OFFSET_GP_KES_SIG]
+                .copy_from_slice(s)
+        }
     }
 
     #[allow(dead_code)]
     pub fn set_gp_kes_signature(&mut self, s: &GpKesSignature) {
-        assert_eq!(self.version(), VERSION_GP);
-        unsafe { self.gp[HEADER_OFFSET_GP_KES_SIG..HEADER_GP_SIZE].copy_from_slice(&s[..]) }
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
+        unsafe {
+            self.max[ext + HEADER_OFFSET_GP_KES_SIG..ext + HEADER_GP_SIZE].copy_from_slice(&s[..])
+        }
     }
 
     pub fn set_gp_kes_signature_slice(&mut self, s: &[u8]) {
-        assert_eq!(self.version(), VERSION_GP);
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
         assert_eq!(s.len(), size_of::<GpKesSignature>());
-        unsafe { self.gp[HEADER_OFFSET_GP_KES_SIG..HEADER_GP_SIZE].copy_from_slice(s) }
+        unsafe { self.max[ext + HEADER_OFFSET_GP_KES_SIG..ext + HEADER_GP_SIZE].copy_from_slice(s) }
     }
 }
 
@@ -263,35 +337,12 @@ impl<'a> HeaderSlice<'a> {
         }
 
         let hdr = HeaderSlice(slice);
-        match hdr.version() {
-            VERSION_UNSIGNED => {
-                if len != HEADER_COMMON_SIZE {
-                    return Err(HeaderError::SizeMismatch {
-                        expected: HEADER_COMMON_SIZE,
-                        got: len,
-                    });
-                }
-                Ok(hdr)
+        match header_size(hdr.version()) {
+            Some(expected) if len != expected => {
+                Err(HeaderError::SizeMismatch { expected, got: len })
             }
-            VERSION_BFT => {
-                if len != HEADER_BFT_SIZE {
-                    return Err(HeaderError::SizeMismatch {
-                        expected: HEADER_BFT_SIZE,
-                        got: len,
-                    });
-                }
-                Ok(hdr)
-            }
-            VERSION_GP => {
-                if len != HEADER_GP_SIZE {
-                    return Err(HeaderError::SizeMismatch {
-                        expected: HEADER_GP_SIZE,
-                        got: len,
-                    });
-                }
-                Ok(hdr)
-            }
-            _ => Err(HeaderError::UnknownVersion),
+            Some(_) => Ok(hdr),
+            None => Err(HeaderError::UnknownVersion),
         }
     }
 
@@ -311,6 +362,14 @@ impl<'a> HeaderSlice<'a> {
         Version::from_be_bytes(buf)
     }
 
+    pub fn consensus_version(&self) -> Version {
+        consensus_version(self.version())
+    }
+
+    fn extension_size(&self) -> usize {
+        extension_size(self.version())
+    }
+
     pub fn content_size(&self) -> ContentSize {
         let mut buf = [0u8; size_of::<ContentSize>()];
         buf.copy_from_slice(&self.0[HEADER_OFFSET_CONTENT_SIZE..HEADER_OFFSET_DATE_EPOCH]);
@@ -355,9 +414,28 @@ impl<'a> HeaderSlice<'a> {
         buf
     }
 
+    pub fn fragments_root_ref(&self) -> Option<&[u8]> {
+        if self.extension_size() == 0 {
+            return None;
+        }
+        Some(
+            &self.0[HEADER_OFFSET_FRAGMENTS_ROOT
+                ..HEADER_OFFSET_FRAGMENTS_ROOT + HEADER_FRAGMENTS_ROOT_SIZE],
+        )
+    }
+
+    pub fn fragments_root(&self) -> Option<FragmentsRoot> {
+        self.fragments_root_ref().map(|s| {
+            let mut buf = [0u8; size_of::<FragmentsRoot>()];
+            buf.copy_from_slice(s);
+            buf
+        })
+    }
+
     pub fn bft_leader_id_ref(&self) -> &[u8] {
-        assert_eq!(self.version(), VERSION_BFT);
-        &self.0[HEADER_OFFSET_BFT_LEADER_ID..HEADER_OFFSET_BFT_SIGNATURE]
+        assert_eq!(self.consensus_version(), VERSION_BFT);
+        let ext = self.extension_size();
+        &self.0[ext + HEADER_OFFSET_BFT_LEADER_ID..ext + HEADER_OFFSET_BFT_SIGNATURE]
     }
 
     pub fn bft_leader_id(&self) -> BftLeaderId {
@@ -367,8 +445,9 @@ impl<'a> HeaderSlice<'a> {
     }
 
     pub fn bft_signature_ref(&self) -> &[u8] {
-        assert_eq!(self.version(), VERSION_BFT);
-        &self.0[HEADER_OFFSET_BFT_SIGNATURE..HEADER_BFT_SIZE]
+        assert_eq!(self.consensus_version(), VERSION_BFT);
+        let ext = self.extension_size();
+        &self.0[ext + HEADER_OFFSET_BFT_SIGNATURE..ext + HEADER_BFT_SIZE]
     }
 
     pub fn bft_signature(&self) -> BftSignature {
@@ -378,8 +457,9 @@ impl<'a> HeaderSlice<'a> {
     }
 
     pub fn gp_node_id_ref(&self) -> &[u8] {
-        assert_eq!(self.version(), VERSION_GP);
-        &self.0[HEADER_OFFSET_GP_ID..HEADER_OFFSET_GP_VRF_PROOF]
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
+        &self.0[ext + HEADER_OFFSET_GP_ID..ext + HEADER_OFFSET_GP_VRF_PROOF]
     }
 
     pub fn gp_node_id(&self) -> GpNodeId {
@@ -389,8 +469,9 @@ impl<'a> HeaderSlice<'a> {
     }
 
     pub fn gp_vrf_proof_ref(&self) -> &[u8] {
-        assert_eq!(self.version(), VERSION_GP);
-        &self.0[HEADER_OFFSET_GP_VRF_PROOF..HEADER_OFFSET_GP_KES_SIG]
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
+        &self.0[ext + HEADER_OFFSET_GP_VRF_PROOF..ext + HEADER_OFFSET_GP_KES_SIG]
     }
 
     pub fn gp_vrf_proof(&self) -> GpVrfProof {
@@ -400,8 +481,9 @@ impl<'a> HeaderSlice<'a> {
     }
 
     pub fn gp_kes_signature_ref(&self) -> &[u8] {
-        assert_eq!(self.version(), VERSION_GP);
-        &self.0[HEADER_OFFSET_GP_KES_SIG..HEADER_GP_SIZE]
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
+        &self.0[ext + HEADER_OFFSET_GP_KES_SIG..ext + HEADER_GP_SIZE]
     }
 
     pub fn gp_kes_signature(&self) -> GpKesSignature {
@@ -411,13 +493,15 @@ impl<'a> HeaderSlice<'a> {
     }
 
     pub fn slice_bft_auth(self) -> &'a [u8] {
-        assert_eq!(self.version(), VERSION_BFT);
-        &self.0[0..HEADER_BFT_AUTHED_SIZE]
+        assert_eq!(self.consensus_version(), VERSION_BFT);
+        let ext = self.extension_size();
+        &self.0[0..ext + HEADER_BFT_AUTHED_SIZE]
     }
 
     pub fn slice_gp_auth(self) -> &'a [u8] {
-        assert_eq!(self.version(), VERSION_GP);
-        &self.0[0..HEADER_GP_AUTHED_SIZE]
+        assert_eq!(self.consensus_version(), VERSION_GP);
+        let ext = self.extension_size();
+        &self.0[0..ext + HEADER_GP_AUTHED_SIZE]
     }
 }
 
@@ -446,6 +530,28 @@ mod tests {
         header.set_gp_kes_signature(&gp_kes_signature);
     }
 
+    #[test]
+    pub fn header_fragments_root_extension() {
+        let mut header = Header::new(VERSION_BFT);
+        let fragments_root = [1; 32];
+        header.set_fragments_root(&fragments_root);
+        let bft_leader_id = [2; 32];
+        header.set_bft_leader_id(&bft_leader_id);
+
+        let slice = HeaderSlice::from_slice(header.as_slice().as_slice()).unwrap();
+        assert_eq!(
+            slice.as_slice().len(),
+            HEADER_BFT_SIZE + HEADER_FRAGMENTS_ROOT_SIZE
+        );
+        assert_eq!(slice.consensus_version(), VERSION_BFT);
+        assert_eq!(slice.fragments_root(), Some(fragments_root));
+        assert_eq!(slice.bft_leader_id(), bft_leader_id);
+        assert_eq!(
+            slice.slice_bft_auth().len(),
+            HEADER_BFT_AUTHED_SIZE + HEADER_FRAGMENTS_ROOT_SIZE
+        );
+    }
+
     #[test]
     pub fn header_slice_from_slice_below_min_known_size() {
         assert_eq!(
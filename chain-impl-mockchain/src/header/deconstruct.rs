@@ -3,7 +3,7 @@ use super::version::BlockVersion;
 use crate::certificate::PoolId;
 use crate::chaintypes::{ChainLength, HeaderId};
 use crate::date::BlockDate;
-use crate::fragment::{BlockContentHash, BlockContentSize};
+use crate::fragment::{BlockContentHash, BlockContentSize, BlockFragmentsRoot};
 use crate::key::BftLeaderId;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -12,6 +12,7 @@ pub struct Common {
     pub block_date: BlockDate,
     pub block_content_size: BlockContentSize,
     pub block_content_hash: BlockContentHash,
+    pub block_fragments_root: Option<BlockFragmentsRoot>,
     pub block_parent_hash: HeaderId,
     pub chain_length: ChainLength,
 }
@@ -7,7 +7,7 @@ use crate::certificate::PoolId;
 use crate::chaineval::{HeaderContentEvalContext, HeaderGPContentEvalContext};
 use crate::chaintypes::{ChainLength, HeaderId};
 use crate::date::BlockDate;
-use crate::fragment::{BlockContentHash, BlockContentSize};
+use crate::fragment::{BlockContentHash, BlockContentSize, BlockFragmentsRoot};
 use crate::key::BftLeaderId;
 use crate::leadership;
 
@@ -35,6 +35,10 @@ pub enum Header {
     BFT(HeaderBft),
 }
 
+/// Header borrowing the data it is decoded from
+#[derive(Clone, Copy)]
+pub struct HeaderSlice<'a>(cstruct::HeaderSlice<'a>);
+
 impl HeaderUnsigned {
     pub fn id(&self) -> HeaderId {
         HeaderId::hash_bytes(self.0.as_slice().as_slice())
@@ -105,7 +109,7 @@ impl Header {
     }
 
     pub fn size(&self) -> NonZeroUsize {
-        self.block_version().get_size()
+        NonZeroUsize::new(self.as_slice().len()).expect("header is never empty")
     }
 
     // deprecated: use .id()
@@ -169,6 +173,16 @@ impl Header {
         self.get_cstruct().content_size()
     }
 
+    /// The Merkle root of the IDs of the fragments in the block,
+    /// if the header commits to it.
+    ///
+    /// Proofs of inclusion of fragments in the block are verified
+    /// against this root.
+    #[inline]
+    pub fn fragments_root(&self) -> Option<BlockFragmentsRoot> {
+        self.get_cstruct().fragments_root().map(Into::into)
+    }
+
     #[inline]
     pub fn block_parent_hash(&self) -> HeaderId {
         self.get_cstruct().parent_hash().into()
@@ -180,13 +194,12 @@ impl Header {
     }
 
     pub fn from_slice(slice: &[u8]) -> Result<Header, HeaderError> {
-        let hdr_slice = cstruct::HeaderSlice::from_slice(slice)?;
-        let hdr = hdr_slice.to_owned();
-        match BlockVersion::from_u16(hdr.version()).expect("header slice only know version") {
-            BlockVersion::Genesis => Ok(Header::Unsigned(HeaderUnsigned(hdr))),
-            BlockVersion::Ed25519Signed => Ok(Header::BFT(HeaderBft(hdr))),
-            BlockVersion::KesVrfproof => Ok(Header::GenesisPraos(HeaderGenesisPraos(hdr))),
-        }
+        HeaderSlice::from_slice(slice).map(HeaderSlice::to_owned)
+    }
+
+    /// Borrow the header data as a header slice
+    pub fn to_header_slice(&self) -> HeaderSlice<'_> {
+        HeaderSlice(self.get_cstruct())
     }
 
     pub fn to_raw(&self) -> Box<[u8]> {
@@ -201,6 +214,7 @@ impl Header {
             block_date: self.block_date(),
             block_content_size: self.block_content_size(),
             block_content_hash: self.block_content_hash(),
+            block_fragments_root: self.fragments_root(),
             block_parent_hash: self.block_parent_hash(),
             chain_length: self.chain_length(),
         }
@@ -256,11 +270,93 @@ impl Header {
             block_date: self.block_date(),
             chain_length: self.chain_length(),
             content_hash: self.block_content_hash(),
+            fragments_root: self.fragments_root(),
             gp_content,
         }
     }
 }
 
+impl<'a> HeaderSlice<'a> {
+    pub fn from_slice(slice: &'a [u8]) -> Result<Self, HeaderError> {
+        cstruct::HeaderSlice::from_slice(slice).map(HeaderSlice)
+    }
+
+    pub fn id(&self) -> HeaderId {
+        HeaderId::hash_bytes(self.0.as_slice())
+    }
+
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.0.as_slice()
+    }
+
+    pub fn as_auth_slice(&self) -> &'a [u8] {
+        match self.block_version() {
+            BlockVersion::Genesis => self.0.as_slice(),
+            BlockVersion::Ed25519Signed => self.0.slice_bft_auth(),
+            BlockVersion::KesVrfproof => self.0.slice_gp_auth(),
+        }
+    }
+
+    #[inline]
+    pub fn block_version(&self) -> BlockVersion {
+        BlockVersion::from_u16(self.0.consensus_version()).expect("header slice only know version")
+    }
+
+    #[inline]
+    pub fn block_date(&self) -> BlockDate {
+        BlockDate {
+            epoch: self.0.date_epoch(),
+            slot_id: self.0.date_slotid(),
+        }
+    }
+
+    #[inline]
+    pub fn block_content_hash(&self) -> BlockContentHash {
+        self.0.content_hash().into()
+    }
+
+    #[inline]
+    pub fn block_content_size(&self) -> BlockContentSize {
+        self.0.content_size()
+    }
+
+    #[inline]
+    pub fn fragments_root(&self) -> Option<BlockFragmentsRoot> {
+        self.0.fragments_root().map(Into::into)
+    }
+
+    #[inline]
+    pub fn block_parent_hash(&self) -> HeaderId {
+        self.0.parent_hash().into()
+    }
+
+    #[inline]
+    pub fn chain_length(&self) -> ChainLength {
+        self.0.height().into()
+    }
+
+    /// Copy the header data into an owned header
+    pub fn to_owned(self) -> Header {
+        let hdr = self.0.to_owned();
+        match self.block_version() {
+            BlockVersion::Genesis => Header::Unsigned(HeaderUnsigned(hdr)),
+            BlockVersion::Ed25519Signed => Header::BFT(HeaderBft(hdr)),
+            BlockVersion::KesVrfproof => Header::GenesisPraos(HeaderGenesisPraos(hdr)),
+        }
+    }
+}
+
+impl<'a> Debug for HeaderSlice<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HeaderSlice")
+            .field("version", &self.block_version())
+            .field("date", &self.block_date())
+            .field("height", &self.chain_length())
+            .field("self_hash", &self.id())
+            .finish()
+    }
+}
+
 impl Debug for Header {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let hs = self.get_cstruct();
@@ -271,7 +367,8 @@ impl Debug for Header {
             .field("date", &self.block_date())
             .field("height", &self.chain_length())
             .field("content_hash", &hs.content_hash_ref())
-            .field("parent_hash", &hs.parent_hash_ref());
+            .field("parent_hash", &hs.parent_hash_ref())
+            .field("fragments_root", &hs.fragments_root_ref());
         let r = match self {
             Header::Unsigned(_) => r,
             Header::BFT(_) => r
@@ -287,10 +384,18 @@ impl Debug for Header {
 }
 
 use chain_core::{
-    mempack::{ReadBuf, ReadError, Readable},
+    mempack::{ReadBuf, ReadError, Readable, ReadableRef},
     property,
 };
 
+fn header_read_error(e: HeaderError) -> ReadError {
+    match e {
+        HeaderError::InvalidSize => ReadError::NotEnoughBytes(0, 0),
+        HeaderError::UnknownVersion => ReadError::UnknownTag(0),
+        HeaderError::SizeMismatch { expected, got } => ReadError::SizeTooBig(expected, got),
+    }
+}
+
 impl property::Serialize for Header {
     type Error = std::io::Error;
 
@@ -301,10 +406,12 @@ impl property::Serialize for Header {
 
 impl Readable for Header {
     fn read(buf: &mut ReadBuf) -> Result<Self, ReadError> {
-        Header::from_slice(buf.get_slice_end()).map_err(|e| match e {
-            HeaderError::InvalidSize => ReadError::NotEnoughBytes(0, 0),
-            HeaderError::UnknownVersion => ReadError::UnknownTag(0),
-            HeaderError::SizeMismatch { expected, got } => ReadError::SizeTooBig(expected, got),
-        })
+        Header::from_slice(buf.get_slice_end()).map_err(header_read_error)
+    }
+}
+
+impl<'a> ReadableRef<'a> for HeaderSlice<'a> {
+    fn read_ref(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
+        HeaderSlice::from_slice(buf.get_slice_end()).map_err(header_read_error)
     }
 }
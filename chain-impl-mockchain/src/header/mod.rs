@@ -18,5 +18,5 @@ pub use builder::{
 };
 pub use components::{BftSignature, KESSignature, VrfProof};
 pub use deconstruct::{BftProof, Common, GenesisPraosProof, Proof};
-pub use header::{Header, HeaderBft, HeaderDesc, HeaderGenesisPraos, HeaderUnsigned};
+pub use header::{Header, HeaderBft, HeaderDesc, HeaderGenesisPraos, HeaderSlice, HeaderUnsigned};
 pub use version::{AnyBlockVersion, BlockVersion};
@@ -15,6 +15,22 @@ quickcheck! {
     fn header_serialization_bijection(b: Header) -> TestResult {
         chain_test_utils::property::serialization_bijection_r(b)
     }
+
+    fn header_slice_matches_owned(b: Header) -> TestResult {
+        use chain_core::mempack::read_ref_from_raw;
+        let raw = b.to_raw();
+        let slice: HeaderSlice = read_ref_from_raw(&raw).unwrap();
+        TestResult::from_bool(
+            slice.id() == b.id()
+                && slice.as_auth_slice() == b.as_auth_slice()
+                && slice.block_date() == b.block_date()
+                && slice.chain_length() == b.chain_length()
+                && slice.block_content_hash() == b.block_content_hash()
+                && slice.block_parent_hash() == b.block_parent_hash()
+                && slice.fragments_root() == b.fragments_root()
+                && slice.to_owned() == b,
+        )
+    }
 }
 
 impl Arbitrary for BlockVersion {
@@ -36,6 +52,7 @@ impl Arbitrary for Common {
             block_date: Arbitrary::arbitrary(g),
             block_content_size: Arbitrary::arbitrary(g),
             block_content_hash: Arbitrary::arbitrary(g),
+            block_fragments_root: Arbitrary::arbitrary(g),
             block_parent_hash: Arbitrary::arbitrary(g),
             chain_length: ChainLength(Arbitrary::arbitrary(g)),
         }
@@ -84,13 +101,17 @@ impl Arbitrary for GenesisPraosProof {
 impl Arbitrary for Header {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
         let common = Common::arbitrary(g);
-        let hdrbuilder = HeaderBuilderNew::new_raw(
+        let mut hdrbuilder = HeaderBuilderNew::new_raw(
             common.block_version,
             &common.block_content_hash,
             common.block_content_size,
-        )
-        .set_parent(&common.block_parent_hash, common.chain_length)
-        .set_date(common.block_date);
+        );
+        if let Some(fragments_root) = &common.block_fragments_root {
+            hdrbuilder = hdrbuilder.set_fragments_root(fragments_root);
+        }
+        let hdrbuilder = hdrbuilder
+            .set_parent(&common.block_parent_hash, common.chain_length)
+            .set_date(common.block_date);
         match common.block_version {
             BlockVersion::Genesis => hdrbuilder.into_unsigned_header().unwrap().generalize(),
             BlockVersion::Ed25519Signed => {
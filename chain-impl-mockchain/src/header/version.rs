@@ -75,6 +75,8 @@ impl BlockVersion {
         }
     }
 
+    /// The size of the headers of this version, not including
+    /// the fragments root extension of the common part.
     pub const fn get_size(self) -> NonZeroUsize {
         const SIZE: [NonZeroUsize; 3] = [
             unsafe { NonZeroUsize::new_unchecked(cstruct::HEADER_COMMON_SIZE) },
@@ -19,6 +19,7 @@ impl Ledger {
             leaders_log: _,
             votes: _,
             governance: _,
+            block_utilisation: _,
         } = self;
 
         vec![
@@ -62,6 +63,7 @@ impl Ledger {
             leaders_log: leaders_log1,
             votes: votes1,
             governance: governance1,
+            block_utilisation: block_utilisation1,
         } = self;
 
         let Ledger {
@@ -80,6 +82,7 @@ impl Ledger {
             leaders_log: leaders_log2,
             votes: votes2,
             governance: governance2,
+            block_utilisation: block_utilisation2,
         } = other;
 
         vec![
@@ -98,6 +101,10 @@ impl Ledger {
             format!("leaders-log-same: {}", leaders_log1 == leaders_log2),
             format!("vote-plans: {}", votes1 == votes2),
             format!("governance: {}", governance1 == governance2),
+            format!(
+                "block-utilisation: {}",
+                block_utilisation1 == block_utilisation2
+            ),
         ]
     }
 }
@@ -2,10 +2,12 @@ use super::governance::Governance;
 use super::ledger::{Error, Ledger, LedgerStaticParameters};
 use super::pots::{self, Pots};
 use super::LeadersParticipationRecord;
+use crate::accounting::account::SpendingCounter;
 use crate::certificate::{VotePlan, VotePlanId};
 use crate::chaintypes::ChainLength;
 use crate::config::ConfigParam;
 use crate::date::BlockDate;
+use crate::fee::BlockUtilisation;
 use crate::key::Hash;
 use crate::stake::PoolsState;
 use crate::vote::{VotePlanLedger, VotePlanManager};
@@ -25,6 +27,7 @@ pub enum Entry<'a> {
             &'a crate::accounting::account::AccountState<()>,
         ),
     ),
+    AccountTombstone((&'a account::Identifier, &'a SpendingCounter)),
     ConfigParam(ConfigParam),
     UpdateProposal(
         (
@@ -61,6 +64,7 @@ pub enum EntryOwned {
             crate::accounting::account::AccountState<()>,
         ),
     ),
+    AccountTombstone((account::Identifier, SpendingCounter)),
     ConfigParam(ConfigParam),
     UpdateProposal(
         (
@@ -105,6 +109,9 @@ impl EntryOwned {
             EntryOwned::Account((identifier, account_state)) => {
                 Some(Entry::Account((identifier, account_state)))
             }
+            EntryOwned::AccountTombstone((identifier, counter)) => {
+                Some(Entry::AccountTombstone((identifier, counter)))
+            }
             EntryOwned::ConfigParam(config_param) => Some(Entry::ConfigParam(config_param.clone())),
             EntryOwned::UpdateProposal((proposal_id, proposal_state)) => {
                 Some(Entry::UpdateProposal((proposal_id, proposal_state)))
@@ -133,6 +140,7 @@ pub struct Globals {
     pub chain_length: ChainLength,
     pub static_params: LedgerStaticParameters,
     pub era: TimeEra,
+    pub block_utilisation: BlockUtilisation,
 }
 
 enum IterState<'a> {
@@ -140,6 +148,7 @@ enum IterState<'a> {
     Utxo(utxo::Iter<'a, Address>),
     OldUtxo(utxo::Iter<'a, legacy::OldAddress>),
     Accounts(crate::accounting::account::Iter<'a, account::Identifier, ()>),
+    AccountTombstones(imhamt::HamtIter<'a, account::Identifier, SpendingCounter>),
     ConfigParams(Vec<ConfigParam>),
     UpdateProposals(
         std::collections::btree_map::Iter<
@@ -176,6 +185,7 @@ impl<'a> Iterator for LedgerIterator<'a> {
                     chain_length: self.ledger.chain_length,
                     static_params: (*self.ledger.static_params).clone(),
                     era: self.ledger.era.clone(),
+                    block_utilisation: self.ledger.block_utilisation,
                 }))
             }
             IterState::Utxo(iter) => match iter.next() {
@@ -194,11 +204,19 @@ impl<'a> Iterator for LedgerIterator<'a> {
             },
             IterState::Accounts(iter) => match iter.next() {
                 None => {
-                    self.state = IterState::ConfigParams(self.ledger.settings.to_config_params().0);
+                    self.state =
+                        IterState::AccountTombstones(self.ledger.accounts.iter_tombstones());
                     self.next()
                 }
                 Some(x) => Some(Entry::Account(x)),
             },
+            IterState::AccountTombstones(iter) => match iter.next() {
+                None => {
+                    self.state = IterState::ConfigParams(self.ledger.settings.to_config_params().0);
+                    self.next()
+                }
+                Some(x) => Some(Entry::AccountTombstone(x)),
+            },
             IterState::ConfigParams(params) => {
                 if let Some(param) = params.pop() {
                     Some(Entry::ConfigParam(param))
@@ -277,6 +295,7 @@ impl<'a> std::iter::FromIterator<Entry<'a>> for Result<Ledger, Error> {
         let mut utxos: HashMap<Hash, Vec<_>> = HashMap::new();
         let mut oldutxos: HashMap<Hash, Vec<_>> = HashMap::new();
         let mut accounts = vec![];
+        let mut account_tombstones = vec![];
         let mut config_params = crate::fragment::ConfigParams::new();
         let mut updates = update::UpdateState::new();
         let mut multisig_accounts = vec![];
@@ -310,6 +329,9 @@ impl<'a> std::iter::FromIterator<Entry<'a>> for Result<Ledger, Error> {
                 Entry::Account((account_id, account_state)) => {
                     accounts.push((account_id.clone(), account_state.clone()));
                 }
+                Entry::AccountTombstone((account_id, counter)) => {
+                    account_tombstones.push((account_id.clone(), *counter));
+                }
                 Entry::ConfigParam(param) => {
                     config_params.push(param.clone());
                 }
@@ -352,7 +374,10 @@ impl<'a> std::iter::FromIterator<Entry<'a>> for Result<Ledger, Error> {
         Ok(Ledger {
             utxos: utxos.into_iter().collect(),
             oldutxos: oldutxos.into_iter().collect(),
-            accounts: accounts.into_iter().collect(),
+            accounts: accounts
+                .into_iter()
+                .collect::<account::Ledger>()
+                .with_tombstones(account_tombstones)?,
             settings: setting::Settings::new().apply(&config_params)?,
             updates,
             multisig: multisig::Ledger::restore(multisig_accounts, multisig_declarations),
@@ -365,6 +390,7 @@ impl<'a> std::iter::FromIterator<Entry<'a>> for Result<Ledger, Error> {
             leaders_log,
             votes,
             governance,
+            block_utilisation: globals.block_utilisation,
         })
     }
 }
@@ -387,6 +413,10 @@ mod tests {
                 chain_length: Arbitrary::arbitrary(g),
                 static_params: Arbitrary::arbitrary(g),
                 era: Arbitrary::arbitrary(g),
+                block_utilisation: BlockUtilisation {
+                    blocks: Arbitrary::arbitrary(g),
+                    content_size: Arbitrary::arbitrary(g),
+                },
             }
         }
     }
@@ -426,6 +456,9 @@ mod tests {
                         state.value,
                     );
                 }
+                Entry::AccountTombstone((id, counter)) => {
+                    println!("AccountTombstone {} {}", id, u32::from(*counter));
+                }
                 Entry::ConfigParam(param) => {
                     println!(
                         "ConfigParam {:?} {:?}",
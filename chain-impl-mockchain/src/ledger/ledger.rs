@@ -11,8 +11,10 @@ use crate::chaineval::HeaderContentEvalContext;
 use crate::chaintypes::{ChainLength, ConsensusType, HeaderId};
 use crate::config::{self, ConfigParam};
 use crate::date::{BlockDate, Epoch};
-use crate::fee::{FeeAlgorithm, LinearFee};
-use crate::fragment::{BlockContentHash, BlockContentSize, Contents, Fragment, FragmentId};
+use crate::fee::{BlockUtilisation, DynamicFeePeriod, FeeAlgorithm, LinearFee};
+use crate::fragment::{
+    BlockContentHash, BlockContentSize, BlockFragmentsRoot, Contents, Fragment, FragmentId,
+};
 use crate::rewards;
 use crate::setting::ActiveSlotsCoeffError;
 use crate::stake::{
@@ -25,7 +27,10 @@ use crate::vote::{CommitteeId, VotePlanLedger, VotePlanLedgerError, VotePlanStat
 use crate::{account, certificate, legacy, multisig, setting, stake, update, utxo};
 use chain_addr::{Address, Discrimination, Kind};
 use chain_crypto::Verification;
-use chain_time::{Epoch as TimeEpoch, SlotDuration, TimeEra, TimeFrame, Timeline};
+use chain_time::{
+    Epoch as TimeEpoch, SlotDuration, TimeEra, TimeFrame, TimeOffsetSeconds, Timeline,
+};
+use std::collections::HashSet;
 use std::mem::swap;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
@@ -60,6 +65,10 @@ pub struct LedgerParameters {
     pub fees_goes_to: setting::FeesGoesTo,
     /// List of committee members
     pub committees: Arc<Box<[CommitteeId]>>,
+    /// Deposit locked on stake pool registration
+    pub pool_registration_deposit: Value,
+    /// Deposit locked on an account's first delegation
+    pub stake_key_deposit: Value,
 }
 
 /// Overall ledger structure.
@@ -86,6 +95,7 @@ pub struct Ledger {
     pub(crate) leaders_log: LeadersParticipationRecord,
     pub(crate) votes: VotePlanLedger,
     pub(crate) governance: Governance,
+    pub(crate) block_utilisation: BlockUtilisation,
 }
 
 // Dummy implementation of Debug for Ledger
@@ -125,6 +135,8 @@ pub enum Block0Error {
     InitialMessageNoDate,
     #[error("Missing slot duration in the initial fragment")]
     InitialMessageNoSlotDuration,
+    #[error("Invalid slot duration in the initial fragment")]
+    InitialMessageInvalidSlotDuration,
     #[error("Missing slots per epoch in the initial fragment")]
     InitialMessageNoSlotsPerEpoch,
     #[error("Missing address discrimination in the initial fragment")]
@@ -151,6 +163,8 @@ pub enum Block0Error {
     HasVoteCast,
     #[error("Vote tallying are not valid in the block0")]
     HasVoteTally,
+    #[error("Account deregistration are not valid in the block0")]
+    HasAccountDeregistration,
 }
 
 pub type OutputOldAddress = Output<legacy::OldAddress>;
@@ -252,6 +266,11 @@ pub enum Error {
         actual: BlockContentHash,
         expected: BlockContentHash,
     },
+    #[error("Wrong block fragments root, received {actual} but expected {expected}")]
+    InvalidFragmentsRoot {
+        actual: BlockFragmentsRoot,
+        expected: BlockFragmentsRoot,
+    },
     #[error("Ledger cannot be reconstructed from serialized state because of missing entries")]
     IncompleteLedger,
     #[error("Ledger pot value invalid: {error}")]
@@ -272,6 +291,10 @@ pub enum Error {
     StakeDelegationSignatureFailed,
     #[error("Pool Retirement payload signature failed")]
     PoolRetirementSignatureFailed,
+    #[error("Account deregistration payload signature failed")]
+    AccountDeregistrationSignatureFailed,
+    #[error("Account deregistration cannot sweep funds back to the deregistered account")]
+    AccountDeregistrationInvalidDestination,
     #[error("Vote Plan Proof has an invalid signature")]
     VotePlanProofInvalidSignature,
     #[error("Vote Plan Proof ID is not present in the committee")]
@@ -325,6 +348,7 @@ impl Ledger {
             leaders_log: LeadersParticipationRecord::new(),
             votes: VotePlanLedger::new(),
             governance: Governance::default(),
+            block_utilisation: BlockUtilisation::default(),
         }
     }
 
@@ -358,7 +382,12 @@ impl Ledger {
                         discrimination = Some(*d);
                     }
                     ConfigParam::SlotDuration(d) => {
-                        slot_duration = Some(*d);
+                        slot_duration = Some(SlotDuration::from_secs(u32::from(*d)));
+                    }
+                    ConfigParam::SlotDurationMillis(d) => {
+                        slot_duration = Some(setting::slot_duration_from_millis(*d).ok_or(
+                            Error::Block0(Block0Error::InitialMessageInvalidSlotDuration),
+                        )?);
                     }
                     ConfigParam::SlotsPerEpoch(n) => {
                         slots_per_epoch = Some(*n);
@@ -397,12 +426,18 @@ impl Ledger {
 
             let system_time = SystemTime::UNIX_EPOCH + Duration::from_secs(block0_start_time.0);
             let timeline = Timeline::new(system_time);
-            let tf = TimeFrame::new(timeline, SlotDuration::from_secs(slot_duration as u32));
+            let tf = TimeFrame::new(timeline, slot_duration);
             let slot0 = tf.slot0();
 
             let era = TimeEra::new(slot0, TimeEpoch(0), slots_per_epoch);
 
-            let settings = setting::Settings::new().apply(&regular_ents)?;
+            // the slot duration and the number of slots per epoch are taken out
+            // of the initial parameters above, so they have to be copied into the
+            // settings, which would otherwise keep their default values. The
+            // epoch of a pool retirement is computed from the settings.
+            let mut settings = setting::Settings::new().apply(&regular_ents)?;
+            settings.slot_duration = slot_duration;
+            settings.slots_per_epoch = slots_per_epoch;
 
             if settings.bft_leaders.is_empty() {
                 return Err(Error::Block0(
@@ -477,6 +512,14 @@ impl Ledger {
                 Fragment::EncryptedVoteTally(_) => {
                     return Err(Error::Block0(Block0Error::HasVoteTally));
                 }
+                Fragment::AccountDeregistration(_) => {
+                    return Err(Error::Block0(Block0Error::HasAccountDeregistration));
+                }
+                Fragment::MultisigRegistration(tx) => {
+                    let tx = tx.as_slice();
+                    check::valid_block0_cert_transaction(&tx)?;
+                    ledger = ledger.apply_multisig_registration(&tx.payload().into_payload())?;
+                }
             }
         }
 
@@ -748,6 +791,13 @@ impl Ledger {
             });
         }
 
+        if let Some(expected) = metadata.fragments_root {
+            let actual = contents.compute_fragments_root();
+            if actual != expected {
+                return Err(Error::InvalidFragmentsRoot { actual, expected });
+            }
+        }
+
         // Check if the metadata (date/heigth) check out compared to the current state
         if metadata.chain_length != new_ledger.chain_length {
             return Err(Error::WrongChainLength {
@@ -777,11 +827,21 @@ impl Ledger {
         new_ledger.updates = updates;
         new_ledger.settings = settings;
 
+        // Retire the stake pools scheduled for the new epoch
+        if metadata.block_date.epoch > new_ledger.date.epoch {
+            new_ledger = new_ledger.apply_pool_retirements(metadata.block_date.epoch)?;
+        }
+
         // Apply all the fragments
         for content in contents.iter() {
             new_ledger = new_ledger.apply_fragment(ledger_params, content, metadata.block_date)?;
         }
 
+        new_ledger.apply_dynamic_fees(
+            content_size,
+            metadata.block_date.epoch > new_ledger.date.epoch,
+        );
+
         // Update the ledger metadata related to eval context
         new_ledger.date = metadata.block_date;
         match metadata.gp_content {
@@ -800,6 +860,33 @@ impl Ledger {
         Ok(new_ledger)
     }
 
+    /// Record the block utilisation and, when the dynamic fee period is over,
+    /// adjust the fee constant accordingly
+    fn apply_dynamic_fees(&mut self, content_size: BlockContentSize, new_epoch: bool) {
+        let period = match &self.settings.dynamic_fees {
+            None => {
+                // forget the utilisation recorded before the rule was disabled
+                self.block_utilisation = BlockUtilisation::default();
+                return;
+            }
+            Some(dynamic_fees) => dynamic_fees.period,
+        };
+        match period {
+            DynamicFeePeriod::Epoch => {
+                if new_epoch {
+                    if let Some(average) = self.block_utilisation.average() {
+                        self.settings = self.settings.adjust_linear_fees(average);
+                    }
+                    self.block_utilisation = BlockUtilisation::default();
+                }
+                self.block_utilisation.record(content_size);
+            }
+            DynamicFeePeriod::Block => {
+                self.settings = self.settings.adjust_linear_fees(content_size as u64);
+            }
+        }
+    }
+
     /// Try to apply a message to the State, and return the new State if successful
     ///
     /// this does not _advance_ the state to the new _state_ but apply a simple fragment
@@ -833,15 +920,15 @@ impl Ledger {
                 let tx = tx.as_slice();
                 let payload = tx.payload().into_payload();
                 let payload_auth = tx.payload_auth().into_payload_auth();
+                let account_pk = payload
+                    .account_id
+                    .to_single_account()
+                    .ok_or(Error::AccountIdentifierInvalid)?;
                 let verified = match payload_auth {
-                    AccountBindingSignature::Single(signature) => {
-                        let account_pk = payload
-                            .account_id
-                            .to_single_account()
-                            .ok_or(Error::AccountIdentifierInvalid)?;
-                        signature
-                            .verify_slice(&account_pk.into(), &tx.transaction_binding_auth_data())
-                    }
+                    AccountBindingSignature::Single(signature) => signature.verify_slice(
+                        &account_pk.clone().into(),
+                        &tx.transaction_binding_auth_data(),
+                    ),
                     AccountBindingSignature::Multi(_) => {
                         // TODO
                         Verification::Failed
@@ -852,18 +939,32 @@ impl Ledger {
                     return Err(Error::StakeDelegationSignatureFailed);
                 }
 
-                let (new_ledger_, _fee) =
-                    new_ledger.apply_transaction(&fragment_id, &tx, &ledger_params)?;
+                let deposit = new_ledger.stake_key_deposit_due(&account_pk, ledger_params);
+                let (new_ledger_, _fee) = new_ledger.apply_transaction_with_deposit(
+                    &fragment_id,
+                    &tx,
+                    ledger_params,
+                    deposit,
+                )?;
                 new_ledger = new_ledger_.apply_stake_delegation(&payload)?;
+                if deposit != Value::zero() {
+                    new_ledger.accounts = new_ledger.accounts.add_deposit(&account_pk, deposit)?;
+                }
             }
             Fragment::PoolRegistration(tx) => {
                 let tx = tx.as_slice();
-                let (new_ledger_, _fee) =
-                    new_ledger.apply_transaction(&fragment_id, &tx, &ledger_params)?;
+                let deposit = ledger_params.pool_registration_deposit;
+                let (new_ledger_, _fee) = new_ledger.apply_transaction_with_deposit(
+                    &fragment_id,
+                    &tx,
+                    &ledger_params,
+                    deposit,
+                )?;
                 new_ledger = new_ledger_.apply_pool_registration_signcheck(
                     &tx.payload().into_payload(),
                     &tx.transaction_binding_auth_data(),
                     tx.payload_auth().into_payload_auth(),
+                    deposit,
                 )?;
             }
             Fragment::PoolRetirement(tx) => {
@@ -875,6 +976,7 @@ impl Ledger {
                     &tx.payload().into_payload(),
                     &tx.transaction_binding_auth_data(),
                     tx.payload_auth().into_payload_auth(),
+                    block_date,
                 )?;
             }
             Fragment::PoolUpdate(tx) => {
@@ -942,16 +1044,48 @@ impl Ledger {
                     tx.payload_auth().into_payload_auth(),
                 )?;
             }
+            Fragment::AccountDeregistration(tx) => {
+                let tx = tx.as_slice();
+                new_ledger =
+                    new_ledger.apply_account_deregistration(&fragment_id, &tx, ledger_params)?;
+            }
+            Fragment::MultisigRegistration(tx) => {
+                let tx = tx.as_slice();
+                // the account is created first, so the transaction outputs
+                // can already fund it
+                new_ledger =
+                    new_ledger.apply_multisig_registration(&tx.payload().into_payload())?;
+                let (new_ledger_, _fee) =
+                    new_ledger.apply_transaction(&fragment_id, &tx, ledger_params)?;
+                new_ledger = new_ledger_;
+            }
         }
 
         Ok(new_ledger)
     }
 
     pub fn apply_transaction<'a, Extra>(
+        self,
+        fragment_id: &FragmentId,
+        tx: &TransactionSlice<'a, Extra>,
+        dyn_params: &LedgerParameters,
+    ) -> Result<(Self, Value), Error>
+    where
+        Extra: Payload,
+        LinearFee: FeeAlgorithm,
+    {
+        self.apply_transaction_with_deposit(fragment_id, tx, dyn_params, Value::zero())
+    }
+
+    /// Same as `apply_transaction`, except that the inputs also need to cover
+    /// the given deposit, which is kept aside by the caller instead of being
+    /// collected as fees.
+    pub fn apply_transaction_with_deposit<'a, Extra>(
         mut self,
         fragment_id: &FragmentId,
         tx: &TransactionSlice<'a, Extra>,
         dyn_params: &LedgerParameters,
+        deposit: Value,
     ) -> Result<(Self, Value), Error>
     where
         Extra: Payload,
@@ -959,7 +1093,7 @@ impl Ledger {
     {
         check::valid_transaction_ios_number(tx)?;
         let fee = calculate_fee(tx, dyn_params);
-        tx.verify_strictly_balanced(fee)?;
+        tx.verify_strictly_balanced((fee + deposit)?)?;
         self = self.apply_tx_inputs(tx)?;
         self = self.apply_tx_outputs(*fragment_id, tx.outputs())?;
         self = self.apply_tx_fee(fee)?;
@@ -1004,7 +1138,7 @@ impl Ledger {
             return Err(Error::VotePlanInvalidGovernanceParameters);
         }
 
-        let committee: std::collections::HashSet<CommitteeId> = {
+        let committee: HashSet<CommitteeId> = {
             let mut vec = Vec::with_capacity(tx.nb_inputs() as usize);
 
             if !vote_plan.is_governance() {
@@ -1177,6 +1311,7 @@ impl Ledger {
         cert: &certificate::PoolRegistration,
         bad: &TransactionBindingAuthData<'a>,
         sig: certificate::PoolSignature,
+        deposit: Value,
     ) -> Result<Self, Error> {
         check::valid_pool_registration_certificate(cert)?;
         check::valid_pool_signature(&sig)?;
@@ -1185,24 +1320,49 @@ impl Ledger {
             return Err(Error::PoolRetirementSignatureFailed);
         }
 
-        self.apply_pool_registration(cert)
+        self.apply_pool_registration_with_deposit(cert, deposit)
+    }
+
+    /// Register a multisig declaration, creating an empty multisig account
+    pub fn apply_multisig_registration(
+        mut self,
+        cert: &certificate::MultisigRegistration,
+    ) -> Result<Self, Error> {
+        self.multisig = self.multisig.add_account(&cert.declaration)?;
+        Ok(self)
     }
 
     pub fn apply_pool_registration(
+        self,
+        cert: &certificate::PoolRegistration,
+    ) -> Result<Self, Error> {
+        self.apply_pool_registration_with_deposit(cert, Value::zero())
+    }
+
+    fn apply_pool_registration_with_deposit(
         mut self,
         cert: &certificate::PoolRegistration,
+        deposit: Value,
     ) -> Result<Self, Error> {
         check::valid_pool_registration_certificate(cert)?;
 
-        self.delegation = self.delegation.register_stake_pool(cert.clone())?;
+        self.delegation = self
+            .delegation
+            .register_stake_pool_with_deposit(cert.clone(), deposit)?;
         Ok(self)
     }
 
+    /// Apply a pool retirement certificate.
+    ///
+    /// The pool is retired straight away if its retirement time falls in the
+    /// epoch of `cur_date` or before, otherwise the retirement is scheduled for
+    /// the start of the epoch containing the retirement time.
     pub fn apply_pool_retirement<'a>(
         mut self,
         auth_cert: &certificate::PoolRetirement,
         bad: &TransactionBindingAuthData<'a>,
         sig: certificate::PoolSignature,
+        cur_date: BlockDate,
     ) -> Result<Self, Error> {
         check::valid_pool_retirement_certificate(auth_cert)?;
         check::valid_pool_signature(&sig)?;
@@ -1212,10 +1372,90 @@ impl Ledger {
             return Err(Error::PoolRetirementSignatureFailed);
         }
 
-        self.delegation = self.delegation.deregister_stake_pool(&auth_cert.pool_id)?;
+        let retirement_epoch = self.epoch_of_time_offset(auth_cert.retirement_time);
+        if retirement_epoch > cur_date.epoch {
+            self.delegation
+                .stake_pool_schedule_retirement(&auth_cert.pool_id, retirement_epoch)?;
+            Ok(self)
+        } else {
+            self.retire_pools(vec![auth_cert.pool_id.clone()])
+        }
+    }
+
+    /// Retire all the stake pools scheduled to retire at or before the given epoch
+    fn apply_pool_retirements(self, epoch: Epoch) -> Result<Self, Error> {
+        let pool_ids = self.delegation.stake_pools_retiring_by(epoch);
+        if pool_ids.is_empty() {
+            return Ok(self);
+        }
+        self.retire_pools(pool_ids)
+    }
+
+    /// Deregister stake pools, refund their deposits and remove them from the
+    /// delegation of the accounts still pointing to them.
+    ///
+    /// The accounts are walked once for all the pools.
+    fn retire_pools(mut self, pool_ids: Vec<PoolId>) -> Result<Self, Error> {
+        for pool_id in &pool_ids {
+            let state = self.delegation.stake_pool_get_state(pool_id)?.clone();
+            self.refund_pool_deposit(&state.registration, state.deposit)?;
+            self.delegation = self.delegation.deregister_stake_pool(pool_id)?;
+        }
+        let pool_ids: HashSet<PoolId> = pool_ids.into_iter().collect();
+        self.accounts = self.accounts.remove_pools_delegation(&pool_ids)?;
+        self.multisig = self.multisig.remove_pools_delegation(&pool_ids)?;
         Ok(self)
     }
 
+    /// Give back the pool deposit to the reward account, or split it between
+    /// the owners if the pool has no reward account.
+    fn refund_pool_deposit(
+        &mut self,
+        reg: &certificate::PoolRegistration,
+        deposit: Value,
+    ) -> Result<(), Error> {
+        if deposit == Value::zero() {
+            return Ok(());
+        }
+        match &reg.reward_account {
+            Some(AccountIdentifier::Single(single_account)) => {
+                self.add_value_or_create_account(single_account, deposit)?;
+            }
+            Some(AccountIdentifier::Multi(multi_account)) => {
+                match self.multisig.add_value(multi_account, deposit) {
+                    Ok(multisig) => self.multisig = multisig,
+                    // the multisig account doesn't exist anymore, the deposit goes to the treasury
+                    Err(multisig::LedgerError::AccountError(account::LedgerError::NonExistent)) => {
+                        self.pots.treasury_add(deposit)?
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
+            None => {
+                let splitted = deposit.split_in(reg.owners.len() as u32);
+                for owner in &reg.owners {
+                    self.add_value_or_create_account(&owner.clone().into(), splitted.parts)?;
+                }
+                if splitted.remaining > Value::zero() {
+                    self.add_value_or_create_account(
+                        &reg.owners[0].clone().into(),
+                        splitted.remaining,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Epoch containing the given offset from the start of block0
+    fn epoch_of_time_offset(&self, offset: TimeOffsetSeconds) -> Epoch {
+        let slot_duration = self.settings.slot_duration.as_millis().max(1);
+        let slot = u64::from(offset) * 1000 / slot_duration;
+        self.era
+            .from_slot_to_era(slot.into())
+            .map_or(0, |position| position.epoch.0)
+    }
+
     pub fn apply_pool_update<'a>(
         mut self,
         auth_cert: &certificate::PoolUpdate,
@@ -1290,15 +1530,17 @@ impl Ledger {
         };
 
         let fee = dyn_params.fees.calculate_tx(tx);
-        if fee != value {
-            return Err(Error::NotBalanced {
-                inputs: value,
-                outputs: fee,
-            });
-        }
 
         match match_identifier_witness(&account_id, &witness)? {
             MatchingIdentifierWitness::Single(account_id, witness) => {
+                let deposit = self.stake_key_deposit_due(&account_id, dyn_params);
+                let expected = (fee + deposit)?;
+                if expected != value {
+                    return Err(Error::NotBalanced {
+                        inputs: value,
+                        outputs: expected,
+                    });
+                }
                 let single = input_single_account_verify(
                     self.accounts,
                     &self.static_params.block0_initial_hash,
@@ -1311,8 +1553,17 @@ impl Ledger {
                     &account_id,
                     tx.payload().into_payload().get_delegation_type(),
                 )?;
+                if deposit != Value::zero() {
+                    self.accounts = self.accounts.add_deposit(&account_id, deposit)?;
+                }
             }
             MatchingIdentifierWitness::Multi(account_id, witness) => {
+                if fee != value {
+                    return Err(Error::NotBalanced {
+                        inputs: value,
+                        outputs: fee,
+                    });
+                }
                 let multi = input_multi_account_verify(
                     self.multisig,
                     &self.static_params.block0_initial_hash,
@@ -1331,6 +1582,75 @@ impl Ledger {
         Ok((self, fee))
     }
 
+    /// Deregister an account: the remaining value and key deposit of the
+    /// account are swept to the certificate's destination, in the same
+    /// fragment as the regular transaction outputs, and the account state
+    /// is removed from the ledger, leaving a tombstone with its spending
+    /// counter.
+    pub fn apply_account_deregistration<'a>(
+        mut self,
+        fragment_id: &FragmentId,
+        tx: &TransactionSlice<'a, certificate::AccountDeregistration>,
+        dyn_params: &LedgerParameters,
+    ) -> Result<Self, Error> {
+        let cert = tx.payload().into_payload();
+        let account_id = cert
+            .account_id
+            .to_single_account()
+            .ok_or(Error::AccountIdentifierInvalid)?;
+
+        let verified = match tx.payload_auth().into_payload_auth() {
+            AccountBindingSignature::Single(signature) => signature.verify_slice(
+                &account_id.clone().into(),
+                &tx.transaction_binding_auth_data(),
+            ),
+            AccountBindingSignature::Multi(_) => Verification::Failed,
+        };
+        if verified == Verification::Failed {
+            return Err(Error::AccountDeregistrationSignatureFailed);
+        }
+
+        if let Kind::Account(destination) = cert.destination.kind() {
+            if account::Identifier::from(destination.clone()) == account_id {
+                return Err(Error::AccountDeregistrationInvalidDestination);
+            }
+        }
+
+        check::valid_transaction_ios_number(tx)?;
+        let fee = calculate_fee(tx, dyn_params);
+        tx.verify_strictly_balanced(fee)?;
+        self = self.apply_tx_inputs(tx)?;
+
+        let (accounts, state) = self.accounts.deregister_account(&account_id)?;
+        self.accounts = accounts;
+        let swept = (state.value + state.deposit)?;
+
+        let sweep = if swept == Value::zero() {
+            None
+        } else {
+            Some(Output {
+                address: cert.destination,
+                value: swept,
+            })
+        };
+        self = self.apply_outputs(*fragment_id, tx.outputs().iter().chain(sweep))?;
+        self = self.apply_tx_fee(fee)?;
+        Ok(self)
+    }
+
+    /// The key deposit the given account has to lock when delegating, which is
+    /// zero once a deposit has already been paid for the account.
+    fn stake_key_deposit_due(
+        &self,
+        account_id: &account::Identifier,
+        dyn_params: &LedgerParameters,
+    ) -> Value {
+        match self.accounts.get_state(account_id) {
+            Ok(state) if state.deposit == Value::zero() => dyn_params.stake_key_deposit,
+            _ => Value::zero(),
+        }
+    }
+
     pub fn get_stake_distribution(&self) -> StakeDistribution {
         stake::get_distribution(&self.accounts, &self.delegation, &self.utxos)
     }
@@ -1356,6 +1676,8 @@ impl Ledger {
             epoch_stability_depth: self.settings.epoch_stability_depth,
             fees_goes_to: self.settings.fees_goes_to,
             committees: self.settings.committees.clone(),
+            pool_registration_deposit: self.settings.pool_registration_deposit,
+            stake_key_deposit: self.settings.stake_key_deposit,
         }
     }
 
@@ -1413,14 +1735,24 @@ impl Ledger {
             .accounts
             .get_total_value()
             .map_err(|_| Error::Block0(Block0Error::UtxoTotalValueTooBig))?;
+        let account_deposits_value = self
+            .accounts
+            .get_total_deposits()
+            .map_err(|_| Error::Block0(Block0Error::UtxoTotalValueTooBig))?;
         let multisig_value = self
             .multisig
             .get_total_value()
             .map_err(|_| Error::Block0(Block0Error::UtxoTotalValueTooBig))?;
+        let deposits_value = self
+            .delegation
+            .get_total_deposits()
+            .map_err(|_| Error::Block0(Block0Error::UtxoTotalValueTooBig))?;
         let all_utxo_values = old_utxo_values
             .chain(new_utxo_values)
             .chain(Some(account_value))
+            .chain(Some(account_deposits_value))
             .chain(Some(multisig_value))
+            .chain(Some(deposits_value))
             .chain(self.pots.values());
         Value::sum(all_utxo_values).map_err(|_| Error::Block0(Block0Error::UtxoTotalValueTooBig))
     }
@@ -1465,12 +1797,19 @@ impl Ledger {
     }
 
     fn apply_tx_outputs(
-        mut self,
+        self,
         fragment_id: FragmentId,
         outputs: OutputsSlice<'_>,
     ) -> Result<Self, Error> {
+        self.apply_outputs(fragment_id, outputs.iter())
+    }
+
+    fn apply_outputs<I>(mut self, fragment_id: FragmentId, outputs: I) -> Result<Self, Error>
+    where
+        I: Iterator<Item = Output<Address>>,
+    {
         let mut new_utxos = Vec::new();
-        for (index, output) in outputs.iter().enumerate() {
+        for (index, output) in outputs.enumerate() {
             check::valid_output_value(&output)?;
 
             if output.address.discrimination() != self.static_params.discrimination {
@@ -1768,6 +2107,8 @@ mod tests {
                 epoch_stability_depth: Arbitrary::arbitrary(g),
                 fees_goes_to: Arbitrary::arbitrary(g),
                 committees: Arc::new(committees.into()),
+                pool_registration_deposit: Value::zero(),
+                stake_key_deposit: Value::zero(),
             }
         }
     }
@@ -2211,6 +2552,8 @@ mod tests {
                 epoch_stability_depth: 1000,
                 fees_goes_to: FeesGoesTo::Rewards,
                 committees: Arc::new(Box::new([])),
+                pool_registration_deposit: Value::zero(),
+                stake_key_deposit: Value::zero(),
             };
             InternalApplyTransactionTestParams {
                 dyn_params,
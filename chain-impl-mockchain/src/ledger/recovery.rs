@@ -44,6 +44,7 @@ use crate::accounting::account::{
 use crate::certificate::{PoolId, PoolRegistration, Proposal, Proposals, VoteAction, VotePlan};
 use crate::config::ConfigParam;
 use crate::date::BlockDate;
+use crate::fee::BlockUtilisation;
 use crate::fragment::FragmentId;
 use crate::header::{ChainLength, HeaderId};
 use crate::key::serialize_public_key;
@@ -71,7 +72,7 @@ use std::sync::Arc;
 #[cfg(test)]
 use crate::{
     chaintypes::ConsensusVersion,
-    fee::{LinearFee, PerCertificateFee, PerVoteCertificateFee},
+    fee::{ExtendedFee, LinearFee, PerCertificateFee, PerVoteCertificateFee},
     fragment::ConfigParams,
     key::BftLeaderId,
 };
@@ -168,6 +169,7 @@ fn pack_account_state<W: std::io::Write>(
     pack_delegation_type(&account_state.delegation, codec)?;
     codec.put_u64(account_state.value.0)?;
     pack_last_rewards(&account_state.last_rewards, codec)?;
+    codec.put_u64(account_state.deposit.0)?;
     Ok(())
 }
 
@@ -178,11 +180,13 @@ fn unpack_account_state<R: std::io::BufRead>(
     let delegation = unpack_delegation_type(codec)?;
     let value = codec.get_u64()?;
     let last_rewards = unpack_last_rewards(codec)?;
+    let deposit = codec.get_u64()?;
     Ok(AccountState {
         counter: SpendingCounter(counter),
         delegation,
         value: Value(value),
         last_rewards,
+        deposit: Value(deposit),
         extra: (),
     })
 }
@@ -375,6 +379,7 @@ fn pack_linear_fee<W: std::io::Write>(
     codec.put_u64(linear_fee.certificate)?;
     pack_per_certificate_fee(&linear_fee.per_certificate_fees, codec)?;
     pack_per_vote_certificate_fee(&linear_fee.per_vote_certificate_fees, codec)?;
+    pack_extended_fee(&linear_fee.extended_fees, codec)?;
     Ok(())
 }
 
@@ -387,12 +392,14 @@ fn unpack_linear_fee<R: std::io::BufRead>(
     let certificate = codec.get_u64()?;
     let per_certificate_fees = unpack_per_certificate_fee(codec)?;
     let per_vote_certificate_fees = unpack_per_vote_certificate_fee(codec)?;
+    let extended_fees = unpack_extended_fee(codec)?;
     Ok(LinearFee {
         constant,
         coefficient,
         certificate,
         per_certificate_fees,
         per_vote_certificate_fees,
+        extended_fees,
     })
 }
 
@@ -442,6 +449,18 @@ fn pack_per_vote_certificate_fee<W: std::io::Write>(
     Ok(())
 }
 
+#[cfg(test)]
+fn pack_extended_fee<W: std::io::Write>(
+    extended_fee: &ExtendedFee,
+    codec: &mut Codec<W>,
+) -> Result<(), std::io::Error> {
+    codec.put_u64(extended_fee.per_byte)?;
+    codec.put_u64(extended_fee.per_utxo_witness)?;
+    codec.put_u64(extended_fee.per_account_witness)?;
+    codec.put_u64(extended_fee.per_multisig_witness)?;
+    Ok(())
+}
+
 #[cfg(test)]
 fn unpack_per_certificate_fee<R: std::io::BufRead>(
     codec: &mut Codec<R>,
@@ -470,6 +489,23 @@ fn unpack_per_vote_certificate_fee<R: std::io::BufRead>(
     })
 }
 
+#[cfg(test)]
+fn unpack_extended_fee<R: std::io::BufRead>(
+    codec: &mut Codec<R>,
+) -> Result<ExtendedFee, std::io::Error> {
+    let per_byte = codec.get_u64()?;
+    let per_utxo_witness = codec.get_u64()?;
+    let per_account_witness = codec.get_u64()?;
+    let per_multisig_witness = codec.get_u64()?;
+
+    Ok(ExtendedFee {
+        per_byte,
+        per_utxo_witness,
+        per_account_witness,
+        per_multisig_witness,
+    })
+}
+
 #[allow(dead_code)]
 #[cfg(test)]
 fn pack_config_params<W: std::io::Write>(
@@ -547,6 +583,8 @@ fn pack_globals<W: std::io::Write>(
     codec.put_u32(globals.chain_length.0)?;
     pack_ledger_static_parameters(&globals.static_params, codec)?;
     pack_time_era(&globals.era, codec)?;
+    codec.put_u32(globals.block_utilisation.blocks)?;
+    codec.put_u64(globals.block_utilisation.content_size)?;
     Ok(())
 }
 
@@ -555,11 +593,17 @@ fn unpack_globals<R: std::io::BufRead>(codec: &mut Codec<R>) -> Result<Globals,
     let chain_length = ChainLength(codec.get_u32()?);
     let static_params = unpack_ledger_static_parameters(codec)?;
     let era = unpack_time_era(codec)?;
+    let blocks = codec.get_u32()?;
+    let content_size = codec.get_u64()?;
     Ok(Globals {
         date,
         chain_length,
         static_params,
         era,
+        block_utilisation: BlockUtilisation {
+            blocks,
+            content_size,
+        },
     })
 }
 
@@ -696,6 +740,16 @@ fn pack_pool_state<W: std::io::Write>(
 ) -> Result<(), std::io::Error> {
     pack_pool_last_rewards(&pool_state.last_rewards, codec)?;
     pack_pool_registration(&pool_state.registration, codec)?;
+    codec.put_u64(pool_state.deposit.0)?;
+    match pool_state.retirement_epoch {
+        None => {
+            codec.put_u8(0)?;
+        }
+        Some(epoch) => {
+            codec.put_u8(1)?;
+            codec.put_u32(epoch)?;
+        }
+    }
     Ok(())
 }
 
@@ -704,10 +758,23 @@ fn unpack_pool_state<R: std::io::BufRead>(
 ) -> Result<PoolState, std::io::Error> {
     let last_rewards = unpack_pool_last_rewards(codec)?;
     let registration = Arc::new(unpack_pool_registration(codec)?);
+    let deposit = Value(codec.get_u64()?);
+    let retirement_epoch = match codec.get_u8()? {
+        0 => None,
+        1 => Some(codec.get_u32()?),
+        code => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid retirement epoch type code {}", code),
+            ))
+        }
+    };
 
     Ok(PoolState {
         last_rewards,
         registration,
+        deposit,
+        retirement_epoch,
     })
 }
 
@@ -1009,6 +1076,7 @@ enum EntrySerializeCode {
     StakePool = 9,
     LeaderParticipation = 10,
     VotePlan = 11,
+    AccountTombstone = 12,
     SerializationEnd = 99,
 }
 
@@ -1027,6 +1095,7 @@ impl EntrySerializeCode {
             9 => Some(EntrySerializeCode::StakePool),
             10 => Some(EntrySerializeCode::LeaderParticipation),
             11 => Some(EntrySerializeCode::VotePlan),
+            12 => Some(EntrySerializeCode::AccountTombstone),
             99 => Some(EntrySerializeCode::SerializationEnd),
             _ => None,
         }
@@ -1059,6 +1128,11 @@ fn pack_entry<W: std::io::Write>(
             pack_account_identifier(identifier, codec)?;
             pack_account_state(account_state, codec)?;
         }
+        Entry::AccountTombstone((identifier, counter)) => {
+            codec.put_u8(EntrySerializeCode::AccountTombstone as u8)?;
+            pack_account_identifier(identifier, codec)?;
+            codec.put_u32((**counter).into())?;
+        }
         Entry::ConfigParam(config_param) => {
             codec.put_u8(EntrySerializeCode::ConfigParam as u8)?;
             pack_config_param(config_param, codec)?;
@@ -1122,6 +1196,11 @@ fn unpack_entry_owned<R: std::io::BufRead>(
             let account = unpack_account_state(codec)?;
             Ok(EntryOwned::Account((identifier, account)))
         }
+        EntrySerializeCode::AccountTombstone => {
+            let identifier = unpack_account_identifier(codec)?;
+            let counter = SpendingCounter::from(codec.get_u32()?);
+            Ok(EntryOwned::AccountTombstone((identifier, counter)))
+        }
         EntrySerializeCode::ConfigParam => Ok(EntryOwned::ConfigParam(unpack_config_param(codec)?)),
         EntrySerializeCode::UpdateProposal => {
             let proposal_id = unpack_update_proposal_id(codec)?;
@@ -1472,6 +1551,37 @@ pub mod test {
         Ok(())
     }
 
+    #[test]
+    pub fn ledger_serialize_deserialize_account_tombstones() -> Result<(), std::io::Error> {
+        let test_ledger = LedgerBuilder::from_config(ConfigBuilder::new(0))
+            .faucet_value(Value(42000))
+            .build()
+            .expect("cannot build test ledger");
+
+        let mut ledger: Ledger = test_ledger.into();
+        let id = crate::testing::TestGen::identifier();
+        ledger.accounts = ledger
+            .accounts
+            .add_account(&id, Value::zero(), ())
+            .unwrap()
+            .remove_value(&id, Value::zero())
+            .unwrap()
+            .0
+            .deregister_account(&id)
+            .unwrap()
+            .0;
+        let mut c = std::io::Cursor::new(Vec::new());
+        ledger.serialize(&mut c)?;
+        c.set_position(0);
+        let other_ledger = Ledger::deserialize(&mut c)?;
+        assert_eq!(
+            other_ledger.accounts.iter_tombstones().collect::<Vec<_>>(),
+            vec![(&id, &SpendingCounter::from(1))]
+        );
+        assert_eq!(ledger, other_ledger);
+        Ok(())
+    }
+
     #[cfg(test)]
     fn pack_unpack_bijection<T, Pack, Unpack>(
         pack_method: &Pack,
@@ -1556,6 +1666,14 @@ pub mod test {
             )
         }
 
+        fn extended_fee_pack_unpack_bijection(extended_fee: ExtendedFee) -> TestResult {
+            pack_unpack_bijection(
+                &pack_extended_fee,
+                &unpack_extended_fee,
+                extended_fee
+            )
+        }
+
         fn linear_fee_pack_unpack_bijection(linear_fee: LinearFee) -> TestResult {
             pack_unpack_bijection(
                 &pack_linear_fee,
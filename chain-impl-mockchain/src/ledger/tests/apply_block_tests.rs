@@ -3,6 +3,7 @@ use crate::{
     accounting::account::LedgerError::ValueError,
     chaintypes::ChainLength,
     date::BlockDate,
+    fragment::{Contents, ContentsBuilder},
     ledger::{ledger::Error::Account, Error as LedgerError},
     testing::{
         builders::{GenesisPraosBlockBuilder, TestTxBuilder},
@@ -208,3 +209,79 @@ pub fn apply_block_above_max_content_size() {
         ledger.apply_block(block)
     );
 }
+
+#[test]
+pub fn apply_block_with_fragments_root() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_initials(vec![
+            wallet("Alice").with(1_000).owns("stake_pool"),
+            wallet("Bob").with(1_000),
+        ])
+        .build()
+        .unwrap();
+    let alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let stake_pool = controller.stake_pool("stake_pool").unwrap();
+    let date = BlockDate {
+        epoch: 1,
+        slot_id: 0,
+    };
+
+    let fragment_factory = FragmentFactory::from_ledger(&ledger);
+    let fragment = fragment_factory.transaction(&alice, &bob, &mut ledger, 10);
+    let mut contents = ContentsBuilder::new();
+    contents.push(fragment.clone());
+    let fragments_root = Contents::from(contents).compute_fragments_root();
+
+    let block = GenesisPraosBlockBuilder::new()
+        .with_date(date)
+        .with_fragment(fragment)
+        .with_fragments_root(fragments_root)
+        .with_chain_length(ledger.chain_length())
+        .with_parent_id(ledger.block0_hash)
+        .build(&stake_pool, ledger.era());
+
+    assert_eq!(block.header.fragments_root(), Some(fragments_root));
+    assert!(ledger.apply_block(block).is_ok());
+}
+
+#[test]
+pub fn apply_block_wrong_fragments_root() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_initials(vec![
+            wallet("Alice").with(1_000).owns("stake_pool"),
+            wallet("Bob").with(1_000),
+        ])
+        .build()
+        .unwrap();
+    let alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let stake_pool = controller.stake_pool("stake_pool").unwrap();
+    let date = BlockDate {
+        epoch: 1,
+        slot_id: 0,
+    };
+
+    let fragment_factory = FragmentFactory::from_ledger(&ledger);
+    let fragment = fragment_factory.transaction(&alice, &bob, &mut ledger, 10);
+    let mut contents = ContentsBuilder::new();
+    contents.push(fragment.clone());
+    let fragments_root = Contents::from(contents).compute_fragments_root();
+
+    let block = GenesisPraosBlockBuilder::new()
+        .with_date(date)
+        .with_fragment(fragment)
+        .with_fragments_root(Contents::empty().compute_fragments_root())
+        .with_chain_length(ledger.chain_length())
+        .with_parent_id(ledger.block0_hash)
+        .build(&stake_pool, ledger.era());
+
+    assert!(!block.is_consistent());
+    assert_err!(
+        LedgerError::InvalidFragmentsRoot {
+            actual: fragments_root,
+            expected: Contents::empty().compute_fragments_root(),
+        },
+        ledger.apply_block(block)
+    );
+}
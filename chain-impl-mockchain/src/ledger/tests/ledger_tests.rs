@@ -22,6 +22,7 @@ use crate::{
 };
 
 use chain_addr::Discrimination;
+use chain_time::SlotDuration;
 use quickcheck::TestResult;
 use quickcheck_macros::quickcheck;
 
@@ -309,6 +310,50 @@ pub fn ledger_new_no_slot_duration() {
     );
 }
 
+#[test]
+pub fn ledger_new_slot_duration_millis() {
+    let leader_pair = TestGen::leader_pair();
+    let header_id = TestGen::hash();
+    let mut ie = ConfigParams::new();
+    ie.push(ConfigParam::Discrimination(Discrimination::Test));
+    ie.push(ConfigParam::AddBftLeader(leader_pair.leader_id));
+    ie.push(ConfigParam::SlotDurationMillis(250u32));
+    ie.push(ConfigParam::SlotsPerEpoch(10u32));
+    ie.push(ConfigParam::KESUpdateSpeed(3600));
+    ie.push(ConfigParam::Block0Date(crate::config::Block0Date(0)));
+
+    let mut ledger = Ledger::new(header_id, vec![&Fragment::Initial(ie)]).unwrap();
+    assert_eq!(
+        ledger.settings().slot_duration,
+        SlotDuration::from_millis(250)
+    );
+    assert!(ledger
+        .settings()
+        .to_config_params()
+        .iter()
+        .any(|param| *param == ConfigParam::SlotDurationMillis(250u32)));
+}
+
+#[test]
+pub fn ledger_new_invalid_slot_duration_millis() {
+    let leader_pair = TestGen::leader_pair();
+    let header_id = TestGen::hash();
+    let mut ie = ConfigParams::new();
+    ie.push(ConfigParam::Discrimination(Discrimination::Test));
+    ie.push(ConfigParam::AddBftLeader(leader_pair.leader_id));
+    ie.push(ConfigParam::SlotDurationMillis(0u32));
+    ie.push(ConfigParam::SlotsPerEpoch(10u32));
+    ie.push(ConfigParam::KESUpdateSpeed(3600));
+    ie.push(ConfigParam::Block0Date(crate::config::Block0Date(0)));
+
+    assert_eq!(
+        Ledger::new(header_id, vec![&Fragment::Initial(ie)])
+            .err()
+            .unwrap(),
+        Block0(Block0Error::InitialMessageInvalidSlotDuration)
+    );
+}
+
 #[test]
 pub fn ledger_new_no_slots_per_epoch() {
     let leader_pair = TestGen::leader_pair();
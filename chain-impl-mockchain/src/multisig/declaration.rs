@@ -1,7 +1,7 @@
 use crate::{account, key};
 use chain_crypto::{PublicKey, Signature};
 
-use super::index::{Index, TreeIndex, LEVEL_MAXLIMIT};
+use super::index::{Index, TreeIndex, DEPTH_MAXLIMIT, LEVEL_MAXLIMIT};
 pub use crate::transaction::WitnessMultisigData;
 use thiserror::Error;
 
@@ -35,6 +35,8 @@ pub enum DeclarationError {
     HasNotEnoughOwners,
     #[error("Too many owners")]
     HasTooManyOwners,
+    #[error("Too many levels of sub declarations")]
+    HasTooManyLevels,
     #[error("Sub not implemented")]
     SubNotImplemented,
 }
@@ -56,6 +58,14 @@ pub struct Declaration {
 }
 
 impl Declaration {
+    /// Create a new declaration, checking that the threshold and the
+    /// number of owners are valid
+    pub fn new(threshold: u8, owners: Vec<DeclElement>) -> Result<Self, DeclarationError> {
+        let declaration = Declaration { threshold, owners };
+        declaration.is_valid()?;
+        Ok(declaration)
+    }
+
     pub fn threshold(&self) -> usize {
         self.threshold as usize
     }
@@ -101,7 +111,14 @@ impl Declaration {
         owners_to_identifier(self.threshold, &self.owners)
     }
 
+    /// Check the threshold and the number of owners of the declaration
+    /// and of all its sub declarations, which cannot be nested deeper
+    /// than a TreeIndex can address
     pub fn is_valid(&self) -> Result<(), DeclarationError> {
+        self.is_valid_at_level(1)
+    }
+
+    fn is_valid_at_level(&self, level: usize) -> Result<(), DeclarationError> {
         if self.threshold < 1 || self.threshold as usize > self.owners.len() {
             return Err(DeclarationError::ThresholdInvalid);
         }
@@ -111,9 +128,43 @@ impl Declaration {
         if self.owners.len() > LEVEL_MAXLIMIT {
             return Err(DeclarationError::HasTooManyOwners);
         }
+        for owner in self.owners.iter() {
+            if let DeclElement::Sub(sub) = owner {
+                if level >= DEPTH_MAXLIMIT {
+                    return Err(DeclarationError::HasTooManyLevels);
+                }
+                sub.is_valid_at_level(level + 1)?;
+            }
+        }
         Ok(())
     }
 
+    /// Get the index of the given key among the owners of the declaration,
+    /// or else among the owners of its sub declarations, if present
+    pub fn owner_index(&self, key: &Pk) -> Option<TreeIndex> {
+        let owner = DeclElement::from_publickey(key);
+        let position = |declaration: &Declaration| {
+            declaration
+                .owners
+                .iter()
+                .position(|o| *o == owner)
+                .and_then(|i| Index::from_u8(i as u8))
+        };
+        if let Some(idx) = position(self) {
+            return Some(TreeIndex::D1(idx));
+        }
+        // a valid declaration has no more than 2 levels, as many as a TreeIndex
+        self.owners
+            .iter()
+            .enumerate()
+            .find_map(|(r, element)| match element {
+                DeclElement::Sub(sub) => {
+                    Some(TreeIndex::D2(Index::from_u8(r as u8)?, position(sub)?))
+                }
+                DeclElement::Owner(_) => None,
+            })
+    }
+
     pub fn get_path(&self, ti: TreeIndex) -> Option<(&Declaration, Index)> {
         match ti {
             TreeIndex::D1(idx) => Some((self, idx)),
@@ -2,6 +2,10 @@ use std::cmp::Ordering;
 
 pub const LEVEL_MAXLIMIT: usize = 8;
 
+/// The maximum number of levels of nested declarations that a TreeIndex
+/// can address
+pub const DEPTH_MAXLIMIT: usize = 2;
+
 /// The Index is really just 3 bits and has a hardbound linked to the LEVEL_MAXLIMIT
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Index(u8);
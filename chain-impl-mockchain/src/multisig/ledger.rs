@@ -1,9 +1,11 @@
 use imhamt::{Hamt, HamtIter, InsertError, RemoveError};
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use thiserror::Error;
 
 use super::declaration::{Declaration, DeclarationError, Identifier};
 use crate::accounting::account::{self, DelegationType, Iter, SpendingCounter};
+use crate::certificate::PoolId;
 use crate::value::{Value, ValueError};
 
 #[derive(Clone, PartialEq, Eq, Default)]
@@ -99,6 +101,14 @@ impl Ledger {
         })
     }
 
+    /// Get the state of a multisig account
+    pub fn get_state(
+        &self,
+        identifier: &Identifier,
+    ) -> Result<&account::AccountState<()>, LedgerError> {
+        Ok(self.accounts.get_state(identifier)?)
+    }
+
     pub fn iter_accounts(&self) -> Iter<'_, Identifier, ()> {
         self.accounts.iter()
     }
@@ -141,6 +151,16 @@ impl Ledger {
         })
     }
 
+    /// Remove the given pools from the delegation of every account
+    /// delegating to them.
+    pub fn remove_pools_delegation(&self, pool_ids: &HashSet<PoolId>) -> Result<Self, LedgerError> {
+        let new_accounts = self.accounts.remove_pools_delegation(pool_ids)?;
+        Ok(Self {
+            accounts: new_accounts,
+            declarations: self.declarations.clone(),
+        })
+    }
+
     pub fn get_total_value(&self) -> Result<Value, ValueError> {
         self.accounts.get_total_value()
     }
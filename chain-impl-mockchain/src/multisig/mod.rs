@@ -7,9 +7,9 @@ pub use declaration::{
     DeclElement, Declaration, DeclarationError, Identifier, WitnessMultisigData,
 };
 pub use ledger::{Ledger, LedgerError};
-pub use witness::{Witness, WitnessBuilder};
+pub use witness::{PartialSignature, Witness, WitnessBuilder};
 
-pub use index::{Index, TreeIndex};
+pub use index::{Index, TreeIndex, DEPTH_MAXLIMIT};
 
 #[cfg(any(test, feature = "property-test-api"))]
 mod test {
@@ -141,6 +141,46 @@ mod test {
         }
     }
 
+    #[test]
+    fn witness_serialization_roundtrip() {
+        use chain_core::mempack::{ReadBuf, Readable};
+        use chain_core::property::Serialize;
+
+        let mut rng = rand_core::OsRng;
+        let msg = WitnessMultisigData::new(
+            &key::Hash::hash_bytes(&[1, 2, 3]),
+            &TransactionSignDataHash::digest(&vec![4, 5, 6].into()),
+            SpendingCounter::zero(),
+        );
+        for nb_signatures in 1..=3 {
+            let mut witness_builder = WitnessBuilder::new();
+            for idx in 0..nb_signatures {
+                let (sk, pk, _, i) = make_participant(&mut rng, idx);
+                witness_builder.append(TreeIndex::D1(i), pk, sk.sign(&msg).coerce());
+            }
+            let witness = witness_builder.finalize();
+
+            let bytes = witness.serialize_as_vec().unwrap();
+            let mut buf = ReadBuf::from(&bytes);
+            let decoded = Witness::read(&mut buf).unwrap();
+            buf.expect_end().unwrap();
+            assert_eq!(decoded.serialize_as_vec().unwrap(), bytes);
+        }
+    }
+
+    impl Arbitrary for Declaration {
+        fn arbitrary<G: Gen>(g: &mut G) -> Self {
+            let nb_owners = usize::arbitrary(g) % (index::LEVEL_MAXLIMIT - 1) + 2;
+            let owners = (0..nb_owners)
+                .map(|_| DeclElement::Owner(Arbitrary::arbitrary(g)))
+                .collect();
+            Declaration {
+                threshold: (u8::arbitrary(g) % nb_owners as u8) + 1,
+                owners,
+            }
+        }
+    }
+
     impl Arbitrary for Identifier {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
             let mut b = [0u8; 32];
@@ -33,24 +33,36 @@ impl std::fmt::Display for Witness {
 }
 
 impl Witness {
+    /// Number of owner signatures carried by the witness
+    pub fn signatures(&self) -> usize {
+        self.0.len()
+    }
+
     pub fn verify(&self, declaration: &Declaration, msg: &WitnessMultisigData) -> bool {
         let mut v = Vec::new();
+        let mut subs: BTreeMap<Index, Vec<(Index, Pk)>> = BTreeMap::new();
         for (ti, pk, sig) in self.0.iter() {
+            if sig.verify(pk, msg) == Verification::Failed {
+                return false;
+            };
             match ti {
-                TreeIndex::D2(_, _) => {
-                    // Code doesn't support multi level verification yet
-                    return false;
-                }
-                TreeIndex::D1(i) => {
-                    //let sig: Signature<[u8], account::AccountAlg> = sig.clone().coerce();
-                    if sig.verify(pk, msg) == Verification::Failed {
-                        return false;
-                    };
-                    v.push((*i, pk.clone()))
-                }
+                TreeIndex::D1(i) => v.push((*i, pk.clone())),
+                TreeIndex::D2(r, i) => subs.entry(*r).or_default().push((*i, pk.clone())),
             }
         }
-        if verify_identifier_threshold(declaration, &v[..]).is_err() {
+        // a sub declaration meeting its own threshold counts as one owner
+        let mut satisfied_subs = Vec::new();
+        for (r, witnesses) in subs.iter() {
+            let sub = match declaration.owners.get(r.to_usize()) {
+                Some(DeclElement::Sub(sub)) => sub,
+                _ => return false,
+            };
+            if verify_identifier_threshold(sub, witnesses).is_err() {
+                return false;
+            }
+            satisfied_subs.push(*r);
+        }
+        if verify_threshold(declaration, &v[..], &satisfied_subs).is_err() {
             return false;
         };
         true
@@ -100,7 +112,7 @@ impl Readable for Witness {
         v.push((first_index, first_key, first_sig));
 
         let mut prev_index = first_index;
-        for _ in 0..len {
+        for _ in 1..len {
             let ti = deserialize_index(buf)?;
             if ti <= prev_index {
                 return Err(ReadError::StructureInvalid(
@@ -116,6 +128,39 @@ impl Readable for Witness {
     }
 }
 
+/// Signature of the witness data by one of the owners of a multisig account.
+///
+/// Each owner produces its own partial signature, which are then collected
+/// in a `WitnessBuilder` until the threshold of the declaration is met.
+#[derive(Debug, Clone)]
+pub struct PartialSignature {
+    pub index: TreeIndex,
+    pub public_key: Pk,
+    pub signature: Sig,
+}
+
+impl PartialSignature {
+    /// Sign the witness data as the owner of the given public key, returns
+    /// `None` if the key is not one of the owners of the declaration or of
+    /// its sub declarations.
+    pub fn new<F>(
+        declaration: &Declaration,
+        public_key: Pk,
+        data: &WitnessMultisigData,
+        sign: F,
+    ) -> Option<Self>
+    where
+        F: FnOnce(&WitnessMultisigData) -> Sig,
+    {
+        let index = declaration.owner_index(&public_key)?;
+        Some(PartialSignature {
+            index,
+            public_key,
+            signature: sign(data),
+        })
+    }
+}
+
 #[derive(Default)]
 pub struct WitnessBuilder(BTreeMap<TreeIndex, (Pk, Sig)>);
 
@@ -130,6 +175,48 @@ impl WitnessBuilder {
         assert_eq!(r.is_none(), true);
     }
 
+    /// Add the partial signature of one of the owners, returns false if a
+    /// signature was already collected for this owner
+    pub fn append_partial(&mut self, partial: PartialSignature) -> bool {
+        if self.0.contains_key(&partial.index) {
+            return false;
+        }
+        self.0
+            .insert(partial.index, (partial.public_key, partial.signature));
+        true
+    }
+
+    /// Number of signatures collected so far
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Check if enough signatures are collected to meet the threshold of the
+    /// declaration, a sub declaration counting as one owner once its own
+    /// threshold is met
+    pub fn threshold_met(&self, declaration: &Declaration) -> bool {
+        let mut subs: BTreeMap<Index, usize> = BTreeMap::new();
+        let mut owners = 0;
+        for index in self.0.keys() {
+            match index {
+                TreeIndex::D1(_) => owners += 1,
+                TreeIndex::D2(r, _) => *subs.entry(*r).or_default() += 1,
+            }
+        }
+        for (r, signatures) in subs {
+            if let Some(DeclElement::Sub(sub)) = declaration.owners.get(r.to_usize()) {
+                if signatures >= sub.threshold() {
+                    owners += 1;
+                }
+            }
+        }
+        owners >= declaration.threshold()
+    }
+
     pub fn finalize(&self) -> Witness {
         let mut v = Vec::new();
         for (idx, (pk, sig)) in self.0.iter() {
@@ -147,7 +234,17 @@ pub fn verify_identifier_threshold(
     declaration: &Declaration,
     witnesses: &[(Index, PublicKey<Ed25519>)],
 ) -> Result<(), LedgerError> {
-    if witnesses.len() < declaration.threshold() {
+    verify_threshold(declaration, witnesses, &[])
+}
+
+/// Same as `verify_identifier_threshold`, with the owners at the given
+/// indices being sub declarations whose threshold is already verified
+fn verify_threshold(
+    declaration: &Declaration,
+    witnesses: &[(Index, PublicKey<Ed25519>)],
+    satisfied_subs: &[Index],
+) -> Result<(), LedgerError> {
+    if witnesses.len() + satisfied_subs.len() < declaration.threshold() {
         return Err(LedgerError::ThresholdNotMet);
     }
 
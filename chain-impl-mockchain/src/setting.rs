@@ -8,23 +8,26 @@ use crate::{
     chaineval::PraosNonce,
     chaintypes::ConsensusType,
     config::{ConfigParam, RewardParams},
-    fee::LinearFee,
+    fee::{DynamicFeeParams, ExtendedFee, LinearFee},
     key::BftLeaderId,
     rewards,
+    value::Value,
     vote::CommitteeId,
 };
+use chain_time::SlotDuration;
 use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
 use std::num::NonZeroU32;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Settings {
     pub consensus_version: ConsensusType,
     pub consensus_nonce: PraosNonce,
     pub slots_per_epoch: u32,
-    pub slot_duration: u8,
+    pub slot_duration: SlotDuration,
     pub epoch_stability_depth: u32,
     pub active_slots_coeff: ActiveSlotsCoeff,
     pub block_content_max_size: BlockContentSize,
@@ -41,6 +44,15 @@ pub struct Settings {
     pub rewards_limit: rewards::Limit,
     pub pool_participation_capping: Option<(NonZeroU32, NonZeroU32)>,
     pub committees: Arc<Box<[CommitteeId]>>,
+    /// Deposit locked when registering a stake pool, refunded to the pool's
+    /// reward account when the pool retires.
+    pub pool_registration_deposit: Value,
+    /// Refundable deposit locked on an account the first time it delegates,
+    /// returned when the account is deregistered.
+    pub stake_key_deposit: Value,
+    /// Optional rule adjusting the `linear_fees` constant according to the
+    /// block utilisation.
+    pub dynamic_fees: Option<DynamicFeeParams>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -114,7 +126,7 @@ impl Settings {
             consensus_version: ConsensusType::Bft,
             consensus_nonce: PraosNonce::zero(),
             slots_per_epoch: 1,
-            slot_duration: 10,         // 10 sec
+            slot_duration: SlotDuration::from_secs(10),
             epoch_stability_depth: 10, // num of block
             active_slots_coeff: ActiveSlotsCoeff::try_from(Milli::HALF).unwrap(),
             block_content_max_size: 102_400,
@@ -127,6 +139,9 @@ impl Settings {
             rewards_limit: rewards::Limit::None,
             pool_participation_capping: None,
             committees: Arc::new(Box::new([])),
+            pool_registration_deposit: Value::zero(),
+            stake_key_deposit: Value::zero(),
+            dynamic_fees: None,
         }
     }
 
@@ -134,10 +149,25 @@ impl Settings {
         self.linear_fees
     }
 
+    /// Adjust the fee constant following the dynamic fee rule, if any, given
+    /// the average block content size observed during the last period.
+    pub fn adjust_linear_fees(&self, average_content_size: u64) -> Self {
+        let mut new_state = self.clone();
+        if let Some(dynamic_fees) = &self.dynamic_fees {
+            new_state.linear_fees.constant = dynamic_fees.adjust_constant(
+                self.linear_fees.constant,
+                average_content_size,
+                self.block_content_max_size as u64,
+            );
+        }
+        new_state
+    }
+
     pub fn apply(&self, changes: &ConfigParams) -> Result<Self, update::Error> {
         let mut new_state = self.clone();
         let mut per_certificate_fees = None;
         let mut per_vote_certificate_fees = None;
+        let mut extended_fees = None;
 
         for param in changes.iter() {
             match param {
@@ -155,7 +185,11 @@ impl Settings {
                     new_state.slots_per_epoch = *d;
                 }
                 ConfigParam::SlotDuration(d) => {
-                    new_state.slot_duration = *d;
+                    new_state.slot_duration = SlotDuration::from_secs(u32::from(*d));
+                }
+                ConfigParam::SlotDurationMillis(d) => {
+                    new_state.slot_duration =
+                        slot_duration_from_millis(*d).ok_or(update::Error::BadSlotDuration(*d))?;
                 }
                 ConfigParam::EpochStabilityDepth(d) => {
                     new_state.epoch_stability_depth = *d;
@@ -237,6 +271,18 @@ impl Settings {
                             .into(),
                     );
                 }
+                ConfigParam::PoolRegistrationDeposit(value) => {
+                    new_state.pool_registration_deposit = *value;
+                }
+                ConfigParam::StakeKeyDeposit(value) => {
+                    new_state.stake_key_deposit = *value;
+                }
+                ConfigParam::DynamicFees(params) => {
+                    new_state.dynamic_fees = *params;
+                }
+                ConfigParam::ExtendedFees(ef) => {
+                    extended_fees = Some(ef);
+                }
             }
         }
 
@@ -248,6 +294,10 @@ impl Settings {
             new_state.linear_fees.per_vote_certificate_fees(*pcf);
         }
 
+        if let Some(ef) = extended_fees {
+            new_state.linear_fees.extended_fees(*ef);
+        }
+
         Ok(new_state)
     }
 
@@ -256,7 +306,7 @@ impl Settings {
 
         params.push(ConfigParam::ConsensusVersion(self.consensus_version));
         params.push(ConfigParam::SlotsPerEpoch(self.slots_per_epoch));
-        params.push(ConfigParam::SlotDuration(self.slot_duration));
+        params.push(slot_duration_to_config_param(self.slot_duration));
         params.push(ConfigParam::EpochStabilityDepth(self.epoch_stability_depth));
         params.push(ConfigParam::ConsensusGenesisPraosActiveSlotsCoeff(
             self.active_slots_coeff.into(),
@@ -278,6 +328,20 @@ impl Settings {
             Some(p) => params.push(ConfigParam::TreasuryParams(*p)),
             None => (),
         };
+        if self.pool_registration_deposit != Value::zero() {
+            params.push(ConfigParam::PoolRegistrationDeposit(
+                self.pool_registration_deposit,
+            ));
+        }
+        if self.stake_key_deposit != Value::zero() {
+            params.push(ConfigParam::StakeKeyDeposit(self.stake_key_deposit));
+        }
+        if self.dynamic_fees.is_some() {
+            params.push(ConfigParam::DynamicFees(self.dynamic_fees));
+        }
+        if self.linear_fees.extended_fees != ExtendedFee::default() {
+            params.push(ConfigParam::ExtendedFees(self.linear_fees.extended_fees));
+        }
 
         debug_assert_eq!(self, &Settings::new().apply(&params).unwrap());
 
@@ -328,10 +392,29 @@ impl Default for Settings {
     }
 }
 
+pub(crate) fn slot_duration_from_millis(millis: u32) -> Option<SlotDuration> {
+    SlotDuration::from_duration(Duration::from_millis(u64::from(millis)))
+}
+
+/// The slot duration in whole seconds when it fits, so that the settings
+/// of the chains not using sub-second slots keep the same encoding
+fn slot_duration_to_config_param(slot_duration: SlotDuration) -> ConfigParam {
+    let millis = slot_duration.as_millis();
+    if millis % 1000 == 0 && millis / 1000 <= u64::from(u8::MAX) {
+        ConfigParam::SlotDuration((millis / 1000) as u8)
+    } else {
+        ConfigParam::SlotDurationMillis(millis as u32)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{FeesGoesTo, Settings};
+    use crate::config::ConfigParam;
+    use crate::fee::{DynamicFeeParams, DynamicFeePeriod};
+    use crate::fragment::config::ConfigParams;
     use quickcheck::{Arbitrary, Gen};
+    use std::num::NonZeroU64;
 
     impl Arbitrary for FeesGoesTo {
         fn arbitrary<G: Gen>(g: &mut G) -> Self {
@@ -348,4 +431,30 @@ mod tests {
             Settings::new()
         }
     }
+
+    #[test]
+    fn dynamic_fees_can_be_disabled() {
+        let params = DynamicFeeParams {
+            period: DynamicFeePeriod::Block,
+            target_utilisation: 50,
+            max_change_denominator: NonZeroU64::new(8).unwrap(),
+            min_constant: 1,
+            max_constant: 100,
+        };
+        let apply = |settings: &Settings, param| {
+            let mut changes = ConfigParams::new();
+            changes.push(param);
+            settings.apply(&changes).unwrap()
+        };
+
+        let enabled = apply(&Settings::new(), ConfigParam::DynamicFees(Some(params)));
+        assert_eq!(enabled.dynamic_fees, Some(params));
+        assert!(enabled
+            .to_config_params()
+            .iter()
+            .any(|p| *p == ConfigParam::DynamicFees(Some(params))));
+
+        let disabled = apply(&enabled, ConfigParam::DynamicFees(None));
+        assert_eq!(disabled.dynamic_fees, None);
+    }
 }
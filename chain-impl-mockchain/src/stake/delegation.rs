@@ -1,6 +1,6 @@
 use crate::certificate::{PoolId, PoolRegistration, PoolRegistrationHash};
 use crate::date::Epoch;
-use crate::value::Value;
+use crate::value::{Value, ValueError};
 use imhamt::Hamt;
 use std::collections::hash_map::DefaultHasher;
 use std::fmt::{self, Debug};
@@ -39,13 +39,23 @@ impl PoolLastRewards {
 pub struct PoolState {
     pub last_rewards: PoolLastRewards,
     pub registration: Arc<PoolRegistration>,
+    /// Deposit locked at registration, refunded when the pool retires
+    pub deposit: Value,
+    /// Epoch at which the pool is scheduled to be retired, if any
+    pub retirement_epoch: Option<Epoch>,
 }
 
 impl PoolState {
     pub fn new(reg: PoolRegistration) -> Self {
+        Self::new_with_deposit(reg, Value::zero())
+    }
+
+    pub fn new_with_deposit(reg: PoolRegistration, deposit: Value) -> Self {
         PoolState {
             last_rewards: PoolLastRewards::default(),
             registration: Arc::new(reg),
+            deposit,
+            retirement_epoch: None,
         }
     }
 
@@ -160,11 +170,50 @@ impl PoolsState {
         Ok(())
     }
 
+    /// Schedule the retirement of a stake pool at the beginning of the given epoch
+    pub fn stake_pool_schedule_retirement(
+        &mut self,
+        pool_id: &PoolId,
+        epoch: Epoch,
+    ) -> Result<(), PoolError> {
+        self.stake_pools = self
+            .stake_pools
+            .replace_with(pool_id, |st| {
+                let mut st = st.clone();
+                st.retirement_epoch = Some(epoch);
+                st
+            })
+            .map_err(|_| PoolError::NotFound(pool_id.clone()))?;
+        Ok(())
+    }
+
+    /// All the stake pools which are scheduled to retire at or before the given epoch
+    pub fn stake_pools_retiring_by(&self, epoch: Epoch) -> Vec<PoolId> {
+        self.stake_pools
+            .iter()
+            .filter(|(_, st)| matches!(st.retirement_epoch, Some(e) if e <= epoch))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// Total value of the deposits locked by the registered stake pools
+    pub fn get_total_deposits(&self) -> Result<Value, ValueError> {
+        Value::sum(self.stake_pools.iter().map(|(_, st)| st.deposit))
+    }
+
     pub fn register_stake_pool(&self, owner: PoolRegistration) -> Result<Self, PoolError> {
+        self.register_stake_pool_with_deposit(owner, Value::zero())
+    }
+
+    pub fn register_stake_pool_with_deposit(
+        &self,
+        owner: PoolRegistration,
+        deposit: Value,
+    ) -> Result<Self, PoolError> {
         let id = owner.to_id();
         let new_pools = self
             .stake_pools
-            .insert(id.clone(), PoolState::new(owner))
+            .insert(id.clone(), PoolState::new_with_deposit(owner, deposit))
             .map_err(|_| PoolError::AlreadyExists(id))?;
         Ok(PoolsState {
             stake_pools: new_pools,
@@ -211,6 +260,8 @@ mod tests {
             PoolState {
                 last_rewards: PoolLastRewards::arbitrary(gen),
                 registration,
+                deposit: Value(u64::arbitrary(gen)),
+                retirement_epoch: Arbitrary::arbitrary(gen),
             }
         }
     }
@@ -6,6 +6,7 @@ use crate::{
     utxo,
 };
 use chain_addr::{Address, Kind};
+use rayon::prelude::*;
 use std::collections::{hash_map, HashMap};
 use std::sync::Arc;
 
@@ -70,6 +71,12 @@ impl PoolStakeDistribution {
         self.total += s;
     }
 
+    fn merge(&mut self, other: PoolStakeDistribution) {
+        for (id, s) in other.accounts {
+            self.add(id, s)
+        }
+    }
+
     pub fn to_total(&self) -> Stake {
         Stake::sum(self.accounts.values().copied())
     }
@@ -105,6 +112,21 @@ impl StakeDistribution {
     pub fn get_distribution(&self, pool_id: &PoolId) -> Option<&PoolStakeInformation> {
         self.to_pools.get(pool_id)
     }
+
+    /// Add the stake of a distribution computed over the same pools
+    fn merge(mut self, other: StakeDistribution) -> Self {
+        self.unassigned += other.unassigned;
+        self.dangling += other.dangling;
+        for (pool_id, info) in other.to_pools {
+            match self.to_pools.entry(pool_id) {
+                hash_map::Entry::Occupied(mut entry) => entry.get_mut().stake.merge(info.stake),
+                hash_map::Entry::Vacant(entry) => {
+                    entry.insert(info);
+                }
+            }
+        }
+        self
+    }
 }
 
 fn assign_account_value(
@@ -162,7 +184,7 @@ pub fn get_distribution(
     dstate: &PoolsState,
     utxos: &utxo::Ledger<Address>,
 ) -> StakeDistribution {
-    let mut distribution = StakeDistribution {
+    let empty = StakeDistribution {
         unassigned: Stake::zero(),
         dangling: Stake::zero(),
         to_pools: dstate
@@ -180,14 +202,23 @@ pub fn get_distribution(
             .collect(),
     };
 
-    for (identifier, account_state) in accounts.iter() {
-        assign_account_value(
-            &mut distribution,
-            identifier,
-            &account_state.delegation(),
-            Stake::from_value(account_state.value()),
+    // the accounts are assigned in parallel, each worker starting from
+    // an empty distribution over the same pools
+    let mut distribution = accounts
+        .par_iter()
+        .fold(
+            || empty.clone(),
+            |mut distribution, (identifier, account_state)| {
+                assign_account_value(
+                    &mut distribution,
+                    identifier,
+                    &account_state.delegation(),
+                    Stake::from_value(account_state.value()),
+                );
+                distribution
+            },
         )
-    }
+        .reduce(|| empty.clone(), StakeDistribution::merge);
 
     for output in utxos.values() {
         // We're only interested in "group" addresses
@@ -13,6 +13,7 @@ pub struct GenesisPraosBlockBuilder {
     date: Option<BlockDate>,
     chain_length: Option<ChainLength>,
     parent_id: Option<Hash>,
+    fragments_root: Option<Hash>,
     contents_builder: ContentsBuilder,
 }
 
@@ -28,6 +29,7 @@ impl GenesisPraosBlockBuilder {
             date: None,
             chain_length: None,
             parent_id: None,
+            fragments_root: None,
             contents_builder: ContentsBuilder::new(),
         }
     }
@@ -54,6 +56,11 @@ impl GenesisPraosBlockBuilder {
         self
     }
 
+    pub fn with_fragments_root(&mut self, fragments_root: Hash) -> &mut Self {
+        self.fragments_root = Some(fragments_root);
+        self
+    }
+
     pub fn with_fragment(&mut self, fragment: Fragment) -> &mut Self {
         self.contents_builder.push(fragment);
         self
@@ -72,7 +79,11 @@ impl GenesisPraosBlockBuilder {
         }
         let vrf_proof = TestGen::vrf_proof(&stake_pool);
         let contents: Contents = self.contents_builder.clone().into();
-        let header = HeaderBuilderNew::new(BlockVersion::KesVrfproof, &contents)
+        let mut header_builder = HeaderBuilderNew::new(BlockVersion::KesVrfproof, &contents);
+        if let Some(fragments_root) = &self.fragments_root {
+            header_builder = header_builder.set_fragments_root(fragments_root);
+        }
+        let header = header_builder
             .set_parent(
                 &self.parent_id.unwrap(),
                 self.chain_length.unwrap().increase(),
@@ -1,12 +1,14 @@
 use crate::{
     account::{DelegationType, Identifier},
     certificate::{
-        Certificate, OwnerStakeDelegation, PoolId, PoolRegistration, PoolRetirement, PoolUpdate,
-        StakeDelegation, VotePlanId, VoteTally,
+        AccountDeregistration, Certificate, MultisigRegistration, OwnerStakeDelegation, PoolId,
+        PoolRegistration, PoolRetirement, PoolUpdate, StakeDelegation, VotePlanId, VoteTally,
     },
+    multisig::Declaration,
     testing::data::AddressData,
     transaction::UnspecifiedAccountIdentifier,
 };
+use chain_addr::Address;
 use chain_time::units::DurationSeconds;
 
 pub fn build_stake_delegation_cert(
@@ -22,6 +24,25 @@ pub fn build_stake_delegation_cert(
     })
 }
 
+pub fn build_multisig_registration_cert(declaration: &Declaration) -> Certificate {
+    Certificate::MultisigRegistration(MultisigRegistration {
+        declaration: declaration.clone(),
+    })
+}
+
+pub fn build_account_deregistration_cert(
+    account: &AddressData,
+    destination: Address,
+) -> Certificate {
+    let account_id = UnspecifiedAccountIdentifier::from_single_account(Identifier::from(
+        account.delegation_key(),
+    ));
+    Certificate::AccountDeregistration(AccountDeregistration {
+        account_id,
+        destination,
+    })
+}
+
 pub fn build_stake_pool_registration_cert(stake_pool: &PoolRegistration) -> Certificate {
     Certificate::PoolRegistration(stake_pool.clone())
 }
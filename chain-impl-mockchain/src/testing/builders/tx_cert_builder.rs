@@ -24,11 +24,22 @@ use std::iter;
 pub struct TestTxCertBuilder {
     block0_hash: HeaderId,
     fee: LinearFee,
+    deposit: Value,
 }
 
 impl TestTxCertBuilder {
     pub fn new(block0_hash: HeaderId, fee: LinearFee) -> Self {
-        Self { block0_hash, fee }
+        Self {
+            block0_hash,
+            fee,
+            deposit: Value::zero(),
+        }
+    }
+
+    /// add a deposit to be paid by the funder on top of the fee
+    pub fn with_deposit(mut self, deposit: Value) -> Self {
+        self.deposit = deposit;
+        self
     }
 
     pub fn block0_hash(&self) -> &HeaderId {
@@ -185,6 +196,32 @@ impl TestTxCertBuilder {
                 let tx = builder.set_payload_auth(&committee_signature);
                 Fragment::EncryptedVoteTally(tx)
             }
+            Certificate::AccountDeregistration(s) => {
+                let builder = self.set_initial_ios(
+                    TxBuilder::new().set_payload(s),
+                    funder,
+                    inputs,
+                    outputs,
+                    make_witness,
+                );
+                let signature =
+                    AccountBindingSignature::new_single(&builder.get_auth_data(), |d| {
+                        keys[0].sign_slice(d.0)
+                    });
+                let tx = builder.set_payload_auth(&signature);
+                Fragment::AccountDeregistration(tx)
+            }
+            Certificate::MultisigRegistration(s) => {
+                let builder = self.set_initial_ios(
+                    TxBuilder::new().set_payload(s),
+                    funder,
+                    inputs,
+                    outputs,
+                    make_witness,
+                );
+                let tx = builder.set_payload_auth(&());
+                Fragment::MultisigRegistration(tx)
+            }
         }
     }
 
@@ -211,7 +248,8 @@ impl TestTxCertBuilder {
         T: IntoIterator<Item = &'a Wallet>,
     {
         let keys = signers.into_iter().map(|x| x.private_key()).collect();
-        let input = funder.make_input_with_value(self.fee(certificate));
+        let value = (self.fee(certificate) + self.deposit).unwrap();
+        let input = funder.make_input_with_value(value);
         self.fragment(certificate, keys, &[input], &[], true, funder)
     }
 }
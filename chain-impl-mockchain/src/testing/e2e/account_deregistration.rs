@@ -0,0 +1,145 @@
+use crate::{
+    fee::LinearFee,
+    testing::{
+        ledger::ConfigBuilder,
+        scenario::{prepare_scenario, wallet},
+        verifiers::LedgerStateVerifier,
+    },
+    value::Value,
+};
+
+#[test]
+pub fn account_deregistration_sweeps_value_to_destination() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(ConfigBuilder::new(0).with_fee(LinearFee::new(1, 1, 1)))
+        .with_initials(vec![wallet("Alice").with(1_000), wallet("Bob").with(1_000)])
+        .build()
+        .unwrap();
+
+    let alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let total_funds = ledger.total_funds();
+
+    controller
+        .deregisters(&alice, &alice, &bob, &mut ledger)
+        .unwrap();
+
+    let mut ledger_verifier = LedgerStateVerifier::new(ledger.clone().into());
+    ledger_verifier
+        .info("after deregistration")
+        .account(alice.as_account_data())
+        .does_not_exist();
+    ledger_verifier
+        .account(bob.as_account_data())
+        .has_value(&Value(1_997));
+    assert_eq!(ledger.total_funds(), total_funds);
+}
+
+#[test]
+pub fn stake_key_deposit_is_refunded_on_deregistration() {
+    let deposit = Value(100);
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(
+            ConfigBuilder::new(0)
+                .with_fee(LinearFee::new(1, 1, 1))
+                .with_stake_key_deposit(deposit),
+        )
+        .with_initials(vec![
+            wallet("Alice").with(1_000).owns("stake_pool"),
+            wallet("Bob").with(1_000),
+        ])
+        .build()
+        .unwrap();
+
+    let alice = controller.wallet("Alice").unwrap();
+    let mut bob = controller.wallet("Bob").unwrap();
+    let stake_pool = controller.stake_pool("stake_pool").unwrap();
+    let total_funds = ledger.total_funds();
+
+    assert!(controller
+        .delegates(&bob, &stake_pool, &mut ledger)
+        .is_err());
+
+    controller
+        .delegates_with_deposit(&bob, &stake_pool, deposit, &mut ledger)
+        .unwrap();
+    bob.confirm_transaction();
+
+    LedgerStateVerifier::new(ledger.clone().into())
+        .info("after delegation")
+        .account(bob.as_account_data())
+        .has_value(&Value(897));
+    assert_eq!(ledger.total_funds(), total_funds);
+
+    // the deposit is only locked once per account
+    controller
+        .delegates(&bob, &stake_pool, &mut ledger)
+        .unwrap();
+    bob.confirm_transaction();
+
+    controller
+        .deregisters(&bob, &bob, &alice, &mut ledger)
+        .unwrap();
+
+    let mut ledger_verifier = LedgerStateVerifier::new(ledger.clone().into());
+    ledger_verifier
+        .info("after deregistration")
+        .account(bob.as_account_data())
+        .does_not_exist();
+    ledger_verifier
+        .account(alice.as_account_data())
+        .has_value(&Value(1_991));
+    assert_eq!(ledger.total_funds(), total_funds);
+}
+
+#[test]
+pub fn transactions_before_deregistration_cannot_be_replayed() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_initials(vec![wallet("Alice").with(1_000), wallet("Bob").with(1_000)])
+        .build()
+        .unwrap();
+
+    let mut alice = controller.wallet("Alice").unwrap();
+    let mut bob = controller.wallet("Bob").unwrap();
+
+    let transaction = controller
+        .fragment_factory()
+        .transaction(&alice, &bob, &mut ledger, 100);
+    ledger.apply_transaction(transaction.clone()).unwrap();
+    alice.confirm_transaction();
+
+    controller
+        .deregisters(&bob, &alice, &bob, &mut ledger)
+        .unwrap();
+    bob.confirm_transaction();
+
+    // funding the account again doesn't reset its spending counter
+    controller
+        .transfer_funds(&bob, &alice, &mut ledger, 500)
+        .unwrap();
+
+    assert!(ledger.apply_transaction(transaction).is_err());
+    LedgerStateVerifier::new(ledger.into())
+        .info("after replay")
+        .account(alice.as_account_data())
+        .has_value(&Value(497));
+}
+
+#[test]
+pub fn account_deregistration_to_itself_is_rejected() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_initials(vec![wallet("Alice").with(1_000)])
+        .build()
+        .unwrap();
+
+    let alice = controller.wallet("Alice").unwrap();
+
+    assert!(controller
+        .deregisters(&alice, &alice, &alice, &mut ledger)
+        .is_err());
+
+    LedgerStateVerifier::new(ledger.into())
+        .info("after rejected deregistration")
+        .account(alice.as_account_data())
+        .has_value(&Value(1_000));
+}
@@ -1,11 +1,20 @@
 use crate::{
-    fee::{LinearFee, PerCertificateFee},
+    date::BlockDate,
+    fee::{
+        DynamicFeeParams, DynamicFeePeriod, ExtendedFee, FeeAlgorithm, LinearFee,
+        PerCertificateFee, WitnessCount,
+    },
+    fragment::Fragment,
     testing::{
         builders::StakePoolBuilder,
-        ledger::ConfigBuilder,
+        data::Wallet,
+        ledger::{ConfigBuilder, TestLedger},
         scenario::{prepare_scenario, wallet},
         verifiers::LedgerStateVerifier,
     },
+    transaction::{
+        Balance, InputOutputBuilder, NoExtra, OutputPolicy, Payload, Transaction, TxBuilder,
+    },
     value::Value,
 };
 use chain_addr::Discrimination;
@@ -182,3 +191,168 @@ pub fn owner_delegates_fee() {
 
     ledger_verifier.total_value_is(&Value(expected_total_funds_after));
 }
+
+fn dynamic_fees(period: DynamicFeePeriod) -> DynamicFeeParams {
+    DynamicFeeParams {
+        period,
+        target_utilisation: 10,
+        max_change_denominator: NonZeroU64::new(1).unwrap(),
+        min_constant: 1,
+        max_constant: 100,
+    }
+}
+
+#[test]
+pub fn dynamic_fees_follow_block_utilisation() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(
+            ConfigBuilder::new(0)
+                .with_fee(LinearFee::new(10, 1, 1))
+                .with_block_content_max_size(1_000)
+                .with_dynamic_fees(dynamic_fees(DynamicFeePeriod::Block)),
+        )
+        .with_initials(vec![
+            wallet("Alice").with(1_000).owns("stake_pool"),
+            wallet("Bob").with(1_000),
+        ])
+        .build()
+        .unwrap();
+
+    let alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let stake_pool = controller.stake_pool("stake_pool").unwrap();
+
+    let fragment = controller
+        .fragment_factory()
+        .transaction(&alice, &bob, &mut ledger, 100);
+    ledger.produce_block(&stake_pool, vec![fragment]).unwrap();
+
+    let constant_after_full_block = ledger.fee().constant;
+    assert!(constant_after_full_block > 10);
+
+    ledger.produce_empty_block(&stake_pool).unwrap();
+    assert!(ledger.fee().constant < constant_after_full_block);
+
+    for _ in 0..10 {
+        ledger.produce_empty_block(&stake_pool).unwrap();
+    }
+    assert_eq!(ledger.fee().constant, 1);
+}
+
+#[test]
+pub fn dynamic_fees_are_adjusted_at_epoch_boundary() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(
+            ConfigBuilder::new(0)
+                .with_fee(LinearFee::new(10, 1, 1))
+                .with_slots_per_epoch(10)
+                .with_block_content_max_size(1_000)
+                .with_dynamic_fees(dynamic_fees(DynamicFeePeriod::Epoch)),
+        )
+        .with_initials(vec![
+            wallet("Alice").with(1_000).owns("stake_pool"),
+            wallet("Bob").with(1_000),
+        ])
+        .build()
+        .unwrap();
+
+    let alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let stake_pool = controller.stake_pool("stake_pool").unwrap();
+
+    let fragment = controller
+        .fragment_factory()
+        .transaction(&alice, &bob, &mut ledger, 100);
+    ledger.produce_block(&stake_pool, vec![fragment]).unwrap();
+    assert_eq!(ledger.fee().constant, 10);
+
+    ledger.fast_forward_to(BlockDate {
+        epoch: 0,
+        slot_id: 9,
+    });
+    ledger.distribute_rewards().unwrap();
+    ledger.produce_empty_block(&stake_pool).unwrap();
+    assert!(ledger.fee().constant > 10);
+}
+
+fn build_transfer(
+    ledger: &TestLedger,
+    fee: &LinearFee,
+    from: &mut Wallet,
+    to: &Wallet,
+    value: Value,
+) -> Transaction<NoExtra> {
+    let mut io_builder = InputOutputBuilder::empty();
+    io_builder
+        .add_input(&from.make_input_with_value(Value(500)))
+        .unwrap();
+    io_builder.add_output(to.as_address(), value).unwrap();
+    let (balance, _, io) = io_builder
+        .seal_with_output_policy(
+            NoExtra.payload_data().borrow(),
+            fee,
+            OutputPolicy::One(from.as_address()),
+        )
+        .unwrap();
+    assert!(matches!(balance, Balance::Zero));
+
+    let builder = TxBuilder::new()
+        .set_nopayload()
+        .set_ios(&io.inputs, &io.outputs);
+    let witness = from.make_witness(ledger.block0_hash(), builder.get_auth_data_for_witness());
+    builder.set_witnesses(&[witness]).set_payload_auth(&())
+}
+
+#[test]
+pub fn extended_fees_charge_size_and_witnesses() {
+    let linear_fee = LinearFee::new(1, 1, 1);
+    let extended_fee = ExtendedFee::new(1, 0, 10, 0);
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(
+            ConfigBuilder::new(0)
+                .with_fee(linear_fee)
+                .with_extended_fee(extended_fee),
+        )
+        .with_initials(vec![wallet("Alice").with(1_000), wallet("Bob").with(1_000)])
+        .build()
+        .unwrap();
+
+    let mut alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+
+    // a transaction balanced without the extended fee is rejected
+    let tx = build_transfer(&ledger, &linear_fee, &mut alice, &bob, Value(100));
+    assert!(ledger.apply_transaction(Fragment::Transaction(tx)).is_err());
+
+    let fee = ledger.fee();
+    assert_eq!(fee.extended_fees, extended_fee);
+    let tx = build_transfer(&ledger, &fee, &mut alice, &bob, Value(100));
+
+    // constant + 3 ios + 125 bytes of body + one account witness
+    let expected_fee = Value(1 + 3 + 125 + 10);
+    let builder = TxBuilder::new().set_nopayload().set_ios(
+        &tx.as_slice().inputs().iter().collect::<Vec<_>>(),
+        &tx.as_slice().outputs().iter().collect::<Vec<_>>(),
+    );
+    let witnesses = WitnessCount {
+        account: 1,
+        ..WitnessCount::default()
+    };
+    assert_eq!(builder.estimate_fee(&fee, witnesses), expected_fee);
+    assert_eq!(fee.calculate_tx(&tx.as_slice()), expected_fee);
+
+    ledger.apply_transaction(Fragment::Transaction(tx)).unwrap();
+    alice.confirm_transaction();
+
+    let mut ledger_verifier = LedgerStateVerifier::new(ledger.into());
+    ledger_verifier
+        .info("after transfer")
+        .pots()
+        .has_fee_equals_to(&expected_fee);
+    ledger_verifier
+        .account(alice.as_account_data())
+        .has_value(&Value(1_000 - 100 - expected_fee.0));
+    ledger_verifier
+        .account(bob.as_account_data())
+        .has_value(&Value(1_100));
+}
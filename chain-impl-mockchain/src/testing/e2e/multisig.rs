@@ -0,0 +1,361 @@
+use crate::{
+    account::SpendingCounter,
+    fee::{ExtendedFee, FeeAlgorithm, LinearFee},
+    fragment::Fragment,
+    multisig::{DeclElement, Declaration, DeclarationError, PartialSignature, WitnessBuilder},
+    testing::{
+        data::Wallet,
+        ledger::{ConfigBuilder, TestLedger},
+        scenario::{prepare_scenario, wallet},
+        verifiers::LedgerStateVerifier,
+    },
+    transaction::{InputOutputBuilder, NoExtra, Payload, TxBuilder, Witness},
+    value::Value,
+};
+use chain_addr::{Address, Kind};
+
+fn declaration(threshold: u8, owners: &[&Wallet]) -> Declaration {
+    let owners = owners
+        .iter()
+        .map(|owner| DeclElement::from_publickey(&owner.public_key()))
+        .collect();
+    Declaration::new(threshold, owners).unwrap()
+}
+
+fn multisig_address(ledger: &TestLedger, declaration: &Declaration) -> Address {
+    Address(
+        ledger.discrimination(),
+        Kind::Multisig(declaration.to_identifier().into()),
+    )
+}
+
+fn fund_multisig(
+    ledger: &mut TestLedger,
+    funder: &mut Wallet,
+    declaration: &Declaration,
+    value: Value,
+) {
+    let fee = ledger.fee().calculate(None, 1, 1);
+    let inputs = [funder.make_input_with_value((value + fee).unwrap())];
+    let outputs = [crate::transaction::Output {
+        address: multisig_address(ledger, declaration),
+        value,
+    }];
+    let builder = TxBuilder::new().set_nopayload().set_ios(&inputs, &outputs);
+    let witness = funder.make_witness(ledger.block0_hash(), builder.get_auth_data_for_witness());
+    let tx = builder.set_witnesses(&[witness]).set_payload_auth(&());
+    ledger
+        .apply_fragment(&Fragment::Transaction(tx), ledger.date())
+        .unwrap();
+    funder.confirm_transaction();
+}
+
+fn spend_from_multisig(
+    ledger: &TestLedger,
+    declaration: &Declaration,
+    signers: &[&Wallet],
+    destination: &Wallet,
+    value: Value,
+) -> Fragment {
+    let fee = ledger.fee();
+    let mut io_builder = InputOutputBuilder::empty();
+    io_builder
+        .add_multisig_input(&declaration.to_identifier(), value, signers.len() as u8)
+        .unwrap();
+    io_builder
+        .add_output(destination.as_address(), Value::zero())
+        .unwrap();
+    let output_value =
+        (value - io_builder.estimate_fee(NoExtra.payload_data().borrow(), &fee)).unwrap();
+    io_builder.remove_output(0);
+    io_builder
+        .add_output(destination.as_address(), output_value)
+        .unwrap();
+    let io = io_builder
+        .seal(NoExtra.payload_data().borrow(), &fee)
+        .unwrap();
+
+    let builder = TxBuilder::new()
+        .set_nopayload()
+        .set_ios(&io.inputs, &io.outputs);
+    let sign_data_hash = builder.get_auth_data_for_witness().hash();
+
+    // each owner signs on its own and the partial signatures are then collected
+    let witness = Witness::new_multisig(
+        ledger.block0_hash(),
+        &sign_data_hash,
+        SpendingCounter::zero(),
+        |data| {
+            let mut witness_builder = WitnessBuilder::new();
+            for signer in signers {
+                let partial = PartialSignature::new(declaration, signer.public_key(), data, |d| {
+                    signer.private_key().sign(d)
+                })
+                .expect("signer is not an owner of the multisig account");
+                assert!(witness_builder.append_partial(partial));
+            }
+            witness_builder.finalize()
+        },
+    );
+    let tx = builder.set_witnesses(&[witness]).set_payload_auth(&());
+
+    // go through the binary representation as the fragment would be received
+    let fragment = Fragment::Transaction(tx);
+    Fragment::from_raw(&fragment.to_raw()).unwrap()
+}
+
+#[test]
+pub fn multisig_account_spending() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(ConfigBuilder::new(0).with_fee(LinearFee::new(1, 1, 1)))
+        .with_initials(vec![
+            wallet("Alice").with(1_000),
+            wallet("Bob").with(1_000),
+            wallet("Clarice").with(1_000),
+            wallet("David").with(1_000),
+        ])
+        .build()
+        .unwrap();
+
+    let mut alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let clarice = controller.wallet("Clarice").unwrap();
+    let david = controller.wallet("David").unwrap();
+    let declaration = declaration(2, &[&alice, &bob, &clarice]);
+
+    controller
+        .registers_multisig(&alice, &declaration, &mut ledger)
+        .unwrap();
+    alice.confirm_transaction();
+    fund_multisig(&mut ledger, &mut alice, &declaration, Value(300));
+
+    LedgerStateVerifier::new(ledger.clone().into())
+        .info("after funding")
+        .multisig_account_has_value(&declaration.to_identifier(), &Value(300));
+
+    let fragment = spend_from_multisig(
+        &ledger,
+        &declaration,
+        &[&alice, &clarice],
+        &david,
+        Value(100),
+    );
+    ledger.apply_fragment(&fragment, ledger.date()).unwrap();
+
+    let mut ledger_verifier = LedgerStateVerifier::new(ledger.into());
+    ledger_verifier
+        .info("after spending")
+        .multisig_account_has_value(&declaration.to_identifier(), &Value(200));
+    ledger_verifier
+        .account(david.as_account_data())
+        .has_value(&Value(1_097));
+}
+
+#[test]
+pub fn multisig_account_spending_through_sub_declaration() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(ConfigBuilder::new(0).with_fee(LinearFee::new(1, 1, 1)))
+        .with_initials(vec![
+            wallet("Alice").with(1_000),
+            wallet("Bob").with(1_000),
+            wallet("Clarice").with(1_000),
+            wallet("David").with(1_000),
+        ])
+        .build()
+        .unwrap();
+
+    let mut alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let clarice = controller.wallet("Clarice").unwrap();
+    let david = controller.wallet("David").unwrap();
+    let sub = declaration(1, &[&bob, &clarice]);
+    let declaration = Declaration::new(
+        2,
+        vec![
+            DeclElement::from_publickey(&alice.public_key()),
+            DeclElement::Sub(sub),
+        ],
+    )
+    .unwrap();
+
+    controller
+        .registers_multisig(&alice, &declaration, &mut ledger)
+        .unwrap();
+    alice.confirm_transaction();
+    fund_multisig(&mut ledger, &mut alice, &declaration, Value(300));
+
+    // the sub declaration alone doesn't meet the threshold
+    let fragment = spend_from_multisig(&ledger, &declaration, &[&clarice], &david, Value(100));
+    assert!(ledger.apply_fragment(&fragment, ledger.date()).is_err());
+
+    let fragment = spend_from_multisig(
+        &ledger,
+        &declaration,
+        &[&alice, &clarice],
+        &david,
+        Value(100),
+    );
+    ledger.apply_fragment(&fragment, ledger.date()).unwrap();
+
+    let mut ledger_verifier = LedgerStateVerifier::new(ledger.into());
+    ledger_verifier
+        .info("after spending")
+        .multisig_account_has_value(&declaration.to_identifier(), &Value(200));
+    ledger_verifier
+        .account(david.as_account_data())
+        .has_value(&Value(1_097));
+}
+
+#[test]
+pub fn multisig_witness_fee_grows_with_signatures() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(
+            ConfigBuilder::new(0)
+                .with_fee(LinearFee::new(1, 1, 1))
+                .with_extended_fee(ExtendedFee::new(0, 0, 0, 10)),
+        )
+        .with_initials(vec![
+            wallet("Alice").with(1_000),
+            wallet("Bob").with(1_000),
+            wallet("Clarice").with(1_000),
+            wallet("David").with(1_000),
+        ])
+        .build()
+        .unwrap();
+
+    let mut alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let clarice = controller.wallet("Clarice").unwrap();
+    let david = controller.wallet("David").unwrap();
+    let declaration = declaration(2, &[&alice, &bob, &clarice]);
+
+    controller
+        .registers_multisig(&alice, &declaration, &mut ledger)
+        .unwrap();
+    alice.confirm_transaction();
+    fund_multisig(&mut ledger, &mut alice, &declaration, Value(300));
+
+    let fee_of = |fragment: &Fragment| match fragment {
+        Fragment::Transaction(tx) => ledger.fee().calculate_tx(&tx.as_slice()),
+        _ => unreachable!(),
+    };
+    let two_signatures = spend_from_multisig(
+        &ledger,
+        &declaration,
+        &[&alice, &clarice],
+        &david,
+        Value(100),
+    );
+    let three_signatures = spend_from_multisig(
+        &ledger,
+        &declaration,
+        &[&alice, &bob, &clarice],
+        &david,
+        Value(100),
+    );
+    assert_eq!(
+        fee_of(&three_signatures),
+        (fee_of(&two_signatures) + Value(10)).unwrap()
+    );
+
+    ledger
+        .apply_fragment(&three_signatures, ledger.date())
+        .unwrap();
+    LedgerStateVerifier::new(ledger.into())
+        .info("after spending")
+        .multisig_account_has_value(&declaration.to_identifier(), &Value(200));
+}
+
+#[test]
+pub fn multisig_account_spending_below_threshold_is_rejected() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_initials(vec![
+            wallet("Alice").with(1_000),
+            wallet("Bob").with(1_000),
+            wallet("Clarice").with(1_000),
+        ])
+        .build()
+        .unwrap();
+
+    let mut alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let clarice = controller.wallet("Clarice").unwrap();
+    let declaration = declaration(2, &[&alice, &bob]);
+
+    controller
+        .registers_multisig(&alice, &declaration, &mut ledger)
+        .unwrap();
+    alice.confirm_transaction();
+    fund_multisig(&mut ledger, &mut alice, &declaration, Value(300));
+
+    let fragment = spend_from_multisig(&ledger, &declaration, &[&bob], &clarice, Value(100));
+    assert!(ledger.apply_fragment(&fragment, ledger.date()).is_err());
+
+    LedgerStateVerifier::new(ledger.into())
+        .info("after rejected spending")
+        .multisig_account_has_value(&declaration.to_identifier(), &Value(300));
+}
+
+#[test]
+pub fn multisig_declaration_cannot_be_registered_twice() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_initials(vec![wallet("Alice").with(1_000), wallet("Bob").with(1_000)])
+        .build()
+        .unwrap();
+
+    let mut alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let declaration = declaration(1, &[&alice, &bob]);
+
+    controller
+        .registers_multisig(&alice, &declaration, &mut ledger)
+        .unwrap();
+    alice.confirm_transaction();
+
+    assert!(controller
+        .registers_multisig(&alice, &declaration, &mut ledger)
+        .is_err());
+}
+
+#[test]
+pub fn multisig_declaration_threshold_is_validated() {
+    let alice = Wallet::from_value(Value(1));
+    let bob = Wallet::from_value(Value(1));
+    let owners = vec![
+        DeclElement::from_publickey(&alice.public_key()),
+        DeclElement::from_publickey(&bob.public_key()),
+    ];
+    assert_eq!(
+        Declaration::new(3, owners.clone()),
+        Err(DeclarationError::ThresholdInvalid)
+    );
+    assert_eq!(
+        Declaration::new(1, owners[..1].to_vec()),
+        Err(DeclarationError::HasNotEnoughOwners)
+    );
+}
+
+#[test]
+pub fn multisig_sub_declarations_are_validated() {
+    let alice = Wallet::from_value(Value(1));
+    let bob = Wallet::from_value(Value(1));
+    let owners = vec![
+        DeclElement::from_publickey(&alice.public_key()),
+        DeclElement::from_publickey(&bob.public_key()),
+    ];
+    let invalid_sub = Declaration {
+        threshold: 3,
+        owners: owners.clone(),
+    };
+    assert_eq!(
+        Declaration::new(1, vec![owners[0].clone(), DeclElement::Sub(invalid_sub)]),
+        Err(DeclarationError::ThresholdInvalid)
+    );
+
+    let sub = Declaration::new(1, owners.clone()).unwrap();
+    let nested = Declaration::new(1, vec![owners[0].clone(), DeclElement::Sub(sub)]).unwrap();
+    assert_eq!(
+        Declaration::new(1, vec![owners[1].clone(), DeclElement::Sub(nested)]),
+        Err(DeclarationError::HasTooManyLevels)
+    );
+}
@@ -0,0 +1,196 @@
+use crate::{
+    date::BlockDate,
+    fee::LinearFee,
+    testing::{
+        builders::StakePoolBuilder,
+        ledger::ConfigBuilder,
+        scenario::{prepare_scenario, wallet},
+        verifiers::LedgerStateVerifier,
+    },
+    value::Value,
+};
+
+#[test]
+pub fn pool_retirement_is_applied_at_epoch_boundary() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(
+            ConfigBuilder::new(0)
+                .with_slot_duration(20)
+                .with_slots_per_epoch(10),
+        )
+        .with_initials(vec![
+            wallet("Alice").with(1_000).owns("stake_pool"),
+            wallet("Bob").with(1_000).delegates_to("stake_pool"),
+        ])
+        .build()
+        .unwrap();
+
+    let alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let stake_pool = controller.stake_pool("stake_pool").unwrap();
+
+    // an epoch lasts 200 seconds, so the pool retires at the start of epoch 1
+    controller
+        .retire_at(Some(&alice), &stake_pool, 200, &mut ledger)
+        .unwrap();
+
+    LedgerStateVerifier::new(ledger.clone().into())
+        .info("after retirement certificate")
+        .stake_pools()
+        .is_not_retired(&stake_pool);
+
+    ledger.fast_forward_to(BlockDate {
+        epoch: 0,
+        slot_id: 9,
+    });
+    ledger.produce_empty_block(&stake_pool).unwrap();
+
+    LedgerStateVerifier::new(ledger.clone().into())
+        .info("after epoch transition")
+        .stake_pools()
+        .is_retired(&stake_pool);
+
+    LedgerStateVerifier::new(ledger.into())
+        .info("delegation is reset after retirement")
+        .account(bob.as_account_data())
+        .delegation()
+        .is_not_delegated();
+}
+
+#[test]
+pub fn pool_deposit_is_refunded_on_retirement() {
+    let deposit = Value(100);
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(
+            ConfigBuilder::new(0)
+                .with_fee(LinearFee::new(1, 1, 1))
+                .with_pool_registration_deposit(deposit),
+        )
+        .with_initials(vec![wallet("Alice").with(1_000)])
+        .build()
+        .unwrap();
+
+    let mut alice = controller.wallet("Alice").unwrap();
+    let stake_pool = StakePoolBuilder::new()
+        .with_owners(vec![alice.public_key()])
+        .build();
+    let total_funds = ledger.total_funds();
+
+    assert!(controller
+        .register(&alice, &stake_pool, &mut ledger)
+        .is_err());
+
+    controller
+        .register_with_deposit(&alice, &stake_pool, deposit, &mut ledger)
+        .unwrap();
+    alice.confirm_transaction();
+
+    LedgerStateVerifier::new(ledger.clone().into())
+        .info("after registration")
+        .account(alice.as_account_data())
+        .has_value(&Value(897));
+    assert_eq!(ledger.total_funds(), total_funds);
+
+    controller
+        .retire(Some(&alice), &stake_pool, &mut ledger)
+        .unwrap();
+    alice.confirm_transaction();
+
+    let mut ledger_verifier = LedgerStateVerifier::new(ledger.clone().into());
+    ledger_verifier
+        .info("after retirement")
+        .account(alice.as_account_data())
+        .has_value(&Value(994));
+    ledger_verifier.stake_pools().is_retired(&stake_pool);
+    assert_eq!(ledger.total_funds(), total_funds);
+}
+
+#[test]
+pub fn ratio_delegation_is_rebalanced_after_pool_retirement() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_initials(vec![
+            wallet("Alice").with(1_000).owns("alice_stake_pool"),
+            wallet("Bob").with(1_000).owns("bob_stake_pool"),
+            wallet("Clarice").with(1_000),
+        ])
+        .build()
+        .unwrap();
+
+    let alice = controller.wallet("Alice").unwrap();
+    let mut clarice = controller.wallet("Clarice").unwrap();
+    let alice_stake_pool = controller.stake_pool("alice_stake_pool").unwrap();
+    let bob_stake_pool = controller.stake_pool("bob_stake_pool").unwrap();
+
+    controller
+        .delegates_to_many(
+            &clarice,
+            &[(&alice_stake_pool, 1), (&bob_stake_pool, 1)],
+            &mut ledger,
+        )
+        .unwrap();
+    clarice.confirm_transaction();
+
+    controller
+        .retire(Some(&alice), &alice_stake_pool, &mut ledger)
+        .unwrap();
+
+    LedgerStateVerifier::new(ledger.into())
+        .info("after retirement")
+        .account(clarice.as_account_data())
+        .delegation()
+        .is_fully_delegated_to(bob_stake_pool.id());
+}
+
+#[test]
+pub fn pools_retiring_in_the_same_epoch_are_removed_from_delegation() {
+    let (mut ledger, controller) = prepare_scenario()
+        .with_config(
+            ConfigBuilder::new(0)
+                .with_slot_duration(20)
+                .with_slots_per_epoch(10),
+        )
+        .with_initials(vec![
+            wallet("Alice").with(1_000).owns("alice_stake_pool"),
+            wallet("Bob").with(1_000).owns("bob_stake_pool"),
+            wallet("Clarice").with(1_000),
+        ])
+        .build()
+        .unwrap();
+
+    let alice = controller.wallet("Alice").unwrap();
+    let bob = controller.wallet("Bob").unwrap();
+    let mut clarice = controller.wallet("Clarice").unwrap();
+    let alice_stake_pool = controller.stake_pool("alice_stake_pool").unwrap();
+    let bob_stake_pool = controller.stake_pool("bob_stake_pool").unwrap();
+
+    controller
+        .delegates_to_many(
+            &clarice,
+            &[(&alice_stake_pool, 1), (&bob_stake_pool, 1)],
+            &mut ledger,
+        )
+        .unwrap();
+    clarice.confirm_transaction();
+
+    controller
+        .retire_at(Some(&alice), &alice_stake_pool, 200, &mut ledger)
+        .unwrap();
+    controller
+        .retire_at(Some(&bob), &bob_stake_pool, 200, &mut ledger)
+        .unwrap();
+
+    ledger.fast_forward_to(BlockDate {
+        epoch: 0,
+        slot_id: 9,
+    });
+    ledger.produce_empty_block(&alice_stake_pool).unwrap();
+
+    let mut ledger_verifier = LedgerStateVerifier::new(ledger.into());
+    ledger_verifier.info("after epoch transition");
+    ledger_verifier.stake_pools().is_retired(&alice_stake_pool);
+    ledger_verifier.stake_pools().is_retired(&bob_stake_pool);
+    ledger_verifier
+        .account(clarice.as_account_data())
+        .delegation()
+        .is_not_delegated();
+}
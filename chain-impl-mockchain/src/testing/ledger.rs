@@ -7,7 +7,7 @@ use crate::{
     chaintypes::{ChainLength, ConsensusType, ConsensusVersion, HeaderId},
     config::{Block0Date, ConfigParam, RewardParams},
     date::BlockDate,
-    fee::{LinearFee, PerCertificateFee, PerVoteCertificateFee},
+    fee::{DynamicFeeParams, ExtendedFee, LinearFee, PerCertificateFee, PerVoteCertificateFee},
     fragment::{config::ConfigParams, Fragment, FragmentId},
     key::BftLeaderId,
     leadership::genesis::LeadershipData,
@@ -43,6 +43,7 @@ pub struct ConfigBuilder {
     linear_fee: Option<LinearFee>,
     per_certificate_fee: Option<PerCertificateFee>,
     per_vote_certificate_fee: Option<PerVoteCertificateFee>,
+    extended_fee: Option<ExtendedFee>,
     leaders: Vec<BftLeaderId>,
     seed: u64,
     committees_ids: Vec<CommitteeId>,
@@ -56,6 +57,9 @@ pub struct ConfigBuilder {
     block0_date: Block0Date,
     consensus_version: ConsensusVersion,
     pool_capping_ratio: Ratio,
+    pool_registration_deposit: Value,
+    stake_key_deposit: Value,
+    dynamic_fees: Option<DynamicFeeParams>,
 }
 
 impl ConfigBuilder {
@@ -92,6 +96,10 @@ impl ConfigBuilder {
             kes_update_speed: 3600 * 12,
             block0_date: Block0Date(0),
             consensus_version: ConsensusVersion::Bft,
+            pool_registration_deposit: Value::zero(),
+            stake_key_deposit: Value::zero(),
+            dynamic_fees: None,
+            extended_fee: None,
         }
     }
 
@@ -147,6 +155,21 @@ impl ConfigBuilder {
         self
     }
 
+    pub fn with_pool_registration_deposit(mut self, deposit: Value) -> Self {
+        self.pool_registration_deposit = deposit;
+        self
+    }
+
+    pub fn with_stake_key_deposit(mut self, deposit: Value) -> Self {
+        self.stake_key_deposit = deposit;
+        self
+    }
+
+    pub fn with_dynamic_fees(mut self, dynamic_fees: DynamicFeeParams) -> Self {
+        self.dynamic_fees = Some(dynamic_fees);
+        self
+    }
+
     pub fn with_leaders(mut self, leaders: &[BftLeaderId]) -> Self {
         self.leaders.extend(leaders.iter().cloned());
         self
@@ -170,6 +193,11 @@ impl ConfigBuilder {
         self
     }
 
+    pub fn with_extended_fee(mut self, extended_fee: ExtendedFee) -> Self {
+        self.extended_fee = Some(extended_fee);
+        self
+    }
+
     pub fn with_slots_per_epoch(mut self, slots_per_epoch: u32) -> Self {
         self.slots_per_epoch = slots_per_epoch;
         self
@@ -255,6 +283,24 @@ impl ConfigBuilder {
             ));
         }
 
+        if let Some(extended_fee) = self.extended_fee {
+            ie.push(ConfigParam::ExtendedFees(extended_fee));
+        }
+
+        if self.pool_registration_deposit > Value::zero() {
+            ie.push(ConfigParam::PoolRegistrationDeposit(
+                self.pool_registration_deposit,
+            ));
+        }
+
+        if self.stake_key_deposit > Value::zero() {
+            ie.push(ConfigParam::StakeKeyDeposit(self.stake_key_deposit));
+        }
+
+        if self.dynamic_fees.is_some() {
+            ie.push(ConfigParam::DynamicFees(self.dynamic_fees));
+        }
+
         for committee_id in self.committees_ids {
             ie.push(ConfigParam::AddCommitteeId(committee_id));
         }
@@ -499,6 +545,7 @@ impl TestLedger {
             &block.contents,
             &header_meta,
         )?;
+        self.parameters = self.ledger.get_ledger_parameters();
         Ok(())
     }
 
@@ -529,6 +576,10 @@ impl TestLedger {
         &self.block0_hash
     }
 
+    pub fn discrimination(&self) -> Discrimination {
+        self.ledger.get_static_parameters().discrimination
+    }
+
     pub fn faucets(&self) -> Vec<AddressDataValue> {
         self.faucets.clone()
     }
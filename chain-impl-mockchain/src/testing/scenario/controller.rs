@@ -6,11 +6,13 @@ use crate::{
     fee::LinearFee,
     key::Hash,
     ledger::Error as LedgerError,
+    multisig::Declaration,
     testing::{
         data::{StakePool, Wallet},
         ledger::TestLedger,
         scenario::template::VotePlanDef,
     },
+    value::Value,
     vote::{Choice, Payload, PayloadType, ProofOfCorrectVote},
 };
 
@@ -124,6 +126,19 @@ impl Controller {
         test_ledger.apply_fragment(&fragment, test_ledger.date())
     }
 
+    pub fn register_with_deposit(
+        &self,
+        funder: &Wallet,
+        stake_pool: &StakePool,
+        deposit: Value,
+        test_ledger: &mut TestLedger,
+    ) -> Result<(), LedgerError> {
+        let fragment = self
+            .fragment_factory
+            .stake_pool_registration_with_deposit(funder, stake_pool, deposit);
+        test_ledger.apply_fragment(&fragment, test_ledger.date())
+    }
+
     pub fn delegates(
         &self,
         from: &Wallet,
@@ -134,6 +149,44 @@ impl Controller {
         test_ledger.apply_fragment(&fragment, test_ledger.date())
     }
 
+    pub fn delegates_with_deposit(
+        &self,
+        from: &Wallet,
+        stake_pool: &StakePool,
+        deposit: Value,
+        test_ledger: &mut TestLedger,
+    ) -> Result<(), LedgerError> {
+        let fragment = self
+            .fragment_factory
+            .delegation_with_deposit(from, stake_pool, deposit);
+        test_ledger.apply_fragment(&fragment, test_ledger.date())
+    }
+
+    pub fn deregisters(
+        &self,
+        funder: &Wallet,
+        account: &Wallet,
+        destination: &Wallet,
+        test_ledger: &mut TestLedger,
+    ) -> Result<(), LedgerError> {
+        let fragment = self
+            .fragment_factory
+            .account_deregistration(funder, account, destination);
+        test_ledger.apply_fragment(&fragment, test_ledger.date())
+    }
+
+    pub fn registers_multisig(
+        &self,
+        funder: &Wallet,
+        declaration: &Declaration,
+        test_ledger: &mut TestLedger,
+    ) -> Result<(), LedgerError> {
+        let fragment = self
+            .fragment_factory
+            .multisig_registration(funder, declaration);
+        test_ledger.apply_fragment(&fragment, test_ledger.date())
+    }
+
     pub fn delegates_different_funder(
         &self,
         funder: &Wallet,
@@ -186,6 +239,19 @@ impl Controller {
         test_ledger.apply_fragment(&fragment, test_ledger.date())
     }
 
+    pub fn retire_at<'a>(
+        &'a self,
+        owners: impl IntoIterator<Item = &'a Wallet>,
+        stake_pool: &'a StakePool,
+        retirement_time: u64,
+        test_ledger: &'a mut TestLedger,
+    ) -> Result<(), LedgerError> {
+        let fragment =
+            self.fragment_factory
+                .stake_pool_retire_at(owners, stake_pool, retirement_time);
+        test_ledger.apply_fragment(&fragment, test_ledger.date())
+    }
+
     pub fn update<'a>(
         &'a self,
         stake_pool: &'a StakePool,
@@ -374,15 +440,16 @@ mod tests {
             .retire(Some(&clarice), &stake_pool, &mut ledger)
             .unwrap();
         clarice.confirm_transaction();
-        // unassigned = clarice - fee (becaue thus clarise is an onwer of the stake she did not delegates any stakes)
-        // dangling = bob and alice funds (minus fees for transactions and certs)
+        // unassigned = all funds (minus fees for transactions and certs), because the
+        // delegations to the retired stake pool are removed along with the pool
+        // dangling = 0
         // total pool = 0, because stake pool was retired
 
         LedgerStateVerifier::new(ledger.into())
             .distribution()
-            .unassigned_is(Stake::from_value(Value(997)))
+            .unassigned_is(Stake::from_value(Value(2991)))
             .and()
-            .dangling_is(Stake::from_value(Value(1994)))
+            .dangling_is(Stake::zero())
             .and()
             .pools_total_stake_is(Stake::zero());
     }
@@ -6,8 +6,10 @@ use crate::{
     fee::LinearFee,
     fragment::Fragment,
     key::Hash,
+    multisig::Declaration,
     testing::{
         builders::{
+            build_account_deregistration_cert, build_multisig_registration_cert,
             build_no_stake_delegation, build_owner_stake_delegation,
             build_owner_stake_full_delegation, build_stake_delegation_cert,
             build_stake_pool_registration_cert, build_stake_pool_retirement_cert,
@@ -56,11 +58,55 @@ impl FragmentFactory {
         self.transaction_with_cert(Some(funder), &cert)
     }
 
+    pub fn stake_pool_registration_with_deposit(
+        &self,
+        funder: &Wallet,
+        stake_pool: &StakePool,
+        deposit: Value,
+    ) -> Fragment {
+        let cert = build_stake_pool_registration_cert(&stake_pool.info());
+        TestTxCertBuilder::new(self.block0_hash, self.fee)
+            .with_deposit(deposit)
+            .make_transaction(Some(funder), &cert)
+    }
+
     pub fn delegation(&self, from: &Wallet, stake_pool: &StakePool) -> Fragment {
         let cert = build_stake_delegation_cert(&stake_pool.info(), &from.as_account_data());
         self.transaction_with_cert(Some(from), &cert)
     }
 
+    pub fn delegation_with_deposit(
+        &self,
+        from: &Wallet,
+        stake_pool: &StakePool,
+        deposit: Value,
+    ) -> Fragment {
+        let cert = build_stake_delegation_cert(&stake_pool.info(), &from.as_account_data());
+        TestTxCertBuilder::new(self.block0_hash, self.fee)
+            .with_deposit(deposit)
+            .make_transaction(Some(from), &cert)
+    }
+
+    pub fn account_deregistration(
+        &self,
+        funder: &Wallet,
+        account: &Wallet,
+        destination: &Wallet,
+    ) -> Fragment {
+        let cert =
+            build_account_deregistration_cert(&account.as_account_data(), destination.as_address());
+        TestTxCertBuilder::new(self.block0_hash, self.fee).make_transaction_different_signers(
+            funder,
+            Some(account),
+            &cert,
+        )
+    }
+
+    pub fn multisig_registration(&self, funder: &Wallet, declaration: &Declaration) -> Fragment {
+        let cert = build_multisig_registration_cert(declaration);
+        TestTxCertBuilder::new(self.block0_hash, self.fee).make_transaction(Some(funder), &cert)
+    }
+
     pub fn delegation_different_funder(
         &self,
         funder: &Wallet,
@@ -99,7 +145,16 @@ impl FragmentFactory {
         owners: impl IntoIterator<Item = &'a Wallet>,
         stake_pool: &StakePool,
     ) -> Fragment {
-        let certificate = build_stake_pool_retirement_cert(stake_pool.id(), 0);
+        self.stake_pool_retire_at(owners, stake_pool, 0)
+    }
+
+    pub fn stake_pool_retire_at<'a>(
+        &self,
+        owners: impl IntoIterator<Item = &'a Wallet>,
+        stake_pool: &StakePool,
+        retirement_time: u64,
+    ) -> Fragment {
+        let certificate = build_stake_pool_retirement_cert(stake_pool.id(), retirement_time);
         self.transaction_with_cert(owners, &certificate)
     }
 
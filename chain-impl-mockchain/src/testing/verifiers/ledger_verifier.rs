@@ -3,6 +3,7 @@ use crate::{
     accounting::account::{account_state::AccountState, DelegationType},
     certificate::{PoolId, PoolRegistration},
     ledger::{ledger::Ledger, Pots},
+    multisig,
     stake::PoolsState,
     stake::{Stake, StakeDistribution},
     testing::data::{AddressData, StakePool},
@@ -115,6 +116,29 @@ impl LedgerStateVerifier {
         self
     }
 
+    pub fn multisig_account_has_value(
+        &self,
+        identifier: &multisig::Identifier,
+        value: &Value,
+    ) -> &Self {
+        let state = self
+            .ledger
+            .multisig
+            .get_state(identifier)
+            .unwrap_or_else(|_| {
+                panic!(
+                    "multisig account {} does not exist {}",
+                    identifier, self.info
+                )
+            });
+        assert_eq!(
+            state.value, *value,
+            "multisig account {} value should be {} {}",
+            identifier, value, self.info
+        );
+        self
+    }
+
     pub fn distribution(&self) -> DistributionVerifier {
         DistributionVerifier::new(self.ledger.get_stake_distribution(), self.info.clone())
     }
@@ -285,6 +309,16 @@ impl DelegationVerifier {
         };
         self
     }
+
+    pub fn is_not_delegated(&self) -> &Self {
+        assert_eq!(
+            self.delegation_type,
+            DelegationType::NonDelegated,
+            "{}: wrong delegation type. Expected: NonDelegated",
+            self.info
+        );
+        self
+    }
 }
 
 pub struct PotsVerifier {
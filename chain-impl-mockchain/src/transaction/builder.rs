@@ -1,10 +1,12 @@
 use super::input::Input;
-use super::payload::{NoExtra, Payload};
+use super::payload::{NoExtra, Payload, PayloadSlice};
 use super::transaction::{
     Transaction, TransactionAuthData, TransactionBindingAuthData, TransactionStruct,
 };
 use super::transfer::Output;
 use super::witness::Witness;
+use crate::fee::{FeeAlgorithm, WitnessCount};
+use crate::value::Value;
 use chain_addr::Address;
 use std::marker::PhantomData;
 
@@ -139,6 +141,25 @@ impl<P> TxBuilderState<SetWitnesses<P>> {
         TransactionAuthData(&self.data[FRAGMENT_OVERHEAD..])
     }
 
+    /// Calculate the fee of the transaction being built, once it carries
+    /// the given witnesses, on a given fee algorithm
+    pub fn estimate_fee<F: FeeAlgorithm>(&self, fee_algorithm: &F, witnesses: WitnessCount) -> Value
+    where
+        P: Payload,
+    {
+        let payload: PayloadSlice<P> = PayloadSlice(
+            &self.data[FRAGMENT_OVERHEAD..FRAGMENT_OVERHEAD + self.tstruct.inputs],
+            PhantomData,
+        );
+        fee_algorithm.calculate_extended(
+            payload.into_certificate_slice(),
+            self.tstruct.nb_inputs,
+            self.tstruct.nb_outputs,
+            self.current_pos(),
+            witnesses,
+        )
+    }
+
     /// Set the witnesses of the transaction. There's need to be 1 witness per inputs,
     /// although it is not enforced by this construction
     ///
@@ -1,14 +1,24 @@
-use super::{Balance, Input, Output, Payload, PayloadSlice};
-use crate::fee::FeeAlgorithm;
+use super::{Balance, Input, InputType, Output, Payload, PayloadSlice, INPUT_SIZE};
+use crate::fee::{FeeAlgorithm, WitnessCount};
+use crate::multisig;
 use crate::value::{Value, ValueError};
 use chain_addr::Address;
 use std::error;
 use std::fmt;
 
+/// Size of the value of an output
+const OUTPUT_VALUE_SIZE: usize = 8;
+
+/// Size of a single or account address, used for the outputs placeholders
+const PLACEHOLDER_ADDRESS_SIZE: usize = 33;
+
 /// Inputs & Outputs for a transaction being built
 pub struct InputOutputBuilder {
     inputs: Vec<Input>,
     outputs: Vec<Output<Address>>,
+    // for an input at the same position spent from a multisig account,
+    // the number of signatures its witness is expected to carry
+    multisig_inputs: Vec<Option<u8>>,
 }
 
 /// Inputs & Outputs for a built transaction
@@ -67,6 +77,7 @@ impl InputOutputBuilder {
         InputOutputBuilder {
             inputs: Vec::new(),
             outputs: Vec::new(),
+            multisig_inputs: Vec::new(),
         }
     }
 
@@ -84,7 +95,12 @@ impl InputOutputBuilder {
         if outputs.len() > 255 {
             return Err(Error::TxTooManyOutputs);
         }
-        Ok(InputOutputBuilder { inputs, outputs })
+        let multisig_inputs = vec![None; inputs.len()];
+        Ok(InputOutputBuilder {
+            inputs,
+            outputs,
+            multisig_inputs,
+        })
     }
 
     /// Build the InputOutput from the Builder
@@ -103,6 +119,27 @@ impl InputOutputBuilder {
             return Err(Error::TxTooManyInputs);
         }
         self.inputs.push(input.clone());
+        self.multisig_inputs.push(None);
+        Ok(())
+    }
+
+    /// Add an input spending from a multisig account.
+    ///
+    /// The input will be witnessed by the multisig witness of the account
+    /// owners carrying the given number of signatures, which is taken into
+    /// account when estimating the fee.
+    pub fn add_multisig_input(
+        &mut self,
+        identifier: &multisig::Identifier,
+        value: Value,
+        signatures: u8,
+    ) -> Result<(), Error> {
+        if self.inputs.len() == 255 {
+            return Err(Error::TxTooManyInputs);
+        }
+        self.inputs
+            .push(Input::from_multisig_account(identifier.clone(), value));
+        self.multisig_inputs.push(Some(signatures));
         Ok(())
     }
 
@@ -121,6 +158,7 @@ impl InputOutputBuilder {
     pub fn remove_input(&mut self, input: usize) {
         if input < self.inputs.len() {
             let _ = self.inputs.remove(input);
+            let _ = self.multisig_inputs.remove(input);
         }
     }
 
@@ -131,6 +169,34 @@ impl InputOutputBuilder {
         }
     }
 
+    /// Size of the body (payload, inputs and outputs) of the transaction
+    /// once built with the given payload data
+    fn body_size<P>(&self, payload: &PayloadSlice<'_, P>) -> usize {
+        payload.0.len()
+            + 2
+            + self.inputs.len() * INPUT_SIZE
+            + self
+                .outputs
+                .iter()
+                .map(|o| o.address.to_bytes().len() + OUTPUT_VALUE_SIZE)
+                .sum::<usize>()
+    }
+
+    /// Witnesses expected for the current inputs
+    fn witness_count(&self) -> WitnessCount {
+        let mut count = WitnessCount::default();
+        for (input, multisig) in self.inputs.iter().zip(self.multisig_inputs.iter()) {
+            match (input.get_type(), multisig) {
+                (InputType::Utxo, _) => count.utxo += 1,
+                (InputType::Account, Some(signatures)) => {
+                    count.multisig_signatures += *signatures as u32
+                }
+                (InputType::Account, None) => count.account += 1,
+            }
+        }
+        count
+    }
+
     pub fn balance(&self, fee: Value) -> Result<Balance, ValueError> {
         let inputs = Value::sum(self.inputs.iter().map(|i| i.value()))?;
         let outputs = Value::sum(self.outputs.iter().map(|o| o.value))?;
@@ -149,10 +215,25 @@ impl InputOutputBuilder {
         payload: PayloadSlice<'a, P>,
         fee_algorithm: &F,
     ) -> Value {
-        fee_algorithm.calculate(
+        let witnesses = self.witness_count();
+        self.estimate_fee_with_witnesses(payload, fee_algorithm, witnesses)
+    }
+
+    /// Calculate the fees on a given fee algorithm for the current transaction
+    /// carrying the given witnesses
+    pub fn estimate_fee_with_witnesses<'a, P: Payload, F: FeeAlgorithm>(
+        &self,
+        payload: PayloadSlice<'a, P>,
+        fee_algorithm: &F,
+        witnesses: WitnessCount,
+    ) -> Value {
+        let size = self.body_size(&payload);
+        fee_algorithm.calculate_extended(
             payload.into_certificate_slice(),
             self.inputs.len() as u8,
             self.outputs.len() as u8,
+            size,
+            witnesses,
         )
     }
 
@@ -167,6 +248,9 @@ impl InputOutputBuilder {
     }
 
     /// Get balance including current fee.
+    ///
+    /// Placeholder inputs are expected to be account inputs and placeholder
+    /// outputs to be sent to single or account addresses.
     pub fn get_balance_with_placeholders<'a, P: Payload, F: FeeAlgorithm>(
         &self,
         payload: PayloadSlice<'a, P>,
@@ -184,7 +268,19 @@ impl InputOutputBuilder {
         let nb_inputs = self.inputs.len() as u8 + inputs_placeholders;
         let nb_outputs = self.outputs.len() as u8 + outputs_placeholders;
 
-        let fee = fee_algorithm.calculate(payload.into_certificate_slice(), nb_inputs, nb_outputs);
+        let size = self.body_size(&payload)
+            + inputs_placeholders as usize * INPUT_SIZE
+            + outputs_placeholders as usize * (PLACEHOLDER_ADDRESS_SIZE + OUTPUT_VALUE_SIZE);
+        let mut witnesses = self.witness_count();
+        witnesses.account += inputs_placeholders;
+
+        let fee = fee_algorithm.calculate_extended(
+            payload.into_certificate_slice(),
+            nb_inputs,
+            nb_outputs,
+            size,
+            witnesses,
+        );
         self.balance(fee).map_err(Error::MathErr)
     }
 
@@ -12,7 +12,7 @@ mod witness;
 #[cfg(any(test, feature = "property-test-api"))]
 pub mod test;
 
-use chain_core::mempack::{ReadBuf, ReadError, Readable};
+use chain_core::mempack::{ReadBuf, ReadError, Readable, ReadableRef};
 use chain_core::property;
 
 // to remove..
@@ -35,11 +35,15 @@ impl<Extra: Payload> property::Serialize for Transaction<Extra> {
 
 impl<Extra: Payload> Readable for Transaction<Extra> {
     fn read(buf: &mut ReadBuf) -> Result<Self, ReadError> {
+        TransactionSlice::read_ref(buf).map(|tx| tx.to_owned())
+    }
+}
+
+impl<'a, Extra: Payload> ReadableRef<'a> for TransactionSlice<'a, Extra> {
+    fn read_ref(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError> {
         let utx = UnverifiedTransactionSlice::from(buf.get_slice_end());
-        match utx.check() {
-            Ok(tx) => Ok(tx.to_owned()),
-            Err(_) => Err(ReadError::StructureInvalid("transaction".to_string())),
-        }
+        utx.check()
+            .map_err(|_| ReadError::StructureInvalid("transaction".to_string()))
     }
 }
 
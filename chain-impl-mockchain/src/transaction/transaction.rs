@@ -54,6 +54,16 @@ pub struct TransactionSlice<'a, P: ?Sized> {
     pub(super) phantom: PhantomData<P>,
 }
 
+impl<'a, P> Clone for TransactionSlice<'a, P> {
+    fn clone(&self) -> Self {
+        TransactionSlice {
+            data: self.data,
+            tstruct: self.tstruct.clone(),
+            phantom: self.phantom,
+        }
+    }
+}
+
 pub struct UnverifiedTransactionSlice<'a, P: ?Sized> {
     data: &'a [u8],
     phantom: PhantomData<P>,
@@ -175,6 +175,19 @@ impl Witness {
         Witness::Account(sig)
     }
 
+    pub fn new_multisig<F>(
+        block0: &HeaderId,
+        sign_data_hash: &TransactionSignDataHash,
+        spending_counter: account::SpendingCounter,
+        sign: F,
+    ) -> Self
+    where
+        F: FnOnce(&WitnessMultisigData) -> multisig::Witness,
+    {
+        let wmd = WitnessMultisigData::new(block0, sign_data_hash, spending_counter);
+        Witness::Multisig(sign(&wmd))
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         use chain_core::property::Serialize;
         self.serialize_as_vec()
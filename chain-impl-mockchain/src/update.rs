@@ -162,6 +162,7 @@ pub enum Error {
     ReadOnlySetting,
     BadBftSlotsRatio(crate::milli::Milli),
     BadConsensusGenesisPraosActiveSlotsCoeff(ActiveSlotsCoeffError),
+    BadSlotDuration(u32),
 }
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -220,6 +221,11 @@ impl std::fmt::Display for Error {
                 "Cannot set consensus genesis praos active slots coefficient: {}",
                 err
             ),
+            Error::BadSlotDuration(millis) => write!(
+                f,
+                "Cannot set slot duration to invalid value {} milliseconds",
+                millis
+            ),
         }
     }
 }
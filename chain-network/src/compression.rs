@@ -0,0 +1,196 @@
+//! Compression of the content of blocks, headers and fragments
+//! exchanged over the network.
+
+use crate::error::{Code, Error};
+
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+// Limit on the size of decompressed content, to protect against
+// maliciously crafted compressed payloads.
+const MAX_DECOMPRESSED_LEN: u64 = 64 * 1024 * 1024;
+
+/// Compression algorithm applied to the serialized content of blocks,
+/// headers and fragments.
+///
+/// The algorithm is negotiated in the handshake: the client offers the
+/// algorithms it accepts, and the server selects one of these in its
+/// own order of preference.
+/// Support for algorithms other than `None` is enabled by crate features
+/// `compression-gzip` and `compression-zstd`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::None
+    }
+}
+
+impl Compression {
+    /// Lists the algorithms supported by this build of the crate,
+    /// in the default order of preference.
+    pub fn supported() -> Vec<Compression> {
+        [Compression::Zstd, Compression::Gzip, Compression::None]
+            .iter()
+            .copied()
+            .filter(|algorithm| algorithm.is_supported())
+            .collect()
+    }
+
+    /// Checks if the algorithm is supported by this build of the crate.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Gzip => cfg!(feature = "compression-gzip"),
+            Compression::Zstd => cfg!(feature = "compression-zstd"),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// Compresses the data with this algorithm.
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let res: io::Result<Vec<u8>> = match self {
+            Compression::None => Ok(data.to_vec()),
+            #[cfg(feature = "compression-gzip")]
+            Compression::Gzip => gzip::compress(data),
+            #[cfg(feature = "compression-zstd")]
+            Compression::Zstd => zstd::stream::encode_all(data, 0),
+            #[allow(unreachable_patterns)]
+            _ => return Err(self.unsupported()),
+        };
+        res.map_err(|e| Error::new(Code::Internal, e))
+    }
+
+    /// Decompresses data compressed with this algorithm.
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let res: io::Result<Vec<u8>> = match self {
+            Compression::None => Ok(data.to_vec()),
+            #[cfg(feature = "compression-gzip")]
+            Compression::Gzip => read_limited(flate2::read::GzDecoder::new(data)),
+            #[cfg(feature = "compression-zstd")]
+            Compression::Zstd => zstd::stream::read::Decoder::new(data).and_then(read_limited),
+            #[allow(unreachable_patterns)]
+            _ => return Err(self.unsupported()),
+        };
+        res.map_err(|e| Error::new(Code::InvalidArgument, e))
+    }
+
+    fn unsupported(self) -> Error {
+        Error::new(
+            Code::Unimplemented,
+            format!("{} compression is not supported", self),
+        )
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown compression algorithm")]
+pub struct ParseCompressionError;
+
+impl FromStr for Compression {
+    type Err = ParseCompressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Compression::None),
+            "gzip" => Ok(Compression::Gzip),
+            "zstd" => Ok(Compression::Zstd),
+            _ => Err(ParseCompressionError),
+        }
+    }
+}
+
+/// Selects the first of the algorithms preferred by the server that
+/// is accepted by the client. Falls back to `Compression::None`,
+/// which is accepted by all peers.
+pub fn negotiate(preferred: &[Compression], accepted: &[Compression]) -> Compression {
+    preferred
+        .iter()
+        .copied()
+        .find(|algorithm| accepted.contains(algorithm))
+        .unwrap_or(Compression::None)
+}
+
+#[allow(dead_code)]
+fn read_limited<R: Read>(reader: R) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    reader
+        .take(MAX_DECOMPRESSED_LEN + 1)
+        .read_to_end(&mut out)?;
+    if out.len() as u64 > MAX_DECOMPRESSED_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "decompressed content is too large",
+        ));
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "compression-gzip")]
+mod gzip {
+    use flate2::write::GzEncoder;
+    use std::io::{self, Write};
+
+    pub fn compress(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data)?;
+        encoder.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_supported() {
+        let data = vec![42u8; 4096];
+        for algorithm in Compression::supported() {
+            let compressed = algorithm.compress(&data).unwrap();
+            assert_eq!(algorithm.decompress(&compressed).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn negotiation() {
+        use Compression::{Gzip, Zstd};
+        assert_eq!(
+            negotiate(&[Zstd, Gzip, Compression::None], &[Gzip, Zstd]),
+            Zstd
+        );
+        assert_eq!(
+            negotiate(&[Gzip, Compression::None], &[Zstd, Compression::None]),
+            Compression::None
+        );
+        assert_eq!(negotiate(&[Zstd], &[]), Compression::None);
+    }
+
+    #[test]
+    fn parse_name() {
+        for algorithm in &[Compression::None, Compression::Gzip, Compression::Zstd] {
+            assert_eq!(
+                algorithm.as_str().parse::<Compression>().unwrap(),
+                *algorithm
+            );
+        }
+    }
+}
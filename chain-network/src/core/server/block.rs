@@ -1,6 +1,6 @@
 use super::PushStream;
-use crate::data::{Block, BlockEvent, BlockId, BlockIds, Header, Peer};
-use crate::error::Error;
+use crate::data::{Block, BlockEvent, BlockId, BlockIds, FragmentId, FragmentProof, Header, Peer};
+use crate::error::{Code, Error};
 use async_trait::async_trait;
 use futures::prelude::*;
 
@@ -63,6 +63,24 @@ pub trait BlockService {
         from: BlockIds,
     ) -> Result<Self::PullBlocksToTipStream, Error>;
 
+    /// Serves a light client request for a proof of inclusion
+    /// of the fragment identified by `fragment` in the block `block`.
+    ///
+    /// The default implementation reports that the method is not supported.
+    /// Nodes implementing this method should enable the light client
+    /// capability with `grpc::server::Builder::light_client`.
+    async fn get_fragment_proof(
+        &self,
+        block: BlockId,
+        fragment: FragmentId,
+    ) -> Result<FragmentProof, Error> {
+        let _ = (block, fragment);
+        Err(Error::new(
+            Code::Unimplemented,
+            "fragment proofs are not supported by this node",
+        ))
+    }
+
     /// Called by the protocol implementation to handle a stream
     /// of block headers sent by the peer in response to a
     /// `BlockEvent::Missing` solicitation.
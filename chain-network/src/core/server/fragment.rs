@@ -1,5 +1,5 @@
 use super::PushStream;
-use crate::data::{Fragment, FragmentIds, Peer};
+use crate::data::{Fragment, FragmentIds, FragmentStatusEvent, Peer};
 use crate::error::Error;
 use async_trait::async_trait;
 use futures::prelude::*;
@@ -24,9 +24,71 @@ pub trait FragmentService {
     /// bidirectional subscription stream.
     /// The inbound stream is passed to the asynchronous method,
     /// which resolves to the outbound stream.
+    ///
+    /// Depending on the propagation policy of the protocol implementation,
+    /// the fragments produced by the outbound stream may be discarded
+    /// rather than sent to subscribers that receive the fragment IDs
+    /// over an inventory subscription.
     async fn fragment_subscription(
         &self,
         subscriber: Peer,
         stream: PushStream<Fragment>,
     ) -> Result<Self::SubscriptionStream, Error>;
+
+    /// The type of outbound asynchronous streams returned by the
+    /// `fragment_inventory_subscription` method.
+    ///
+    /// Implementations not supporting inventory subscriptions can
+    /// use `futures::stream::Empty`.
+    type InventorySubscriptionStream: Stream<Item = Result<FragmentIds, Error>> + Send + Sync;
+
+    /// Called by the protocol implementation to establish a
+    /// bidirectional stream announcing the IDs of new fragments.
+    /// The inbound stream is passed to the asynchronous method,
+    /// which resolves to the outbound stream.
+    ///
+    /// The protocol implementation keeps track of the fragment IDs
+    /// announced in either direction over the subscription, and omits
+    /// the IDs known to the subscriber from the outbound announcements.
+    /// The service should retrieve the announced fragments it does not
+    /// have with a `get_fragments` request to the subscriber.
+    ///
+    /// The default implementation reports that the method is not supported.
+    async fn fragment_inventory_subscription(
+        &self,
+        subscriber: Peer,
+        stream: PushStream<FragmentIds>,
+    ) -> Result<Self::InventorySubscriptionStream, Error> {
+        let _ = (subscriber, stream);
+        Err(Error::unimplemented())
+    }
+
+    /// The type of asynchronous streams returned by the
+    /// `fragment_status_subscription` method.
+    ///
+    /// Implementations not supporting status subscriptions can
+    /// use `futures::stream::Empty`.
+    type StatusSubscriptionStream: Stream<Item = Result<FragmentStatusEvent, Error>> + Send + Sync;
+
+    /// Serves a subscription to the status of the fragments identified
+    /// by `ids`. Resolves to a stream of status events to send to
+    /// the subscriber.
+    ///
+    /// The stream should start with the current status of each of
+    /// the fragments known to the node, followed by the changes of status
+    /// as they occur. The stream may end once all of the fragments have
+    /// reached a final status.
+    ///
+    /// The protocol implementation rejects the requests for more than
+    /// `grpc::server::MAX_STATUS_SUBSCRIPTION_IDS` fragments.
+    ///
+    /// The default implementation reports that the method is not supported.
+    async fn fragment_status_subscription(
+        &self,
+        subscriber: Peer,
+        ids: FragmentIds,
+    ) -> Result<Self::StatusSubscriptionStream, Error> {
+        let _ = (subscriber, ids);
+        Err(Error::unimplemented())
+    }
 }
@@ -18,6 +18,11 @@ pub trait GossipService {
     /// bidirectional subscription stream.
     /// The inbound stream is passed to the asynchronous method,
     /// which resolves to the outbound stream.
+    ///
+    /// The node records in gossip items received on the inbound stream
+    /// have been validated by the protocol implementation: records of
+    /// unsupported versions or with invalid signatures are dropped from
+    /// the gossip items, keeping the valid records.
     async fn gossip_subscription(
         &self,
         subscriber: Peer,
@@ -1,6 +1,6 @@
 use super::{BlockService, FragmentService, GossipService};
 use crate::data::p2p::{AuthenticatedNodeId, Peer};
-use crate::data::HandshakeResponse;
+use crate::data::{HandshakeResponse, Protocol};
 use crate::error::Error;
 use async_trait::async_trait;
 
@@ -22,7 +22,17 @@ pub trait Node: Send + Sync + 'static {
 
     /// Implements node handshake. The server returns the ID of the genesis
     /// block and its own node ID, authenticated with the signature of `nonce`.
-    async fn handshake(&self, peer: Peer, nonce: &[u8]) -> Result<HandshakeResponse, Error>;
+    ///
+    /// The protocol version and capabilities negotiated with the peer are
+    /// passed in `protocol`. The implementation should not use features
+    /// outside of the negotiated capabilities in the session with this peer,
+    /// and may reject the handshake if the protocol is not acceptable.
+    async fn handshake(
+        &self,
+        peer: Peer,
+        nonce: &[u8],
+        protocol: Protocol,
+    ) -> Result<HandshakeResponse, Error>;
 
     /// Handles client ID authentication.
     async fn client_auth(&self, peer: Peer, auth: AuthenticatedNodeId) -> Result<(), Error>;
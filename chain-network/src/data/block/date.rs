@@ -0,0 +1,15 @@
+use std::fmt;
+
+/// Position of a block in the blockchain time, as an epoch number
+/// and a slot number within the epoch.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockDate {
+    pub epoch: u32,
+    pub slot: u32,
+}
+
+impl fmt::Display for BlockDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.epoch, self.slot)
+    }
+}
@@ -2,6 +2,9 @@
 #[derive(Clone, Debug)]
 pub struct Header(Box<[u8]>);
 
+/// A batch of block headers.
+pub type Headers = Box<[Header]>;
+
 impl Header {
     #[inline]
     pub fn from_bytes<B: Into<Box<[u8]>>>(bytes: B) -> Self {
@@ -1,10 +1,12 @@
 #[allow(clippy::module_inception)]
 mod block;
+mod date;
 mod header;
 mod id;
 mod subscription;
 
 pub use block::Block;
-pub use header::Header;
+pub use date::BlockDate;
+pub use header::{Header, Headers};
 pub use id::{try_ids_from_iter, BlockId, BlockIds};
 pub use subscription::{BlockEvent, ChainPullRequest};
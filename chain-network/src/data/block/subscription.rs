@@ -1,7 +1,7 @@
 use super::{BlockId, BlockIds, Header};
 
 /// An event sent from client to server over the block subscription stream.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum BlockEvent {
     /// Announcement of a new block in the chain.
     Announce(Header),
@@ -12,7 +12,7 @@ pub enum BlockEvent {
 }
 
 /// A request to send headers in the block chain sequence.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ChainPullRequest {
     /// A list of starting points known by the requester.
     /// The sender should pick the latest one.
@@ -0,0 +1,185 @@
+use super::{FragmentId, FragmentIds};
+
+use std::collections::{HashSet, VecDeque};
+
+/// Bounded set of fragment IDs known to a peer, used to avoid
+/// announcing or sending fragments that the peer already has.
+///
+/// When the capacity is reached, the IDs inserted earliest are evicted
+/// first.
+#[derive(Clone, Debug)]
+pub struct KnownFragments {
+    capacity: usize,
+    ids: HashSet<FragmentId>,
+    order: VecDeque<FragmentId>,
+}
+
+impl KnownFragments {
+    pub fn with_capacity(capacity: usize) -> Self {
+        KnownFragments {
+            capacity,
+            ids: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    #[inline]
+    pub fn contains(&self, id: &FragmentId) -> bool {
+        self.ids.contains(id)
+    }
+
+    /// Adds the ID to the set. Returns `false` if the ID
+    /// was already present.
+    ///
+    /// A set with zero capacity does not remember any IDs,
+    /// so every ID is reported as new.
+    pub fn insert(&mut self, id: FragmentId) -> bool {
+        if self.capacity == 0 {
+            return true;
+        }
+        if !self.ids.insert(id) {
+            return false;
+        }
+        if self.order.len() == self.capacity {
+            let evicted = self.order.pop_front().unwrap();
+            self.ids.remove(&evicted);
+        }
+        self.order.push_back(id);
+        true
+    }
+
+    /// Adds the IDs to the set, returning the ones that were not
+    /// present before, in their original order.
+    pub fn insert_new(&mut self, ids: &[FragmentId]) -> FragmentIds {
+        ids.iter()
+            .copied()
+            .filter(|id| self.insert(*id))
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+/// The way a node propagates new fragments to its subscribed peers.
+///
+/// With inventory announcements, the peers are sent only the IDs
+/// of new fragments, and retrieve the fragments they don't have with
+/// a `get_fragments` request.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PropagationPolicy {
+    /// Push the full fragments to all subscribers.
+    PushAll,
+    /// Announce fragment IDs to all subscribers.
+    AnnounceAll,
+    /// Push the full fragments to at most `fan_out` subscribers
+    /// and announce the IDs to the rest.
+    Mixed { fan_out: usize },
+}
+
+impl Default for PropagationPolicy {
+    fn default() -> Self {
+        PropagationPolicy::PushAll
+    }
+}
+
+impl PropagationPolicy {
+    /// Splits the subscribers into the ones to push fragments to
+    /// and the ones to announce fragment IDs to.
+    ///
+    /// Subscribers are selected for pushing in the order given, so
+    /// the caller should shuffle or rank them as appropriate.
+    pub fn split<'a, T>(&self, subscribers: &'a [T]) -> (&'a [T], &'a [T]) {
+        let fan_out = match *self {
+            PropagationPolicy::PushAll => subscribers.len(),
+            PropagationPolicy::AnnounceAll => 0,
+            PropagationPolicy::Mixed { fan_out } => fan_out.min(subscribers.len()),
+        };
+        subscribers.split_at(fan_out)
+    }
+
+    /// Returns whether the full fragments should be pushed to a new
+    /// subscriber, given the number of subscribers they are already
+    /// pushed to.
+    pub fn pushes_to_next(&self, pushed: usize) -> bool {
+        match *self {
+            PropagationPolicy::PushAll => true,
+            PropagationPolicy::AnnounceAll => false,
+            PropagationPolicy::Mixed { fan_out } => pushed < fan_out,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn id(n: u8) -> FragmentId {
+        FragmentId::try_from(&[n; 32][..]).unwrap()
+    }
+
+    #[test]
+    fn known_fragments_evicts_oldest() {
+        let mut known = KnownFragments::with_capacity(2);
+        assert!(known.insert(id(1)));
+        assert!(!known.insert(id(1)));
+        assert!(known.insert(id(2)));
+        assert!(known.insert(id(3)));
+        assert_eq!(known.len(), 2);
+        assert!(!known.contains(&id(1)));
+        assert!(known.contains(&id(2)));
+        assert!(known.contains(&id(3)));
+    }
+
+    #[test]
+    fn insert_new_filters_known() {
+        let mut known = KnownFragments::with_capacity(16);
+        known.insert(id(2));
+        let new = known.insert_new(&[id(1), id(2), id(3), id(1)]);
+        assert_eq!(&new[..], &[id(1), id(3)]);
+        assert!(known.insert_new(&[id(3)]).is_empty());
+    }
+
+    #[test]
+    fn zero_capacity_does_not_dedup() {
+        let mut known = KnownFragments::with_capacity(0);
+        assert!(known.insert(id(1)));
+        assert!(known.insert(id(1)));
+        assert!(known.is_empty());
+        let new = known.insert_new(&[id(1), id(1)]);
+        assert_eq!(&new[..], &[id(1), id(1)]);
+    }
+
+    #[test]
+    fn propagation_fan_out() {
+        let peers = [1, 2, 3];
+        let (push, announce) = PropagationPolicy::PushAll.split(&peers);
+        assert_eq!((push, announce), (&peers[..], &[][..]));
+        let (push, announce) = PropagationPolicy::AnnounceAll.split(&peers);
+        assert_eq!((push, announce), (&[][..], &peers[..]));
+        let (push, announce) = PropagationPolicy::Mixed { fan_out: 1 }.split(&peers);
+        assert_eq!((push, announce), (&peers[..1], &peers[1..]));
+        let (push, announce) = PropagationPolicy::Mixed { fan_out: 5 }.split(&peers);
+        assert_eq!((push, announce), (&peers[..], &[][..]));
+
+        let policy = PropagationPolicy::Mixed { fan_out: 1 };
+        assert!(policy.pushes_to_next(0));
+        assert!(!policy.pushes_to_next(1));
+        assert!(PropagationPolicy::PushAll.pushes_to_next(usize::MAX));
+        assert!(!PropagationPolicy::AnnounceAll.pushes_to_next(0));
+    }
+}
@@ -1,6 +1,12 @@
 #[allow(clippy::module_inception)]
 mod fragment;
 mod id;
+mod inventory;
+mod proof;
+mod status;
 
 pub use fragment::Fragment;
 pub use id::{try_ids_from_iter, FragmentId, FragmentIds};
+pub use inventory::{KnownFragments, PropagationPolicy};
+pub use proof::FragmentProof;
+pub use status::{FragmentStatus, FragmentStatusEvent};
@@ -0,0 +1,38 @@
+/// Proof of inclusion of a fragment in a block, in the byte array
+/// representation defined by the blockchain implementation.
+///
+/// A light client verifies the proof against a commitment to the block
+/// content, as defined by the blockchain, without downloading the block.
+#[derive(Clone, Debug)]
+pub struct FragmentProof(Box<[u8]>);
+
+impl FragmentProof {
+    #[inline]
+    pub fn from_bytes<B: Into<Box<[u8]>>>(bytes: B) -> Self {
+        FragmentProof(bytes.into())
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0.into()
+    }
+}
+
+impl AsRef<[u8]> for FragmentProof {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl From<FragmentProof> for Vec<u8> {
+    #[inline]
+    fn from(proof: FragmentProof) -> Self {
+        proof.into_bytes()
+    }
+}
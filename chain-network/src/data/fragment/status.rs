@@ -0,0 +1,31 @@
+use super::FragmentId;
+use crate::data::block::{BlockDate, BlockId};
+
+/// Status of a fragment submitted to the network.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FragmentStatus {
+    /// The fragment is waiting to be included in a block.
+    Pending,
+    /// The fragment has been included in a block.
+    InBlock { block: BlockId, date: BlockDate },
+    /// The fragment has been rejected by the node.
+    Rejected { reason: String },
+    /// The fragment has expired without being included in a block.
+    Expired,
+}
+
+impl FragmentStatus {
+    /// Checks if the status is final, that is, no further changes
+    /// to the status are expected to be reported.
+    pub fn is_final(&self) -> bool {
+        !matches!(self, FragmentStatus::Pending)
+    }
+}
+
+/// Change of the status of a fragment, reported in a fragment status
+/// subscription.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FragmentStatusEvent {
+    pub fragment: FragmentId,
+    pub status: FragmentStatus,
+}
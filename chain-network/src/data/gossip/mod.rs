@@ -1,3 +1,3 @@
 mod node;
 
-pub use node::{Gossip, Node, Nodes};
+pub use node::{Gossip, Node, NodeInfo, Nodes, Topic, RECORD_VERSION};
@@ -1,40 +1,284 @@
-/// A gossip node in an opaque byte array representation.
-#[derive(Clone)]
-pub struct Node(Box<[u8]>);
+use crate::data::p2p::{NodeId, NodeKeyPair};
+use crate::error::{Code, Error};
+
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Version of the gossip node record format produced by this
+/// implementation.
+pub const RECORD_VERSION: u32 = 1;
+
+// Prefix of the signed record data, distinguishing the signature of
+// a record from the signatures of other data made with the node key.
+const RECORD_CONTEXT: &[u8] = b"gossip-node-record";
+
+/// Topics of network traffic that a node can subscribe to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Topic {
+    Blocks,
+    Fragments,
+}
+
+impl Topic {
+    // Code of the topic in the signed record data.
+    fn code(self) -> u32 {
+        match self {
+            Topic::Blocks => 0,
+            Topic::Fragments => 1,
+        }
+    }
+}
+
+/// Information that a node advertises about itself in gossip.
+///
+/// The information becomes a gossip node record once it is signed
+/// with the key pair of the node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeInfo {
+    /// Addresses the node accepts connections on.
+    pub addresses: Vec<SocketAddr>,
+    /// Versions of the network protocol supported by the node.
+    pub protocol_versions: Vec<u32>,
+    /// Topics the node is subscribed to.
+    pub topics: Vec<Topic>,
+    /// Time when the information was produced, with precision
+    /// of whole seconds.
+    pub timestamp: SystemTime,
+}
+
+impl NodeInfo {
+    /// Creates information on a node listening on the given addresses,
+    /// supporting the protocol versions implemented by this crate and
+    /// timestamped with the current time.
+    pub fn new(addresses: Vec<SocketAddr>, topics: Vec<Topic>) -> Self {
+        NodeInfo {
+            addresses,
+            protocol_versions: (crate::MIN_PROTOCOL_VERSION..=crate::PROTOCOL_VERSION).collect(),
+            topics,
+            timestamp: now_secs(),
+        }
+    }
+
+    /// Signs the information with the key pair of the node,
+    /// producing a gossip node record.
+    pub fn sign(self, key_pair: &NodeKeyPair) -> Node {
+        let auth = key_pair.sign_data(&self.signed_data(RECORD_VERSION));
+        Node {
+            version: RECORD_VERSION,
+            id: auth.id().clone(),
+            signature: auth.signature().into(),
+            info: self,
+        }
+    }
+
+    fn timestamp_secs(&self) -> u64 {
+        self.timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+
+    // The byte representation covered by the signature, starting with
+    // the record context string. Every variable-length list is prefixed
+    // with its length, so that the representation is unambiguous.
+    fn signed_data(&self, version: u32) -> Vec<u8> {
+        let mut data = Vec::with_capacity(RECORD_CONTEXT.len() + 64 + self.addresses.len() * 19);
+        data.extend_from_slice(RECORD_CONTEXT);
+        data.extend_from_slice(&version.to_be_bytes());
+        data.extend_from_slice(&(self.addresses.len() as u32).to_be_bytes());
+        for addr in &self.addresses {
+            match addr {
+                SocketAddr::V4(addr) => {
+                    data.push(4);
+                    data.extend_from_slice(&addr.ip().octets());
+                }
+                SocketAddr::V6(addr) => {
+                    data.push(6);
+                    data.extend_from_slice(&addr.ip().octets());
+                }
+            }
+            data.extend_from_slice(&addr.port().to_be_bytes());
+        }
+        data.extend_from_slice(&(self.protocol_versions.len() as u32).to_be_bytes());
+        for version in &self.protocol_versions {
+            data.extend_from_slice(&version.to_be_bytes());
+        }
+        data.extend_from_slice(&(self.topics.len() as u32).to_be_bytes());
+        for topic in &self.topics {
+            data.extend_from_slice(&topic.code().to_be_bytes());
+        }
+        data.extend_from_slice(&self.timestamp_secs().to_be_bytes());
+        data
+    }
+}
+
+// The current time, truncated to whole seconds as represented in
+// the signed record.
+fn now_secs() -> SystemTime {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// A signed record of a node in the network, propagated in gossip.
+///
+/// Records received from the network are validated before being
+/// passed on to the application: the record format version must be
+/// supported and the signature must be valid for the node ID.
+#[derive(Clone, Debug)]
+pub struct Node {
+    version: u32,
+    id: NodeId,
+    info: NodeInfo,
+    signature: Box<[u8]>,
+}
 
 pub type Nodes = Box<[Node]>;
 
 impl Node {
-    #[inline]
-    pub fn from_bytes<B: Into<Box<[u8]>>>(bytes: B) -> Self {
-        Node(bytes.into())
+    /// Reassembles a record from its decoded parts and validates it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record format version is not supported
+    /// or the signature does not match the node ID and the information.
+    pub fn from_parts(
+        version: u32,
+        id: NodeId,
+        info: NodeInfo,
+        signature: Box<[u8]>,
+    ) -> Result<Self, Error> {
+        if version != RECORD_VERSION {
+            return Err(Error::new(
+                Code::Unimplemented,
+                format!("unsupported gossip node record version {}", version),
+            ));
+        }
+        let node = Node {
+            version,
+            id,
+            info,
+            signature,
+        };
+        node.verify()?;
+        Ok(node)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        self.id
+            .clone()
+            .authenticated(&self.signature)?
+            .verify_data(&self.info.signed_data(self.version))
     }
 
     #[inline]
-    pub fn as_bytes(&self) -> &[u8] {
-        &self.0
+    pub fn version(&self) -> u32 {
+        self.version
     }
 
     #[inline]
-    pub fn into_bytes(self) -> Vec<u8> {
-        self.0.into()
+    pub fn id(&self) -> &NodeId {
+        &self.id
     }
-}
 
-impl AsRef<[u8]> for Node {
     #[inline]
-    fn as_ref(&self) -> &[u8] {
-        self.as_bytes()
+    pub fn info(&self) -> &NodeInfo {
+        &self.info
     }
-}
 
-impl From<Node> for Vec<u8> {
     #[inline]
-    fn from(block: Node) -> Self {
-        block.into_bytes()
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Returns the age of the record at the given time, or `None` if
+    /// the record is timestamped later than `now`.
+    pub fn age(&self, now: SystemTime) -> Option<Duration> {
+        now.duration_since(self.info.timestamp).ok()
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct Gossip {
     pub nodes: Nodes,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_info() -> NodeInfo {
+        NodeInfo::new(
+            vec![
+                "127.0.0.1:3000".parse().unwrap(),
+                "[::1]:3000".parse().unwrap(),
+            ],
+            vec![Topic::Blocks, Topic::Fragments],
+        )
+    }
+
+    fn reassemble(node: &Node, info: NodeInfo) -> Result<Node, Error> {
+        Node::from_parts(
+            node.version(),
+            node.id().clone(),
+            info,
+            node.signature().into(),
+        )
+    }
+
+    #[test]
+    fn signed_record_is_valid() {
+        let key_pair = NodeKeyPair::generate(rand::thread_rng());
+        let node = sample_info().sign(&key_pair);
+        reassemble(&node, node.info().clone()).unwrap();
+    }
+
+    #[test]
+    fn tampered_record_is_rejected() {
+        let key_pair = NodeKeyPair::generate(rand::thread_rng());
+        let node = sample_info().sign(&key_pair);
+
+        let mut info = node.info().clone();
+        info.addresses.push("10.0.0.1:3000".parse().unwrap());
+        reassemble(&node, info).unwrap_err();
+
+        let mut info = node.info().clone();
+        info.topics.pop();
+        reassemble(&node, info).unwrap_err();
+
+        let mut info = node.info().clone();
+        info.timestamp += Duration::from_secs(60);
+        reassemble(&node, info).unwrap_err();
+    }
+
+    #[test]
+    fn handshake_signature_is_not_a_record_signature() {
+        let key_pair = NodeKeyPair::generate(rand::thread_rng());
+        let info = sample_info();
+        // a peer could get the data signed as a handshake nonce
+        let auth = key_pair.sign(&info.signed_data(RECORD_VERSION));
+        Node::from_parts(
+            RECORD_VERSION,
+            auth.id().clone(),
+            info,
+            auth.signature().into(),
+        )
+        .unwrap_err();
+    }
+
+    #[test]
+    fn unsupported_version_is_rejected() {
+        let key_pair = NodeKeyPair::generate(rand::thread_rng());
+        let node = sample_info().sign(&key_pair);
+        let err = Node::from_parts(
+            RECORD_VERSION + 1,
+            node.id().clone(),
+            node.info().clone(),
+            node.signature().into(),
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), Code::Unimplemented);
+    }
+}
@@ -1,8 +1,205 @@
 use super::block::BlockId;
 use super::p2p::AuthenticatedNodeId;
+use crate::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
+use std::fmt;
+use std::ops::{BitAnd, BitOr};
 
 pub struct HandshakeResponse {
     pub block0_id: BlockId,
     pub auth: AuthenticatedNodeId,
     pub nonce: Box<[u8]>,
 }
+
+/// A set of optional protocol features, negotiated in the handshake.
+///
+/// Capabilities let new features and methods be rolled out without
+/// breaking interoperability with peers that do not implement them:
+/// a feature is only used on a connection if both peers have announced
+/// the corresponding capability. Bits not defined by this version of
+/// the crate are ignored on receipt.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Capabilities(u64);
+
+impl Capabilities {
+    /// Compression of block, header and fragment content.
+    pub const COMPRESSION: Capabilities = Capabilities(1 << 0);
+    /// Gossip carrying signed node records.
+    pub const GOSSIP_RECORDS: Capabilities = Capabilities(1 << 1);
+    /// Methods serving light clients: batched header retrieval
+    /// and fragment inclusion proofs.
+    pub const LIGHT_CLIENT: Capabilities = Capabilities(1 << 2);
+    /// Subscription announcing the IDs of new fragments.
+    pub const FRAGMENT_INVENTORY: Capabilities = Capabilities(1 << 3);
+    /// Subscription to the status of submitted fragments.
+    pub const FRAGMENT_STATUS: Capabilities = Capabilities(1 << 4);
+
+    const ALL: Capabilities = Capabilities(
+        Self::COMPRESSION.0
+            | Self::GOSSIP_RECORDS.0
+            | Self::LIGHT_CLIENT.0
+            | Self::FRAGMENT_INVENTORY.0
+            | Self::FRAGMENT_STATUS.0,
+    );
+
+    // Capabilities requiring node service methods that are not
+    // implemented by default, so a server only offers them on request.
+    const OPT_IN: Capabilities = Capabilities(Self::LIGHT_CLIENT.0);
+
+    /// The empty set of capabilities, which is what peers
+    /// not implementing capability negotiation support.
+    #[inline]
+    pub const fn empty() -> Self {
+        Capabilities(0)
+    }
+
+    /// All capabilities implemented by this version of the crate.
+    #[inline]
+    pub const fn supported() -> Self {
+        Self::ALL
+    }
+
+    /// Capabilities offered by a server unless configured otherwise:
+    /// all supported capabilities except those requiring the node
+    /// to implement optional service methods, such as `LIGHT_CLIENT`.
+    #[inline]
+    pub const fn server_default() -> Self {
+        Capabilities(Self::ALL.0 & !Self::OPT_IN.0)
+    }
+
+    /// Creates the set from its wire representation, dropping
+    /// the bits unknown to this version of the crate.
+    #[inline]
+    pub const fn from_bits_truncate(bits: u64) -> Self {
+        Capabilities(bits & Self::ALL.0)
+    }
+
+    #[inline]
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn contains(self, other: Capabilities) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[inline]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline]
+    pub fn insert(&mut self, other: Capabilities) {
+        self.0 |= other.0;
+    }
+
+    #[inline]
+    pub fn remove(&mut self, other: Capabilities) {
+        self.0 &= !other.0;
+    }
+}
+
+impl BitAnd for Capabilities {
+    type Output = Self;
+
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self {
+        Capabilities(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Capabilities(self.0 | rhs.0)
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [
+            (Capabilities::COMPRESSION, "COMPRESSION"),
+            (Capabilities::GOSSIP_RECORDS, "GOSSIP_RECORDS"),
+            (Capabilities::LIGHT_CLIENT, "LIGHT_CLIENT"),
+            (Capabilities::FRAGMENT_INVENTORY, "FRAGMENT_INVENTORY"),
+            (Capabilities::FRAGMENT_STATUS, "FRAGMENT_STATUS"),
+        ];
+        let mut set = f.debug_set();
+        for (cap, name) in names.iter() {
+            if self.contains(*cap) {
+                set.entry(&format_args!("{}", name));
+            }
+        }
+        set.finish()
+    }
+}
+
+/// The protocol version and capabilities agreed upon in the handshake.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Protocol {
+    pub version: u32,
+    pub capabilities: Capabilities,
+}
+
+impl Protocol {
+    /// Selects the protocol to use with a peer that implements
+    /// versions in the range from `min_version` to `max_version`
+    /// and has announced `capabilities`.
+    ///
+    /// The highest version supported by both sides is selected;
+    /// the negotiated capabilities are the ones supported by both sides.
+    /// Returns `None` if there is no protocol version supported by both.
+    pub fn negotiate(
+        min_version: u32,
+        max_version: u32,
+        capabilities: Capabilities,
+    ) -> Option<Self> {
+        let version = max_version.min(PROTOCOL_VERSION);
+        if version < min_version.max(MIN_PROTOCOL_VERSION) {
+            return None;
+        }
+        Some(Protocol {
+            version,
+            capabilities: capabilities & Capabilities::supported(),
+        })
+    }
+
+    /// Checks if the negotiated protocol includes the capability.
+    #[inline]
+    pub fn supports(&self, capability: Capabilities) -> bool {
+        self.capabilities.contains(capability)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_capabilities_are_ignored() {
+        let caps = Capabilities::from_bits_truncate(!0);
+        assert_eq!(caps, Capabilities::supported());
+        assert!(caps.contains(Capabilities::COMPRESSION));
+        assert!(Capabilities::from_bits_truncate(1 << 63).is_empty());
+    }
+
+    #[test]
+    fn negotiate_version() {
+        let proto =
+            Protocol::negotiate(MIN_PROTOCOL_VERSION, u32::MAX, Capabilities::empty()).unwrap();
+        assert_eq!(proto.version, PROTOCOL_VERSION);
+        assert!(proto.capabilities.is_empty());
+
+        let proto = Protocol::negotiate(1, PROTOCOL_VERSION, Capabilities::COMPRESSION).unwrap();
+        assert_eq!(proto.version, PROTOCOL_VERSION);
+        assert!(proto.supports(Capabilities::COMPRESSION));
+        assert!(!proto.supports(Capabilities::GOSSIP_RECORDS));
+
+        assert!(Protocol::negotiate(1, 1, Capabilities::ALL).is_none());
+
+        assert!(Protocol::negotiate(PROTOCOL_VERSION + 1, u32::MAX, Capabilities::ALL).is_none());
+    }
+}
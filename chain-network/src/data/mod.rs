@@ -4,8 +4,10 @@ pub mod gossip;
 mod handshake;
 pub mod p2p;
 
-pub use block::{Block, BlockEvent, BlockId, BlockIds, Header};
-pub use fragment::{Fragment, FragmentId, FragmentIds};
+pub use block::{Block, BlockDate, BlockEvent, BlockId, BlockIds, Header, Headers};
+pub use fragment::{
+    Fragment, FragmentId, FragmentIds, FragmentProof, FragmentStatus, FragmentStatusEvent,
+};
 pub use gossip::Gossip;
-pub use handshake::HandshakeResponse;
+pub use handshake::{Capabilities, HandshakeResponse, Protocol};
 pub use p2p::{AuthenticatedNodeId, NodeId, NodeKeyPair, Peer, Peers};
@@ -33,6 +33,18 @@ impl fmt::Display for Peer {
     }
 }
 
+// Prefix of the data signed to authenticate the node ID in the handshake.
+// The nonce is chosen by the peer, so without the prefix its signature
+// could pass for the signature of any other data signed with the node key.
+const NONCE_CONTEXT: &[u8] = b"handshake-nonce";
+
+fn nonce_data(nonce: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(NONCE_CONTEXT.len() + nonce.len());
+    data.extend_from_slice(NONCE_CONTEXT);
+    data.extend_from_slice(nonce);
+    data
+}
+
 /// The key pair used to authenticate a network node,
 /// including the secret key.
 #[derive(Clone, Debug)]
@@ -46,8 +58,17 @@ impl NodeKeyPair {
 
     /// Produces the node ID (i.e. the public key), authenticated by signing
     /// the provided nonce with the secret key.
+    ///
+    /// The signed data is the nonce prefixed with a fixed context string,
+    /// so the signature cannot be used for anything else than the handshake.
     pub fn sign(&self, nonce: &[u8]) -> AuthenticatedNodeId {
-        let signature = self.0.private_key().sign(nonce);
+        self.sign_data(&nonce_data(nonce))
+    }
+
+    // Signs the data as given. The data must start with a context string
+    // distinct from the one of the handshake nonces.
+    pub(crate) fn sign_data(&self, data: &[u8]) -> AuthenticatedNodeId {
+        let signature = self.0.private_key().sign(data);
         AuthenticatedNodeId {
             id: NodeId(self.0.public_key().clone()),
             signature,
@@ -82,6 +103,12 @@ impl NodeId {
     }
 }
 
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 impl TryFrom<&[u8]> for NodeId {
     type Error = Error;
 
@@ -115,7 +142,12 @@ impl AuthenticatedNodeId {
     /// Verifies that the signature is correct for this node ID and
     /// the given nonce.
     pub fn verify(&self, nonce: &[u8]) -> Result<(), Error> {
-        match self.signature.verify(&self.id.0, nonce) {
+        self.verify_data(&nonce_data(nonce))
+    }
+
+    // Verifies the signature of data signed with `NodeKeyPair::sign_data`.
+    pub(crate) fn verify_data(&self, data: &[u8]) -> Result<(), Error> {
+        match self.signature.verify(&self.id.0, data) {
             Verification::Success => Ok(()),
             Verification::Failed => Err(Error::new(
                 Code::InvalidArgument,
@@ -1,3 +1,4 @@
+use crate::compression::Compression;
 use std::{error, fmt};
 
 /// Common error codes for network protocol requests.
@@ -84,4 +85,20 @@ pub enum HandshakeError {
     InvalidNodeId(#[source] Error),
     #[error("invalid node signature format")]
     MalformedSignature(#[source] Error),
+    /// The signature of the nonce by the server is not valid.
+    #[error("node signature verification failed")]
+    InvalidSignature(#[source] Error),
+    /// The server is on a blockchain with a different genesis block.
+    #[error("genesis block does not match")]
+    Block0Mismatch,
+    #[error("invalid compression algorithm")]
+    InvalidCompression(#[source] Error),
+    /// The server selected a compression algorithm that
+    /// the client did not offer.
+    #[error("unsupported compression algorithm {0}")]
+    UnsupportedCompression(Compression),
+    /// The node ID reported by the server differs from the one
+    /// authenticated by the encrypted transport.
+    #[error("node ID does not match the peer authenticated on the connection")]
+    NodeIdMismatch,
 }
@@ -1,22 +1,29 @@
-use super::convert;
+use super::convert::{self, FromProtobuf};
+use super::inventory::{self, InventoryInbound};
 use super::proto;
+use super::server::DEFAULT_INVENTORY_CACHE_SIZE;
 use super::streaming::{InboundStream, OutboundStream};
+use super::{CAPABILITIES_METADATA_KEY, COMPRESSION_METADATA_KEY};
 
 #[cfg(feature = "legacy")]
 use super::legacy;
 
-use crate::data::block::{Block, BlockEvent, BlockId, BlockIds, Header};
-use crate::data::fragment::{Fragment, FragmentIds};
+#[cfg(feature = "noise")]
+use super::noise;
+
+use crate::compression::Compression;
+use crate::data::block::{Block, BlockEvent, BlockId, BlockIds, Header, Headers};
+use crate::data::fragment::{
+    Fragment, FragmentId, FragmentIds, FragmentProof, FragmentStatusEvent,
+};
 use crate::data::p2p::{AuthenticatedNodeId, NodeId};
-use crate::data::{Gossip, HandshakeResponse, Peers};
-use crate::error::{Error, HandshakeError};
-use crate::PROTOCOL_VERSION;
+use crate::data::{Capabilities, Gossip, HandshakeResponse, Peers, Protocol};
+use crate::error::{Code, Error, HandshakeError};
+use crate::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
 use futures::prelude::*;
 use tonic::body::{Body, BoxBody};
 use tonic::client::GrpcService;
 use tonic::codegen::{HttpBody, StdError};
-
-#[cfg(feature = "legacy")]
 use tonic::metadata::MetadataValue;
 
 #[cfg(feature = "transport")]
@@ -28,20 +35,52 @@ use std::convert::TryFrom;
 use std::convert::TryInto;
 
 /// Builder to customize the gRPC client.
-#[derive(Default)]
 pub struct Builder {
+    capabilities: Capabilities,
+    accept_compression: Vec<Compression>,
     #[cfg(feature = "legacy")]
     legacy_node_id: Option<legacy::NodeId>,
+    #[cfg(feature = "noise")]
+    noise: Option<noise::Config>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder::new()
+    }
 }
 
 impl Builder {
     pub fn new() -> Self {
         Builder {
+            capabilities: Capabilities::supported(),
+            accept_compression: Compression::supported(),
             #[cfg(feature = "legacy")]
             legacy_node_id: None,
+            #[cfg(feature = "noise")]
+            noise: None,
         }
     }
 
+    /// Sets the optional protocol capabilities that the client offers
+    /// to the server in the handshake.
+    ///
+    /// By default, all capabilities implemented by this crate are offered.
+    pub fn capabilities(&mut self, capabilities: Capabilities) -> &mut Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Sets the compression algorithms that the client offers to
+    /// the server in the handshake.
+    ///
+    /// By default, all algorithms supported by this build of the crate
+    /// are offered.
+    pub fn accept_compression(&mut self, algorithms: &[Compression]) -> &mut Self {
+        self.accept_compression = algorithms.to_vec();
+        self
+    }
+
     /// Make the client add "node-id-bin" metadata with the passed value
     /// into subscription requests, for backward compatibility with
     /// jormungandr versions prior to 0.9.
@@ -51,6 +90,17 @@ impl Builder {
         self
     }
 
+    /// Makes `connect` establish the connection over the Noise transport
+    /// with the given configuration, instead of plain TCP.
+    ///
+    /// The handshake of a client connected this way fails if the server
+    /// reports a node ID other than the one authenticated by the transport.
+    #[cfg(feature = "noise")]
+    pub fn noise(&mut self, config: noise::Config) -> &mut Self {
+        self.noise = Some(config);
+        self
+    }
+
     pub fn build<T>(&self, service: T) -> Client<T>
     where
         T: GrpcService<BoxBody>,
@@ -60,8 +110,14 @@ impl Builder {
     {
         Client {
             inner: proto::node_client::NodeClient::new(service),
+            capabilities: self.capabilities,
+            accept_compression: self.accept_compression.clone(),
+            protocol: None,
+            compression: Compression::None,
             #[cfg(feature = "legacy")]
             legacy_node_id: self.legacy_node_id,
+            #[cfg(feature = "noise")]
+            noise_connector: None,
         }
     }
 
@@ -71,20 +127,31 @@ impl Builder {
         D: TryInto<transport::Endpoint>,
         D::Error: Into<StdError>,
     {
-        let inner = proto::node_client::NodeClient::connect(dst).await?;
-        Ok(Client {
-            inner,
-            #[cfg(feature = "legacy")]
-            legacy_node_id: self.legacy_node_id,
-        })
+        let endpoint = transport::Endpoint::new(dst)?;
+        #[cfg(feature = "noise")]
+        if let Some(config) = &self.noise {
+            let connector = noise::Connector::new(config.clone());
+            let channel = endpoint.connect_with_connector(connector.clone()).await?;
+            let mut client = self.build(channel);
+            client.noise_connector = Some(connector);
+            return Ok(client);
+        }
+        let channel = endpoint.connect().await?;
+        Ok(self.build(channel))
     }
 }
 
 #[derive(Clone)]
 pub struct Client<T> {
     inner: proto::node_client::NodeClient<T>,
+    capabilities: Capabilities,
+    accept_compression: Vec<Compression>,
+    protocol: Option<Protocol>,
+    compression: Compression,
     #[cfg(feature = "legacy")]
     legacy_node_id: Option<legacy::NodeId>,
+    #[cfg(feature = "noise")]
+    noise_connector: Option<noise::Connector>,
 }
 
 /// The inbound subscription stream of block events.
@@ -93,6 +160,14 @@ pub type BlockSubscription = InboundStream<proto::BlockEvent, BlockEvent>;
 /// The inbound subscription stream of fragments.
 pub type FragmentSubscription = InboundStream<proto::Fragment, Fragment>;
 
+/// The inbound subscription stream of fragment ID announcements.
+pub type FragmentInventorySubscription =
+    InventoryInbound<InboundStream<proto::FragmentIds, FragmentIds>>;
+
+/// The inbound stream of fragment status events.
+pub type FragmentStatusSubscription =
+    InboundStream<proto::FragmentStatusEvent, FragmentStatusEvent>;
+
 /// The inbound subscription stream of P2P gossip.
 pub type GossipSubscription = InboundStream<proto::Gossip, Gossip>;
 
@@ -118,10 +193,54 @@ where
         Builder::new().build(service)
     }
 
+    /// The protocol version and capabilities negotiated in the handshake,
+    /// or `None` if the handshake has not been performed.
+    pub fn protocol(&self) -> Option<Protocol> {
+        self.protocol
+    }
+
+    // Fails if the handshake has established that the server does not
+    // support the capability needed for a request.
+    fn require_capability(&self, capability: Capabilities, feature: &str) -> Result<(), Error> {
+        match self.protocol {
+            Some(protocol) if !protocol.supports(capability) => Err(Error::new(
+                Code::Unimplemented,
+                format!("{} is not supported by the server", feature),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The compression algorithm negotiated in the handshake.
+    ///
+    /// Until the handshake is performed, the content is not compressed.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    // Makes a request reporting the capabilities negotiated in the handshake
+    // and asking the server to compress the content it sends in the response
+    // with the negotiated algorithm.
+    fn request<M>(&self, message: M) -> tonic::Request<M> {
+        let mut req = tonic::Request::new(message);
+        if let Some(protocol) = self.protocol {
+            if !protocol.capabilities.is_empty() {
+                let val = MetadataValue::from_str(&protocol.capabilities.bits().to_string())
+                    .expect("decimal number is valid metadata");
+                req.metadata_mut().insert(CAPABILITIES_METADATA_KEY, val);
+            }
+        }
+        if self.compression != Compression::None {
+            let val = MetadataValue::from_static(self.compression.as_str());
+            req.metadata_mut().insert(COMPRESSION_METADATA_KEY, val);
+        }
+        req
+    }
+
     #[allow(unused_mut)]
     #[allow(clippy::let_and_return)]
     fn subscription_request<S>(&self, outbound: S) -> tonic::Request<S> {
-        let mut req = tonic::Request::new(outbound);
+        let mut req = self.request(outbound);
         #[cfg(feature = "legacy")]
         if let Some(node_id) = self.legacy_node_id {
             let val = MetadataValue::from_bytes(&node_id.encode());
@@ -149,6 +268,14 @@ where
     pub async fn handshake(&mut self, nonce: &[u8]) -> Result<HandshakeResponse, HandshakeError> {
         let req = proto::HandshakeRequest {
             nonce: nonce.into(),
+            accept_compression: self
+                .accept_compression
+                .iter()
+                .map(|c| convert::compression_into_protobuf(*c) as i32)
+                .collect(),
+            min_version: MIN_PROTOCOL_VERSION,
+            max_version: PROTOCOL_VERSION,
+            capabilities: self.capabilities.bits(),
         };
         let res = self
             .inner
@@ -156,7 +283,7 @@ where
             .await
             .map_err(|status| HandshakeError::Rpc(convert::error_from_grpc(status)))?
             .into_inner();
-        if res.version != PROTOCOL_VERSION {
+        if res.version < MIN_PROTOCOL_VERSION || res.version > PROTOCOL_VERSION {
             return Err(HandshakeError::UnsupportedVersion(
                 res.version.to_string().into(),
             ));
@@ -167,6 +294,26 @@ where
         let auth = node_id
             .authenticated(&res.signature)
             .map_err(HandshakeError::MalformedSignature)?;
+        #[cfg(feature = "noise")]
+        if let Some(connector) = &self.noise_connector {
+            if connector.peer_id().as_ref() != Some(auth.id()) {
+                return Err(HandshakeError::NodeIdMismatch);
+            }
+        }
+        let protocol = Protocol {
+            version: res.version,
+            capabilities: Capabilities::from_bits_truncate(res.capabilities) & self.capabilities,
+        };
+        let compression = convert::compression_from_protobuf(res.compression)
+            .map_err(HandshakeError::InvalidCompression)?;
+        if compression != Compression::None
+            && (!protocol.supports(Capabilities::COMPRESSION)
+                || !self.accept_compression.contains(&compression))
+        {
+            return Err(HandshakeError::UnsupportedCompression(compression));
+        }
+        self.protocol = Some(protocol);
+        self.compression = compression;
         let nonce = res.nonce.into();
         Ok(HandshakeResponse {
             block0_id,
@@ -212,7 +359,8 @@ where
         let ids = proto::BlockIds {
             ids: convert::ids_into_repeated_bytes(ids.iter()),
         };
-        let stream = self.inner.get_blocks(ids).await?.into_inner();
+        let req = self.request(ids);
+        let stream = self.inner.get_blocks(req).await?.into_inner();
         Ok(InboundStream::new(stream))
     }
 
@@ -224,7 +372,8 @@ where
         let ids = proto::BlockIds {
             ids: convert::ids_into_repeated_bytes(ids.iter()),
         };
-        let stream = self.inner.get_headers(ids).await?.into_inner();
+        let req = self.request(ids);
+        let stream = self.inner.get_headers(req).await?.into_inner();
         Ok(InboundStream::new(stream))
     }
 
@@ -236,7 +385,8 @@ where
         let ids = proto::FragmentIds {
             ids: convert::ids_into_repeated_bytes(ids.into_vec()),
         };
-        let stream = self.inner.get_fragments(ids).await?.into_inner();
+        let req = self.request(ids);
+        let stream = self.inner.get_fragments(req).await?.into_inner();
         Ok(InboundStream::new(stream))
     }
 
@@ -246,10 +396,10 @@ where
         from: BlockIds,
         to: BlockId,
     ) -> Result<InboundStream<proto::Block, Block>, Error> {
-        let req = proto::PullBlocksRequest {
+        let req = self.request(proto::PullBlocksRequest {
             from: convert::ids_into_repeated_bytes(from.into_vec()),
             to: to.as_ref().to_vec(),
-        };
+        });
         let stream = self.inner.pull_blocks(req).await?.into_inner();
         Ok(InboundStream::new(stream))
     }
@@ -260,9 +410,9 @@ where
         &mut self,
         from: BlockIds,
     ) -> Result<InboundStream<proto::Block, Block>, Error> {
-        let req = proto::PullBlocksToTipRequest {
+        let req = self.request(proto::PullBlocksToTipRequest {
             from: convert::ids_into_repeated_bytes(from.into_vec()),
-        };
+        });
         let stream = self.inner.pull_blocks_to_tip(req).await?.into_inner();
         Ok(InboundStream::new(stream))
     }
@@ -277,14 +427,58 @@ where
         from: BlockIds,
         to: BlockId,
     ) -> Result<InboundStream<proto::Header, Header>, Error> {
-        let req = proto::PullHeadersRequest {
+        let req = self.request(proto::PullHeadersRequest {
             from: convert::ids_into_repeated_bytes(from.into_vec()),
             to: to.as_bytes().into(),
-        };
+        });
         let stream = self.inner.pull_headers(req).await?.into_inner();
         Ok(InboundStream::new(stream))
     }
 
+    /// Like `pull_headers`, but receives the headers in batches of up to
+    /// `batch_size` headers each. With `batch_size` of zero, the service
+    /// selects the batch size.
+    ///
+    /// This method is meant for light clients synchronizing the chain
+    /// of headers. It fails with an `Unimplemented` error if the handshake
+    /// has established that the service does not support light clients.
+    pub async fn pull_header_batches(
+        &mut self,
+        from: BlockIds,
+        to: BlockId,
+        batch_size: u32,
+    ) -> Result<InboundStream<proto::HeaderBatch, Headers>, Error> {
+        self.require_capability(Capabilities::LIGHT_CLIENT, "light client support")?;
+        let req = self.request(proto::PullHeaderBatchesRequest {
+            from: convert::ids_into_repeated_bytes(from.into_vec()),
+            to: to.as_bytes().into(),
+            batch_size,
+        });
+        let stream = self.inner.pull_header_batches(req).await?.into_inner();
+        Ok(InboundStream::new(stream))
+    }
+
+    /// Requests a proof of inclusion of the fragment identified by
+    /// `fragment` in the block identified by `block`. The proof is verified
+    /// against a commitment to the block content using the rules
+    /// of the blockchain.
+    ///
+    /// This method fails with an `Unimplemented` error if the handshake
+    /// has established that the service does not support light clients.
+    pub async fn get_fragment_proof(
+        &mut self,
+        block: BlockId,
+        fragment: FragmentId,
+    ) -> Result<FragmentProof, Error> {
+        self.require_capability(Capabilities::LIGHT_CLIENT, "light client support")?;
+        let req = proto::FragmentProofRequest {
+            block_id: block.as_bytes().into(),
+            fragment_id: fragment.as_bytes().into(),
+        };
+        let res = self.inner.get_fragment_proof(req).await?.into_inner();
+        FragmentProof::from_message(res)
+    }
+
     /// The outbound counterpart of `pull_headers`, called in response to a
     /// `BlockEvent::Missing` solicitation.
     /// An empty stream can be used to indicate that the solicitation
@@ -293,7 +487,7 @@ where
     where
         S: Stream<Item = Header> + Send + Sync + 'static,
     {
-        let outbound = OutboundStream::new(headers);
+        let outbound = OutboundStream::new(headers, self.compression);
         let proto::PushHeadersResponse {} = self.inner.push_headers(outbound).await?.into_inner();
         Ok(())
     }
@@ -305,7 +499,7 @@ where
     where
         S: Stream<Item = Block> + Send + Sync + 'static,
     {
-        let outbound = OutboundStream::new(blocks);
+        let outbound = OutboundStream::new(blocks, self.compression);
         let proto::UploadBlocksResponse {} = self.inner.upload_blocks(outbound).await?.into_inner();
         Ok(())
     }
@@ -319,7 +513,7 @@ where
     where
         S: Stream<Item = Header> + Send + Sync + 'static,
     {
-        let req = self.subscription_request(OutboundStream::new(outbound));
+        let req = self.subscription_request(OutboundStream::new(outbound, self.compression));
         let inbound = self.inner.block_subscription(req).await?.into_inner();
         Ok(InboundStream::new(inbound))
     }
@@ -336,11 +530,71 @@ where
     where
         S: Stream<Item = Fragment> + Send + Sync + 'static,
     {
-        let req = self.subscription_request(OutboundStream::new(outbound));
+        let req = self.subscription_request(OutboundStream::new(outbound, self.compression));
         let inbound = self.inner.fragment_subscription(req).await?.into_inner();
         Ok(InboundStream::new(inbound))
     }
 
+    /// Establishes a bidirectional stream announcing the IDs of new
+    /// fragments created or accepted by either of the peers.
+    ///
+    /// The IDs announced by the server are not announced back to it,
+    /// and the client does not repeat its own announcements.
+    /// The fragments not known to the client can be retrieved with
+    /// `get_fragments`.
+    pub async fn fragment_inventory_subscription<S>(
+        &mut self,
+        outbound: S,
+    ) -> Result<FragmentInventorySubscription, Error>
+    where
+        S: Stream<Item = FragmentIds> + Send + Sync + 'static,
+    {
+        self.require_capability(
+            Capabilities::FRAGMENT_INVENTORY,
+            "fragment inventory subscription",
+        )?;
+        let known = inventory::known_fragments(DEFAULT_INVENTORY_CACHE_SIZE);
+        let outbound = {
+            let known = known.clone();
+            outbound.filter_map(move |ids| {
+                let ids = known.lock().unwrap().insert_new(&ids);
+                future::ready(if ids.is_empty() { None } else { Some(ids) })
+            })
+        };
+        let req = self.subscription_request(OutboundStream::new(outbound, self.compression));
+        let inbound = self
+            .inner
+            .fragment_inventory_subscription(req)
+            .await?
+            .into_inner();
+        Ok(InventoryInbound::new(InboundStream::new(inbound), known))
+    }
+
+    /// Subscribes to the status of the fragments identified by `ids`,
+    /// for example to learn whether fragments submitted with
+    /// `fragment_subscription` have been included in a block.
+    ///
+    /// The stream starts with the current status of each of
+    /// the fragments known to the server, followed by status changes.
+    pub async fn fragment_status_subscription(
+        &mut self,
+        ids: FragmentIds,
+    ) -> Result<FragmentStatusSubscription, Error> {
+        self.require_capability(
+            Capabilities::FRAGMENT_STATUS,
+            "fragment status subscription",
+        )?;
+        let ids = proto::FragmentIds {
+            ids: convert::ids_into_repeated_bytes(ids.into_vec()),
+        };
+        let stream = self
+            .inner
+            .fragment_status_subscription(ids)
+            .await?
+            .into_inner();
+        Ok(InboundStream::new(stream))
+    }
+
     /// Establishes a bidirectional stream for exchanging network gossip.
     ///
     /// The client can use the stream that the returned future resolves to
@@ -349,7 +603,11 @@ where
     where
         S: Stream<Item = Gossip> + Send + Sync + 'static,
     {
-        let req = self.subscription_request(OutboundStream::new(outbound));
+        self.require_capability(
+            Capabilities::GOSSIP_RECORDS,
+            "gossip with signed node records",
+        )?;
+        let req = self.subscription_request(OutboundStream::new(outbound, self.compression));
         let inbound = self.inner.gossip_subscription(req).await?.into_inner();
         Ok(InboundStream::new(inbound))
     }
@@ -1,15 +1,19 @@
 use super::proto;
+use crate::compression::Compression;
 use crate::data::{
-    block::{self, Block, BlockEvent, BlockId, ChainPullRequest, Header},
-    fragment::Fragment,
-    gossip::{Gossip, Node},
-    p2p::Peer,
+    block::{self, Block, BlockDate, BlockEvent, BlockId, ChainPullRequest, Header, Headers},
+    fragment::{
+        self, Fragment, FragmentId, FragmentIds, FragmentProof, FragmentStatus, FragmentStatusEvent,
+    },
+    gossip::{self, Gossip},
+    p2p::{NodeId, Peer},
 };
 use crate::error::{self, Error};
 use tonic::{Code, Status};
 
 use std::convert::TryFrom;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, UNIX_EPOCH};
 
 pub(super) fn error_into_grpc(err: Error) -> Status {
     use error::Code::*;
@@ -25,16 +29,20 @@ pub(super) fn error_into_grpc(err: Error) -> Status {
         Internal => Code::Internal,
         Unavailable => Code::Unavailable,
         // When a new case has to be added here, remember to
-        // add the corresponding case in error_from_grpc below.
+        // add the corresponding case in code_from_grpc below.
     };
 
     Status::new(code, err.to_string())
 }
 
 pub(super) fn error_from_grpc(e: Status) -> Error {
+    Error::new(code_from_grpc(e.code()), e)
+}
+
+pub(super) fn code_from_grpc(code: Code) -> error::Code {
     use error::Code::*;
 
-    let code = match e.code() {
+    match code {
         Code::Cancelled => Canceled,
         Code::Unknown => Unknown,
         Code::InvalidArgument => InvalidArgument,
@@ -45,9 +53,7 @@ pub(super) fn error_from_grpc(e: Status) -> Error {
         Code::Internal => Internal,
         Code::Unavailable => Unavailable,
         _ => Unknown,
-    };
-
-    Error::new(code, e)
+    }
 }
 
 impl From<Error> for Status {
@@ -71,6 +77,57 @@ pub trait FromProtobuf<R>: Sized {
 pub trait IntoProtobuf {
     type Message;
     fn into_message(self) -> Self::Message;
+
+    /// Converts into the protobuf message, compressing the content
+    /// with the given algorithm if the message supports it.
+    fn into_message_compressed(self, compression: Compression) -> Self::Message
+    where
+        Self: Sized,
+    {
+        let _ = compression;
+        self.into_message()
+    }
+}
+
+pub(super) fn compression_into_protobuf(compression: Compression) -> proto::Compression {
+    match compression {
+        Compression::None => proto::Compression::None,
+        Compression::Gzip => proto::Compression::Gzip,
+        Compression::Zstd => proto::Compression::Zstd,
+    }
+}
+
+pub(super) fn compression_from_protobuf(value: i32) -> Result<Compression, Error> {
+    match proto::Compression::from_i32(value) {
+        Some(proto::Compression::None) => Ok(Compression::None),
+        Some(proto::Compression::Gzip) => Ok(Compression::Gzip),
+        Some(proto::Compression::Zstd) => Ok(Compression::Zstd),
+        None => Err(Error::new(
+            error::Code::InvalidArgument,
+            format!("unknown compression algorithm code {}", value),
+        )),
+    }
+}
+
+// Compresses the content if this makes it smaller, otherwise the content
+// is sent uncompressed. Returns the content with the protobuf code
+// of the compression algorithm applied.
+fn compress_content(content: Vec<u8>, compression: Compression) -> (Vec<u8>, i32) {
+    if compression != Compression::None {
+        if let Ok(compressed) = compression.compress(&content) {
+            if compressed.len() < content.len() {
+                return (compressed, compression_into_protobuf(compression) as i32);
+            }
+        }
+    }
+    (content, proto::Compression::None as i32)
+}
+
+fn decompress_content(content: Vec<u8>, compression: i32) -> Result<Vec<u8>, Error> {
+    match compression_from_protobuf(compression)? {
+        Compression::None => Ok(content),
+        compression => compression.decompress(&content),
+    }
 }
 
 pub(super) fn from_protobuf_repeated<P, T>(message: Vec<P>) -> Result<Box<[T]>, Error>
@@ -98,7 +155,8 @@ where
 
 impl FromProtobuf<proto::Block> for Block {
     fn from_message(message: proto::Block) -> Result<Self, Error> {
-        Ok(Block::from_bytes(message.content))
+        let content = decompress_content(message.content, message.compression)?;
+        Ok(Block::from_bytes(content))
     }
 }
 
@@ -106,15 +164,22 @@ impl IntoProtobuf for Block {
     type Message = proto::Block;
 
     fn into_message(self) -> proto::Block {
+        self.into_message_compressed(Compression::None)
+    }
+
+    fn into_message_compressed(self, compression: Compression) -> proto::Block {
+        let (content, compression) = compress_content(self.into(), compression);
         proto::Block {
-            content: self.into(),
+            content,
+            compression,
         }
     }
 }
 
 impl FromProtobuf<proto::Header> for Header {
     fn from_message(message: proto::Header) -> Result<Self, Error> {
-        Ok(Header::from_bytes(message.content))
+        let content = decompress_content(message.content, message.compression)?;
+        Ok(Header::from_bytes(content))
     }
 }
 
@@ -122,15 +187,78 @@ impl IntoProtobuf for Header {
     type Message = proto::Header;
 
     fn into_message(self) -> proto::Header {
+        self.into_message_compressed(Compression::None)
+    }
+
+    fn into_message_compressed(self, compression: Compression) -> proto::Header {
+        let (content, compression) = compress_content(self.into(), compression);
         proto::Header {
+            content,
+            compression,
+        }
+    }
+}
+
+impl FromProtobuf<proto::HeaderBatch> for Headers {
+    fn from_message(message: proto::HeaderBatch) -> Result<Self, Error> {
+        from_protobuf_repeated(message.headers)
+    }
+}
+
+impl IntoProtobuf for Headers {
+    type Message = proto::HeaderBatch;
+
+    fn into_message(self) -> proto::HeaderBatch {
+        self.into_message_compressed(Compression::None)
+    }
+
+    fn into_message_compressed(self, compression: Compression) -> proto::HeaderBatch {
+        proto::HeaderBatch {
+            headers: self
+                .into_vec()
+                .into_iter()
+                .map(|header| header.into_message_compressed(compression))
+                .collect(),
+        }
+    }
+}
+
+impl FromProtobuf<proto::FragmentProof> for FragmentProof {
+    fn from_message(message: proto::FragmentProof) -> Result<Self, Error> {
+        Ok(FragmentProof::from_bytes(message.content))
+    }
+}
+
+impl IntoProtobuf for FragmentProof {
+    type Message = proto::FragmentProof;
+
+    fn into_message(self) -> proto::FragmentProof {
+        proto::FragmentProof {
             content: self.into(),
         }
     }
 }
 
+impl FromProtobuf<proto::FragmentIds> for FragmentIds {
+    fn from_message(message: proto::FragmentIds) -> Result<Self, Error> {
+        fragment::try_ids_from_iter(message.ids)
+    }
+}
+
+impl IntoProtobuf for FragmentIds {
+    type Message = proto::FragmentIds;
+
+    fn into_message(self) -> proto::FragmentIds {
+        proto::FragmentIds {
+            ids: ids_into_repeated_bytes(self.into_vec()),
+        }
+    }
+}
+
 impl FromProtobuf<proto::Fragment> for Fragment {
     fn from_message(message: proto::Fragment) -> Result<Self, Error> {
-        Ok(Fragment::from_bytes(message.content))
+        let content = decompress_content(message.content, message.compression)?;
+        Ok(Fragment::from_bytes(content))
     }
 }
 
@@ -138,23 +266,116 @@ impl IntoProtobuf for Fragment {
     type Message = proto::Fragment;
 
     fn into_message(self) -> proto::Fragment {
+        self.into_message_compressed(Compression::None)
+    }
+
+    fn into_message_compressed(self, compression: Compression) -> proto::Fragment {
+        let (content, compression) = compress_content(self.into(), compression);
         proto::Fragment {
-            content: self.into(),
+            content,
+            compression,
+        }
+    }
+}
+
+fn topic_from_protobuf(value: i32) -> Result<gossip::Topic, Error> {
+    match proto::GossipTopic::from_i32(value) {
+        Some(proto::GossipTopic::Blocks) => Ok(gossip::Topic::Blocks),
+        Some(proto::GossipTopic::Fragments) => Ok(gossip::Topic::Fragments),
+        None => Err(Error::new(
+            error::Code::InvalidArgument,
+            format!("unknown gossip topic code {}", value),
+        )),
+    }
+}
+
+fn topic_into_protobuf(topic: gossip::Topic) -> proto::GossipTopic {
+    match topic {
+        gossip::Topic::Blocks => proto::GossipTopic::Blocks,
+        gossip::Topic::Fragments => proto::GossipTopic::Fragments,
+    }
+}
+
+impl FromProtobuf<proto::GossipNode> for gossip::Node {
+    fn from_message(message: proto::GossipNode) -> Result<Self, Error> {
+        let timestamp = UNIX_EPOCH
+            .checked_add(Duration::from_secs(message.timestamp))
+            .ok_or_else(|| {
+                Error::new(
+                    error::Code::InvalidArgument,
+                    format!(
+                        "gossip node timestamp {} is out of range",
+                        message.timestamp
+                    ),
+                )
+            })?;
+        let id = NodeId::try_from(&message.node_id[..])?;
+        let addresses = message
+            .addresses
+            .into_iter()
+            .map(|peer| Peer::from_message(peer).map(|peer| peer.addr()))
+            .collect::<Result<_, _>>()?;
+        let topics = message
+            .topics
+            .into_iter()
+            .map(topic_from_protobuf)
+            .collect::<Result<_, _>>()?;
+        let info = gossip::NodeInfo {
+            addresses,
+            protocol_versions: message.protocol_versions,
+            topics,
+            timestamp,
+        };
+        gossip::Node::from_parts(message.version, id, info, message.signature.into())
+    }
+}
+
+impl IntoProtobuf for gossip::Node {
+    type Message = proto::GossipNode;
+
+    fn into_message(self) -> proto::GossipNode {
+        let info = self.info();
+        let timestamp = info
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        proto::GossipNode {
+            version: self.version(),
+            node_id: self.id().as_bytes().into(),
+            addresses: info
+                .addresses
+                .iter()
+                .map(|addr| Peer::from(*addr).into_message())
+                .collect(),
+            protocol_versions: info.protocol_versions.clone(),
+            topics: info
+                .topics
+                .iter()
+                .map(|topic| topic_into_protobuf(*topic) as i32)
+                .collect(),
+            timestamp,
+            signature: self.signature().into(),
         }
     }
 }
 
 impl FromProtobuf<proto::Gossip> for Gossip {
+    // Invalid node records are skipped, so that one bad record
+    // does not discard the rest of the gossip.
     fn from_message(message: proto::Gossip) -> Result<Self, Error> {
-        let gossip = Gossip {
-            nodes: message
-                .nodes
-                .into_iter()
-                .map(Node::from_bytes)
-                .collect::<Vec<_>>()
-                .into(),
-        };
-        Ok(gossip)
+        let nodes = message
+            .nodes
+            .into_iter()
+            .filter_map(|node| match gossip::Node::from_message(node) {
+                Ok(node) => Some(node),
+                Err(e) => {
+                    tracing::debug!(error = %e, "skipping invalid gossip node record");
+                    None
+                }
+            })
+            .collect();
+        Ok(Gossip { nodes })
     }
 }
 
@@ -163,12 +384,7 @@ impl IntoProtobuf for Gossip {
 
     fn into_message(self) -> proto::Gossip {
         proto::Gossip {
-            nodes: self
-                .nodes
-                .into_vec()
-                .into_iter()
-                .map(|node| node.into_bytes())
-                .collect(),
+            nodes: into_protobuf_repeated(self.nodes.into_vec()),
         }
     }
 }
@@ -316,3 +532,92 @@ impl IntoProtobuf for BlockEvent {
         proto::BlockEvent { item: Some(item) }
     }
 }
+
+impl FromProtobuf<proto::FragmentStatusEvent> for FragmentStatusEvent {
+    fn from_message(msg: proto::FragmentStatusEvent) -> Result<Self, Error> {
+        use proto::fragment_status_event::Status::*;
+
+        let fragment = FragmentId::try_from(&msg.fragment_id[..])?;
+        let status = match msg.status {
+            Some(Pending(_)) => FragmentStatus::Pending,
+            Some(InBlock(in_block)) => {
+                let block = BlockId::try_from(&in_block.block_id[..])?;
+                let date = in_block.date.ok_or_else(|| {
+                    Error::new(
+                        error::Code::InvalidArgument,
+                        "the date of the block must be present",
+                    )
+                })?;
+                FragmentStatus::InBlock {
+                    block,
+                    date: BlockDate {
+                        epoch: date.epoch,
+                        slot: date.slot,
+                    },
+                }
+            }
+            Some(Rejected(rejected)) => FragmentStatus::Rejected {
+                reason: rejected.reason,
+            },
+            Some(Expired(_)) => FragmentStatus::Expired,
+            None => {
+                return Err(Error::new(
+                    error::Code::InvalidArgument,
+                    "one of the FragmentStatusEvent status variants must be present",
+                ))
+            }
+        };
+        Ok(FragmentStatusEvent { fragment, status })
+    }
+}
+
+impl IntoProtobuf for FragmentStatusEvent {
+    type Message = proto::FragmentStatusEvent;
+
+    fn into_message(self) -> proto::FragmentStatusEvent {
+        use proto::fragment_status_event::Status;
+        let status = match self.status {
+            FragmentStatus::Pending => Status::Pending(proto::FragmentPending {}),
+            FragmentStatus::InBlock { block, date } => Status::InBlock(proto::FragmentInBlock {
+                block_id: block.as_bytes().into(),
+                date: Some(proto::BlockDate {
+                    epoch: date.epoch,
+                    slot: date.slot,
+                }),
+            }),
+            FragmentStatus::Rejected { reason } => {
+                Status::Rejected(proto::FragmentRejected { reason })
+            }
+            FragmentStatus::Expired => Status::Expired(proto::FragmentExpired {}),
+        };
+        proto::FragmentStatusEvent {
+            fragment_id: self.fragment.as_bytes().into(),
+            status: Some(status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::gossip::{NodeInfo, Topic};
+    use crate::data::p2p::NodeKeyPair;
+
+    #[test]
+    fn gossip_skips_records_with_out_of_range_timestamps() {
+        let key_pair = NodeKeyPair::generate(rand::thread_rng());
+        let info = NodeInfo::new(vec!["10.0.0.1:3000".parse().unwrap()], vec![Topic::Blocks]);
+        let valid = info.sign(&key_pair).into_message();
+        let mut invalid = valid.clone();
+        invalid.timestamp = u64::MAX;
+
+        let err = gossip::Node::from_message(invalid.clone()).unwrap_err();
+        assert_eq!(err.code(), error::Code::InvalidArgument);
+
+        let message = proto::Gossip {
+            nodes: vec![invalid, valid],
+        };
+        let gossip = Gossip::from_message(message).unwrap();
+        assert_eq!(gossip.nodes.len(), 1);
+    }
+}
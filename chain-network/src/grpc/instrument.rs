@@ -0,0 +1,216 @@
+use super::convert::code_from_grpc;
+use crate::data::p2p::{NodeId, Peer};
+use crate::metrics::Metrics;
+use futures::prelude::*;
+use tonic::Status;
+use tracing::field;
+use tracing_futures::Instrument;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// The tracing span and the metrics collector of an RPC call
+/// served by the node.
+#[derive(Clone, Debug)]
+pub(crate) struct CallContext {
+    method: &'static str,
+    span: tracing::Span,
+    metrics: Option<Arc<dyn Metrics>>,
+}
+
+impl CallContext {
+    pub fn new(
+        method: &'static str,
+        peer: Option<&Peer>,
+        node_id: Option<&NodeId>,
+        metrics: Option<Arc<dyn Metrics>>,
+    ) -> Self {
+        let span = tracing::info_span!("rpc", method, peer = field::Empty, node_id = field::Empty);
+        if let Some(peer) = peer {
+            span.record("peer", field::display(peer));
+        }
+        if let Some(node_id) = node_id {
+            span.record("node_id", field::display(node_id));
+        }
+        CallContext {
+            method,
+            span,
+            metrics,
+        }
+    }
+
+    pub fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+
+    /// Runs the handler of the call in the span, reporting the outcome
+    /// and the time taken.
+    pub async fn run<F, T>(&self, handler: F) -> Result<T, Status>
+    where
+        F: Future<Output = Result<T, Status>>,
+    {
+        if let Some(metrics) = &self.metrics {
+            metrics.call_started(self.method);
+        }
+        let started = Instant::now();
+        let res = handler.instrument(self.span.clone()).await;
+        let latency = started.elapsed();
+        match &res {
+            Ok(_) => {
+                tracing::debug!(parent: &self.span, ?latency, "request handled");
+            }
+            Err(status) => {
+                tracing::info!(
+                    parent: &self.span,
+                    ?latency,
+                    code = ?status.code(),
+                    error = status.message(),
+                    "request failed",
+                );
+            }
+        }
+        if let Some(metrics) = &self.metrics {
+            let result = res
+                .as_ref()
+                .map(|_| ())
+                .map_err(|status| code_from_grpc(status.code()));
+            metrics.call_finished(self.method, result, latency);
+        }
+        res
+    }
+
+    /// Runs the handler of a call with unary request and response,
+    /// also reporting the sizes of the messages.
+    pub async fn unary<R, M, F, Fut>(
+        &self,
+        req: tonic::Request<R>,
+        handler: F,
+    ) -> Result<tonic::Response<M>, Status>
+    where
+        R: prost::Message,
+        M: prost::Message,
+        F: FnOnce(tonic::Request<R>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<M>, Status>>,
+    {
+        self.message_received(req.get_ref());
+        let res = self.run(handler(req)).await;
+        if let Ok(res) = &res {
+            self.message_sent(res.get_ref());
+        }
+        res
+    }
+
+    pub fn message_received<M: prost::Message>(&self, msg: &M) {
+        if let Some(metrics) = &self.metrics {
+            metrics.message_received(self.method, msg.encoded_len());
+        }
+    }
+
+    pub fn message_sent<M: prost::Message>(&self, msg: &M) {
+        if let Some(metrics) = &self.metrics {
+            metrics.message_sent(self.method, msg.encoded_len());
+        }
+    }
+
+    pub fn stream_error(&self, status: &Status) {
+        tracing::info!(
+            parent: &self.span,
+            code = ?status.code(),
+            error = status.message(),
+            "error in the response stream",
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.stream_error(self.method, code_from_grpc(status.code()));
+        }
+    }
+
+    /// Reports the response stream of the call as opened.
+    /// The returned guard reports the stream closed when dropped.
+    pub fn open_stream(self) -> OpenStream {
+        if let Some(metrics) = &self.metrics {
+            metrics.stream_opened(self.method);
+        }
+        OpenStream {
+            ctx: self,
+            opened: Instant::now(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct OpenStream {
+    ctx: CallContext,
+    opened: Instant,
+}
+
+impl OpenStream {
+    pub fn context(&self) -> &CallContext {
+        &self.ctx
+    }
+}
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        let lifetime = self.opened.elapsed();
+        tracing::debug!(parent: &self.ctx.span, ?lifetime, "response stream closed");
+        if let Some(metrics) = &self.ctx.metrics {
+            metrics.stream_closed(self.ctx.method, lifetime);
+        }
+    }
+}
+
+/// Bounded map of what the server knows about its clients, such as the
+/// node IDs they have authenticated with, keyed by the remote address of
+/// the connection.
+///
+/// When the capacity is reached, the entries inserted earliest
+/// are evicted first.
+#[derive(Debug)]
+pub(super) struct PeerMap<V> {
+    capacity: usize,
+    inner: Mutex<PeerMapInner<V>>,
+}
+
+#[derive(Debug)]
+struct PeerMapInner<V> {
+    values: HashMap<Peer, V>,
+    order: VecDeque<Peer>,
+}
+
+impl<V> Default for PeerMapInner<V> {
+    fn default() -> Self {
+        PeerMapInner {
+            values: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+}
+
+impl<V: Clone> PeerMap<V> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        PeerMap {
+            capacity,
+            inner: Default::default(),
+        }
+    }
+
+    pub fn get(&self, peer: &Peer) -> Option<V> {
+        self.inner.lock().unwrap().values.get(peer).cloned()
+    }
+
+    pub fn insert(&self, peer: Peer, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if inner.values.insert(peer.clone(), value).is_some() {
+            return;
+        }
+        if inner.order.len() == self.capacity {
+            let evicted = inner.order.pop_front().unwrap();
+            inner.values.remove(&evicted);
+        }
+        inner.order.push_back(peer);
+    }
+}
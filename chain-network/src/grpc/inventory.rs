@@ -0,0 +1,208 @@
+use crate::data::fragment::{FragmentIds, KnownFragments, PropagationPolicy};
+use crate::data::Fragment;
+use crate::error::Error;
+use futures::prelude::*;
+use futures::ready;
+use pin_project::pin_project;
+
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// The fragment IDs known to the peer on the other side of
+/// an inventory subscription, shared between the inbound and
+/// the outbound streams of the subscription.
+pub(super) type SharedKnownFragments = Arc<Mutex<KnownFragments>>;
+
+pub(super) fn known_fragments(capacity: usize) -> SharedKnownFragments {
+    Arc::new(Mutex::new(KnownFragments::with_capacity(capacity)))
+}
+
+/// Stream of fragment IDs announced by the peer, recording the IDs
+/// as known to the peer so that they are not announced back.
+#[must_use = "streams do nothing unless polled"]
+#[pin_project]
+pub struct InventoryInbound<S> {
+    #[pin]
+    inner: S,
+    known: SharedKnownFragments,
+}
+
+impl<S> InventoryInbound<S> {
+    pub(super) fn new(inner: S, known: SharedKnownFragments) -> Self {
+        InventoryInbound { inner, known }
+    }
+}
+
+impl<S> Stream for InventoryInbound<S>
+where
+    S: Stream<Item = Result<FragmentIds, Error>>,
+{
+    type Item = Result<FragmentIds, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = ready!(this.inner.poll_next(cx));
+        if let Some(Ok(ids)) = &item {
+            let mut known = this.known.lock().unwrap();
+            for id in ids.iter() {
+                known.insert(*id);
+            }
+        }
+        Poll::Ready(item)
+    }
+}
+
+/// Stream of fragment IDs to announce to the peer, omitting the IDs
+/// that the peer is known to have. Announcements left empty after
+/// filtering are skipped.
+#[must_use = "streams do nothing unless polled"]
+#[pin_project]
+pub struct InventoryOutbound<S> {
+    #[pin]
+    inner: S,
+    known: SharedKnownFragments,
+}
+
+impl<S> InventoryOutbound<S> {
+    pub(super) fn new(inner: S, known: SharedKnownFragments) -> Self {
+        InventoryOutbound { inner, known }
+    }
+}
+
+impl<S> Stream for InventoryOutbound<S>
+where
+    S: TryStream<Ok = FragmentIds, Error = Error>,
+{
+    type Item = Result<FragmentIds, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            match ready!(this.inner.as_mut().try_poll_next(cx)) {
+                Some(Ok(ids)) => {
+                    let ids = this.known.lock().unwrap().insert_new(&ids);
+                    if !ids.is_empty() {
+                        return Poll::Ready(Some(Ok(ids)));
+                    }
+                }
+                other => return Poll::Ready(other),
+            }
+        }
+    }
+}
+
+/// Selects the fragment subscriptions that the full fragments are pushed to,
+/// according to the propagation policy of the server.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Propagation {
+    policy: PropagationPolicy,
+    pushed: Arc<AtomicUsize>,
+}
+
+impl Propagation {
+    pub(super) fn new(policy: PropagationPolicy) -> Self {
+        Propagation {
+            policy,
+            pushed: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Selects whether the fragments are pushed to a new subscriber.
+    /// Subscribers that have not negotiated inventory announcements
+    /// are always pushed the fragments, as they could not learn about
+    /// new fragments otherwise.
+    pub(super) fn subscribe(&self, announce_capable: bool) -> Option<PushSlot> {
+        let pushed = self.pushed.fetch_add(1, Ordering::AcqRel);
+        if !announce_capable || self.policy.pushes_to_next(pushed) {
+            Some(PushSlot(self.pushed.clone()))
+        } else {
+            self.pushed.fetch_sub(1, Ordering::AcqRel);
+            None
+        }
+    }
+}
+
+/// Counts a subscriber as pushed to for as long as it is alive.
+#[derive(Debug)]
+pub(super) struct PushSlot(Arc<AtomicUsize>);
+
+impl Drop for PushSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Outbound stream of a fragment subscription. If the subscriber
+/// has not been selected for pushing, the fragments produced by the
+/// service are discarded and the subscriber is expected to learn
+/// about them from the announcements on its inventory subscription.
+#[must_use = "streams do nothing unless polled"]
+#[pin_project]
+pub struct FragmentPush<S> {
+    #[pin]
+    inner: S,
+    slot: Option<PushSlot>,
+}
+
+impl<S> FragmentPush<S> {
+    pub(super) fn new(inner: S, slot: Option<PushSlot>) -> Self {
+        FragmentPush { inner, slot }
+    }
+}
+
+impl<S> Stream for FragmentPush<S>
+where
+    S: TryStream<Ok = Fragment, Error = Error>,
+{
+    type Item = Result<Fragment, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            match ready!(this.inner.as_mut().try_poll_next(cx)) {
+                Some(Ok(_)) if this.slot.is_none() => {}
+                other => return Poll::Ready(other),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn fragments() -> impl Stream<Item = Result<Fragment, Error>> {
+        stream::iter(vec![
+            Ok(Fragment::from_bytes(&b"first"[..])),
+            Ok(Fragment::from_bytes(&b"second"[..])),
+        ])
+    }
+
+    #[test]
+    fn propagation_pushes_up_to_fan_out() {
+        let propagation = Propagation::new(PropagationPolicy::Mixed { fan_out: 1 });
+        let first = propagation.subscribe(true);
+        assert!(first.is_some());
+        assert!(propagation.subscribe(true).is_none());
+        // subscribers unable to receive announcements are always pushed to
+        let legacy = propagation.subscribe(false);
+        assert!(legacy.is_some());
+        drop((first, legacy));
+        assert!(propagation.subscribe(true).is_some());
+    }
+
+    #[test]
+    fn fragments_are_not_pushed_without_slot() {
+        let propagation = Propagation::new(PropagationPolicy::PushAll);
+        let pushed: Vec<_> =
+            block_on(FragmentPush::new(fragments(), propagation.subscribe(true)).try_collect())
+                .unwrap();
+        assert_eq!(pushed.len(), 2);
+
+        let pushed: Vec<_> = block_on(FragmentPush::new(fragments(), None).try_collect()).unwrap();
+        assert!(pushed.is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+//! In-process transport connecting a gRPC client directly to the server
+//! of a node, without binding any sockets.
+//!
+//! This is mostly useful for testing `Node` implementations and
+//! deterministic multi-node scenarios within a single process.
+
+use super::client::Client;
+use super::server::Server;
+use crate::core::server::Node;
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Context, Never, Poll, Service};
+use tonic::metadata::MetadataMap;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Request header carrying the address assigned to the client end
+// of the loopback channel.
+const PEER_ADDR_HEADER: &str = "loopback-peer-addr";
+
+// Request header carrying the token of the loopback channels of this process.
+const TOKEN_HEADER: &str = "loopback-token";
+
+static TOKEN: AtomicU64 = AtomicU64::new(0);
+
+// Random value generated once per process, vouching for the peer addresses
+// added by the loopback channels. Clients connecting over the network
+// cannot know it, so they cannot set their own peer address.
+//
+// The request extensions would be the natural place to pass the address,
+// but tonic does not give access to them to the service methods.
+fn token() -> u64 {
+    let token = TOKEN.load(Ordering::Relaxed);
+    if token != 0 {
+        return token;
+    }
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(&TOKEN as *const AtomicU64 as usize);
+    let new_token = hasher.finish() | 1;
+    match TOKEN.compare_exchange(0, new_token, Ordering::Relaxed, Ordering::Relaxed) {
+        Ok(_) => new_token,
+        Err(token) => token,
+    }
+}
+
+/// A client transport channel delivering requests directly to the
+/// service of a node in the same process.
+///
+/// The channel identifies itself to the service with the peer address
+/// it was created with, which is reported to the `Node` implementation
+/// in place of the remote address of a network connection.
+pub struct Channel<T: Node> {
+    server: Server<T>,
+    peer_addr: SocketAddr,
+}
+
+impl<T: Node> Clone for Channel<T> {
+    fn clone(&self) -> Self {
+        Channel {
+            server: self.server.clone(),
+            peer_addr: self.peer_addr,
+        }
+    }
+}
+
+impl<T: Node> Channel<T> {
+    pub fn new(server: Server<T>, peer_addr: SocketAddr) -> Self {
+        Channel { server, peer_addr }
+    }
+
+    /// The address under which the client end of this channel is known
+    /// to the node service.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+}
+
+impl<T: Node> Service<http::Request<BoxBody>> for Channel<T> {
+    type Response = http::Response<BoxBody>;
+    type Error = Never;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Service::<http::Request<BoxBody>>::poll_ready(&mut self.server, cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<BoxBody>) -> Self::Future {
+        let headers = req.headers_mut();
+        headers.insert(PEER_ADDR_HEADER, header_value(&self.peer_addr));
+        headers.insert(TOKEN_HEADER, header_value(&token()));
+        self.server.call(req)
+    }
+}
+
+/// Creates a client connected to the node served by `server`
+/// in the same process.
+///
+/// The node sees the requests from this client as coming
+/// from `peer_addr`.
+pub fn connect<T: Node>(server: &Server<T>, peer_addr: SocketAddr) -> Client<Channel<T>> {
+    Client::new(Channel::new(server.clone(), peer_addr))
+}
+
+fn header_value<V: ToString>(value: &V) -> http::HeaderValue {
+    http::HeaderValue::from_str(&value.to_string()).expect("value should be a valid header value")
+}
+
+/// Get the peer address set by a loopback channel, ignoring the address
+/// unless it comes with the token of the channels of this process.
+pub(super) fn peer_addr(metadata: &MetadataMap) -> Option<SocketAddr> {
+    let token: u64 = metadata.get(TOKEN_HEADER)?.to_str().ok()?.parse().ok()?;
+    if token != self::token() {
+        return None;
+    }
+    metadata.get(PEER_ADDR_HEADER)?.to_str().ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(peer_addr: &str, token: Option<u64>) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(PEER_ADDR_HEADER, peer_addr.parse().unwrap());
+        if let Some(token) = token {
+            metadata.insert(TOKEN_HEADER, token.to_string().parse().unwrap());
+        }
+        metadata
+    }
+
+    #[test]
+    fn peer_addr_requires_channel_token() {
+        let addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+        assert_eq!(
+            peer_addr(&metadata("127.0.0.1:3000", Some(token()))),
+            Some(addr)
+        );
+        assert_eq!(peer_addr(&metadata("127.0.0.1:3000", None)), None);
+        assert_eq!(
+            peer_addr(&metadata("127.0.0.1:3000", Some(token() ^ 2))),
+            None
+        );
+    }
+}
@@ -4,13 +4,31 @@ mod proto {
 }
 
 pub mod client;
+pub mod pool;
 pub mod server;
 
 #[cfg(feature = "legacy")]
 pub mod legacy;
 
+#[cfg(any(test, feature = "loopback"))]
+pub mod loopback;
+
+#[cfg(feature = "noise")]
+pub mod noise;
+
 mod convert;
+mod instrument;
+mod inventory;
 mod streaming;
 
+// Request metadata key for the compression algorithm that the client
+// asks to apply to the content in the response.
+const COMPRESSION_METADATA_KEY: &str = "content-compression";
+
+// Request metadata key for the capabilities that the client has
+// negotiated in the handshake, as a decimal bit set.
+const CAPABILITIES_METADATA_KEY: &str = "protocol-capabilities";
+
 pub use client::Client;
+pub use pool::ClientPool;
 pub use server::{NodeService, Server};
@@ -0,0 +1,685 @@
+//! Encrypted and authenticated peer transport using the Noise protocol
+//! framework, as an alternative to TLS.
+//!
+//! The connection is established with the `Noise_XX_25519_ChaChaPoly_BLAKE2b`
+//! handshake. In the handshake payload, each side sends its node ID and
+//! a signature of its Noise static key made with the `NodeKeyPair` of
+//! the node, so that the node identity is bound to the connection without
+//! any certificates.
+//!
+//! On the client side, enable the transport with
+//! `grpc::client::Builder::noise`. On the server side, serve the
+//! connections accepted with `incoming` and configure the node service with
+//! `grpc::server::Builder::noise`, so that `ClientAuth` requests are checked
+//! against the node ID authenticated on the connection.
+
+use crate::data::p2p::{NodeId, NodeKeyPair};
+use crate::error::Code;
+
+use futures::channel::mpsc;
+use futures::future::Future;
+use futures::ready;
+use futures::sink::SinkExt;
+use futures::stream::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tonic::codegen::{http::Uri, Service};
+use tonic::transport::server::Connected;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2b";
+
+// Domain separation prefix for the signature of the Noise static key.
+const IDENTITY_CONTEXT: &[u8] = b"chain-network noise static key:";
+
+const NODE_ID_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+
+const MAX_MESSAGE_LEN: usize = 65535;
+const TAG_LEN: usize = 16;
+const MAX_PAYLOAD_LEN: usize = MAX_MESSAGE_LEN - TAG_LEN;
+
+// The number of established connections waiting to be taken by the server.
+const ACCEPTED_QUEUE_LEN: usize = 64;
+
+const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Errors occurring when establishing a Noise connection.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("I/O error")]
+    Io(#[from] io::Error),
+    #[error("Noise protocol error")]
+    Noise(#[from] snow::Error),
+    #[error("failed to authenticate the peer's node ID")]
+    Authentication(#[source] crate::error::Error),
+    #[error("the peer's node ID does not match the expected one")]
+    PeerMismatch,
+    #[error("invalid peer address")]
+    InvalidAddress,
+    #[error("the handshake has timed out")]
+    Timeout,
+}
+
+/// Registry of node IDs authenticated on the Noise connections
+/// accepted by a server, indexed by the remote address.
+#[derive(Clone, Debug, Default)]
+pub struct Sessions(Arc<Mutex<HashMap<SocketAddr, NodeId>>>);
+
+impl Sessions {
+    /// Returns the node ID authenticated on the open connection
+    /// from the given address.
+    pub fn node_id(&self, addr: &SocketAddr) -> Option<NodeId> {
+        self.0.lock().unwrap().get(addr).cloned()
+    }
+
+    fn register(&self, addr: SocketAddr, node_id: NodeId) -> SessionGuard {
+        self.0.lock().unwrap().insert(addr, node_id);
+        SessionGuard {
+            sessions: self.clone(),
+            addr,
+        }
+    }
+}
+
+// Removes the connection from the registry when it is closed.
+struct SessionGuard {
+    sessions: Sessions,
+    addr: SocketAddr,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.sessions.0.lock().unwrap().remove(&self.addr);
+    }
+}
+
+struct StaticKey {
+    private: Vec<u8>,
+    public: Vec<u8>,
+}
+
+/// Configuration of the Noise transport for a node.
+#[derive(Clone)]
+pub struct Config {
+    key_pair: NodeKeyPair,
+    static_key: Arc<StaticKey>,
+    expected_peer: Option<NodeId>,
+    handshake_timeout: Duration,
+    sessions: Sessions,
+}
+
+impl Config {
+    /// Creates the configuration authenticating the node with
+    /// the given key pair.
+    ///
+    /// A Noise static key is generated for the lifetime of the
+    /// configuration and certified with the node's key pair.
+    pub fn new(key_pair: NodeKeyPair) -> Result<Self, Error> {
+        let keypair = builder().generate_keypair()?;
+        Ok(Config {
+            key_pair,
+            static_key: Arc::new(StaticKey {
+                private: keypair.private,
+                public: keypair.public,
+            }),
+            expected_peer: None,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            sessions: Sessions::default(),
+        })
+    }
+
+    /// Requires the peer to authenticate with the given node ID.
+    ///
+    /// This is only applicable on the client side.
+    pub fn expect_peer(mut self, node_id: NodeId) -> Self {
+        self.expected_peer = Some(node_id);
+        self
+    }
+
+    /// Sets the time limit for completing the handshake.
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// The registry of node IDs authenticated on the connections
+    /// accepted with this configuration.
+    pub fn sessions(&self) -> &Sessions {
+        &self.sessions
+    }
+
+    fn identity_payload(&self) -> Vec<u8> {
+        let auth = self
+            .key_pair
+            .sign_data(&signed_identity(&self.static_key.public));
+        let mut payload = Vec::with_capacity(NODE_ID_LEN + SIGNATURE_LEN);
+        payload.extend_from_slice(auth.id().as_bytes());
+        payload.extend_from_slice(auth.signature());
+        payload
+    }
+}
+
+fn builder() -> snow::Builder<'static> {
+    snow::Builder::new(NOISE_PARAMS.parse().expect("valid Noise protocol name"))
+}
+
+fn signed_identity(static_key: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(IDENTITY_CONTEXT.len() + static_key.len());
+    data.extend_from_slice(IDENTITY_CONTEXT);
+    data.extend_from_slice(static_key);
+    data
+}
+
+fn authentication_error(msg: &'static str) -> Error {
+    Error::Authentication(crate::error::Error::new(Code::InvalidArgument, msg))
+}
+
+fn verify_identity(payload: &[u8], remote_static: Option<&[u8]>) -> Result<NodeId, Error> {
+    let remote_static =
+        remote_static.ok_or_else(|| authentication_error("the peer has not sent a static key"))?;
+    if payload.len() != NODE_ID_LEN + SIGNATURE_LEN {
+        return Err(authentication_error(
+            "invalid length of the identity payload",
+        ));
+    }
+    let (id, signature) = payload.split_at(NODE_ID_LEN);
+    let id = NodeId::try_from(id).map_err(Error::Authentication)?;
+    id.clone()
+        .authenticated(signature)
+        .and_then(|auth| auth.verify_data(&signed_identity(remote_static)))
+        .map_err(Error::Authentication)?;
+    Ok(id)
+}
+
+async fn send_frame<IO>(io: &mut IO, frame: &[u8]) -> io::Result<()>
+where
+    IO: AsyncWrite + Unpin,
+{
+    io.write_all(&(frame.len() as u16).to_be_bytes()).await?;
+    io.write_all(frame).await?;
+    io.flush().await
+}
+
+async fn recv_frame<IO>(io: &mut IO, frame: &mut Vec<u8>) -> io::Result<()>
+where
+    IO: AsyncRead + Unpin,
+{
+    let mut len = [0; 2];
+    io.read_exact(&mut len).await?;
+    frame.resize(u16::from_be_bytes(len) as usize, 0);
+    io.read_exact(frame).await?;
+    Ok(())
+}
+
+/// Performs the client side of the handshake on an established
+/// connection.
+pub async fn initiate<IO>(mut io: IO, config: &Config) -> Result<NoiseStream<IO>, Error>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut hs = builder()
+        .local_private_key(&config.static_key.private)
+        .build_initiator()?;
+    let mut buf = vec![0; MAX_MESSAGE_LEN];
+    let mut frame = Vec::new();
+
+    // -> e
+    let len = hs.write_message(&[], &mut buf)?;
+    send_frame(&mut io, &buf[..len]).await?;
+
+    // <- e, ee, s, es
+    recv_frame(&mut io, &mut frame).await?;
+    let len = hs.read_message(&frame, &mut buf)?;
+    let remote_id = verify_identity(&buf[..len], hs.get_remote_static())?;
+    if let Some(expected) = &config.expected_peer {
+        if *expected != remote_id {
+            return Err(Error::PeerMismatch);
+        }
+    }
+
+    // -> s, se
+    let len = hs.write_message(&config.identity_payload(), &mut buf)?;
+    send_frame(&mut io, &buf[..len]).await?;
+
+    let transport = hs.into_transport_mode()?;
+    Ok(NoiseStream::new(io, transport, remote_id))
+}
+
+/// Performs the server side of the handshake on an accepted
+/// connection.
+pub async fn respond<IO>(mut io: IO, config: &Config) -> Result<NoiseStream<IO>, Error>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut hs = builder()
+        .local_private_key(&config.static_key.private)
+        .build_responder()?;
+    let mut buf = vec![0; MAX_MESSAGE_LEN];
+    let mut frame = Vec::new();
+
+    // -> e
+    recv_frame(&mut io, &mut frame).await?;
+    hs.read_message(&frame, &mut buf)?;
+
+    // <- e, ee, s, es
+    let len = hs.write_message(&config.identity_payload(), &mut buf)?;
+    send_frame(&mut io, &buf[..len]).await?;
+
+    // -> s, se
+    recv_frame(&mut io, &mut frame).await?;
+    let len = hs.read_message(&frame, &mut buf)?;
+    let remote_id = verify_identity(&buf[..len], hs.get_remote_static())?;
+
+    let transport = hs.into_transport_mode()?;
+    Ok(NoiseStream::new(io, transport, remote_id))
+}
+
+async fn accept(
+    tcp: TcpStream,
+    addr: SocketAddr,
+    config: Config,
+) -> Result<NoiseStream<TcpStream>, Error> {
+    let mut stream = tokio::time::timeout(config.handshake_timeout, respond(tcp, &config))
+        .await
+        .map_err(|_| Error::Timeout)??;
+    let guard = config.sessions.register(addr, stream.remote_id.clone());
+    stream._session = Some(guard);
+    Ok(stream)
+}
+
+/// Accepts connections on the listener and performs the Noise handshake
+/// on them, producing a stream of connections to serve with
+/// `tonic::transport::Server::serve_with_incoming`.
+///
+/// Each handshake is performed in its own task, so clients stalling
+/// the handshake do not hold up the other connections.
+/// Connections failing the handshake are closed and skipped.
+/// The node IDs authenticated on the connections are registered in
+/// the `Sessions` of the configuration while the connections are open.
+///
+/// This function must be called in the context of a Tokio runtime.
+pub fn incoming(
+    mut listener: TcpListener,
+    config: Config,
+) -> impl Stream<Item = io::Result<NoiseStream<TcpStream>>> + Send {
+    let (mut tx, rx) = mpsc::channel(ACCEPTED_QUEUE_LEN);
+    tokio::spawn(async move {
+        while !tx.is_closed() {
+            match listener.accept().await {
+                Ok((tcp, addr)) => {
+                    let config = config.clone();
+                    let mut tx = tx.clone();
+                    tokio::spawn(async move {
+                        if let Ok(stream) = accept(tcp, addr, config).await {
+                            let _ = tx.send(Ok(stream)).await;
+                        }
+                    });
+                }
+                Err(e) => {
+                    if tx.send(Err(e)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    rx
+}
+
+/// Connector establishing Noise connections for the gRPC client.
+///
+/// The node ID of the server authenticated on the first connection is
+/// retained, and the connections re-established later must be
+/// authenticated with the same node ID.
+#[derive(Clone)]
+pub struct Connector {
+    config: Config,
+    peer_id: Arc<Mutex<Option<NodeId>>>,
+}
+
+impl Connector {
+    pub fn new(config: Config) -> Self {
+        let peer_id = Arc::new(Mutex::new(config.expected_peer.clone()));
+        Connector { config, peer_id }
+    }
+
+    /// The node ID of the server authenticated on the connection,
+    /// if the connection has been established.
+    pub fn peer_id(&self) -> Option<NodeId> {
+        self.peer_id.lock().unwrap().clone()
+    }
+}
+
+impl Service<Uri> for Connector {
+    type Response = NoiseStream<TcpStream>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let mut config = self.config.clone();
+        let peer_id = self.peer_id.clone();
+        Box::pin(async move {
+            let host = uri.host().ok_or(Error::InvalidAddress)?;
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            let port = uri.port_u16().ok_or(Error::InvalidAddress)?;
+            let tcp = TcpStream::connect((host, port)).await?;
+            tcp.set_nodelay(true)?;
+            config.expected_peer = peer_id.lock().unwrap().clone();
+            let stream = tokio::time::timeout(config.handshake_timeout, initiate(tcp, &config))
+                .await
+                .map_err(|_| Error::Timeout)??;
+            *peer_id.lock().unwrap() = Some(stream.remote_id().clone());
+            Ok(stream)
+        })
+    }
+}
+
+/// A connection encrypted with the Noise transport.
+///
+/// Application data is sent in frames prefixed with the length of
+/// the frame as a big-endian 16-bit number.
+pub struct NoiseStream<IO> {
+    io: IO,
+    transport: snow::TransportState,
+    remote_id: NodeId,
+    // keeps the peer registered in the server sessions while open
+    _session: Option<SessionGuard>,
+    // inbound frame being received
+    read_len: [u8; 2],
+    read_len_pos: usize,
+    read_frame: Vec<u8>,
+    read_frame_pos: usize,
+    // decrypted data not yet read
+    plaintext: Vec<u8>,
+    plaintext_pos: usize,
+    // encrypted frame not yet written
+    write_buf: Vec<u8>,
+    write_pos: usize,
+}
+
+impl<IO> NoiseStream<IO> {
+    fn new(io: IO, transport: snow::TransportState, remote_id: NodeId) -> Self {
+        NoiseStream {
+            io,
+            transport,
+            remote_id,
+            _session: None,
+            read_len: [0; 2],
+            read_len_pos: 0,
+            read_frame: Vec::new(),
+            read_frame_pos: 0,
+            plaintext: Vec::new(),
+            plaintext_pos: 0,
+            write_buf: Vec::new(),
+            write_pos: 0,
+        }
+    }
+
+    /// The node ID of the peer, authenticated in the handshake.
+    pub fn remote_id(&self) -> &NodeId {
+        &self.remote_id
+    }
+
+    pub fn get_ref(&self) -> &IO {
+        &self.io
+    }
+}
+
+fn invalid_data(e: snow::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+impl<IO: AsyncRead + Unpin> NoiseStream<IO> {
+    // Reads the next frame from the underlying connection. Resolves to
+    // `false` if the connection is closed at the frame boundary.
+    fn poll_read_frame(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<bool>> {
+        while self.read_len_pos < self.read_len.len() {
+            let n = ready!(
+                Pin::new(&mut self.io).poll_read(cx, &mut self.read_len[self.read_len_pos..])
+            )?;
+            if n == 0 {
+                if self.read_len_pos == 0 {
+                    return Poll::Ready(Ok(false));
+                }
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            self.read_len_pos += n;
+            if self.read_len_pos == self.read_len.len() {
+                self.read_frame
+                    .resize(u16::from_be_bytes(self.read_len) as usize, 0);
+                self.read_frame_pos = 0;
+            }
+        }
+        while self.read_frame_pos < self.read_frame.len() {
+            let n =
+                ready!(Pin::new(&mut self.io)
+                    .poll_read(cx, &mut self.read_frame[self.read_frame_pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+            self.read_frame_pos += n;
+        }
+        self.read_len_pos = 0;
+        Poll::Ready(Ok(true))
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> NoiseStream<IO> {
+    fn poll_write_frame(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.write_pos < self.write_buf.len() {
+            let n =
+                ready!(Pin::new(&mut self.io).poll_write(cx, &self.write_buf[self.write_pos..]))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_pos += n;
+        }
+        self.write_buf.clear();
+        self.write_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for NoiseStream<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            if this.plaintext_pos < this.plaintext.len() {
+                let available = &this.plaintext[this.plaintext_pos..];
+                let n = available.len().min(buf.len());
+                buf[..n].copy_from_slice(&available[..n]);
+                this.plaintext_pos += n;
+                return Poll::Ready(Ok(n));
+            }
+            if !ready!(this.poll_read_frame(cx))? {
+                return Poll::Ready(Ok(0));
+            }
+            this.plaintext.resize(this.read_frame.len(), 0);
+            let n = this
+                .transport
+                .read_message(&this.read_frame, &mut this.plaintext)
+                .map_err(invalid_data)?;
+            this.plaintext.truncate(n);
+            this.plaintext_pos = 0;
+        }
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for NoiseStream<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_frame(cx))?;
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let n = buf.len().min(MAX_PAYLOAD_LEN);
+        this.write_buf.resize(2 + n + TAG_LEN, 0);
+        let len = this
+            .transport
+            .write_message(&buf[..n], &mut this.write_buf[2..])
+            .map_err(invalid_data)?;
+        this.write_buf[..2].copy_from_slice(&(len as u16).to_be_bytes());
+        this.write_buf.truncate(2 + len);
+        // Start sending the frame; the data is accepted even if
+        // the underlying connection is not ready.
+        if let Poll::Ready(Err(e)) = this.poll_write_frame(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_frame(cx))?;
+        Pin::new(&mut this.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_frame(cx))?;
+        Pin::new(&mut this.io).poll_shutdown(cx)
+    }
+}
+
+impl<IO: Connected> Connected for NoiseStream<IO> {
+    fn remote_addr(&self) -> Option<SocketAddr> {
+        self.io.remote_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future;
+    use futures::stream::StreamExt;
+
+    fn config() -> Config {
+        Config::new(NodeKeyPair::generate(rand::thread_rng())).unwrap()
+    }
+
+    fn node_id(config: &Config) -> NodeId {
+        config.key_pair.sign(b"").id().clone()
+    }
+
+    async fn connect_pair(
+        client: &Config,
+        server: &Config,
+    ) -> (
+        Result<NoiseStream<TcpStream>, Error>,
+        Result<NoiseStream<TcpStream>, Error>,
+    ) {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server_side = async {
+            let (tcp, _) = listener.accept().await.unwrap();
+            respond(tcp, server).await
+        };
+        let client_side = async {
+            let tcp = TcpStream::connect(addr).await.unwrap();
+            initiate(tcp, client).await
+        };
+        future::join(client_side, server_side).await
+    }
+
+    #[tokio::test]
+    async fn handshake_authenticates_both_peers() {
+        let client = config();
+        let server = config();
+        let (client_stream, server_stream) = connect_pair(&client, &server).await;
+        assert_eq!(*client_stream.unwrap().remote_id(), node_id(&server));
+        assert_eq!(*server_stream.unwrap().remote_id(), node_id(&client));
+    }
+
+    #[tokio::test]
+    async fn data_is_exchanged_in_frames() {
+        let client = config();
+        let server = config();
+        let (client_stream, server_stream) = connect_pair(&client, &server).await;
+        let (mut client_stream, mut server_stream) =
+            (client_stream.unwrap(), server_stream.unwrap());
+
+        // larger than the maximum frame payload
+        let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        let send = async {
+            client_stream.write_all(&data).await.unwrap();
+            client_stream.shutdown().await.unwrap();
+        };
+        let receive = async {
+            let mut received = Vec::new();
+            server_stream.read_to_end(&mut received).await.unwrap();
+            received
+        };
+        let ((), received) = future::join(send, receive).await;
+        assert_eq!(received, data);
+    }
+
+    #[tokio::test]
+    async fn unexpected_peer_is_rejected() {
+        let client = config().expect_peer(node_id(&config()));
+        let server = config();
+        let (client_stream, _) = connect_pair(&client, &server).await;
+        match client_stream {
+            Err(Error::PeerMismatch) => {}
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("connection to an unexpected peer succeeded"),
+        }
+    }
+
+    #[test]
+    fn nonce_signature_does_not_authenticate_identity() {
+        let config = config();
+        let static_key = &config.static_key.public;
+        let auth = config.key_pair.sign(&signed_identity(static_key));
+        let mut payload = auth.id().as_bytes().to_vec();
+        payload.extend_from_slice(auth.signature());
+        assert!(verify_identity(&payload, Some(static_key)).is_err());
+        assert!(verify_identity(&config.identity_payload(), Some(static_key)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn stalled_handshakes_do_not_block_accepting() {
+        let client = config();
+        let server = config();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = Box::pin(incoming(listener, server.clone()));
+
+        let mut stalled = Vec::new();
+        for _ in 0..100 {
+            stalled.push(TcpStream::connect(addr).await.unwrap());
+        }
+        let client_side = async {
+            let tcp = TcpStream::connect(addr).await.unwrap();
+            initiate(tcp, &client).await.unwrap()
+        };
+        let server_side = async { incoming.next().await.unwrap().unwrap() };
+        let (_, server_stream) = tokio::time::timeout(
+            Duration::from_secs(5),
+            future::join(client_side, server_side),
+        )
+        .await
+        .expect("the connection should be accepted");
+        assert_eq!(*server_stream.remote_id(), node_id(&client));
+    }
+}
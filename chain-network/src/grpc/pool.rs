@@ -0,0 +1,354 @@
+//! Management of client connections to multiple peers, for fetching
+//! blocks from several peers in parallel.
+
+use super::client::Client;
+use crate::data::block::{Block, BlockId, BlockIds, Header};
+use crate::data::p2p::{NodeKeyPair, Peer};
+use crate::error::{Code, Error, HandshakeError};
+use futures::prelude::*;
+use futures::stream::FuturesUnordered;
+use tonic::body::{Body, BoxBody};
+use tonic::client::GrpcService;
+use tonic::codegen::{HttpBody, StdError};
+
+use std::collections::VecDeque;
+use std::fmt;
+
+/// The default number of blocks requested from a peer at a time.
+pub const DEFAULT_CHUNK_SIZE: usize = 100;
+
+/// An error reported by a peer in the pool.
+#[derive(Debug, thiserror::Error)]
+#[error("request to peer {peer} failed")]
+pub struct PeerError {
+    pub peer: Peer,
+    #[source]
+    pub error: Error,
+}
+
+/// Errors returned by fetch requests of `ClientPool`.
+#[derive(Debug, thiserror::Error)]
+pub enum FetchError {
+    #[error("no peers in the pool")]
+    NoPeers,
+    /// All peers in the pool have failed the request.
+    /// Carries the errors reported by each peer.
+    #[error("request failed with all peers")]
+    Exhausted(Vec<PeerError>),
+}
+
+struct PoolPeer<T> {
+    peer: Peer,
+    client: Client<T>,
+    failures: u32,
+}
+
+/// A pool of authenticated clients connected to peers on the same
+/// blockchain.
+///
+/// Fetch requests are split into chunks that are requested from
+/// the peers in parallel. A chunk that fails with a peer is retried with
+/// other peers; the peer is not used for the rest of the request.
+/// The results are reassembled in the order of the request.
+pub struct ClientPool<T> {
+    block0_id: BlockId,
+    key_pair: NodeKeyPair,
+    chunk_size: usize,
+    peers: Vec<PoolPeer<T>>,
+}
+
+impl<T> fmt::Debug for ClientPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientPool")
+            .field("block0_id", &self.block0_id)
+            .field("chunk_size", &self.chunk_size)
+            .field(
+                "peers",
+                &self.peers.iter().map(|p| &p.peer).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<T> ClientPool<T> {
+    /// Creates an empty pool for peers on the blockchain with
+    /// the genesis block `block0_id`. The clients are authenticated
+    /// with `key_pair`.
+    pub fn new(block0_id: BlockId, key_pair: NodeKeyPair) -> Self {
+        ClientPool {
+            block0_id,
+            key_pair,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            peers: Vec::new(),
+        }
+    }
+
+    /// Sets the maximum number of blocks requested from a peer
+    /// in a single request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn set_chunk_size(&mut self, size: usize) {
+        assert!(size > 0, "chunk size must be positive");
+        self.chunk_size = size;
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+
+    pub fn peers(&self) -> impl Iterator<Item = &Peer> {
+        self.peers.iter().map(|p| &p.peer)
+    }
+
+    /// The number of requests that have failed with the peer since
+    /// the last successful one, or `None` if the peer is not in the pool.
+    pub fn failures(&self, peer: &Peer) -> Option<u32> {
+        self.peers
+            .iter()
+            .find(|p| p.peer == *peer)
+            .map(|p| p.failures)
+    }
+
+    /// Removes the peer from the pool, returning its client.
+    pub fn remove(&mut self, peer: &Peer) -> Option<Client<T>> {
+        let pos = self.peers.iter().position(|p| p.peer == *peer)?;
+        Some(self.peers.remove(pos).client)
+    }
+}
+
+impl<T> ClientPool<T>
+where
+    T: GrpcService<BoxBody> + Clone + Send,
+    T::Future: Send,
+    T::ResponseBody: Body + HttpBody + Send + 'static,
+    T::Error: Into<StdError>,
+    <T::ResponseBody as HttpBody>::Error: Into<StdError> + Send,
+{
+    /// Performs the handshake with the peer, checks that the peer
+    /// is on the same blockchain, authenticates the client to the peer
+    /// and adds the client to the pool.
+    ///
+    /// An existing client for the same peer is replaced.
+    pub async fn connect(
+        &mut self,
+        peer: Peer,
+        mut client: Client<T>,
+        nonce: &[u8],
+    ) -> Result<(), HandshakeError> {
+        let res = client.handshake(nonce).await?;
+        if res.block0_id != self.block0_id {
+            return Err(HandshakeError::Block0Mismatch);
+        }
+        res.auth
+            .verify(nonce)
+            .map_err(HandshakeError::InvalidSignature)?;
+        client
+            .client_auth(self.key_pair.sign(&res.nonce))
+            .await
+            .map_err(HandshakeError::Rpc)?;
+        self.remove(&peer);
+        self.peers.push(PoolPeer {
+            peer,
+            client,
+            failures: 0,
+        });
+        Ok(())
+    }
+
+    /// Fetches the identified blocks, splitting the request among
+    /// the peers. The blocks are returned in the order of `ids`.
+    ///
+    /// The IDs of the received blocks are determined by `block_id`.
+    /// A chunk with blocks not matching the requested IDs in order
+    /// fails with the peer that sent it.
+    pub async fn get_blocks<F>(
+        &mut self,
+        ids: &[BlockId],
+        block_id: F,
+    ) -> Result<Vec<Block>, FetchError>
+    where
+        F: Fn(&Block) -> Result<BlockId, Error>,
+    {
+        if self.peers.is_empty() {
+            return Err(FetchError::NoPeers);
+        }
+        let chunks: Vec<BlockIds> = ids
+            .chunks(self.chunk_size)
+            .map(|chunk| chunk.to_vec().into())
+            .collect();
+        let mut results: Vec<Option<Vec<Block>>> = chunks.iter().map(|_| None).collect();
+        let mut pending: VecDeque<usize> = (0..chunks.len()).collect();
+        let mut idle = vec![true; self.peers.len()];
+        let mut failed = vec![false; self.peers.len()];
+        let mut errors = Vec::new();
+        let mut in_flight = FuturesUnordered::new();
+
+        loop {
+            while !pending.is_empty() {
+                let i = match (0..self.peers.len()).find(|&i| idle[i] && !failed[i]) {
+                    Some(i) => i,
+                    None => break,
+                };
+                let chunk = pending.pop_front().unwrap();
+                idle[i] = false;
+                let client = self.peers[i].client.clone();
+                let ids = chunks[chunk].clone();
+                in_flight.push(fetch_chunk(client, ids, &block_id).map(move |res| (i, chunk, res)));
+            }
+            match in_flight.next().await {
+                Some((i, chunk, Ok(blocks))) => {
+                    idle[i] = true;
+                    self.peers[i].failures = 0;
+                    results[chunk] = Some(blocks);
+                }
+                Some((i, chunk, Err(error))) => {
+                    idle[i] = true;
+                    failed[i] = true;
+                    self.peers[i].failures += 1;
+                    errors.push(PeerError {
+                        peer: self.peers[i].peer.clone(),
+                        error,
+                    });
+                    pending.push_front(chunk);
+                }
+                None if pending.is_empty() => break,
+                None => return Err(FetchError::Exhausted(errors)),
+            }
+        }
+
+        Ok(results
+            .into_iter()
+            .flat_map(|blocks| blocks.expect("all chunks should have been fetched"))
+            .collect())
+    }
+
+    /// Fetches the blocks of the chain starting after the latest of
+    /// the checkpoints in `from` and ending with the block `to`.
+    ///
+    /// The headers of the chain are retrieved from one of the peers,
+    /// with the IDs of the blocks and of their parents determined by
+    /// `header_ids`. The headers must form a chain from one of the
+    /// checkpoints to `to`, otherwise the next peer is tried.
+    /// The blocks are then fetched from all peers with `get_blocks`,
+    /// checking their IDs with `block_id`.
+    pub async fn pull_blocks<F, G>(
+        &mut self,
+        from: BlockIds,
+        to: BlockId,
+        header_ids: F,
+        block_id: G,
+    ) -> Result<Vec<Block>, FetchError>
+    where
+        F: Fn(&Header) -> Result<(BlockId, BlockId), Error>,
+        G: Fn(&Block) -> Result<BlockId, Error>,
+    {
+        if self.peers.is_empty() {
+            return Err(FetchError::NoPeers);
+        }
+        let mut errors = Vec::new();
+        for i in 0..self.peers.len() {
+            let client = self.peers[i].client.clone();
+            match pull_chain_ids(client, from.clone(), to, &header_ids).await {
+                Ok(ids) => return self.get_blocks(&ids, block_id).await,
+                Err(error) => {
+                    let peer = &mut self.peers[i];
+                    peer.failures += 1;
+                    errors.push(PeerError {
+                        peer: peer.peer.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+        Err(FetchError::Exhausted(errors))
+    }
+}
+
+async fn fetch_chunk<T, F>(
+    mut client: Client<T>,
+    ids: BlockIds,
+    block_id: &F,
+) -> Result<Vec<Block>, Error>
+where
+    T: GrpcService<BoxBody> + Send,
+    T::Future: Send,
+    T::ResponseBody: Body + HttpBody + Send + 'static,
+    T::Error: Into<StdError>,
+    <T::ResponseBody as HttpBody>::Error: Into<StdError> + Send,
+    F: Fn(&Block) -> Result<BlockId, Error>,
+{
+    let blocks: Vec<Block> = client.get_blocks(ids.clone()).await?.try_collect().await?;
+    if blocks.len() != ids.len() {
+        return Err(Error::new(
+            Code::NotFound,
+            format!(
+                "peer returned {} blocks out of {} requested",
+                blocks.len(),
+                ids.len()
+            ),
+        ));
+    }
+    for (block, id) in blocks.iter().zip(ids.iter()) {
+        if block_id(block)? != *id {
+            return Err(Error::new(
+                Code::InvalidArgument,
+                "peer returned a block not matching the requested ID",
+            ));
+        }
+    }
+    Ok(blocks)
+}
+
+async fn pull_chain_ids<T, F>(
+    mut client: Client<T>,
+    from: BlockIds,
+    to: BlockId,
+    header_ids: &F,
+) -> Result<Vec<BlockId>, Error>
+where
+    T: GrpcService<BoxBody> + Send,
+    T::Future: Send,
+    T::ResponseBody: Body + HttpBody + Send + 'static,
+    T::Error: Into<StdError>,
+    <T::ResponseBody as HttpBody>::Error: Into<StdError> + Send,
+    F: Fn(&Header) -> Result<(BlockId, BlockId), Error>,
+{
+    let headers: Vec<Header> = client
+        .pull_headers(from.clone(), to)
+        .await?
+        .try_collect()
+        .await?;
+    let mut ids: Vec<BlockId> = Vec::with_capacity(headers.len());
+    for header in &headers {
+        let (id, parent_id) = header_ids(header)?;
+        let linked = match ids.last() {
+            Some(prev_id) => parent_id == *prev_id,
+            None => from.contains(&parent_id),
+        };
+        if !linked {
+            return Err(Error::new(
+                Code::InvalidArgument,
+                "peer returned headers not chained from the checkpoints",
+            ));
+        }
+        ids.push(id);
+    }
+    match ids.last() {
+        Some(last) if *last == to => Ok(ids),
+        None if from.contains(&to) => Ok(ids),
+        Some(_) => Err(Error::new(
+            Code::InvalidArgument,
+            "peer returned a chain not ending with the requested block",
+        )),
+        None => Err(Error::new(
+            Code::NotFound,
+            "peer returned no headers for the requested chain",
+        )),
+    }
+}
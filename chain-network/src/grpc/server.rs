@@ -1,39 +1,161 @@
-use super::convert;
+use super::convert::{self, IntoProtobuf};
+use super::instrument::{CallContext, PeerMap};
+use super::inventory::{self, FragmentPush, InventoryInbound, InventoryOutbound, Propagation};
 use super::proto;
 use super::streaming::{InboundStream, OutboundTryStream};
+use super::{CAPABILITIES_METADATA_KEY, COMPRESSION_METADATA_KEY};
 
 #[cfg(feature = "legacy")]
 use super::legacy;
 
+#[cfg(any(test, feature = "loopback"))]
+use super::loopback;
+
+#[cfg(feature = "noise")]
+use super::noise;
+
+use crate::compression::{self, Compression};
 use crate::core::server::{BlockService, FragmentService, GossipService, Node};
+use crate::data::fragment::PropagationPolicy;
 use crate::data::p2p::NodeId;
-use crate::data::{block, fragment, BlockId, Peer};
-use crate::PROTOCOL_VERSION;
+use crate::data::{
+    block, fragment, BlockId, Capabilities, FragmentId, Header, Headers, Peer, Protocol,
+};
+use crate::error::Error;
+use crate::metrics::Metrics;
+use crate::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+use futures::stream::{self, StreamExt};
 use tonic::{Code, Status};
 
 #[cfg(feature = "legacy")]
 use tonic::metadata::MetadataValue;
 
 use std::convert::TryFrom;
-use std::net::SocketAddr;
+use std::sync::Arc;
 
 pub type Server<T> = proto::node_server::NodeServer<NodeService<T>>;
 
+/// The maximum number of headers sent in a single batch
+/// in response to a `PullHeaderBatches` request.
+pub const MAX_HEADER_BATCH_SIZE: usize = 1000;
+
+/// Stream of header batches assembled from a stream of headers.
+pub type HeaderBatches<S> =
+    stream::Map<stream::ReadyChunks<S>, fn(Vec<Result<Header, Error>>) -> Result<Headers, Error>>;
+
+/// The default number of fragment IDs remembered for each fragment
+/// inventory subscription, to avoid repeating announcements.
+pub const DEFAULT_INVENTORY_CACHE_SIZE: usize = 4096;
+
+/// The maximum number of fragments whose status can be watched
+/// with a single `FragmentStatusSubscription` request.
+pub const MAX_STATUS_SUBSCRIPTION_IDS: usize = 256;
+
+// The maximum number of client node IDs remembered by the server
+// to annotate the tracing spans of the calls.
+const MAX_CLIENT_IDS: usize = 4096;
+
+fn collect_header_batch(chunk: Vec<Result<Header, Error>>) -> Result<Headers, Error> {
+    chunk.into_iter().collect()
+}
+
 /// Builder to customize the gRPC server.
-#[derive(Default)]
 pub struct Builder {
+    capabilities: Capabilities,
+    compression: Vec<Compression>,
+    inventory_cache_size: usize,
+    propagation_policy: PropagationPolicy,
+    metrics: Option<Arc<dyn Metrics>>,
     #[cfg(feature = "legacy")]
     legacy_node_id: Option<legacy::NodeId>,
+    #[cfg(feature = "noise")]
+    noise_sessions: Option<noise::Sessions>,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder::new()
+    }
 }
 
 impl Builder {
     pub fn new() -> Self {
         Builder {
+            capabilities: Capabilities::server_default(),
+            compression: Compression::supported(),
+            inventory_cache_size: DEFAULT_INVENTORY_CACHE_SIZE,
+            propagation_policy: PropagationPolicy::default(),
+            metrics: None,
             #[cfg(feature = "legacy")]
             legacy_node_id: None,
+            #[cfg(feature = "noise")]
+            noise_sessions: None,
         }
     }
 
+    /// Sets the optional protocol capabilities that the server
+    /// agrees to use with clients.
+    ///
+    /// By default, the capabilities given by `Capabilities::server_default`
+    /// are enabled. Capabilities that the node does not implement
+    /// should be excluded.
+    pub fn capabilities(&mut self, capabilities: Capabilities) -> &mut Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Makes the server offer the `LIGHT_CLIENT` capability.
+    ///
+    /// The node should implement `BlockService::get_fragment_proof`
+    /// to serve light clients.
+    pub fn light_client(&mut self) -> &mut Self {
+        self.capabilities.insert(Capabilities::LIGHT_CLIENT);
+        self
+    }
+
+    /// Sets the compression algorithms that the server can select
+    /// in the handshake, in the order of preference.
+    ///
+    /// By default, all algorithms supported by this build of the crate
+    /// can be selected.
+    pub fn compression(&mut self, algorithms: &[Compression]) -> &mut Self {
+        self.compression = algorithms.to_vec();
+        self
+    }
+
+    /// Sets the number of fragment IDs remembered for each fragment
+    /// inventory subscription as known to the subscriber.
+    ///
+    /// The default is `DEFAULT_INVENTORY_CACHE_SIZE`.
+    pub fn inventory_cache_size(&mut self, size: usize) -> &mut Self {
+        self.inventory_cache_size = size;
+        self
+    }
+
+    /// Sets the policy selecting the fragment subscribers that are pushed
+    /// the full fragments produced by the service.
+    ///
+    /// The fragments are not pushed to the other subscribers, which are
+    /// expected to learn about them from the announcements on their
+    /// inventory subscriptions. Subscribers that have not negotiated the
+    /// `FRAGMENT_INVENTORY` capability are always pushed the fragments.
+    ///
+    /// The default is `PropagationPolicy::PushAll`.
+    pub fn propagation_policy(&mut self, policy: PropagationPolicy) -> &mut Self {
+        self.propagation_policy = policy;
+        self
+    }
+
+    /// Sets the collector of metrics on the calls served by the server.
+    ///
+    /// Regardless of this setting, the calls are traced with `tracing`
+    /// spans named "rpc", carrying the method name, the address of the peer,
+    /// and the node ID of the client if it has been authenticated.
+    pub fn metrics(&mut self, metrics: Arc<dyn Metrics>) -> &mut Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
     /// Make the server add "node-id-bin" metadata with the passed value
     /// into subscription responses, for backward compatibility with
     /// jormungandr versions prior to 0.9.
@@ -43,10 +165,28 @@ impl Builder {
         self
     }
 
+    /// Makes the server require that clients authenticate with the node ID
+    /// that has been authenticated on their Noise transport connection.
+    ///
+    /// The server must be served with connections accepted by
+    /// `noise::incoming` with the same configuration.
+    #[cfg(feature = "noise")]
+    pub fn noise(&mut self, config: &noise::Config) -> &mut Self {
+        self.noise_sessions = Some(config.sessions().clone());
+        self
+    }
+
     pub fn build<T: Node>(&self, inner: T) -> Server<T> {
         let service = NodeService {
+            capabilities: self.capabilities,
+            compression: self.compression.clone(),
+            inventory_cache_size: self.inventory_cache_size,
+            propagation: Propagation::new(self.propagation_policy),
+            metrics: self.metrics.clone(),
             #[cfg(feature = "legacy")]
             legacy_node_id: self.legacy_node_id,
+            #[cfg(feature = "noise")]
+            noise_sessions: self.noise_sessions.clone(),
             ..NodeService::new(inner)
         };
         Server::new(service)
@@ -56,8 +196,16 @@ impl Builder {
 #[derive(Debug)]
 pub struct NodeService<T> {
     inner: T,
+    capabilities: Capabilities,
+    compression: Vec<Compression>,
+    inventory_cache_size: usize,
+    propagation: Propagation,
+    metrics: Option<Arc<dyn Metrics>>,
+    client_ids: PeerMap<NodeId>,
     #[cfg(feature = "legacy")]
     legacy_node_id: Option<legacy::NodeId>,
+    #[cfg(feature = "noise")]
+    noise_sessions: Option<noise::Sessions>,
 }
 
 impl<T> NodeService<T>
@@ -67,8 +215,16 @@ where
     pub fn new(inner: T) -> Self {
         NodeService {
             inner,
+            capabilities: Capabilities::server_default(),
+            compression: Compression::supported(),
+            inventory_cache_size: DEFAULT_INVENTORY_CACHE_SIZE,
+            propagation: Propagation::default(),
+            metrics: None,
+            client_ids: PeerMap::with_capacity(MAX_CLIENT_IDS),
             #[cfg(feature = "legacy")]
             legacy_node_id: None,
+            #[cfg(feature = "noise")]
+            noise_sessions: None,
         }
     }
 
@@ -90,10 +246,143 @@ where
             .ok_or_else(|| Status::new(Code::Unimplemented, "not implemented"))
     }
 
+    // Checks if the client has negotiated the capabilities in the handshake.
+    // The client reports the negotiated capabilities in the request metadata,
+    // they are limited to the ones that the handshake on this server can
+    // agree to. The capabilities only affect how the server communicates
+    // with the client that claims them.
+    fn client_negotiated<R>(&self, req: &tonic::Request<R>, capabilities: Capabilities) -> bool {
+        req.metadata()
+            .get(CAPABILITIES_METADATA_KEY)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|s| s.parse().ok())
+            .map(|bits| Capabilities::from_bits_truncate(bits) & self.capabilities)
+            .is_some_and(|caps| caps.contains(capabilities))
+    }
+
+    // Gets the compression algorithm requested by the client for the
+    // content sent in the response. Falls back to no compression if the
+    // client did not negotiate compression in the handshake, or if the
+    // algorithm is not enabled on this server.
+    fn response_compression<R>(&self, req: &tonic::Request<R>) -> Compression {
+        if !self.client_negotiated(req, Capabilities::COMPRESSION) {
+            return Compression::None;
+        }
+        req.metadata()
+            .get(COMPRESSION_METADATA_KEY)
+            .and_then(|val| val.to_str().ok())
+            .and_then(|s| s.parse().ok())
+            .filter(|compression| self.compression.contains(compression))
+            .unwrap_or(Compression::None)
+    }
+
+    // Gets the node ID of the client, as authenticated on the
+    // Noise transport connection or with a ClientAuth request.
+    fn client_id(&self, peer: &Peer) -> Option<NodeId> {
+        #[cfg(feature = "noise")]
+        if let Some(sessions) = &self.noise_sessions {
+            if let Some(node_id) = sessions.node_id(&peer.addr()) {
+                return Some(node_id);
+            }
+        }
+        self.client_ids.get(peer)
+    }
+
+    fn call_context<R>(&self, method: &'static str, req: &tonic::Request<R>) -> CallContext {
+        let peer = remote_peer(req).ok();
+        let node_id = peer.as_ref().and_then(|peer| self.client_id(peer));
+        CallContext::new(
+            method,
+            peer.as_ref(),
+            node_id.as_ref(),
+            self.metrics.clone(),
+        )
+    }
+
+    async fn serve_handshake(
+        &self,
+        req: tonic::Request<proto::HandshakeRequest>,
+    ) -> Result<tonic::Response<proto::HandshakeResponse>, Status> {
+        let peer = remote_peer(&req)?;
+        let req = req.into_inner();
+        // Clients implementing version 1 of the protocol
+        // do not send the version range; they are rejected below.
+        let (min_version, max_version) = if req.max_version == 0 {
+            (1, 1)
+        } else {
+            (req.min_version, req.max_version)
+        };
+        let capabilities = Capabilities::from_bits_truncate(req.capabilities) & self.capabilities;
+        let protocol =
+            Protocol::negotiate(min_version, max_version, capabilities).ok_or_else(|| {
+                Status::new(
+                    Code::FailedPrecondition,
+                    format!(
+                        "protocol versions {}-{} are not supported, expected {}-{}",
+                        min_version, max_version, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+                    ),
+                )
+            })?;
+        let nonce = &req.nonce;
+        let hr = self.inner.handshake(peer, nonce, protocol).await?;
+        let compression = if protocol.supports(Capabilities::COMPRESSION) {
+            // algorithms unknown to this implementation are ignored
+            let accepted: Vec<Compression> = req
+                .accept_compression
+                .iter()
+                .filter_map(|code| convert::compression_from_protobuf(*code).ok())
+                .collect();
+            compression::negotiate(&self.compression, &accepted)
+        } else {
+            Compression::None
+        };
+        let res = proto::HandshakeResponse {
+            version: protocol.version,
+            block0: hr.block0_id.as_bytes().into(),
+            node_id: hr.auth.id().as_bytes().into(),
+            signature: hr.auth.signature().into(),
+            nonce: hr.nonce.into(),
+            compression: convert::compression_into_protobuf(compression) as i32,
+            capabilities: protocol.capabilities.bits(),
+        };
+        Ok(tonic::Response::new(res))
+    }
+
+    async fn serve_client_auth(
+        &self,
+        req: tonic::Request<proto::ClientAuthRequest>,
+    ) -> Result<tonic::Response<proto::ClientAuthResponse>, Status> {
+        let peer = remote_peer(&req)?;
+        let req = req.into_inner();
+        let node_id = NodeId::try_from(&req.node_id[..])?;
+        let auth = node_id.authenticated(&req.signature)?;
+        #[cfg(feature = "noise")]
+        if let Some(sessions) = &self.noise_sessions {
+            if sessions.node_id(&peer.addr()).as_ref() != Some(auth.id()) {
+                return Err(Status::new(
+                    Code::Unauthenticated,
+                    "node ID does not match the peer authenticated on the connection",
+                ));
+            }
+        }
+        let node_id = auth.id().clone();
+        self.inner.client_auth(peer.clone(), auth).await?;
+        tracing::Span::current().record("node_id", tracing::field::display(&node_id));
+        self.client_ids.insert(peer, node_id);
+        let res = proto::ClientAuthResponse {};
+        Ok(tonic::Response::new(res))
+    }
+
     #[allow(unused_mut)]
     #[allow(clippy::let_and_return)]
-    fn subscription_response<S>(&self, outbound: S) -> tonic::Response<OutboundTryStream<S>> {
-        let mut res = tonic::Response::new(OutboundTryStream::new(outbound));
+    fn subscription_response<S>(
+        &self,
+        outbound: S,
+        compression: Compression,
+        ctx: &CallContext,
+    ) -> tonic::Response<OutboundTryStream<S>> {
+        let mut res =
+            tonic::Response::new(OutboundTryStream::new(outbound, compression, ctx.clone()));
         #[cfg(feature = "legacy")]
         if let Some(node_id) = self.legacy_node_id {
             let val = MetadataValue::from_bytes(&node_id.encode());
@@ -103,13 +392,19 @@ where
     }
 }
 
-fn remote_addr_to_peer(maybe_addr: Option<SocketAddr>) -> Result<Peer, Status> {
-    match maybe_addr {
-        Some(addr) => Ok(addr.into()),
-        None => Err(Status::internal(
-            "transport does not provide the remote address",
-        )),
+fn remote_peer<R>(req: &tonic::Request<R>) -> Result<Peer, Status> {
+    if let Some(addr) = req.remote_addr() {
+        return Ok(addr.into());
     }
+    // The in-process transport has no network connection to get
+    // the address from, the client channel passes it in the request.
+    #[cfg(any(test, feature = "loopback"))]
+    if let Some(addr) = loopback::peer_addr(req.metadata()) {
+        return Ok(addr.into());
+    }
+    Err(Status::internal(
+        "transport does not provide the remote address",
+    ))
 }
 
 #[tonic::async_trait]
@@ -121,55 +416,48 @@ where
         &self,
         req: tonic::Request<proto::HandshakeRequest>,
     ) -> Result<tonic::Response<proto::HandshakeResponse>, tonic::Status> {
-        let peer = remote_addr_to_peer(req.remote_addr())?;
-        let req = req.into_inner();
-        let nonce = &req.nonce;
-        let hr = self.inner.handshake(peer, nonce).await?;
-        let res = proto::HandshakeResponse {
-            version: PROTOCOL_VERSION,
-            block0: hr.block0_id.as_bytes().into(),
-            node_id: hr.auth.id().as_bytes().into(),
-            signature: hr.auth.signature().into(),
-            nonce: hr.nonce.into(),
-        };
-        Ok(tonic::Response::new(res))
+        let ctx = self.call_context("Handshake", &req);
+        ctx.unary(req, |req| self.serve_handshake(req)).await
     }
 
     async fn client_auth(
         &self,
         req: tonic::Request<proto::ClientAuthRequest>,
     ) -> Result<tonic::Response<proto::ClientAuthResponse>, tonic::Status> {
-        let peer = remote_addr_to_peer(req.remote_addr())?;
-        let req = req.into_inner();
-        let node_id = NodeId::try_from(&req.node_id[..])?;
-        let auth = node_id.authenticated(&req.signature)?;
-        self.inner.client_auth(peer, auth).await?;
-        let res = proto::ClientAuthResponse {};
-        Ok(tonic::Response::new(res))
+        let ctx = self.call_context("ClientAuth", &req);
+        ctx.unary(req, |req| self.serve_client_auth(req)).await
     }
 
     async fn tip(
         &self,
-        _: tonic::Request<proto::TipRequest>,
+        req: tonic::Request<proto::TipRequest>,
     ) -> Result<tonic::Response<proto::TipResponse>, tonic::Status> {
-        let service = self.block_service()?;
-        let header = service.tip().await?;
-        let res = proto::TipResponse {
-            block_header: header.into(),
-        };
-        Ok(tonic::Response::new(res))
+        let ctx = self.call_context("Tip", &req);
+        ctx.unary(req, |_| async move {
+            let service = self.block_service()?;
+            let header = service.tip().await?;
+            let res = proto::TipResponse {
+                block_header: header.into(),
+            };
+            Ok(tonic::Response::new(res))
+        })
+        .await
     }
 
     async fn peers(
         &self,
         req: tonic::Request<proto::PeersRequest>,
     ) -> Result<tonic::Response<proto::PeersResponse>, tonic::Status> {
-        let service = self.gossip_service()?;
-        let peers = service.peers(req.into_inner().limit).await?;
-        let res = proto::PeersResponse {
-            peers: convert::into_protobuf_repeated(peers.into_vec()),
-        };
-        Ok(tonic::Response::new(res))
+        let ctx = self.call_context("Peers", &req);
+        ctx.unary(req, |req| async move {
+            let service = self.gossip_service()?;
+            let peers = service.peers(req.into_inner().limit).await?;
+            let res = proto::PeersResponse {
+                peers: convert::into_protobuf_repeated(peers.into_vec()),
+            };
+            Ok(tonic::Response::new(res))
+        })
+        .await
     }
 
     type GetBlocksStream = OutboundTryStream<<T::BlockService as BlockService>::GetBlocksStream>;
@@ -178,10 +466,20 @@ where
         &self,
         req: tonic::Request<proto::BlockIds>,
     ) -> Result<tonic::Response<Self::GetBlocksStream>, tonic::Status> {
-        let service = self.block_service()?;
-        let ids = block::try_ids_from_iter(req.into_inner().ids)?;
-        let stream = service.get_blocks(ids).await?;
-        Ok(tonic::Response::new(OutboundTryStream::new(stream)))
+        let ctx = self.call_context("GetBlocks", &req);
+        ctx.message_received(req.get_ref());
+        ctx.run(async {
+            let service = self.block_service()?;
+            let compression = self.response_compression(&req);
+            let ids = block::try_ids_from_iter(req.into_inner().ids)?;
+            let stream = service.get_blocks(ids).await?;
+            Ok(tonic::Response::new(OutboundTryStream::new(
+                stream,
+                compression,
+                ctx.clone(),
+            )))
+        })
+        .await
     }
 
     type GetHeadersStream = OutboundTryStream<<T::BlockService as BlockService>::GetHeadersStream>;
@@ -190,10 +488,20 @@ where
         &self,
         req: tonic::Request<proto::BlockIds>,
     ) -> Result<tonic::Response<Self::GetHeadersStream>, tonic::Status> {
-        let service = self.block_service()?;
-        let ids = block::try_ids_from_iter(req.into_inner().ids)?;
-        let stream = service.get_headers(ids).await?;
-        Ok(tonic::Response::new(OutboundTryStream::new(stream)))
+        let ctx = self.call_context("GetHeaders", &req);
+        ctx.message_received(req.get_ref());
+        ctx.run(async {
+            let service = self.block_service()?;
+            let compression = self.response_compression(&req);
+            let ids = block::try_ids_from_iter(req.into_inner().ids)?;
+            let stream = service.get_headers(ids).await?;
+            Ok(tonic::Response::new(OutboundTryStream::new(
+                stream,
+                compression,
+                ctx.clone(),
+            )))
+        })
+        .await
     }
 
     type GetFragmentsStream =
@@ -203,10 +511,20 @@ where
         &self,
         req: tonic::Request<proto::FragmentIds>,
     ) -> Result<tonic::Response<Self::GetFragmentsStream>, tonic::Status> {
-        let service = self.fragment_service()?;
-        let ids = fragment::try_ids_from_iter(req.into_inner().ids)?;
-        let stream = service.get_fragments(ids).await?;
-        Ok(tonic::Response::new(OutboundTryStream::new(stream)))
+        let ctx = self.call_context("GetFragments", &req);
+        ctx.message_received(req.get_ref());
+        ctx.run(async {
+            let service = self.fragment_service()?;
+            let compression = self.response_compression(&req);
+            let ids = fragment::try_ids_from_iter(req.into_inner().ids)?;
+            let stream = service.get_fragments(ids).await?;
+            Ok(tonic::Response::new(OutboundTryStream::new(
+                stream,
+                compression,
+                ctx.clone(),
+            )))
+        })
+        .await
     }
 
     type PullHeadersStream =
@@ -216,16 +534,26 @@ where
         &self,
         req: tonic::Request<proto::PullHeadersRequest>,
     ) -> Result<tonic::Response<Self::PullHeadersStream>, tonic::Status> {
-        let service = self.block_service()?;
-        let (from, to) = {
-            let req = req.into_inner();
-            (
-                block::try_ids_from_iter(req.from)?,
-                BlockId::try_from(&req.to[..])?,
-            )
-        };
-        let stream = service.pull_headers(from, to).await?;
-        Ok(tonic::Response::new(OutboundTryStream::new(stream)))
+        let ctx = self.call_context("PullHeaders", &req);
+        ctx.message_received(req.get_ref());
+        ctx.run(async {
+            let service = self.block_service()?;
+            let compression = self.response_compression(&req);
+            let (from, to) = {
+                let req = req.into_inner();
+                (
+                    block::try_ids_from_iter(req.from)?,
+                    BlockId::try_from(&req.to[..])?,
+                )
+            };
+            let stream = service.pull_headers(from, to).await?;
+            Ok(tonic::Response::new(OutboundTryStream::new(
+                stream,
+                compression,
+                ctx.clone(),
+            )))
+        })
+        .await
     }
 
     type PullBlocksStream = OutboundTryStream<<T::BlockService as BlockService>::PullBlocksStream>;
@@ -234,12 +562,22 @@ where
         &self,
         req: tonic::Request<proto::PullBlocksRequest>,
     ) -> Result<tonic::Response<Self::PullBlocksStream>, tonic::Status> {
-        let service = self.block_service()?;
-        let req = req.into_inner();
-        let from = block::try_ids_from_iter(req.from)?;
-        let to = BlockId::try_from(&req.to[..])?;
-        let stream = service.pull_blocks(from, to).await?;
-        Ok(tonic::Response::new(OutboundTryStream::new(stream)))
+        let ctx = self.call_context("PullBlocks", &req);
+        ctx.message_received(req.get_ref());
+        ctx.run(async {
+            let service = self.block_service()?;
+            let compression = self.response_compression(&req);
+            let req = req.into_inner();
+            let from = block::try_ids_from_iter(req.from)?;
+            let to = BlockId::try_from(&req.to[..])?;
+            let stream = service.pull_blocks(from, to).await?;
+            Ok(tonic::Response::new(OutboundTryStream::new(
+                stream,
+                compression,
+                ctx.clone(),
+            )))
+        })
+        .await
     }
 
     type PullBlocksToTipStream =
@@ -249,30 +587,96 @@ where
         &self,
         req: tonic::Request<proto::PullBlocksToTipRequest>,
     ) -> Result<tonic::Response<Self::PullBlocksToTipStream>, tonic::Status> {
-        let service = self.block_service()?;
-        let from = block::try_ids_from_iter(req.into_inner().from)?;
-        let stream = service.pull_blocks_to_tip(from).await?;
-        Ok(tonic::Response::new(OutboundTryStream::new(stream)))
+        let ctx = self.call_context("PullBlocksToTip", &req);
+        ctx.message_received(req.get_ref());
+        ctx.run(async {
+            let service = self.block_service()?;
+            let compression = self.response_compression(&req);
+            let from = block::try_ids_from_iter(req.into_inner().from)?;
+            let stream = service.pull_blocks_to_tip(from).await?;
+            Ok(tonic::Response::new(OutboundTryStream::new(
+                stream,
+                compression,
+                ctx.clone(),
+            )))
+        })
+        .await
+    }
+
+    type PullHeaderBatchesStream =
+        OutboundTryStream<HeaderBatches<<T::BlockService as BlockService>::PullHeadersStream>>;
+
+    async fn pull_header_batches(
+        &self,
+        req: tonic::Request<proto::PullHeaderBatchesRequest>,
+    ) -> Result<tonic::Response<Self::PullHeaderBatchesStream>, tonic::Status> {
+        let ctx = self.call_context("PullHeaderBatches", &req);
+        ctx.message_received(req.get_ref());
+        ctx.run(async {
+            let service = self.block_service()?;
+            let compression = self.response_compression(&req);
+            let req = req.into_inner();
+            let from = block::try_ids_from_iter(req.from)?;
+            let to = BlockId::try_from(&req.to[..])?;
+            let batch_size = match req.batch_size as usize {
+                0 => MAX_HEADER_BATCH_SIZE,
+                n => n.min(MAX_HEADER_BATCH_SIZE),
+            };
+            let stream = service.pull_headers(from, to).await?;
+            let batches = stream
+                .ready_chunks(batch_size)
+                .map(collect_header_batch as fn(_) -> _);
+            Ok(tonic::Response::new(OutboundTryStream::new(
+                batches,
+                compression,
+                ctx.clone(),
+            )))
+        })
+        .await
+    }
+
+    async fn get_fragment_proof(
+        &self,
+        req: tonic::Request<proto::FragmentProofRequest>,
+    ) -> Result<tonic::Response<proto::FragmentProof>, tonic::Status> {
+        let ctx = self.call_context("GetFragmentProof", &req);
+        ctx.unary(req, |req| async move {
+            let service = self.block_service()?;
+            let req = req.into_inner();
+            let block = BlockId::try_from(&req.block_id[..])?;
+            let fragment = FragmentId::try_from(&req.fragment_id[..])?;
+            let proof = service.get_fragment_proof(block, fragment).await?;
+            Ok(tonic::Response::new(proof.into_message()))
+        })
+        .await
     }
 
     async fn push_headers(
         &self,
         req: tonic::Request<tonic::Streaming<proto::Header>>,
     ) -> Result<tonic::Response<proto::PushHeadersResponse>, tonic::Status> {
-        let service = self.block_service()?;
-        let stream = InboundStream::new(req.into_inner());
-        service.push_headers(Box::pin(stream)).await?;
-        Ok(tonic::Response::new(proto::PushHeadersResponse {}))
+        let ctx = self.call_context("PushHeaders", &req);
+        ctx.run(async {
+            let service = self.block_service()?;
+            let stream = InboundStream::instrumented(req.into_inner(), ctx.clone());
+            service.push_headers(Box::pin(stream)).await?;
+            Ok(tonic::Response::new(proto::PushHeadersResponse {}))
+        })
+        .await
     }
 
     async fn upload_blocks(
         &self,
         req: tonic::Request<tonic::Streaming<proto::Block>>,
     ) -> Result<tonic::Response<proto::UploadBlocksResponse>, tonic::Status> {
-        let service = self.block_service()?;
-        let stream = InboundStream::new(req.into_inner());
-        service.upload_blocks(Box::pin(stream)).await?;
-        Ok(tonic::Response::new(proto::UploadBlocksResponse {}))
+        let ctx = self.call_context("UploadBlocks", &req);
+        ctx.run(async {
+            let service = self.block_service()?;
+            let stream = InboundStream::instrumented(req.into_inner(), ctx.clone());
+            service.upload_blocks(Box::pin(stream)).await?;
+            Ok(tonic::Response::new(proto::UploadBlocksResponse {}))
+        })
+        .await
     }
 
     type BlockSubscriptionStream =
@@ -282,29 +686,104 @@ where
         &self,
         req: tonic::Request<tonic::Streaming<proto::Header>>,
     ) -> Result<tonic::Response<Self::BlockSubscriptionStream>, tonic::Status> {
-        let service = self.block_service()?;
-        let peer = remote_addr_to_peer(req.remote_addr())?;
-        let inbound = InboundStream::new(req.into_inner());
-        let outbound = service.block_subscription(peer, Box::pin(inbound)).await?;
-        let res = self.subscription_response(outbound);
-        Ok(res)
+        let ctx = self.call_context("BlockSubscription", &req);
+        ctx.run(async {
+            let service = self.block_service()?;
+            let peer = remote_peer(&req)?;
+            let compression = self.response_compression(&req);
+            let inbound = InboundStream::instrumented(req.into_inner(), ctx.clone());
+            let outbound = service.block_subscription(peer, Box::pin(inbound)).await?;
+            let res = self.subscription_response(outbound, compression, &ctx);
+            Ok(res)
+        })
+        .await
     }
 
-    type FragmentSubscriptionStream =
-        OutboundTryStream<<T::FragmentService as FragmentService>::SubscriptionStream>;
+    type FragmentSubscriptionStream = OutboundTryStream<
+        FragmentPush<<T::FragmentService as FragmentService>::SubscriptionStream>,
+    >;
 
     async fn fragment_subscription(
         &self,
         req: tonic::Request<tonic::Streaming<proto::Fragment>>,
     ) -> Result<tonic::Response<Self::FragmentSubscriptionStream>, tonic::Status> {
-        let service = self.fragment_service()?;
-        let peer = remote_addr_to_peer(req.remote_addr())?;
-        let inbound = InboundStream::new(req.into_inner());
-        let outbound = service
-            .fragment_subscription(peer, Box::pin(inbound))
-            .await?;
-        let res = self.subscription_response(outbound);
-        Ok(res)
+        let ctx = self.call_context("FragmentSubscription", &req);
+        ctx.run(async {
+            let service = self.fragment_service()?;
+            let peer = remote_peer(&req)?;
+            let compression = self.response_compression(&req);
+            let slot = self
+                .propagation
+                .subscribe(self.client_negotiated(&req, Capabilities::FRAGMENT_INVENTORY));
+            let inbound = InboundStream::instrumented(req.into_inner(), ctx.clone());
+            let outbound = service
+                .fragment_subscription(peer, Box::pin(inbound))
+                .await?;
+            let outbound = FragmentPush::new(outbound, slot);
+            let res = self.subscription_response(outbound, compression, &ctx);
+            Ok(res)
+        })
+        .await
+    }
+
+    type FragmentInventorySubscriptionStream = OutboundTryStream<
+        InventoryOutbound<<T::FragmentService as FragmentService>::InventorySubscriptionStream>,
+    >;
+
+    async fn fragment_inventory_subscription(
+        &self,
+        req: tonic::Request<tonic::Streaming<proto::FragmentIds>>,
+    ) -> Result<tonic::Response<Self::FragmentInventorySubscriptionStream>, tonic::Status> {
+        let ctx = self.call_context("FragmentInventorySubscription", &req);
+        ctx.run(async {
+            let service = self.fragment_service()?;
+            let peer = remote_peer(&req)?;
+            let compression = self.response_compression(&req);
+            let known = inventory::known_fragments(self.inventory_cache_size);
+            let inbound = InventoryInbound::new(
+                InboundStream::instrumented(req.into_inner(), ctx.clone()),
+                known.clone(),
+            );
+            let outbound = service
+                .fragment_inventory_subscription(peer, Box::pin(inbound))
+                .await?;
+            let outbound = InventoryOutbound::new(outbound, known);
+            let res = self.subscription_response(outbound, compression, &ctx);
+            Ok(res)
+        })
+        .await
+    }
+
+    type FragmentStatusSubscriptionStream =
+        OutboundTryStream<<T::FragmentService as FragmentService>::StatusSubscriptionStream>;
+
+    async fn fragment_status_subscription(
+        &self,
+        req: tonic::Request<proto::FragmentIds>,
+    ) -> Result<tonic::Response<Self::FragmentStatusSubscriptionStream>, tonic::Status> {
+        let ctx = self.call_context("FragmentStatusSubscription", &req);
+        ctx.message_received(req.get_ref());
+        ctx.run(async {
+            let service = self.fragment_service()?;
+            let peer = remote_peer(&req)?;
+            if req.get_ref().ids.len() > MAX_STATUS_SUBSCRIPTION_IDS {
+                return Err(Status::new(
+                    Code::InvalidArgument,
+                    format!(
+                        "at most {} fragments can be watched by one subscription",
+                        MAX_STATUS_SUBSCRIPTION_IDS
+                    ),
+                ));
+            }
+            let ids = fragment::try_ids_from_iter(req.into_inner().ids)?;
+            let stream = service.fragment_status_subscription(peer, ids).await?;
+            Ok(tonic::Response::new(OutboundTryStream::new(
+                stream,
+                Compression::None,
+                ctx.clone(),
+            )))
+        })
+        .await
     }
 
     type GossipSubscriptionStream =
@@ -314,11 +793,72 @@ where
         &self,
         req: tonic::Request<tonic::Streaming<proto::Gossip>>,
     ) -> Result<tonic::Response<Self::GossipSubscriptionStream>, tonic::Status> {
-        let service = self.gossip_service()?;
-        let peer = remote_addr_to_peer(req.remote_addr())?;
-        let inbound = InboundStream::new(req.into_inner());
-        let outbound = service.gossip_subscription(peer, Box::pin(inbound)).await?;
-        let res = self.subscription_response(outbound);
-        Ok(res)
+        let ctx = self.call_context("GossipSubscription", &req);
+        ctx.run(async {
+            let service = self.gossip_service()?;
+            let peer = remote_peer(&req)?;
+            let compression = self.response_compression(&req);
+            let inbound = InboundStream::instrumented(req.into_inner(), ctx.clone());
+            let outbound = service.gossip_subscription(peer, Box::pin(inbound)).await?;
+            let res = self.subscription_response(outbound, compression, &ctx);
+            Ok(res)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::p2p::NodeKeyPair;
+    use crate::testing::{MockBlock, MockNode};
+    use futures::executor::block_on;
+    use tonic::metadata::MetadataValue;
+
+    fn get_block(
+        client: &mut proto::node_client::NodeClient<loopback::Channel<MockNode>>,
+        id: BlockId,
+    ) -> proto::Block {
+        let mut req = tonic::Request::new(proto::BlockIds {
+            ids: vec![id.as_bytes().into()],
+        });
+        let compression = Compression::supported()[0];
+        let val = MetadataValue::from_static(compression.as_str());
+        req.metadata_mut().insert(COMPRESSION_METADATA_KEY, val);
+        block_on(async {
+            let mut blocks = client.get_blocks(req).await.unwrap().into_inner();
+            blocks.message().await.unwrap().unwrap()
+        })
+    }
+
+    #[test]
+    fn compression_requires_handshake() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = MockNode::new(NodeKeyPair::generate(rand::thread_rng()), block0);
+        // content large enough to be compressed
+        node.add_block(&[0; 4096]);
+        let id = node.tip().id();
+        let server = Server::new(NodeService::new(node));
+        let channel = loopback::Channel::new(server, "127.0.0.1:3000".parse().unwrap());
+        let mut client = proto::node_client::NodeClient::new(channel);
+
+        let block = get_block(&mut client, id);
+        assert_eq!(block.compression, proto::Compression::None as i32);
+
+        let req = proto::HandshakeRequest {
+            nonce: b"nonce".to_vec(),
+            min_version: crate::MIN_PROTOCOL_VERSION,
+            max_version: crate::PROTOCOL_VERSION,
+            capabilities: Capabilities::COMPRESSION.bits(),
+            accept_compression: Compression::supported()
+                .into_iter()
+                .map(|compression| convert::compression_into_protobuf(compression) as i32)
+                .collect(),
+        };
+        block_on(client.handshake(req)).unwrap();
+
+        let block = get_block(&mut client, id);
+        let expected = convert::compression_into_protobuf(Compression::supported()[0]);
+        assert_eq!(block.compression, expected as i32);
     }
 }
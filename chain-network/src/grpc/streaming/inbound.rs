@@ -1,5 +1,6 @@
 use crate::error::Error;
 use crate::grpc::convert::{error_from_grpc, FromProtobuf};
+use crate::grpc::instrument::CallContext;
 use futures::prelude::*;
 use pin_project::pin_project;
 use tonic::Streaming;
@@ -13,6 +14,7 @@ use std::task::{Context, Poll};
 pub struct InboundStream<P, T> {
     #[pin]
     inner: Streaming<P>,
+    ctx: Option<CallContext>,
     _phantom: PhantomData<T>,
 }
 
@@ -20,6 +22,17 @@ impl<P, T> InboundStream<P, T> {
     pub(crate) fn new(inner: Streaming<P>) -> Self {
         InboundStream {
             inner,
+            ctx: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    // Creates a stream of requests received by the server,
+    // reporting the received messages in the context of the call.
+    pub(crate) fn instrumented(inner: Streaming<P>, ctx: CallContext) -> Self {
+        InboundStream {
+            inner,
+            ctx: Some(ctx),
             _phantom: PhantomData,
         }
     }
@@ -27,15 +40,20 @@ impl<P, T> InboundStream<P, T> {
 
 impl<P, T> Stream for InboundStream<P, T>
 where
+    P: prost::Message,
     T: FromProtobuf<P>,
 {
     type Item = Result<T, Error>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
         let this = self.project();
+        let ctx = this.ctx.as_ref();
         this.inner.poll_next(cx).map(|opt| {
             opt.map(|item| match item {
                 Ok(msg) => {
+                    if let Some(ctx) = ctx {
+                        ctx.message_received(&msg);
+                    }
                     let item = T::from_message(msg)?;
                     Ok(item)
                 }
@@ -1,5 +1,7 @@
+use crate::compression::Compression;
 use crate::error::Error;
 use crate::grpc::convert::{error_into_grpc, IntoProtobuf};
+use crate::grpc::instrument::{CallContext, OpenStream};
 use futures::prelude::*;
 use pin_project::pin_project;
 use tonic::Status;
@@ -12,11 +14,12 @@ use std::task::{Context, Poll};
 pub struct OutboundStream<S> {
     #[pin]
     inner: S,
+    compression: Compression,
 }
 
 impl<S> OutboundStream<S> {
-    pub(crate) fn new(inner: S) -> Self {
-        OutboundStream { inner }
+    pub(crate) fn new(inner: S, compression: Compression) -> Self {
+        OutboundStream { inner, compression }
     }
 }
 
@@ -29,22 +32,31 @@ where
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.project();
+        let compression = *this.compression;
         this.inner
             .poll_next(cx)
-            .map(|maybe_item| maybe_item.map(|item| item.into_message()))
+            .map(|maybe_item| maybe_item.map(|item| item.into_message_compressed(compression)))
     }
 }
 
+/// Response stream of the server, polled in the tracing span of the call
+/// and reporting the sent messages and errors.
 #[must_use = "streams do nothing unless polled"]
 #[pin_project]
 pub struct OutboundTryStream<S> {
     #[pin]
     inner: S,
+    compression: Compression,
+    call: OpenStream,
 }
 
 impl<S> OutboundTryStream<S> {
-    pub(crate) fn new(inner: S) -> Self {
-        OutboundTryStream { inner }
+    pub(crate) fn new(inner: S, compression: Compression, ctx: CallContext) -> Self {
+        OutboundTryStream {
+            inner,
+            compression,
+            call: ctx.open_stream(),
+        }
     }
 }
 
@@ -52,15 +64,27 @@ impl<S> Stream for OutboundTryStream<S>
 where
     S: TryStream<Error = Error>,
     S::Ok: IntoProtobuf,
+    <S::Ok as IntoProtobuf>::Message: prost::Message,
 {
     type Item = Result<<S::Ok as IntoProtobuf>::Message, Status>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.project();
+        let compression = *this.compression;
+        let ctx = this.call.context();
+        let _enter = ctx.span().enter();
         this.inner.try_poll_next(cx).map(|maybe_item| {
             maybe_item.map(|item| match item {
-                Ok(data) => Ok(data.into_message()),
-                Err(e) => Err(error_into_grpc(e)),
+                Ok(data) => {
+                    let msg = data.into_message_compressed(compression);
+                    ctx.message_sent(&msg);
+                    Ok(msg)
+                }
+                Err(e) => {
+                    let status = error_into_grpc(e);
+                    ctx.stream_error(&status);
+                    Err(status)
+                }
             })
         })
     }
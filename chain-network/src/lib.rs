@@ -1,12 +1,23 @@
 #![warn(clippy::all)]
 
+pub mod compression;
 pub mod core;
 pub mod data;
 pub mod error;
 pub mod grpc;
+pub mod metrics;
 
-/// Version of the protocol implemented by this crate.
+#[cfg(any(test, feature = "loopback"))]
+pub mod testing;
+
+/// Latest version of the protocol implemented by this crate.
 ///
 /// Note that until the protocol is stabilized, breaking changes may still
 /// occur without changing this version number.
-pub const PROTOCOL_VERSION: u32 = 1;
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Earliest version of the protocol that this crate can interoperate with.
+///
+/// Version 1 peers exchange gossip as opaque bytes rather than signed
+/// records, so they are rejected in the handshake.
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
@@ -0,0 +1,53 @@
+//! Collection of metrics on the network protocol requests served
+//! by a node.
+//!
+//! An implementation of `Metrics` can be plugged into the gRPC server
+//! with `grpc::server::Builder::metrics`. The `PrometheusMetrics`
+//! collector provided by this crate renders the metrics in the
+//! Prometheus text exposition format.
+
+mod prometheus;
+
+pub use self::prometheus::PrometheusMetrics;
+
+use crate::error::Code;
+
+use std::fmt;
+use std::time::Duration;
+
+/// Collector of metrics on the RPC calls served by the node.
+///
+/// The methods are identified by their names in the protocol definition,
+/// such as `"GetBlocks"`. All methods of the trait have empty default
+/// implementations, so collectors only need to implement the events
+/// they are interested in.
+///
+/// The methods are called synchronously in the course of processing
+/// the requests, so they should not block.
+pub trait Metrics: fmt::Debug + Send + Sync {
+    /// Called when a call to the RPC method is received.
+    fn call_started(&self, _method: &'static str) {}
+
+    /// Called when the handler of the call has produced the response or
+    /// failed with an error. For methods with streamed responses, this
+    /// is when the response stream is established.
+    fn call_finished(&self, _method: &'static str, _result: Result<(), Code>, _latency: Duration) {}
+
+    /// Called for each message received from the client, with the size
+    /// of the encoded message in bytes.
+    fn message_received(&self, _method: &'static str, _bytes: usize) {}
+
+    /// Called for each message sent to the client in the response,
+    /// with the size of the encoded message in bytes.
+    fn message_sent(&self, _method: &'static str, _bytes: usize) {}
+
+    /// Called when a response stream is established.
+    fn stream_opened(&self, _method: &'static str) {}
+
+    /// Called when the service produces an error in a response stream.
+    fn stream_error(&self, _method: &'static str, _code: Code) {}
+
+    /// Called when a response stream is closed, either by ending or
+    /// by being dropped, with the time elapsed since it was established.
+    fn stream_closed(&self, _method: &'static str, _lifetime: Duration) {}
+}
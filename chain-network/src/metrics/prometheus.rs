@@ -0,0 +1,334 @@
+use super::Metrics;
+use crate::error::Code;
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const NAMESPACE: &str = "chain_network_rpc";
+
+// Upper bounds of the buckets of the call latency histogram, in seconds.
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0, 5.0];
+
+#[derive(Debug, Default)]
+struct MethodMetrics {
+    calls: u64,
+    results: BTreeMap<&'static str, u64>,
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+    latency_count: u64,
+    messages_received: u64,
+    bytes_received: u64,
+    messages_sent: u64,
+    bytes_sent: u64,
+    streams_opened: u64,
+    streams_closed: u64,
+    stream_lifetime_sum: f64,
+    stream_errors: BTreeMap<&'static str, u64>,
+}
+
+fn code_label(code: Code) -> &'static str {
+    match code {
+        Code::Canceled => "canceled",
+        Code::Unknown => "unknown",
+        Code::InvalidArgument => "invalid_argument",
+        Code::NotFound => "not_found",
+        Code::FailedPrecondition => "failed_precondition",
+        Code::Aborted => "aborted",
+        Code::Unimplemented => "unimplemented",
+        Code::Internal => "internal",
+        Code::Unavailable => "unavailable",
+    }
+}
+
+/// Metrics collector keeping the counters in memory and rendering them
+/// in the Prometheus text exposition format.
+///
+/// The metrics are labeled with the RPC method name, and, where
+/// applicable, with the result code of the call.
+#[derive(Debug, Default)]
+pub struct PrometheusMetrics {
+    methods: Mutex<BTreeMap<&'static str, MethodMetrics>>,
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn update<F>(&self, method: &'static str, f: F)
+    where
+        F: FnOnce(&mut MethodMetrics),
+    {
+        let mut methods = self.methods.lock().unwrap();
+        f(methods.entry(method).or_default())
+    }
+
+    /// Renders the current values of the metrics in the Prometheus text
+    /// exposition format, suitable for serving at a scraping endpoint.
+    pub fn render(&self) -> String {
+        let methods = self.methods.lock().unwrap();
+        let mut out = String::new();
+        render_metrics(&mut out, &methods).expect("writing to a string should not fail");
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(out, "# HELP {}_{} {}", NAMESPACE, name, help)?;
+    writeln!(out, "# TYPE {}_{} {}", NAMESPACE, name, kind)
+}
+
+fn render_counter<F>(
+    out: &mut String,
+    methods: &BTreeMap<&'static str, MethodMetrics>,
+    name: &str,
+    help: &str,
+    value: F,
+) -> fmt::Result
+where
+    F: Fn(&MethodMetrics) -> u64,
+{
+    header(out, name, "counter", help)?;
+    for (method, m) in methods {
+        writeln!(
+            out,
+            "{}_{}{{method=\"{}\"}} {}",
+            NAMESPACE,
+            name,
+            method,
+            value(m)
+        )?;
+    }
+    Ok(())
+}
+
+fn render_metrics(
+    out: &mut String,
+    methods: &BTreeMap<&'static str, MethodMetrics>,
+) -> fmt::Result {
+    render_counter(
+        out,
+        methods,
+        "calls_total",
+        "Number of RPC calls received.",
+        |m| m.calls,
+    )?;
+
+    header(
+        out,
+        "results_total",
+        "counter",
+        "Number of RPC calls handled, by result code.",
+    )?;
+    for (method, m) in methods {
+        for (code, count) in &m.results {
+            writeln!(
+                out,
+                "{}_results_total{{method=\"{}\",code=\"{}\"}} {}",
+                NAMESPACE, method, code, count
+            )?;
+        }
+    }
+
+    header(
+        out,
+        "latency_seconds",
+        "histogram",
+        "Time taken to handle RPC calls.",
+    )?;
+    for (method, m) in methods {
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(m.latency_buckets.iter()) {
+            cumulative += count;
+            writeln!(
+                out,
+                "{}_latency_seconds_bucket{{method=\"{}\",le=\"{}\"}} {}",
+                NAMESPACE, method, bound, cumulative
+            )?;
+        }
+        writeln!(
+            out,
+            "{}_latency_seconds_bucket{{method=\"{}\",le=\"+Inf\"}} {}",
+            NAMESPACE, method, m.latency_count
+        )?;
+        writeln!(
+            out,
+            "{}_latency_seconds_sum{{method=\"{}\"}} {}",
+            NAMESPACE, method, m.latency_sum
+        )?;
+        writeln!(
+            out,
+            "{}_latency_seconds_count{{method=\"{}\"}} {}",
+            NAMESPACE, method, m.latency_count
+        )?;
+    }
+
+    render_counter(
+        out,
+        methods,
+        "received_messages_total",
+        "Number of messages received from clients.",
+        |m| m.messages_received,
+    )?;
+    render_counter(
+        out,
+        methods,
+        "received_bytes_total",
+        "Size of encoded messages received from clients.",
+        |m| m.bytes_received,
+    )?;
+    render_counter(
+        out,
+        methods,
+        "sent_messages_total",
+        "Number of messages sent to clients.",
+        |m| m.messages_sent,
+    )?;
+    render_counter(
+        out,
+        methods,
+        "sent_bytes_total",
+        "Size of encoded messages sent to clients.",
+        |m| m.bytes_sent,
+    )?;
+
+    header(
+        out,
+        "active_streams",
+        "gauge",
+        "Number of open response streams.",
+    )?;
+    for (method, m) in methods {
+        writeln!(
+            out,
+            "{}_active_streams{{method=\"{}\"}} {}",
+            NAMESPACE,
+            method,
+            m.streams_opened - m.streams_closed
+        )?;
+    }
+
+    header(
+        out,
+        "stream_lifetime_seconds",
+        "summary",
+        "Lifetime of closed response streams.",
+    )?;
+    for (method, m) in methods {
+        writeln!(
+            out,
+            "{}_stream_lifetime_seconds_sum{{method=\"{}\"}} {}",
+            NAMESPACE, method, m.stream_lifetime_sum
+        )?;
+        writeln!(
+            out,
+            "{}_stream_lifetime_seconds_count{{method=\"{}\"}} {}",
+            NAMESPACE, method, m.streams_closed
+        )?;
+    }
+
+    header(
+        out,
+        "stream_errors_total",
+        "counter",
+        "Number of errors sent in response streams, by error code.",
+    )?;
+    for (method, m) in methods {
+        for (code, count) in &m.stream_errors {
+            writeln!(
+                out,
+                "{}_stream_errors_total{{method=\"{}\",code=\"{}\"}} {}",
+                NAMESPACE, method, code, count
+            )?;
+        }
+    }
+    Ok(())
+}
+
+impl Metrics for PrometheusMetrics {
+    fn call_started(&self, method: &'static str) {
+        self.update(method, |m| m.calls += 1);
+    }
+
+    fn call_finished(&self, method: &'static str, result: Result<(), Code>, latency: Duration) {
+        let label = match result {
+            Ok(()) => "ok",
+            Err(code) => code_label(code),
+        };
+        let secs = latency.as_secs_f64();
+        self.update(method, |m| {
+            *m.results.entry(label).or_default() += 1;
+            if let Some(i) = LATENCY_BUCKETS.iter().position(|bound| secs <= *bound) {
+                m.latency_buckets[i] += 1;
+            }
+            m.latency_sum += secs;
+            m.latency_count += 1;
+        });
+    }
+
+    fn message_received(&self, method: &'static str, bytes: usize) {
+        self.update(method, |m| {
+            m.messages_received += 1;
+            m.bytes_received += bytes as u64;
+        });
+    }
+
+    fn message_sent(&self, method: &'static str, bytes: usize) {
+        self.update(method, |m| {
+            m.messages_sent += 1;
+            m.bytes_sent += bytes as u64;
+        });
+    }
+
+    fn stream_opened(&self, method: &'static str) {
+        self.update(method, |m| m.streams_opened += 1);
+    }
+
+    fn stream_error(&self, method: &'static str, code: Code) {
+        self.update(method, |m| {
+            *m.stream_errors.entry(code_label(code)).or_default() += 1
+        });
+    }
+
+    fn stream_closed(&self, method: &'static str, lifetime: Duration) {
+        self.update(method, |m| {
+            m.streams_closed += 1;
+            m.stream_lifetime_sum += lifetime.as_secs_f64();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_text_format() {
+        let metrics = PrometheusMetrics::new();
+        metrics.call_started("Tip");
+        metrics.call_finished("Tip", Ok(()), Duration::from_millis(2));
+        metrics.message_sent("Tip", 100);
+        metrics.call_started("GetBlocks");
+        metrics.call_finished("GetBlocks", Err(Code::NotFound), Duration::from_secs(10));
+
+        let text = metrics.render();
+        let lines: Vec<_> = text.lines().collect();
+        assert!(lines.contains(&"# TYPE chain_network_rpc_calls_total counter"));
+        assert!(lines.contains(&"chain_network_rpc_calls_total{method=\"Tip\"} 1"));
+        assert!(lines.contains(&"chain_network_rpc_results_total{method=\"Tip\",code=\"ok\"} 1"));
+        assert!(lines.contains(
+            &"chain_network_rpc_results_total{method=\"GetBlocks\",code=\"not_found\"} 1"
+        ));
+        assert!(lines
+            .contains(&"chain_network_rpc_latency_seconds_bucket{method=\"Tip\",le=\"0.0025\"} 1"));
+        assert!(lines.contains(
+            &"chain_network_rpc_latency_seconds_bucket{method=\"GetBlocks\",le=\"5\"} 0"
+        ));
+        assert!(lines.contains(
+            &"chain_network_rpc_latency_seconds_bucket{method=\"GetBlocks\",le=\"+Inf\"} 1"
+        ));
+        assert!(lines.contains(&"chain_network_rpc_sent_bytes_total{method=\"Tip\"} 100"));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::data::{Block, BlockId, Header};
+use crate::error::{Code, Error};
+use chain_crypto::Blake2b256;
+
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+
+const ID_LEN: usize = 32;
+
+// The header of a mock block is made of the parent block ID,
+// the chain length as a big-endian 64-bit number,
+// and the hash of the block content.
+const HEADER_LEN: usize = ID_LEN + 8 + ID_LEN;
+
+/// A block of the mock chain.
+///
+/// The block ID is the hash of the header, which links the block
+/// to its parent and commits to the opaque block content.
+#[derive(Clone)]
+pub struct MockBlock {
+    id: BlockId,
+    parent_id: BlockId,
+    chain_length: u64,
+    block: Block,
+}
+
+impl MockBlock {
+    /// Creates the genesis block of a chain with the given content.
+    pub fn genesis(content: &[u8]) -> Self {
+        MockBlock::build(&[0; ID_LEN], 0, content)
+    }
+
+    /// Creates a block with the given content, extending the chain
+    /// at `parent`.
+    pub fn new(parent: &MockBlock, content: &[u8]) -> Self {
+        MockBlock::build(parent.id.as_bytes(), parent.chain_length + 1, content)
+    }
+
+    fn build(parent_id: &[u8], chain_length: u64, content: &[u8]) -> Self {
+        let mut bytes = Vec::with_capacity(HEADER_LEN + content.len());
+        bytes.extend_from_slice(parent_id);
+        bytes.extend_from_slice(&chain_length.to_be_bytes());
+        bytes.extend_from_slice(Blake2b256::new(content).as_ref());
+        bytes.extend_from_slice(content);
+        MockBlock::decode(Block::from_bytes(bytes)).unwrap()
+    }
+
+    /// Decodes a block received from the network.
+    pub fn decode(block: Block) -> Result<Self, Error> {
+        let bytes = block.as_bytes();
+        if bytes.len() < HEADER_LEN {
+            return Err(Error::new(Code::InvalidArgument, "block is too short"));
+        }
+        let (header, content) = bytes.split_at(HEADER_LEN);
+        if Blake2b256::new(content).as_ref() != &header[ID_LEN + 8..] {
+            return Err(Error::new(
+                Code::InvalidArgument,
+                "block content does not match the header",
+            ));
+        }
+        let (id, parent_id, chain_length) = decode_header_bytes(header)?;
+        Ok(MockBlock {
+            id,
+            parent_id,
+            chain_length,
+            block,
+        })
+    }
+
+    pub fn id(&self) -> BlockId {
+        self.id
+    }
+
+    pub fn parent_id(&self) -> BlockId {
+        self.parent_id
+    }
+
+    pub fn chain_length(&self) -> u64 {
+        self.chain_length
+    }
+
+    pub fn header(&self) -> Header {
+        Header::from_bytes(&self.block.as_bytes()[..HEADER_LEN])
+    }
+
+    pub fn block(&self) -> Block {
+        self.block.clone()
+    }
+}
+
+/// Decodes a header of the mock chain received from the network,
+/// returning the block ID, the parent block ID and the chain length.
+pub fn decode_header(header: &Header) -> Result<(BlockId, BlockId, u64), Error> {
+    let bytes = header.as_bytes();
+    if bytes.len() != HEADER_LEN {
+        return Err(Error::new(
+            Code::InvalidArgument,
+            format!("block header must be {} bytes long", HEADER_LEN),
+        ));
+    }
+    decode_header_bytes(bytes)
+}
+
+fn decode_header_bytes(header: &[u8]) -> Result<(BlockId, BlockId, u64), Error> {
+    let id = BlockId::try_from(Blake2b256::new(header).as_ref())?;
+    let parent_id = BlockId::try_from(&header[..ID_LEN])?;
+    let chain_length = u64::from_be_bytes(header[ID_LEN..ID_LEN + 8].try_into().unwrap());
+    Ok((id, parent_id, chain_length))
+}
+
+/// A simple in-memory block chain.
+///
+/// The chain stores all blocks connected to the genesis block, and selects
+/// the longest branch to determine the tip.
+#[derive(Clone)]
+pub struct MockChain {
+    blocks: HashMap<BlockId, MockBlock>,
+    block0_id: BlockId,
+    tip_id: BlockId,
+}
+
+impl MockChain {
+    pub fn new(block0: MockBlock) -> Self {
+        let block0_id = block0.id;
+        let mut blocks = HashMap::new();
+        blocks.insert(block0_id, block0);
+        MockChain {
+            blocks,
+            block0_id,
+            tip_id: block0_id,
+        }
+    }
+
+    pub fn block0_id(&self) -> BlockId {
+        self.block0_id
+    }
+
+    pub fn tip(&self) -> &MockBlock {
+        &self.blocks[&self.tip_id]
+    }
+
+    pub fn get(&self, id: &BlockId) -> Option<&MockBlock> {
+        self.blocks.get(id)
+    }
+
+    pub fn contains(&self, id: &BlockId) -> bool {
+        self.blocks.contains_key(id)
+    }
+
+    /// Creates a block with the given content on top of the tip
+    /// and adds it to the chain.
+    pub fn add_block(&mut self, content: &[u8]) -> MockBlock {
+        let block = MockBlock::new(self.tip(), content);
+        self.insert(block.clone())
+            .expect("block created on the tip should be accepted");
+        block
+    }
+
+    /// Adds a block to the chain, which must already contain
+    /// the parent block.
+    ///
+    /// Returns `true` if the block was not yet in the chain.
+    pub fn insert(&mut self, block: MockBlock) -> Result<bool, Error> {
+        if self.blocks.contains_key(&block.id) {
+            return Ok(false);
+        }
+        match self.blocks.get(&block.parent_id) {
+            None => {
+                return Err(Error::new(
+                    Code::FailedPrecondition,
+                    "the parent block is not found in the chain",
+                ))
+            }
+            Some(parent) if parent.chain_length + 1 != block.chain_length => {
+                return Err(Error::new(
+                    Code::InvalidArgument,
+                    "invalid chain length of the block",
+                ))
+            }
+            Some(_) => {}
+        }
+        if block.chain_length > self.tip().chain_length {
+            self.tip_id = block.id;
+        }
+        self.blocks.insert(block.id, block);
+        Ok(true)
+    }
+
+    /// IDs of the blocks on the branch of the tip, from the tip
+    /// down to the genesis block.
+    ///
+    /// These are useful as starting points in requests for blocks
+    /// missing from this chain.
+    pub fn checkpoints(&self) -> Vec<BlockId> {
+        let mut ids = Vec::new();
+        let mut block = self.tip();
+        loop {
+            ids.push(block.id);
+            if block.id == self.block0_id {
+                return ids;
+            }
+            block = &self.blocks[&block.parent_id];
+        }
+    }
+
+    /// Blocks in chronological order on the branch ending at `to`,
+    /// following the latest of the `from` starting points found on the branch.
+    pub fn range(&self, from: &[BlockId], to: &BlockId) -> Result<Vec<MockBlock>, Error> {
+        let mut block = self
+            .blocks
+            .get(to)
+            .ok_or_else(|| Error::new(Code::NotFound, "the ending point is not found"))?;
+        let mut blocks = Vec::new();
+        while !from.contains(&block.id) {
+            if block.id == self.block0_id {
+                return Err(Error::new(
+                    Code::NotFound,
+                    "none of the starting points are found",
+                ));
+            }
+            blocks.push(block.clone());
+            block = &self.blocks[&block.parent_id];
+        }
+        blocks.reverse();
+        Ok(blocks)
+    }
+}
@@ -0,0 +1,605 @@
+//! Mock implementation of the node services backed by a simple in-memory
+//! chain, for testing protocol scenarios over the in-process
+//! `grpc::loopback` transport.
+
+mod chain;
+mod node;
+
+pub use chain::{decode_header, MockBlock, MockChain};
+pub use node::{MockNode, MockStream};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compression::Compression;
+    use crate::data::block::BlockEvent;
+    use crate::data::fragment::PropagationPolicy;
+    use crate::data::gossip::{Gossip, NodeInfo, Topic};
+    use crate::data::p2p::{NodeKeyPair, Peer};
+    use crate::data::{
+        Block, BlockDate, BlockId, Capabilities, Fragment, FragmentId, FragmentIds, FragmentStatus,
+        Protocol,
+    };
+    use crate::error::{Code, Error, HandshakeError};
+    use crate::grpc::pool::FetchError;
+    use crate::grpc::{client, loopback, server, ClientPool, NodeService, Server};
+    use crate::metrics::PrometheusMetrics;
+    use crate::PROTOCOL_VERSION;
+    use futures::executor::block_on;
+    use futures::prelude::*;
+
+    use std::convert::TryFrom;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+
+    fn mock_node(block0: &MockBlock) -> MockNode {
+        MockNode::new(NodeKeyPair::generate(rand::thread_rng()), block0.clone())
+    }
+
+    fn serve(node: &MockNode) -> Server<MockNode> {
+        Server::new(NodeService::new(node.clone()))
+    }
+
+    fn serve_light_clients(node: &MockNode) -> Server<MockNode> {
+        server::Builder::new().light_client().build(node.clone())
+    }
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    fn block_id(block: &Block) -> Result<BlockId, Error> {
+        MockBlock::decode(block.clone()).map(|block| block.id())
+    }
+
+    #[test]
+    fn handshake_and_client_auth() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let client_key_pair = NodeKeyPair::generate(rand::thread_rng());
+        let client_addr = addr("127.0.0.1:3001");
+        let mut client = loopback::connect(&serve(&node), client_addr);
+
+        block_on(async {
+            let nonce = b"client nonce";
+            let res = client.handshake(nonce).await.unwrap();
+            assert_eq!(res.block0_id, block0.id());
+            res.auth.verify(nonce).unwrap();
+            client
+                .client_auth(client_key_pair.sign(&res.nonce))
+                .await
+                .unwrap();
+        });
+        assert!(node.is_authenticated(&Peer::from(client_addr)));
+    }
+
+    #[test]
+    fn pull_blocks_to_tip_syncs_chains() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node_a = mock_node(&block0);
+        let node_b = mock_node(&block0);
+        for i in 0..3u8 {
+            node_a.add_block(&[i]);
+        }
+
+        let mut client = loopback::connect(&serve(&node_a), addr("127.0.0.1:3002"));
+        block_on(node_b.pull_from(&mut client)).unwrap();
+        assert_eq!(node_b.tip().id(), node_a.tip().id());
+        assert_eq!(node_b.tip().chain_length(), 3);
+
+        // pulling again when up to date is a no-op
+        block_on(node_b.pull_from(&mut client)).unwrap();
+        assert_eq!(node_b.tip().id(), node_a.tip().id());
+    }
+
+    #[test]
+    fn handshake_negotiates_compression() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node_a = mock_node(&block0);
+        let node_b = mock_node(&block0);
+        for i in 0..3u8 {
+            node_a.add_block(&[i]);
+        }
+        let mut client = loopback::connect(&serve(&node_a), addr("127.0.0.1:3003"));
+        assert_eq!(client.compression(), Compression::None);
+
+        block_on(async {
+            client.handshake(b"nonce").await.unwrap();
+            let expected = Compression::supported()[0];
+            assert_eq!(client.compression(), expected);
+            node_b.pull_from(&mut client).await.unwrap();
+        });
+        assert_eq!(node_b.tip().id(), node_a.tip().id());
+    }
+
+    #[test]
+    fn handshake_negotiates_protocol() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let client_addr = addr("127.0.0.1:3005");
+        let mut client = loopback::connect(&serve(&node), client_addr);
+        assert_eq!(client.protocol(), None);
+
+        let expected = Protocol {
+            version: PROTOCOL_VERSION,
+            capabilities: Capabilities::server_default(),
+        };
+        block_on(client.handshake(b"nonce")).unwrap();
+        assert_eq!(client.protocol(), Some(expected));
+        assert_eq!(node.peer_protocol(&Peer::from(client_addr)), Some(expected));
+    }
+
+    #[test]
+    fn capabilities_not_offered_are_not_used() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let client_addr = addr("127.0.0.1:3006");
+        let channel = loopback::Channel::new(serve(&node), client_addr);
+        let mut client = client::Builder::new()
+            .capabilities(Capabilities::empty())
+            .build(channel);
+
+        block_on(async {
+            client.handshake(b"nonce").await.unwrap();
+            let protocol = client.protocol().unwrap();
+            assert!(protocol.capabilities.is_empty());
+            assert_eq!(client.compression(), Compression::None);
+            let err = client
+                .gossip_subscription(stream::pending::<Gossip>())
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.code(), Code::Unimplemented);
+        });
+        let protocol = node.peer_protocol(&Peer::from(client_addr)).unwrap();
+        assert!(protocol.capabilities.is_empty());
+    }
+
+    #[test]
+    fn pull_header_batches_covers_range() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let blocks: Vec<_> = (0..7u8).map(|i| node.add_block(&[i])).collect();
+        let mut client = loopback::connect(&serve_light_clients(&node), addr("127.0.0.1:3007"));
+
+        let batches: Vec<_> = block_on(async {
+            client.handshake(b"nonce").await.unwrap();
+            client
+                .pull_header_batches(vec![block0.id()].into(), node.tip().id(), 3)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap()
+        });
+        assert!(batches
+            .iter()
+            .all(|batch| !batch.is_empty() && batch.len() <= 3));
+        let ids: Vec<_> = batches
+            .iter()
+            .flat_map(|batch| batch.iter())
+            .map(|header| decode_header(header).unwrap().0)
+            .collect();
+        let expected: Vec<_> = blocks.iter().map(|block| block.id()).collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn fragment_proof_not_implemented_by_default() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let mut client = loopback::connect(&serve_light_clients(&node), addr("127.0.0.1:3008"));
+
+        let err = block_on(
+            client.get_fragment_proof(block0.id(), FragmentId::try_from(&[0; 32][..]).unwrap()),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.code(), Code::Unimplemented);
+    }
+
+    #[test]
+    fn light_client_is_not_offered_by_default() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let mut client = loopback::connect(&serve(&node), addr("127.0.0.1:3018"));
+
+        block_on(async {
+            client.handshake(b"nonce").await.unwrap();
+            let protocol = client.protocol().unwrap();
+            assert!(!protocol.supports(Capabilities::LIGHT_CLIENT));
+            let err = client
+                .pull_header_batches(vec![block0.id()].into(), node.tip().id(), 3)
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.code(), Code::Unimplemented);
+        });
+    }
+
+    #[test]
+    fn block_subscription_solicits_and_announces() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node_a = mock_node(&block0);
+        let node_b = mock_node(&block0);
+        let new_block = node_b.add_block(b"from b");
+        let mut client = loopback::connect(&serve(&node_a), addr("127.0.0.1:3002"));
+
+        block_on(async {
+            let announcements = stream::iter(vec![new_block.header()]);
+            let mut subscription = client.block_subscription(announcements).await.unwrap();
+            match subscription.next().await.unwrap().unwrap() {
+                BlockEvent::Solicit(ids) => assert_eq!(&ids[..], &[new_block.id()]),
+                event => panic!("unexpected block event {:?}", event),
+            }
+
+            client
+                .upload_blocks(stream::iter(vec![new_block.block()]))
+                .await
+                .unwrap();
+            assert_eq!(node_a.tip().id(), new_block.id());
+
+            // blocks accepted from peers are announced to all subscribers
+            match subscription.next().await.unwrap().unwrap() {
+                BlockEvent::Announce(header) => {
+                    assert_eq!(decode_header(&header).unwrap().0, new_block.id())
+                }
+                event => panic!("unexpected block event {:?}", event),
+            }
+
+            let announced = node_a.add_block(b"from a");
+            match subscription.next().await.unwrap().unwrap() {
+                BlockEvent::Announce(header) => {
+                    assert_eq!(decode_header(&header).unwrap().0, announced.id())
+                }
+                event => panic!("unexpected block event {:?}", event),
+            }
+        });
+    }
+
+    #[test]
+    fn fragment_inventory_announces_new_ids_once() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let fragment = Fragment::from_bytes(&b"fragment"[..]);
+        let mut client = loopback::connect(&serve(&node), addr("127.0.0.1:3009"));
+
+        block_on(async {
+            client.handshake(b"nonce").await.unwrap();
+            let mut subscription = client
+                .fragment_inventory_subscription(stream::pending::<FragmentIds>())
+                .await
+                .unwrap();
+            let id = node.post_fragment(fragment.clone());
+            let announced = subscription.next().await.unwrap().unwrap();
+            assert_eq!(&announced[..], &[id]);
+
+            let fragments: Vec<_> = client
+                .get_fragments(announced)
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            assert_eq!(fragments.len(), 1);
+            assert_eq!(fragments[0].as_bytes(), fragment.as_bytes());
+
+            // the ID already announced to the subscriber is not repeated
+            node.post_fragment(fragment);
+            let other_id = node.post_fragment(Fragment::from_bytes(&b"other"[..]));
+            let announced = subscription.next().await.unwrap().unwrap();
+            assert_eq!(&announced[..], &[other_id]);
+        });
+    }
+
+    #[test]
+    fn negotiated_capabilities_are_not_shared_by_address() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let server = server::Builder::new()
+            .propagation_policy(PropagationPolicy::AnnounceAll)
+            .build(node.clone());
+        let client_addr = addr("127.0.0.1:3021");
+        let mut inventory_client = loopback::connect(&server, client_addr);
+        let mut client = loopback::connect(&server, client_addr);
+
+        block_on(async {
+            inventory_client.handshake(b"nonce").await.unwrap();
+            // the client that has not negotiated inventory announcements
+            // is pushed the fragments, even when connecting from the address
+            // of a client that has
+            let mut subscription = client
+                .fragment_subscription(stream::pending::<Fragment>())
+                .await
+                .unwrap();
+            let fragment = Fragment::from_bytes(&b"fragment"[..]);
+            node.post_fragment(fragment.clone());
+            let pushed = subscription.next().await.unwrap().unwrap();
+            assert_eq!(pushed.as_bytes(), fragment.as_bytes());
+        });
+    }
+
+    #[test]
+    fn fragment_status_subscription_reports_changes() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let rejected = FragmentId::try_from(&[1; 32][..]).unwrap();
+        node.set_fragment_status(
+            rejected,
+            FragmentStatus::Rejected {
+                reason: "invalid".into(),
+            },
+        );
+        let mut client = loopback::connect(&serve(&node), addr("127.0.0.1:3016"));
+
+        block_on(async {
+            client.handshake(b"nonce").await.unwrap();
+            let fragment = Fragment::from_bytes(&b"fragment"[..]);
+            let id = node.post_fragment(fragment);
+            let statuses: Vec<_> = client
+                .fragment_status_subscription(vec![rejected, id].into())
+                .await
+                .unwrap()
+                .take(2)
+                .map(|event| {
+                    let event = event.unwrap();
+                    (event.fragment, event.status)
+                })
+                .collect()
+                .await;
+            assert_eq!(
+                statuses,
+                vec![
+                    (
+                        rejected,
+                        FragmentStatus::Rejected {
+                            reason: "invalid".into()
+                        }
+                    ),
+                    (id, FragmentStatus::Pending),
+                ]
+            );
+
+            let mut subscription = client
+                .fragment_status_subscription(vec![id].into())
+                .await
+                .unwrap();
+            let event = subscription.next().await.unwrap().unwrap();
+            assert_eq!(event.status, FragmentStatus::Pending);
+            let in_block = FragmentStatus::InBlock {
+                block: node.add_block(b"block").id(),
+                date: BlockDate { epoch: 0, slot: 1 },
+            };
+            node.set_fragment_status(id, in_block.clone());
+            let event = subscription.next().await.unwrap().unwrap();
+            assert_eq!(event.fragment, id);
+            assert_eq!(event.status, in_block);
+            // the stream ends once all fragments reach a final status
+            assert!(subscription.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn fragment_status_subscription_limits_ids() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let mut client = loopback::connect(&serve(&node), addr("127.0.0.1:3020"));
+
+        let ids: Vec<_> = (0..=server::MAX_STATUS_SUBSCRIPTION_IDS)
+            .map(|i| FragmentId::try_from(&[i as u8; 32][..]).unwrap())
+            .collect();
+        let err = block_on(client.fragment_status_subscription(ids.into()))
+            .err()
+            .unwrap();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn client_pool_fetches_blocks_from_multiple_peers() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node_a = mock_node(&block0);
+        let node_b = mock_node(&block0);
+        // peer C lacks the blocks requested from it
+        let node_c = mock_node(&block0);
+        let blocks: Vec<_> = (0..7u8).map(|i| node_a.add_block(&[i])).collect();
+        let ids: Vec<_> = blocks.iter().map(|block| block.id()).collect();
+        block_on(node_b.pull_from(&mut loopback::connect(
+            &serve(&node_a),
+            addr("127.0.0.1:3010"),
+        )))
+        .unwrap();
+
+        let peer_a = Peer::from(addr("10.0.0.1:3000"));
+        let peer_b = Peer::from(addr("10.0.0.2:3000"));
+        let peer_c = Peer::from(addr("10.0.0.3:3000"));
+        let mut pool = ClientPool::new(block0.id(), NodeKeyPair::generate(rand::thread_rng()));
+        pool.set_chunk_size(2);
+
+        block_on(async {
+            for (peer, node, client_addr) in [
+                (&peer_c, &node_c, "127.0.0.1:3011"),
+                (&peer_a, &node_a, "127.0.0.1:3012"),
+                (&peer_b, &node_b, "127.0.0.1:3013"),
+            ] {
+                let client = loopback::connect(&serve(node), addr(client_addr));
+                pool.connect(peer.clone(), client, b"nonce").await.unwrap();
+            }
+            assert!(node_a.is_authenticated(&Peer::from(addr("127.0.0.1:3012"))));
+            assert_eq!(pool.len(), 3);
+
+            let fetched: Vec<_> = pool
+                .get_blocks(&ids, block_id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|block| MockBlock::decode(block).unwrap().id())
+                .collect();
+            assert_eq!(fetched, ids);
+            assert_eq!(pool.failures(&peer_c), Some(1));
+            assert_eq!(pool.failures(&peer_a), Some(0));
+
+            let pulled: Vec<_> = pool
+                .pull_blocks(
+                    vec![block0.id()].into(),
+                    node_a.tip().id(),
+                    |header| decode_header(header).map(|(id, parent_id, _)| (id, parent_id)),
+                    block_id,
+                )
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|block| MockBlock::decode(block).unwrap().id())
+                .collect();
+            assert_eq!(pulled, ids);
+        });
+    }
+
+    #[test]
+    fn client_pool_reports_peer_errors() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let other_node = mock_node(&MockBlock::genesis(b"other genesis"));
+        let missing = mock_node(&block0).add_block(b"missing");
+        let known = node.add_block(b"known");
+        let peer = Peer::from(addr("10.0.0.1:3000"));
+        let mut pool = ClientPool::new(block0.id(), NodeKeyPair::generate(rand::thread_rng()));
+
+        block_on(async {
+            match pool.get_blocks(&[missing.id()], block_id).await {
+                Err(FetchError::NoPeers) => {}
+                res => panic!("unexpected result {:?}", res.map(|_| ())),
+            }
+
+            let client = loopback::connect(&serve(&other_node), addr("127.0.0.1:3014"));
+            match pool.connect(peer.clone(), client, b"nonce").await {
+                Err(HandshakeError::Block0Mismatch) => {}
+                res => panic!("unexpected result {:?}", res),
+            }
+            assert!(pool.is_empty());
+
+            let client = loopback::connect(&serve(&node), addr("127.0.0.1:3015"));
+            pool.connect(peer.clone(), client, b"nonce").await.unwrap();
+            match pool.get_blocks(&[missing.id()], block_id).await {
+                Err(FetchError::Exhausted(errors)) => {
+                    assert_eq!(errors.len(), 1);
+                    assert_eq!(errors[0].peer, peer);
+                    assert_eq!(errors[0].error.code(), Code::NotFound);
+                }
+                res => panic!("unexpected result {:?}", res.map(|_| ())),
+            }
+
+            let client = loopback::connect(&serve(&node), addr("127.0.0.1:3019"));
+            pool.connect(peer.clone(), client, b"nonce").await.unwrap();
+            match pool.get_blocks(&[known.id()], |_| Ok(block0.id())).await {
+                Err(FetchError::Exhausted(errors)) => {
+                    assert_eq!(errors.len(), 1);
+                    assert_eq!(errors[0].error.code(), Code::InvalidArgument);
+                }
+                res => panic!("unexpected result {:?}", res.map(|_| ())),
+            }
+
+            // headers not linked to their parents are rejected
+            let client = loopback::connect(&serve(&node), addr("127.0.0.1:3022"));
+            pool.connect(peer.clone(), client, b"nonce").await.unwrap();
+            let res = pool
+                .pull_blocks(
+                    vec![block0.id()].into(),
+                    known.id(),
+                    |header| decode_header(header).map(|(id, _, _)| (id, id)),
+                    block_id,
+                )
+                .await;
+            match res {
+                Err(FetchError::Exhausted(errors)) => {
+                    assert_eq!(errors.len(), 1);
+                    assert_eq!(errors[0].error.code(), Code::InvalidArgument);
+                }
+                res => panic!("unexpected result {:?}", res.map(|_| ())),
+            }
+
+            // there is nothing to pull up to a checkpoint
+            let pulled = pool
+                .pull_blocks(
+                    vec![known.id()].into(),
+                    known.id(),
+                    |header| decode_header(header).map(|(id, parent_id, _)| (id, parent_id)),
+                    block_id,
+                )
+                .await
+                .unwrap();
+            assert!(pulled.is_empty());
+        });
+    }
+
+    #[test]
+    fn gossip_carries_signed_node_records() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let gossip_key_pair = NodeKeyPair::generate(rand::thread_rng());
+        let info = NodeInfo::new(vec![addr("10.0.0.1:3000")], vec![Topic::Blocks]);
+        let record = info.clone().sign(&gossip_key_pair);
+        let mut client = loopback::connect(&serve(&node), addr("127.0.0.1:3004"));
+
+        block_on(async {
+            let mut subscription = client
+                .gossip_subscription(stream::pending::<Gossip>())
+                .await
+                .unwrap();
+            node.send_gossip(vec![record.clone()].into());
+            let gossip = subscription.next().await.unwrap().unwrap();
+            assert_eq!(gossip.nodes.len(), 1);
+            assert_eq!(gossip.nodes[0].id(), record.id());
+            assert_eq!(gossip.nodes[0].info(), &info);
+        });
+    }
+
+    #[test]
+    fn server_reports_call_metrics() {
+        let block0 = MockBlock::genesis(b"genesis");
+        let node = mock_node(&block0);
+        let ids: Vec<_> = (0..2u8).map(|i| node.add_block(&[i]).id()).collect();
+        let metrics = Arc::new(PrometheusMetrics::new());
+        let server = server::Builder::new()
+            .light_client()
+            .metrics(metrics.clone())
+            .build(node.clone());
+        let mut client = loopback::connect(&server, addr("127.0.0.1:3017"));
+
+        block_on(async {
+            client.handshake(b"nonce").await.unwrap();
+            let blocks: Vec<_> = client
+                .get_blocks(ids.into())
+                .await
+                .unwrap()
+                .try_collect()
+                .await
+                .unwrap();
+            assert_eq!(blocks.len(), 2);
+            let err = client
+                .get_fragment_proof(block0.id(), FragmentId::try_from(&[0; 32][..]).unwrap())
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.code(), Code::Unimplemented);
+        });
+
+        let text = metrics.render();
+        let lines: Vec<_> = text.lines().collect();
+        for expected in &[
+            "chain_network_rpc_calls_total{method=\"Handshake\"} 1",
+            "chain_network_rpc_results_total{method=\"Handshake\",code=\"ok\"} 1",
+            "chain_network_rpc_calls_total{method=\"GetBlocks\"} 1",
+            "chain_network_rpc_sent_messages_total{method=\"GetBlocks\"} 2",
+            "chain_network_rpc_active_streams{method=\"GetBlocks\"} 0",
+            "chain_network_rpc_stream_lifetime_seconds_count{method=\"GetBlocks\"} 1",
+            "chain_network_rpc_results_total{method=\"GetFragmentProof\",code=\"unimplemented\"} 1",
+        ] {
+            assert!(
+                lines.contains(expected),
+                "missing {:?} in:\n{}",
+                expected,
+                text
+            );
+        }
+    }
+}
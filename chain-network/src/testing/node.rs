@@ -0,0 +1,572 @@
+use super::chain::{decode_header, MockBlock, MockChain};
+use crate::core::server::{BlockService, FragmentService, GossipService, Node, PushStream};
+use crate::data::block::{BlockEvent, ChainPullRequest};
+use crate::data::gossip::{self, Gossip};
+use crate::data::p2p::{AuthenticatedNodeId, NodeKeyPair};
+use crate::data::{
+    Block, BlockId, BlockIds, Fragment, FragmentId, FragmentIds, FragmentStatus,
+    FragmentStatusEvent, HandshakeResponse, Header, Peer, Peers, Protocol,
+};
+use crate::error::{Code, Error};
+use crate::grpc::loopback::Channel;
+use crate::grpc::Client;
+use async_trait::async_trait;
+use chain_crypto::Blake2b256;
+use futures::channel::mpsc;
+use futures::prelude::*;
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+/// Type of the streams returned by the services of the mock node.
+pub type MockStream<T> = Pin<Box<dyn Stream<Item = Result<T, Error>> + Send + Sync>>;
+
+type Subscribers<T> = Vec<mpsc::UnboundedSender<Result<T, Error>>>;
+
+// A subscriber to the status of fragments that have not yet reached
+// a final status.
+struct StatusSubscriber {
+    ids: HashSet<FragmentId>,
+    tx: mpsc::UnboundedSender<Result<FragmentStatusEvent, Error>>,
+}
+
+struct State {
+    chain: MockChain,
+    fragments: HashMap<FragmentId, Fragment>,
+    peers: Vec<Peer>,
+    gossip: Vec<gossip::Node>,
+    nonces: HashMap<Peer, Box<[u8]>>,
+    protocols: HashMap<Peer, Protocol>,
+    nonce_counter: u64,
+    authenticated: HashSet<Peer>,
+    block_subscribers: Subscribers<BlockEvent>,
+    fragment_subscribers: Subscribers<Fragment>,
+    inventory_subscribers: Subscribers<FragmentIds>,
+    missing_fragments: Vec<FragmentId>,
+    fragment_statuses: HashMap<FragmentId, FragmentStatus>,
+    status_subscribers: Vec<StatusSubscriber>,
+    gossip_subscribers: Subscribers<Gossip>,
+}
+
+impl State {
+    fn update_fragment_status(&mut self, id: FragmentId, status: FragmentStatus) {
+        let event = FragmentStatusEvent {
+            fragment: id,
+            status: status.clone(),
+        };
+        self.status_subscribers.retain(|sub| {
+            !sub.ids.contains(&id) || sub.tx.unbounded_send(Ok(event.clone())).is_ok()
+        });
+        if status.is_final() {
+            // End the streams of the subscribers that have nothing
+            // more to wait for.
+            for sub in self.status_subscribers.iter_mut() {
+                sub.ids.remove(&id);
+            }
+            self.status_subscribers.retain(|sub| !sub.ids.is_empty());
+        }
+        self.fragment_statuses.insert(id, status);
+    }
+}
+
+/// Implementation of `Node` backed by an in-memory chain, for testing.
+///
+/// The mock node is a cheaply cloneable handle, so the application
+/// side of a test can keep a clone to drive and inspect the node after
+/// passing it to the server.
+///
+/// The inbound streams of subscriptions are processed as the
+/// corresponding outbound streams are polled, which keeps the node
+/// behavior deterministic without spawning any tasks.
+#[derive(Clone)]
+pub struct MockNode {
+    key_pair: NodeKeyPair,
+    state: Arc<Mutex<State>>,
+}
+
+fn fragment_id(fragment: &Fragment) -> FragmentId {
+    FragmentId::try_from(Blake2b256::new(fragment.as_bytes()).as_ref()).unwrap()
+}
+
+fn broadcast<T: Clone>(subscribers: &mut Subscribers<T>, item: T) {
+    subscribers.retain(|tx| tx.unbounded_send(Ok(item.clone())).is_ok());
+}
+
+fn subscribe<T>(subscribers: &mut Subscribers<T>) -> mpsc::UnboundedReceiver<Result<T, Error>> {
+    let (tx, rx) = mpsc::unbounded();
+    subscribers.push(tx);
+    rx
+}
+
+impl MockNode {
+    pub fn new(key_pair: NodeKeyPair, block0: MockBlock) -> Self {
+        let state = State {
+            chain: MockChain::new(block0),
+            fragments: HashMap::new(),
+            peers: Vec::new(),
+            gossip: Vec::new(),
+            nonces: HashMap::new(),
+            protocols: HashMap::new(),
+            nonce_counter: 0,
+            authenticated: HashSet::new(),
+            block_subscribers: Vec::new(),
+            fragment_subscribers: Vec::new(),
+            inventory_subscribers: Vec::new(),
+            missing_fragments: Vec::new(),
+            fragment_statuses: HashMap::new(),
+            status_subscribers: Vec::new(),
+            gossip_subscribers: Vec::new(),
+        };
+        MockNode {
+            key_pair,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Get a snapshot of the node's chain.
+    pub fn chain(&self) -> MockChain {
+        self.state().chain.clone()
+    }
+
+    pub fn tip(&self) -> MockBlock {
+        self.state().chain.tip().clone()
+    }
+
+    /// Creates a block on top of the tip of the node's chain,
+    /// and announces it to the block subscribers.
+    pub fn add_block(&self, content: &[u8]) -> MockBlock {
+        let mut state = self.state();
+        let block = state.chain.add_block(content);
+        broadcast(
+            &mut state.block_subscribers,
+            BlockEvent::Announce(block.header()),
+        );
+        block
+    }
+
+    /// Adds a fragment to the node's pool, sends it to the fragment
+    /// subscribers and announces its ID to the inventory subscribers.
+    pub fn post_fragment(&self, fragment: Fragment) -> FragmentId {
+        let mut state = self.state();
+        let id = fragment_id(&fragment);
+        state.fragments.insert(id, fragment.clone());
+        broadcast(&mut state.fragment_subscribers, fragment);
+        broadcast(&mut state.inventory_subscribers, vec![id].into());
+        state.update_fragment_status(id, FragmentStatus::Pending);
+        id
+    }
+
+    /// Changes the status of the fragment, notifying the subscribers
+    /// to the status of the fragment.
+    pub fn set_fragment_status(&self, id: FragmentId, status: FragmentStatus) {
+        self.state().update_fragment_status(id, status);
+    }
+
+    /// IDs of fragments announced by the inventory subscribers
+    /// that are not in the node's pool.
+    pub fn missing_fragments(&self) -> Vec<FragmentId> {
+        let state = self.state();
+        state
+            .missing_fragments
+            .iter()
+            .filter(|id| !state.fragments.contains_key(id))
+            .copied()
+            .collect()
+    }
+
+    pub fn fragments(&self) -> Vec<Fragment> {
+        self.state().fragments.values().cloned().collect()
+    }
+
+    /// Adds a peer to the list served in response to peer requests.
+    pub fn add_peer(&self, peer: Peer) {
+        self.state().peers.push(peer);
+    }
+
+    /// Sends gossip to the gossip subscribers.
+    pub fn send_gossip(&self, nodes: gossip::Nodes) {
+        broadcast(&mut self.state().gossip_subscribers, Gossip { nodes });
+    }
+
+    /// Gossip nodes received from the subscribed peers.
+    pub fn received_gossip(&self) -> Vec<gossip::Node> {
+        self.state().gossip.clone()
+    }
+
+    /// The protocol negotiated with the peer in the latest handshake.
+    pub fn peer_protocol(&self, peer: &Peer) -> Option<Protocol> {
+        self.state().protocols.get(peer).copied()
+    }
+
+    /// Checks if the peer has completed client authentication.
+    pub fn is_authenticated(&self, peer: &Peer) -> bool {
+        self.state().authenticated.contains(peer)
+    }
+
+    /// Pulls the blocks missing from the node's chain up to the tip
+    /// of the peer node connected to with `client`.
+    pub async fn pull_from<T: Node>(&self, client: &mut Client<Channel<T>>) -> Result<(), Error> {
+        let checkpoints = self.state().chain.checkpoints();
+        let blocks = client.pull_blocks_to_tip(checkpoints.into()).await?;
+        blocks
+            .try_for_each(|block| future::ready(self.accept_block(block)))
+            .await
+    }
+
+    fn accept_block(&self, block: Block) -> Result<(), Error> {
+        let block = MockBlock::decode(block)?;
+        let mut state = self.state();
+        if state.chain.insert(block.clone())? {
+            broadcast(
+                &mut state.block_subscribers,
+                BlockEvent::Announce(block.header()),
+            );
+        }
+        Ok(())
+    }
+
+    // Reacts to a block announced by a subscribed peer by soliciting it,
+    // or requesting the missing headers of its branch.
+    fn process_announcement(&self, header: Header) -> Result<Option<BlockEvent>, Error> {
+        let (id, parent_id, _) = decode_header(&header)?;
+        let state = self.state();
+        if state.chain.contains(&id) {
+            Ok(None)
+        } else if state.chain.contains(&parent_id) {
+            Ok(Some(BlockEvent::Solicit(vec![id].into())))
+        } else {
+            Ok(Some(BlockEvent::Missing(ChainPullRequest {
+                from: state.chain.checkpoints().into(),
+                to: id,
+            })))
+        }
+    }
+
+    fn blocks_stream<I>(blocks: I) -> MockStream<Block>
+    where
+        I: IntoIterator<Item = Result<MockBlock, Error>>,
+    {
+        let blocks: Vec<_> = blocks
+            .into_iter()
+            .map(|res| res.map(|block| block.block()))
+            .collect();
+        Box::pin(stream::iter(blocks))
+    }
+
+    fn headers_stream<I>(blocks: I) -> MockStream<Header>
+    where
+        I: IntoIterator<Item = Result<MockBlock, Error>>,
+    {
+        let headers: Vec<_> = blocks
+            .into_iter()
+            .map(|res| res.map(|block| block.header()))
+            .collect();
+        Box::pin(stream::iter(headers))
+    }
+
+    fn get_block(&self, id: &BlockId) -> Result<MockBlock, Error> {
+        self.state()
+            .chain
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Error::new(Code::NotFound, format!("block {:?} not found", id)))
+    }
+
+    fn range(
+        &self,
+        from: &[BlockId],
+        to: &BlockId,
+    ) -> Result<Vec<Result<MockBlock, Error>>, Error> {
+        let blocks = self.state().chain.range(from, to)?;
+        Ok(blocks.into_iter().map(Ok).collect())
+    }
+}
+
+// Makes the inbound stream of a subscription `Sync`, as required of the
+// outbound stream it is merged into. The inner stream is only ever
+// accessed through a mutable reference, so the lock is never contended.
+struct SyncStream<S>(Mutex<S>);
+
+impl<S: Stream + Unpin> Stream for SyncStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let inner = self.get_mut().0.get_mut().unwrap();
+        Pin::new(inner).poll_next(cx)
+    }
+}
+
+// Merges the processing of an inbound subscription stream into
+// the outbound stream, which also receives the responses produced
+// by the handler for the inbound items.
+fn subscription<In, Out, F>(
+    inbound: PushStream<In>,
+    outbound: mpsc::UnboundedReceiver<Result<Out, Error>>,
+    mut handler: F,
+) -> MockStream<Out>
+where
+    In: Send + 'static,
+    Out: Send + Sync + 'static,
+    F: FnMut(In) -> Result<Option<Out>, Error> + Send + Sync + 'static,
+{
+    let responses = SyncStream(Mutex::new(inbound)).filter_map(move |item| {
+        let res = match item.and_then(&mut handler) {
+            Ok(response) => response.map(Ok),
+            Err(e) => Some(Err(e)),
+        };
+        future::ready(res)
+    });
+    Box::pin(stream::select(responses, outbound))
+}
+
+#[async_trait]
+impl Node for MockNode {
+    type BlockService = Self;
+    type FragmentService = Self;
+    type GossipService = Self;
+
+    async fn handshake(
+        &self,
+        peer: Peer,
+        nonce: &[u8],
+        protocol: Protocol,
+    ) -> Result<HandshakeResponse, Error> {
+        let mut state = self.state();
+        state.protocols.insert(peer.clone(), protocol);
+        state.nonce_counter += 1;
+        let server_nonce: Box<[u8]> = state.nonce_counter.to_be_bytes()[..].into();
+        state.nonces.insert(peer, server_nonce.clone());
+        Ok(HandshakeResponse {
+            block0_id: state.chain.block0_id(),
+            auth: self.key_pair.sign(nonce),
+            nonce: server_nonce,
+        })
+    }
+
+    async fn client_auth(&self, peer: Peer, auth: AuthenticatedNodeId) -> Result<(), Error> {
+        let mut state = self.state();
+        let nonce = state.nonces.remove(&peer).ok_or_else(|| {
+            Error::new(
+                Code::FailedPrecondition,
+                "handshake has not been performed by the peer",
+            )
+        })?;
+        auth.verify(&nonce)?;
+        state.authenticated.insert(peer);
+        Ok(())
+    }
+
+    fn block_service(&self) -> Option<&Self> {
+        Some(self)
+    }
+
+    fn fragment_service(&self) -> Option<&Self> {
+        Some(self)
+    }
+
+    fn gossip_service(&self) -> Option<&Self> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl BlockService for MockNode {
+    async fn tip(&self) -> Result<Header, Error> {
+        Ok(self.state().chain.tip().header())
+    }
+
+    type GetBlocksStream = MockStream<Block>;
+
+    async fn get_blocks(&self, ids: BlockIds) -> Result<Self::GetBlocksStream, Error> {
+        let blocks: Vec<_> = ids.iter().map(|id| self.get_block(id)).collect();
+        Ok(Self::blocks_stream(blocks))
+    }
+
+    type GetHeadersStream = MockStream<Header>;
+
+    async fn get_headers(&self, ids: BlockIds) -> Result<Self::GetHeadersStream, Error> {
+        let blocks: Vec<_> = ids.iter().map(|id| self.get_block(id)).collect();
+        Ok(Self::headers_stream(blocks))
+    }
+
+    type PullHeadersStream = MockStream<Header>;
+
+    async fn pull_headers(
+        &self,
+        from: BlockIds,
+        to: BlockId,
+    ) -> Result<Self::PullHeadersStream, Error> {
+        let blocks = self.range(&from, &to)?;
+        Ok(Self::headers_stream(blocks))
+    }
+
+    type PullBlocksStream = MockStream<Block>;
+
+    async fn pull_blocks(
+        &self,
+        from: BlockIds,
+        to: BlockId,
+    ) -> Result<Self::PullBlocksStream, Error> {
+        let blocks = self.range(&from, &to)?;
+        Ok(Self::blocks_stream(blocks))
+    }
+
+    type PullBlocksToTipStream = MockStream<Block>;
+
+    async fn pull_blocks_to_tip(
+        &self,
+        from: BlockIds,
+    ) -> Result<Self::PullBlocksToTipStream, Error> {
+        let tip_id = self.state().chain.tip().id();
+        let blocks = self.range(&from, &tip_id)?;
+        Ok(Self::blocks_stream(blocks))
+    }
+
+    async fn push_headers(&self, mut stream: PushStream<Header>) -> Result<(), Error> {
+        while let Some(header) = stream.try_next().await? {
+            decode_header(&header)?;
+        }
+        Ok(())
+    }
+
+    async fn upload_blocks(&self, mut stream: PushStream<Block>) -> Result<(), Error> {
+        while let Some(block) = stream.try_next().await? {
+            self.accept_block(block)?;
+        }
+        Ok(())
+    }
+
+    type SubscriptionStream = MockStream<BlockEvent>;
+
+    async fn block_subscription(
+        &self,
+        _subscriber: Peer,
+        stream: PushStream<Header>,
+    ) -> Result<Self::SubscriptionStream, Error> {
+        let outbound = subscribe(&mut self.state().block_subscribers);
+        let node = self.clone();
+        Ok(subscription(stream, outbound, move |header| {
+            node.process_announcement(header)
+        }))
+    }
+}
+
+#[async_trait]
+impl FragmentService for MockNode {
+    type GetFragmentsStream = MockStream<Fragment>;
+
+    async fn get_fragments(&self, ids: FragmentIds) -> Result<Self::GetFragmentsStream, Error> {
+        let state = self.state();
+        let fragments: Vec<_> = ids
+            .iter()
+            .map(|id| {
+                state.fragments.get(id).cloned().ok_or_else(|| {
+                    Error::new(Code::NotFound, format!("fragment {:?} not found", id))
+                })
+            })
+            .collect();
+        Ok(Box::pin(stream::iter(fragments)))
+    }
+
+    type SubscriptionStream = MockStream<Fragment>;
+
+    async fn fragment_subscription(
+        &self,
+        _subscriber: Peer,
+        stream: PushStream<Fragment>,
+    ) -> Result<Self::SubscriptionStream, Error> {
+        let outbound = subscribe(&mut self.state().fragment_subscribers);
+        let node = self.clone();
+        Ok(subscription(stream, outbound, move |fragment| {
+            let id = fragment_id(&fragment);
+            node.state().fragments.insert(id, fragment);
+            Ok(None)
+        }))
+    }
+
+    type StatusSubscriptionStream = MockStream<FragmentStatusEvent>;
+
+    async fn fragment_status_subscription(
+        &self,
+        _subscriber: Peer,
+        ids: FragmentIds,
+    ) -> Result<Self::StatusSubscriptionStream, Error> {
+        let (tx, rx) = mpsc::unbounded();
+        let mut state = self.state();
+        let mut waiting = HashSet::new();
+        for id in ids.iter() {
+            match state.fragment_statuses.get(id) {
+                Some(status) => {
+                    let event = FragmentStatusEvent {
+                        fragment: *id,
+                        status: status.clone(),
+                    };
+                    let _ = tx.unbounded_send(Ok(event));
+                    if !status.is_final() {
+                        waiting.insert(*id);
+                    }
+                }
+                None => {
+                    waiting.insert(*id);
+                }
+            }
+        }
+        if !waiting.is_empty() {
+            state
+                .status_subscribers
+                .push(StatusSubscriber { ids: waiting, tx });
+        }
+        Ok(Box::pin(rx))
+    }
+
+    type InventorySubscriptionStream = MockStream<FragmentIds>;
+
+    async fn fragment_inventory_subscription(
+        &self,
+        _subscriber: Peer,
+        stream: PushStream<FragmentIds>,
+    ) -> Result<Self::InventorySubscriptionStream, Error> {
+        let outbound = subscribe(&mut self.state().inventory_subscribers);
+        let node = self.clone();
+        Ok(subscription(stream, outbound, move |ids: FragmentIds| {
+            let mut state = node.state();
+            for id in ids.iter() {
+                if !state.fragments.contains_key(id) && !state.missing_fragments.contains(id) {
+                    state.missing_fragments.push(*id);
+                }
+            }
+            Ok(None)
+        }))
+    }
+}
+
+#[async_trait]
+impl GossipService for MockNode {
+    async fn peers(&self, limit: u32) -> Result<Peers, Error> {
+        let state = self.state();
+        let peers: Vec<_> = state.peers.iter().take(limit as usize).cloned().collect();
+        Ok(peers.into())
+    }
+
+    type SubscriptionStream = MockStream<Gossip>;
+
+    async fn gossip_subscription(
+        &self,
+        _subscriber: Peer,
+        stream: PushStream<Gossip>,
+    ) -> Result<Self::SubscriptionStream, Error> {
+        let outbound = subscribe(&mut self.state().gossip_subscribers);
+        let node = self.clone();
+        Ok(subscription(stream, outbound, move |gossip: Gossip| {
+            node.state().gossip.extend(gossip.nodes.into_vec());
+            Ok(None)
+        }))
+    }
+}
@@ -0,0 +1,103 @@
+//! Derivation of the `serialize_in` method building the encoding
+//! with `typed_bytes::ByteBuilder`.
+
+use crate::model::{match_variants, Body, Container, Encoding};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub fn expand(container: &Container) -> TokenStream {
+    let ident = &container.ident;
+    let (impl_generics, ty_generics, where_clause) = container.generics.split_for_impl();
+    let body = match &container.body {
+        Body::Struct { fields, .. } => {
+            let writes = fields.iter().map(|f| {
+                let member = &f.member;
+                build(&f.encoding, &quote!(&self.#member))
+            });
+            quote! {
+                #(#writes)*
+                bb
+            }
+        }
+        Body::Enum { tag, variants } => {
+            let method = tag.builder();
+            match_variants(variants, |v| {
+                let tag = v.tag_literal();
+                let writes = v.fields.iter().map(|f| {
+                    let binding = &f.binding;
+                    build(&f.encoding, &quote!(#binding))
+                });
+                quote! {
+                    let bb = bb.#method(#tag);
+                    #(#writes)*
+                    bb
+                }
+            })
+        }
+    };
+    quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            pub fn serialize_in(
+                &self,
+                bb: ::typed_bytes::ByteBuilder<Self>,
+            ) -> ::typed_bytes::ByteBuilder<Self> {
+                #body
+            }
+        }
+    }
+}
+
+// Generates a statement rebinding `bb` to the builder with the value
+// referenced by `value` appended.
+fn build(encoding: &Encoding, value: &TokenStream) -> TokenStream {
+    match encoding {
+        Encoding::Int {
+            width,
+            convert: false,
+            ..
+        } => {
+            let method = width.builder();
+            quote!(let bb = bb.#method(*#value);)
+        }
+        Encoding::Int {
+            width,
+            ty,
+            convert: true,
+        } => {
+            let method = width.builder();
+            let wty = width.ty();
+            quote! {
+                let bb = bb.#method(
+                    <#wty as ::std::convert::TryFrom<#ty>>::try_from(*#value)
+                        .expect("integer value out of range"),
+                );
+            }
+        }
+        Encoding::Bytes { .. } => quote!(let bb = bb.bytes(#value);),
+        Encoding::FromBytes { .. } => quote! {
+            let bb = bb.bytes(<_ as ::std::convert::AsRef<[u8]>>::as_ref(#value));
+        },
+        Encoding::Array { elem, .. } => {
+            let elem = build(elem, &quote!(elem));
+            quote! {
+                let bb = (#value).iter().fold(bb, |bb, elem| {
+                    #elem
+                    bb
+                });
+            }
+        }
+        Encoding::Seq { prefix, elem, .. } => {
+            let method = prefix.builder();
+            let elem = build(elem, &quote!(elem));
+            quote! {
+                let bb = bb.#method((#value).iter(), |bb, elem| {
+                    #elem
+                    bb
+                });
+            }
+        }
+        Encoding::Nested { .. } => quote! {
+            let bb = bb.sub(|bb| (#value).serialize_in(bb));
+        },
+    }
+}
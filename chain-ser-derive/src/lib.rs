@@ -0,0 +1,112 @@
+//! Derive macros for the binary encodings of `chain-ser`.
+//!
+//! * `#[derive(Readable)]` implements `chain_ser::mempack::Readable`,
+//!   decoding from a `ReadBuf`;
+//! * `#[derive(Serialize)]` implements `chain_ser::deser::Serialize`,
+//!   encoding with a `chain_ser::packer::Codec`;
+//! * `#[derive(ByteBuilder)]` generates an inherent method
+//!   `serialize_in(&self, bb: ByteBuilder<Self>) -> ByteBuilder<Self>`
+//!   appending the encoding to a `typed_bytes::ByteBuilder`.
+//!
+//! All derives produce the same encoding, so a type deriving `Readable`
+//! along with either of the others reads back what it writes.
+//! The crates `chain-ser` and `typed-bytes` must be dependencies of
+//! the crate using the respective derives.
+//!
+//! # Encoding
+//!
+//! The fields of structs and enum variants are encoded in the order
+//! of declaration, with no padding:
+//!
+//! * unsigned integers are encoded in big endian;
+//! * byte arrays `[u8; N]` are written as is, and arrays of other types
+//!   as their elements in sequence;
+//! * fields of other types are encoded with the implementation of
+//!   the derived trait for the type, or the `serialize_in` method
+//!   for `ByteBuilder`;
+//! * enum variants are preceded by their tag, by default the index
+//!   of the variant encoded as `u8`.
+//!
+//! The encoding is customized with `#[codec(...)]` attributes:
+//!
+//! * on fields, `u8`, `u16`, `u32`, `u64` or `u128` encode an integer
+//!   field with the given width. The value is converted with `TryFrom`,
+//!   failing on values that do not fit;
+//! * on fields of type `Vec<T>` or `Box<[T]>`, `iter8`, `iter16` or
+//!   `iter32` encode the elements preceded by their number, as `u8`,
+//!   `u16` or `u32` respectively;
+//! * on fields, `bytes = N` encodes the field as `N` bytes, written from
+//!   its `AsRef<[u8]>` implementation and read with `From<[u8; N]>`.
+//!   This allows fields of types from other crates, such as digests,
+//!   to be encoded without implementing the derived traits;
+//! * on enums, `u8`, `u16`, `u32`, `u64` or `u128` set the width of
+//!   the tag;
+//! * on enum variants, `tag = N` sets the tag of the variant;
+//! * on the type, `error = "path::to::Error"` sets the error type of the
+//!   `Serialize` implementation, `std::io::Error` by default. The type
+//!   must be convertible from the errors of the field types.
+//!
+//! Decoding an enum fails with `ReadError::UnknownTag` on a tag that
+//! matches no variant. Tags too wide for the error are reported in
+//! `ReadError::StructureInvalid` instead.
+//!
+//! Arrays of types other than `u8` are decoded in place of a default
+//! value, so the array type must implement `Default`.
+//!
+//! ```ignore
+//! use chain_ser_derive::{ByteBuilder, Readable, Serialize};
+//!
+//! #[derive(Readable, Serialize, ByteBuilder)]
+//! pub struct Committee {
+//!     #[codec(u8)]
+//!     threshold: usize,
+//!     #[codec(iter8)]
+//!     members: Vec<[u8; 32]>,
+//! }
+//!
+//! #[derive(Readable, Serialize, ByteBuilder)]
+//! #[codec(u16)]
+//! pub enum Action {
+//!     #[codec(tag = 1)]
+//!     Transfer { to: [u8; 32], value: u64 },
+//!     #[codec(tag = 2)]
+//!     Replace(Committee),
+//! }
+//! ```
+
+extern crate proc_macro;
+
+mod byte_builder;
+mod model;
+mod readable;
+mod serialize;
+
+use model::Container;
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+fn expand<F>(input: TokenStream, f: F) -> TokenStream
+where
+    F: FnOnce(&Container) -> proc_macro2::TokenStream,
+{
+    let input = parse_macro_input!(input as DeriveInput);
+    match Container::from_input(&input) {
+        Ok(container) => f(&container).into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(Readable, attributes(codec))]
+pub fn derive_readable(input: TokenStream) -> TokenStream {
+    expand(input, readable::expand)
+}
+
+#[proc_macro_derive(Serialize, attributes(codec))]
+pub fn derive_serialize(input: TokenStream) -> TokenStream {
+    expand(input, serialize::expand)
+}
+
+#[proc_macro_derive(ByteBuilder, attributes(codec))]
+pub fn derive_byte_builder(input: TokenStream) -> TokenStream {
+    expand(input, byte_builder::expand)
+}
@@ -0,0 +1,493 @@
+//! Parsing of the derive input into a description of the encoding.
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, Fields, GenericArgument, Lit, Meta, NestedMeta, Path,
+    PathArguments, Type,
+};
+
+const ATTR: &str = "codec";
+
+/// Width of an encoded integer, in big endian.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Width {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+}
+
+impl Width {
+    fn from_ident(ident: &Ident) -> Option<Self> {
+        match ident.to_string().as_str() {
+            "u8" => Some(Width::U8),
+            "u16" => Some(Width::U16),
+            "u32" => Some(Width::U32),
+            "u64" => Some(Width::U64),
+            "u128" => Some(Width::U128),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Width::U8 => "u8",
+            Width::U16 => "u16",
+            Width::U32 => "u32",
+            Width::U64 => "u64",
+            Width::U128 => "u128",
+        }
+    }
+
+    fn max_value(self) -> u128 {
+        match self {
+            Width::U8 => u8::MAX.into(),
+            Width::U16 => u16::MAX.into(),
+            Width::U32 => u32::MAX.into(),
+            Width::U64 => u64::MAX.into(),
+            Width::U128 => u128::MAX,
+        }
+    }
+
+    /// The integer type.
+    pub fn ty(self) -> Ident {
+        Ident::new(self.name(), Span::call_site())
+    }
+
+    /// The `ReadBuf` and `Codec` method reading the integer.
+    pub fn getter(self) -> Ident {
+        format_ident!("get_{}", self.name())
+    }
+
+    /// The `Codec` method writing the integer.
+    pub fn putter(self) -> Ident {
+        format_ident!("put_{}", self.name())
+    }
+
+    /// The `ByteBuilder` method writing the integer.
+    pub fn builder(self) -> Ident {
+        self.ty()
+    }
+}
+
+/// Width of the length prefix of a collection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LengthPrefix {
+    Iter8,
+    Iter16,
+    Iter32,
+}
+
+impl LengthPrefix {
+    fn from_ident(ident: &Ident) -> Option<Self> {
+        match ident.to_string().as_str() {
+            "iter8" => Some(LengthPrefix::Iter8),
+            "iter16" => Some(LengthPrefix::Iter16),
+            "iter32" => Some(LengthPrefix::Iter32),
+            _ => None,
+        }
+    }
+
+    pub fn width(self) -> Width {
+        match self {
+            LengthPrefix::Iter8 => Width::U8,
+            LengthPrefix::Iter16 => Width::U16,
+            LengthPrefix::Iter32 => Width::U32,
+        }
+    }
+
+    /// The `ByteBuilder` method writing the collection.
+    pub fn builder(self) -> Ident {
+        match self {
+            LengthPrefix::Iter8 => format_ident!("iter8"),
+            LengthPrefix::Iter16 => format_ident!("iter16"),
+            LengthPrefix::Iter32 => format_ident!("iter32"),
+        }
+    }
+}
+
+/// The encoding of a value.
+pub enum Encoding {
+    /// An integer of the given width. If the width differs from the
+    /// width of the type, the value is converted with `TryFrom`.
+    Int {
+        width: Width,
+        ty: Type,
+        convert: bool,
+    },
+    /// A byte array, written as is.
+    Bytes { len: Expr },
+    /// A value converted from a byte array of the given length, and
+    /// written as the bytes it references with `AsRef<[u8]>`.
+    FromBytes { len: Expr },
+    /// A fixed-size array of other values, written in sequence.
+    Array {
+        elem: Box<Encoding>,
+        elem_ty: Type,
+        len: Expr,
+    },
+    /// A `Vec` or a boxed slice, preceded by the number of elements.
+    Seq {
+        prefix: LengthPrefix,
+        elem: Box<Encoding>,
+        boxed: bool,
+    },
+    /// A value of a type implementing the traits itself.
+    Nested { ty: Type },
+}
+
+/// A field of a struct or an enum variant.
+pub struct Field {
+    /// How the field is accessed or bound in patterns.
+    pub member: syn::Member,
+    /// The name the field is bound to in enum variant patterns.
+    pub binding: Ident,
+    pub encoding: Encoding,
+}
+
+pub enum Shape {
+    Named,
+    Unnamed,
+    Unit,
+}
+
+pub struct Variant {
+    pub ident: Ident,
+    pub tag: u128,
+    pub shape: Shape,
+    pub fields: Vec<Field>,
+}
+
+pub enum Body {
+    Struct { shape: Shape, fields: Vec<Field> },
+    Enum { tag: Width, variants: Vec<Variant> },
+}
+
+pub struct Container {
+    pub ident: Ident,
+    pub generics: syn::Generics,
+    /// The error type of the `Serialize` implementation.
+    pub error: TokenStream,
+    pub body: Body,
+}
+
+impl Variant {
+    /// The tag as a literal for the tag integer type.
+    pub fn tag_literal(&self) -> proc_macro2::Literal {
+        proc_macro2::Literal::u128_unsuffixed(self.tag)
+    }
+}
+
+/// The `#[codec(...)]` attribute items, in order.
+fn codec_items(attrs: &[Attribute]) -> syn::Result<Vec<NestedMeta>> {
+    let mut items = Vec::new();
+    for attr in attrs {
+        if !attr.path.is_ident(ATTR) {
+            continue;
+        }
+        match attr.parse_meta()? {
+            Meta::List(list) => items.extend(list.nested),
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected attribute arguments: #[codec(...)]",
+                ))
+            }
+        }
+    }
+    Ok(items)
+}
+
+fn path_ident(path: &Path) -> Option<&Ident> {
+    path.get_ident()
+}
+
+fn unsupported(item: &NestedMeta) -> syn::Error {
+    syn::Error::new_spanned(item, "unsupported codec attribute")
+}
+
+fn parse_tag(lit: &Lit) -> syn::Result<u128> {
+    match lit {
+        Lit::Int(lit) => lit.base10_parse(),
+        _ => Err(syn::Error::new_spanned(lit, "expected an integer tag")),
+    }
+}
+
+impl Container {
+    pub fn from_input(input: &DeriveInput) -> syn::Result<Self> {
+        let mut error = quote!(::std::io::Error);
+        let mut tag = None;
+        for item in codec_items(&input.attrs)? {
+            match &item {
+                NestedMeta::Meta(Meta::Path(path)) => {
+                    match path_ident(path).and_then(Width::from_ident) {
+                        Some(width) => tag = Some((width, path.span())),
+                        None => return Err(unsupported(&item)),
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("error") => {
+                    match &nv.lit {
+                        Lit::Str(s) => error = s.parse::<Path>()?.into_token_stream(),
+                        lit => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "expected the error type path as a string",
+                            ))
+                        }
+                    }
+                }
+                _ => return Err(unsupported(&item)),
+            }
+        }
+
+        let body = match &input.data {
+            Data::Struct(data) => {
+                if let Some((_, span)) = tag {
+                    return Err(syn::Error::new(
+                        span,
+                        "the tag width can only be specified for enums",
+                    ));
+                }
+                let (shape, fields) = parse_fields(&data.fields)?;
+                Body::Struct { shape, fields }
+            }
+            Data::Enum(data) => {
+                let tag = tag.map(|(width, _)| width).unwrap_or(Width::U8);
+                let mut variants = Vec::with_capacity(data.variants.len());
+                for (index, variant) in data.variants.iter().enumerate() {
+                    let mut value = index as u128;
+                    for item in codec_items(&variant.attrs)? {
+                        match &item {
+                            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("tag") => {
+                                value = parse_tag(&nv.lit)?;
+                            }
+                            _ => return Err(unsupported(&item)),
+                        }
+                    }
+                    if value > tag.max_value() {
+                        return Err(syn::Error::new_spanned(
+                            variant,
+                            format!("tag {} does not fit in {}", value, tag.name()),
+                        ));
+                    }
+                    if let Some(other) = variants.iter().find(|v: &&Variant| v.tag == value) {
+                        return Err(syn::Error::new_spanned(
+                            variant,
+                            format!("tag {} is already used by {}", value, other.ident),
+                        ));
+                    }
+                    let (shape, fields) = parse_fields(&variant.fields)?;
+                    variants.push(Variant {
+                        ident: variant.ident.clone(),
+                        tag: value,
+                        shape,
+                        fields,
+                    });
+                }
+                Body::Enum { tag, variants }
+            }
+            Data::Union(data) => {
+                return Err(syn::Error::new_spanned(
+                    data.union_token,
+                    "codec derives are not supported for unions",
+                ))
+            }
+        };
+
+        Ok(Container {
+            ident: input.ident.clone(),
+            generics: input.generics.clone(),
+            error,
+            body,
+        })
+    }
+}
+
+fn parse_fields(fields: &Fields) -> syn::Result<(Shape, Vec<Field>)> {
+    let shape = match fields {
+        Fields::Named(_) => Shape::Named,
+        Fields::Unnamed(_) => Shape::Unnamed,
+        Fields::Unit => Shape::Unit,
+    };
+    let fields = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let (member, binding) = match &field.ident {
+                Some(ident) => (
+                    syn::Member::Named(ident.clone()),
+                    format_ident!("__{}", ident.unraw()),
+                ),
+                None => (
+                    syn::Member::Unnamed(index.into()),
+                    format_ident!("__{}", index),
+                ),
+            };
+            let encoding = field_encoding(field)?;
+            Ok(Field {
+                member,
+                binding,
+                encoding,
+            })
+        })
+        .collect::<syn::Result<_>>()?;
+    Ok((shape, fields))
+}
+
+fn field_encoding(field: &syn::Field) -> syn::Result<Encoding> {
+    let mut encoding = None;
+    for item in codec_items(&field.attrs)? {
+        let parsed = match &item {
+            NestedMeta::Meta(Meta::Path(path)) => {
+                let ident = path_ident(path).ok_or_else(|| unsupported(&item))?;
+                if let Some(width) = Width::from_ident(ident) {
+                    // No conversion is needed if the field type
+                    // is the integer type of the same width.
+                    let convert = match natural_encoding(&field.ty) {
+                        Encoding::Int { width: natural, .. } => natural != width,
+                        _ => true,
+                    };
+                    Encoding::Int {
+                        width,
+                        ty: field.ty.clone(),
+                        convert,
+                    }
+                } else if let Some(prefix) = LengthPrefix::from_ident(ident) {
+                    let (elem_ty, boxed) = collection_element(&field.ty).ok_or_else(|| {
+                        syn::Error::new_spanned(
+                            &field.ty,
+                            "length-prefixed encoding requires a Vec or a boxed slice",
+                        )
+                    })?;
+                    Encoding::Seq {
+                        prefix,
+                        elem: Box::new(natural_encoding(elem_ty)),
+                        boxed,
+                    }
+                } else {
+                    return Err(unsupported(&item));
+                }
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("bytes") => match &nv.lit {
+                Lit::Int(len) => Encoding::FromBytes {
+                    len: syn::parse_quote!(#len),
+                },
+                lit => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "expected the length of the byte array",
+                    ))
+                }
+            },
+            _ => return Err(unsupported(&item)),
+        };
+        if encoding.replace(parsed).is_some() {
+            return Err(syn::Error::new_spanned(
+                item,
+                "only one encoding can be specified for a field",
+            ));
+        }
+    }
+    Ok(encoding.unwrap_or_else(|| natural_encoding(&field.ty)))
+}
+
+/// The encoding of a type without attributes.
+fn natural_encoding(ty: &Type) -> Encoding {
+    match ty {
+        Type::Path(tp) if tp.qself.is_none() => {
+            if let Some(width) = path_ident(&tp.path).and_then(Width::from_ident) {
+                return Encoding::Int {
+                    width,
+                    ty: ty.clone(),
+                    convert: false,
+                };
+            }
+        }
+        Type::Array(array) => {
+            let len = array.len.clone();
+            return match natural_encoding(&array.elem) {
+                Encoding::Int {
+                    width: Width::U8, ..
+                } => Encoding::Bytes { len },
+                elem => Encoding::Array {
+                    elem: Box::new(elem),
+                    elem_ty: (*array.elem).clone(),
+                    len,
+                },
+            };
+        }
+        Type::Paren(paren) => return natural_encoding(&paren.elem),
+        Type::Group(group) => return natural_encoding(&group.elem),
+        _ => {}
+    }
+    Encoding::Nested { ty: ty.clone() }
+}
+
+/// Gets the element type of `Vec<T>` or `Box<[T]>`, and whether
+/// the collection is a boxed slice.
+fn collection_element(ty: &Type) -> Option<(&Type, bool)> {
+    let tp = match ty {
+        Type::Path(tp) if tp.qself.is_none() => tp,
+        _ => return None,
+    };
+    let segment = tp.path.segments.last()?;
+    let arg = match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => &args.args[0],
+        _ => return None,
+    };
+    let arg = match arg {
+        GenericArgument::Type(arg) => arg,
+        _ => return None,
+    };
+    if segment.ident == "Vec" {
+        Some((arg, false))
+    } else if segment.ident == "Box" {
+        match arg {
+            Type::Slice(slice) => Some((&slice.elem, true)),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+impl Variant {
+    /// Pattern matching a reference to the variant, binding the fields
+    /// to their `binding` names.
+    pub fn pattern(&self) -> TokenStream {
+        let ident = &self.ident;
+        let bindings = self.fields.iter().map(|f| &f.binding);
+        match self.shape {
+            Shape::Named => {
+                let members = self.fields.iter().map(|f| &f.member);
+                quote!(Self::#ident { #(#members: #bindings),* })
+            }
+            Shape::Unnamed => quote!(Self::#ident(#(#bindings),*)),
+            Shape::Unit => quote!(Self::#ident),
+        }
+    }
+}
+
+/// Matches `self` over the arms generated for the variants.
+pub fn match_variants<F>(variants: &[Variant], mut arm: F) -> TokenStream
+where
+    F: FnMut(&Variant) -> TokenStream,
+{
+    if variants.is_empty() {
+        return quote!(match *self {});
+    }
+    let arms = variants.iter().map(|v| {
+        let pattern = v.pattern();
+        let body = arm(v);
+        quote!(#pattern => { #body })
+    });
+    quote! {
+        match self {
+            #(#arms)*
+        }
+    }
+}
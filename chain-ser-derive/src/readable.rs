@@ -0,0 +1,139 @@
+//! Derivation of `chain_ser::mempack::Readable`.
+
+use crate::model::{Body, Container, Encoding, Field, Shape, Width};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub fn expand(container: &Container) -> TokenStream {
+    let ident = &container.ident;
+    let (impl_generics, ty_generics, where_clause) = container.generics.split_for_impl();
+    let body = match &container.body {
+        Body::Struct { shape, fields } => {
+            let value = construct(quote!(Self), shape, fields);
+            quote!(Ok(#value))
+        }
+        Body::Enum { tag, variants } => {
+            let get = tag.getter();
+            let arms = variants.iter().map(|v| {
+                let tag = v.tag_literal();
+                let variant = &v.ident;
+                let value = construct(quote!(Self::#variant), &v.shape, &v.fields);
+                quote!(#tag => Ok(#value),)
+            });
+            // tags wider than `ReadError::UnknownTag` can carry are
+            // reported in full in the message of a structure error
+            let unknown = match tag {
+                Width::U64 | Width::U128 => quote! {
+                    match <u32 as ::std::convert::TryFrom<_>>::try_from(tag) {
+                        Ok(tag) => ::chain_ser::mempack::ReadError::UnknownTag(tag),
+                        Err(_) => ::chain_ser::mempack::ReadError::StructureInvalid(
+                            ::std::format!("Unknown tag: {}", tag),
+                        ),
+                    }
+                },
+                _ => quote! {
+                    ::chain_ser::mempack::ReadError::UnknownTag(::std::convert::From::from(tag))
+                },
+            };
+            quote! {
+                match buf.#get()? {
+                    #(#arms)*
+                    tag => Err(#unknown),
+                }
+            }
+        }
+    };
+    quote! {
+        impl #impl_generics ::chain_ser::mempack::Readable for #ident #ty_generics #where_clause {
+            fn read(
+                buf: &mut ::chain_ser::mempack::ReadBuf,
+            ) -> ::std::result::Result<Self, ::chain_ser::mempack::ReadError> {
+                #body
+            }
+        }
+    }
+}
+
+// The fields are read in the order of declaration, which is the order
+// of evaluation of the struct expression.
+fn construct(path: TokenStream, shape: &Shape, fields: &[Field]) -> TokenStream {
+    let values = fields.iter().map(|f| read(&f.encoding));
+    match shape {
+        Shape::Named => {
+            let members = fields.iter().map(|f| &f.member);
+            quote!(#path { #(#members: #values),* })
+        }
+        Shape::Unnamed => quote!(#path(#(#values),*)),
+        Shape::Unit => path,
+    }
+}
+
+fn read(encoding: &Encoding) -> TokenStream {
+    match encoding {
+        Encoding::Int {
+            width,
+            convert: false,
+            ..
+        } => {
+            let get = width.getter();
+            quote!(buf.#get()?)
+        }
+        Encoding::Int {
+            width,
+            ty,
+            convert: true,
+        } => {
+            let get = width.getter();
+            let wty = width.ty();
+            quote! {
+                <#ty as ::std::convert::TryFrom<#wty>>::try_from(buf.#get()?).map_err(|_| {
+                    ::chain_ser::mempack::ReadError::StructureInvalid(
+                        ::std::string::String::from("integer value out of range"),
+                    )
+                })?
+            }
+        }
+        Encoding::Bytes { len } => quote! {{
+            let mut bytes = [0u8; #len];
+            buf.copy_to_slice_mut(&mut bytes)?;
+            bytes
+        }},
+        Encoding::FromBytes { len } => quote! {{
+            let mut bytes = [0u8; #len];
+            buf.copy_to_slice_mut(&mut bytes)?;
+            ::std::convert::From::from(bytes)
+        }},
+        Encoding::Array { elem, elem_ty, len } => {
+            let elem = read(elem);
+            quote! {{
+                let mut array: [#elem_ty; #len] = ::std::default::Default::default();
+                for elem in array.iter_mut() {
+                    *elem = #elem;
+                }
+                array
+            }}
+        }
+        Encoding::Seq {
+            prefix,
+            elem,
+            boxed,
+        } => {
+            let get = prefix.width().getter();
+            let elem = read(elem);
+            let collect = if *boxed {
+                quote!(elems.into_boxed_slice())
+            } else {
+                quote!(elems)
+            };
+            quote! {{
+                let len = buf.#get()? as usize;
+                let mut elems = ::std::vec::Vec::new();
+                for _ in 0..len {
+                    elems.push(#elem);
+                }
+                #collect
+            }}
+        }
+        Encoding::Nested { ty } => quote!(<#ty as ::chain_ser::mempack::Readable>::read(buf)?),
+    }
+}
@@ -0,0 +1,125 @@
+//! Derivation of `chain_ser::deser::Serialize`, writing with
+//! `chain_ser::packer::Codec`.
+
+use crate::model::{match_variants, Body, Container, Encoding, Field};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub fn expand(container: &Container) -> TokenStream {
+    let ident = &container.ident;
+    let error = &container.error;
+    let (impl_generics, ty_generics, where_clause) = container.generics.split_for_impl();
+    let body = match &container.body {
+        Body::Struct { fields, .. } if fields.is_empty() => quote! {
+            let _ = writer;
+        },
+        Body::Struct { fields, .. } => {
+            let writes = fields.iter().map(|f| {
+                let member = &f.member;
+                write(&f.encoding, &quote!(&self.#member))
+            });
+            quote! {
+                let mut codec = ::chain_ser::packer::Codec::new(writer);
+                #(#writes)*
+            }
+        }
+        Body::Enum { tag, variants } => {
+            let put = tag.putter();
+            let arms = match_variants(variants, |v| {
+                let tag = v.tag_literal();
+                let writes = write_fields(&v.fields);
+                quote! {
+                    codec.#put(#tag)?;
+                    #writes
+                }
+            });
+            quote! {
+                let mut codec = ::chain_ser::packer::Codec::new(writer);
+                #arms
+            }
+        }
+    };
+    quote! {
+        impl #impl_generics ::chain_ser::deser::Serialize for #ident #ty_generics #where_clause {
+            type Error = #error;
+
+            fn serialize<__W: ::std::io::Write>(
+                &self,
+                writer: __W,
+            ) -> ::std::result::Result<(), Self::Error> {
+                #body
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_fields(fields: &[Field]) -> TokenStream {
+    let writes = fields.iter().map(|f| {
+        let binding = &f.binding;
+        write(&f.encoding, &quote!(#binding))
+    });
+    quote!(#(#writes)*)
+}
+
+fn invalid_data(msg: &str) -> TokenStream {
+    quote!(::std::io::Error::new(::std::io::ErrorKind::InvalidData, #msg))
+}
+
+// Generates the statements writing the value referenced by `value`.
+fn write(encoding: &Encoding, value: &TokenStream) -> TokenStream {
+    match encoding {
+        Encoding::Int {
+            width,
+            convert: false,
+            ..
+        } => {
+            let put = width.putter();
+            quote!(codec.#put(*#value)?;)
+        }
+        Encoding::Int {
+            width,
+            ty,
+            convert: true,
+        } => {
+            let put = width.putter();
+            let wty = width.ty();
+            let err = invalid_data("integer value out of range");
+            quote! {
+                codec.#put(
+                    <#wty as ::std::convert::TryFrom<#ty>>::try_from(*#value).map_err(|_| #err)?,
+                )?;
+            }
+        }
+        Encoding::Bytes { .. } => quote!(codec.put_bytes(#value)?;),
+        Encoding::FromBytes { .. } => quote! {
+            codec.put_bytes(<_ as ::std::convert::AsRef<[u8]>>::as_ref(#value))?;
+        },
+        Encoding::Array { elem, .. } => {
+            let elem = write(elem, &quote!(elem));
+            quote! {
+                for elem in (#value).iter() {
+                    #elem
+                }
+            }
+        }
+        Encoding::Seq { prefix, elem, .. } => {
+            let width = prefix.width();
+            let put = width.putter();
+            let wty = width.ty();
+            let err = invalid_data("too many elements to encode");
+            let elem = write(elem, &quote!(elem));
+            quote! {
+                let len = <#wty as ::std::convert::TryFrom<usize>>::try_from((#value).len())
+                    .map_err(|_| #err)?;
+                codec.#put(len)?;
+                for elem in (#value).iter() {
+                    #elem
+                }
+            }
+        }
+        Encoding::Nested { .. } => quote! {
+            ::chain_ser::deser::Serialize::serialize(#value, &mut codec)?;
+        },
+    }
+}
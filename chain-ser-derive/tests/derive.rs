@@ -0,0 +1,216 @@
+use chain_ser::deser::Serialize as _;
+use chain_ser::mempack::{ReadBuf, ReadError, Readable as _};
+use chain_ser_derive::{ByteBuilder, Readable, Serialize};
+use typed_bytes::ByteBuilder;
+
+use std::fmt::Debug;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Readable, Serialize, ByteBuilder)]
+struct Point {
+    x: u32,
+    y: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Serialize, ByteBuilder)]
+struct Committee {
+    #[codec(u8)]
+    threshold: usize,
+    #[codec(iter8)]
+    members: Vec<[u8; 4]>,
+    #[codec(iter16)]
+    weights: Box<[u64]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Serialize, ByteBuilder)]
+struct Shape(#[codec(u16)] u8, [Point; 2], #[codec(iter32)] Vec<Point>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Serialize, ByteBuilder)]
+struct Marker;
+
+/// A type with no encoding of its own, converted from and to bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Digest([u8; 4]);
+
+impl From<[u8; 4]> for Digest {
+    fn from(bytes: [u8; 4]) -> Self {
+        Digest(bytes)
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Serialize, ByteBuilder)]
+struct Tagged {
+    #[codec(bytes = 4)]
+    digest: Digest,
+    #[codec(u8)]
+    index: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Serialize, ByteBuilder)]
+enum Simple {
+    A,
+    B(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Serialize, ByteBuilder)]
+#[codec(u16)]
+enum Action {
+    #[codec(tag = 1)]
+    Transfer { to: [u8; 4], value: u64 },
+    #[codec(tag = 0x100)]
+    Replace(Committee),
+    #[codec(tag = 7)]
+    Halt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Readable, Serialize, ByteBuilder)]
+#[codec(u64)]
+enum Wide {
+    #[codec(tag = 0x1_0000_0000)]
+    Far,
+}
+
+fn check_encoding<T>(value: T, expected: &[u8]) -> T
+where
+    T: chain_ser::mempack::Readable + chain_ser::deser::Serialize + Debug + PartialEq,
+{
+    assert_eq!(value.serialize_as_vec().unwrap(), expected);
+    let mut buf = ReadBuf::from(expected);
+    let decoded = T::read(&mut buf).unwrap();
+    buf.expect_end().unwrap();
+    assert_eq!(decoded, value);
+    value
+}
+
+#[test]
+fn struct_fields_in_order() {
+    let point = check_encoding(Point { x: 1, y: 2 }, &[0, 0, 0, 1, 0, 2]);
+    let built = point.serialize_in(ByteBuilder::new()).finalize_as_vec();
+    assert_eq!(built, [0, 0, 0, 1, 0, 2]);
+}
+
+#[test]
+fn length_prefixed_collections() {
+    let committee = Committee {
+        threshold: 2,
+        members: vec![[1; 4], [2; 4]],
+        weights: vec![3].into_boxed_slice(),
+    };
+    let expected = [
+        2, // threshold
+        2, 1, 1, 1, 1, 2, 2, 2, 2, // members
+        0, 1, 0, 0, 0, 0, 0, 0, 0, 3, // weights
+    ];
+    let committee = check_encoding(committee, &expected);
+    let built = committee.serialize_in(ByteBuilder::new()).finalize_as_vec();
+    assert_eq!(built, expected);
+}
+
+#[test]
+fn tuple_struct_with_arrays() {
+    let shape = Shape(
+        5,
+        [Point { x: 1, y: 2 }, Point { x: 3, y: 4 }],
+        vec![Point { x: 5, y: 6 }],
+    );
+    let expected = [
+        0, 5, // converted integer
+        0, 0, 0, 1, 0, 2, 0, 0, 0, 3, 0, 4, // array
+        0, 0, 0, 1, 0, 0, 0, 5, 0, 6, // sequence
+    ];
+    let shape = check_encoding(shape, &expected);
+    let built = shape.serialize_in(ByteBuilder::new()).finalize_as_vec();
+    assert_eq!(built, expected);
+}
+
+#[test]
+fn fields_converted_from_bytes() {
+    let tagged = Tagged {
+        digest: Digest([1, 2, 3, 4]),
+        index: 5,
+    };
+    let tagged = check_encoding(tagged, &[1, 2, 3, 4, 5]);
+    let built = tagged.serialize_in(ByteBuilder::new()).finalize_as_vec();
+    assert_eq!(built, [1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn unit_struct_is_empty() {
+    check_encoding(Marker, &[]);
+    assert!(Marker
+        .serialize_in(ByteBuilder::new())
+        .finalize_as_vec()
+        .is_empty());
+}
+
+#[test]
+fn enum_tags() {
+    check_encoding(Simple::A, &[0]);
+    check_encoding(Simple::B(9), &[1, 9]);
+
+    let transfer = Action::Transfer {
+        to: [1, 2, 3, 4],
+        value: 5,
+    };
+    let expected = [0, 1, 1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 5];
+    let transfer = check_encoding(transfer, &expected);
+    let built = transfer.serialize_in(ByteBuilder::new()).finalize_as_vec();
+    assert_eq!(built, expected);
+
+    let replace = Action::Replace(Committee {
+        threshold: 1,
+        members: vec![],
+        weights: Box::new([]),
+    });
+    let expected = [1, 0, 1, 0, 0, 0];
+    let replace = check_encoding(replace, &expected);
+    let built = replace.serialize_in(ByteBuilder::new()).finalize_as_vec();
+    assert_eq!(built, expected);
+
+    check_encoding(Action::Halt, &[0, 7]);
+}
+
+#[test]
+fn unknown_tag_is_rejected() {
+    let mut buf = ReadBuf::from(&[0, 2]);
+    assert_eq!(Action::read(&mut buf), Err(ReadError::UnknownTag(2)));
+
+    check_encoding(Wide::Far, &[0, 0, 0, 1, 0, 0, 0, 0]);
+    let mut buf = ReadBuf::from(&[0, 0, 0, 0, 0, 0, 0, 3]);
+    assert_eq!(Wide::read(&mut buf), Err(ReadError::UnknownTag(3)));
+    let mut buf = ReadBuf::from(&[0, 0, 0, 2, 0, 0, 0, 3]);
+    assert_eq!(
+        Wide::read(&mut buf),
+        Err(ReadError::StructureInvalid(
+            "Unknown tag: 8589934595".to_string()
+        ))
+    );
+}
+
+#[test]
+fn out_of_range_values_are_rejected() {
+    let committee = Committee {
+        threshold: 256,
+        members: vec![],
+        weights: Box::new([]),
+    };
+    assert!(committee.serialize_as_vec().is_err());
+
+    let committee = Committee {
+        threshold: 1,
+        members: vec![[0; 4]; 256],
+        weights: Box::new([]),
+    };
+    assert!(committee.serialize_as_vec().is_err());
+
+    let mut buf = ReadBuf::from(&[1, 0, 0, 0, 0, 0, 0]);
+    assert!(matches!(
+        Shape::read(&mut buf),
+        Err(ReadError::StructureInvalid(_))
+    ));
+}
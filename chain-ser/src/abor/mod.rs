@@ -1,7 +1,35 @@
+//! ABOR: a simple self-describing binary encoding.
+//!
+//! Each element is preceded by a one-byte tag identifying its type.
+//! Arrays are preceded by the number of their direct elements, which can
+//! be of any type, including nested arrays.
+//!
+//! Blobs can be decoded without knowing their structure into a tree of
+//! [`Value`]s, or checked against the named types of a schema [`Registry`]
+//! to recover the names of struct fields. The values can be printed as
+//! text or JSON for inspection.
+
+mod pretty;
+mod schema;
+mod value;
+
+pub use pretty::{Json, Text};
+pub use schema::{Registry, Schema, SchemaError};
+pub use value::{decode_all, Value};
+
+use std::error::Error;
+use std::fmt;
+
+/// The maximum nesting of arrays decoded into [`Value`]s, bounding the
+/// recursion of the decoders and printers on untrusted blobs
+pub const MAX_DEPTH: usize = 64;
+
 /// ABOR Encoder
 #[derive(Default)]
 pub struct Encoder {
     data: Vec<u8>,
+    // For each array being encoded, the number of elements encoded
+    // in the enclosing array and the offset of the array size.
     hole: Vec<(usize, usize)>,
     current_element: usize,
 }
@@ -32,6 +60,24 @@ impl Tag {
             _ => None,
         }
     }
+
+    fn name(self) -> &'static str {
+        match self {
+            Tag::U8 => "u8",
+            Tag::U16 => "u16",
+            Tag::U32 => "u32",
+            Tag::U64 => "u64",
+            Tag::U128 => "u128",
+            Tag::Bytes => "bytes",
+            Tag::Array => "array",
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
 impl Encoder {
@@ -109,17 +155,16 @@ impl Encoder {
             .incr()
     }
 
-    /// Array cannot contain more than 256 elements
+    /// Array cannot contain more than 255 elements
     pub fn struct_start(self) -> Self {
         let mut d = self.push_tag(Tag::Array);
         let mut h = d.hole;
-        let c = d.current_element + 1;
-        h.push((c, d.data.len()));
+        h.push((d.current_element + 1, d.data.len()));
         d.data.push(0xfe); // placeholder poison until struct end fill the actual size
         Self {
             data: d.data,
             hole: h,
-            current_element: c,
+            current_element: 0,
         }
     }
 
@@ -128,15 +173,15 @@ impl Encoder {
         let mut h = self.hole;
         match h.pop() {
             None => panic!("unmatched end"),
-            Some((start_element, ofs)) => {
+            Some((parent_elements, ofs)) => {
                 let mut v = self.data;
-                let nb_elements = self.current_element - start_element;
+                let nb_elements = self.current_element;
                 assert!(nb_elements < 256);
                 v[ofs] = nb_elements as u8;
                 Self {
                     data: v,
                     hole: h,
-                    current_element: self.current_element,
+                    current_element: parent_elements,
                 }
             }
         }
@@ -156,15 +201,41 @@ pub struct Decoder<'a> {
     //element: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DecodeError {
     EndOfStream,
     StreamTooSmall { want: usize, has: usize },
     StreamPending { left: usize },
     TypeUnknown(u8),
     TypeMismatch { got: Tag, expected: Tag },
+    DepthExceeded,
 }
 
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::EndOfStream => write!(f, "unexpected end of stream"),
+            DecodeError::StreamTooSmall { want, has } => write!(
+                f,
+                "stream too small: expected {} bytes but {} left",
+                want, has
+            ),
+            DecodeError::StreamPending { left } => {
+                write!(f, "unconsumed data: {} bytes left", left)
+            }
+            DecodeError::TypeUnknown(t) => write!(f, "unknown type tag: {}", t),
+            DecodeError::TypeMismatch { got, expected } => {
+                write!(f, "expected {} but got {}", expected, got)
+            }
+            DecodeError::DepthExceeded => {
+                write!(f, "arrays nested deeper than {} levels", MAX_DEPTH)
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
 impl<'a> Decoder<'a> {
     pub fn new(data: &'a [u8]) -> Self {
         Decoder { slice: data }
@@ -180,10 +251,23 @@ impl<'a> Decoder<'a> {
         }
     }
 
+    /// Return the type of the next element, without consuming it
+    pub fn peek_tag(&self) -> Result<Tag, DecodeError> {
+        match self.slice.first() {
+            None => Err(DecodeError::EndOfStream),
+            Some(&t) => Tag::from_u8(t).ok_or(DecodeError::TypeUnknown(t)),
+        }
+    }
+
+    /// Check if all the data has been decoded
+    pub fn is_end(&self) -> bool {
+        self.slice.is_empty()
+    }
+
     fn expect_tag(&mut self, tag: Tag) -> Result<(), DecodeError> {
         let t = self.pop()?;
         match Tag::from_u8(t) {
-            None => Err(DecodeError::TypeUnknown(self.slice[0])),
+            None => Err(DecodeError::TypeUnknown(t)),
             Some(got) if got == tag => Ok(()),
             Some(got) => Err(DecodeError::TypeMismatch { got, expected: tag }),
         }
@@ -229,7 +313,7 @@ impl<'a> Decoder<'a> {
     }
 
     pub fn u32(&mut self) -> Result<u32, DecodeError> {
-        self.expect_tag_size(Tag::U32, 2)?;
+        self.expect_tag_size(Tag::U32, 4)?;
         let v = {
             let mut b = [0; 4];
             b.copy_from_slice(&self.slice[0..4]);
@@ -286,6 +370,38 @@ impl<'a> Decoder<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    pub fn nested_arrays_count_direct_elements() {
+        let e = Encoder::new()
+            .struct_start()
+            .u8(1)
+            .struct_start()
+            .u8(2)
+            .u8(3)
+            .struct_end()
+            .struct_end()
+            .u8(4)
+            .finalize();
+        let mut d = Decoder::new(&e);
+        assert_eq!(d.array().unwrap(), 2);
+        assert_eq!(d.u8().unwrap(), 1);
+        assert_eq!(d.array().unwrap(), 2);
+        assert_eq!(d.u8().unwrap(), 2);
+        assert_eq!(d.u8().unwrap(), 3);
+        assert_eq!(d.u8().unwrap(), 4);
+        assert!(d.end().is_ok());
+    }
+
+    #[test]
+    pub fn truncated_u32_is_an_error() {
+        let e = Encoder::new().u32(1).finalize();
+        let mut d = Decoder::new(&e[..3]);
+        assert_eq!(
+            d.u32().unwrap_err(),
+            DecodeError::StreamTooSmall { want: 4, has: 2 }
+        );
+    }
+
     #[test]
     pub fn serialize_unit1() {
         let v = 0x0f12_35fc;
@@ -0,0 +1,220 @@
+use super::{Value, MAX_DEPTH};
+
+use std::fmt::{self, Write};
+
+/// Display a value as an indented tree. Integers are prefixed by their
+/// type, and byte strings by their length and written in hexadecimal.
+/// The contents of arrays nested deeper than `MAX_DEPTH` are elided as `..`
+pub struct Text<'a>(pub &'a Value);
+
+/// Display a value as JSON. Byte strings are written as hexadecimal
+/// strings, and arrays decoded without a struct schema as JSON arrays.
+/// The alternate flag (`{:#}`) indents the output. Arrays nested deeper
+/// than `MAX_DEPTH` are replaced by the string `".."`
+pub struct Json<'a>(pub &'a Value);
+
+fn hex(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    for b in bytes {
+        write!(f, "{:02x}", b)?;
+    }
+    Ok(())
+}
+
+fn indent(f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+    for _ in 0..depth {
+        f.write_str("  ")?;
+    }
+    Ok(())
+}
+
+fn text(f: &mut fmt::Formatter, value: &Value, depth: usize) -> fmt::Result {
+    match value {
+        Value::U8(v) => write!(f, "u8 {}", v),
+        Value::U16(v) => write!(f, "u16 {}", v),
+        Value::U32(v) => write!(f, "u32 {}", v),
+        Value::U64(v) => write!(f, "u64 {}", v),
+        Value::U128(v) => write!(f, "u128 {}", v),
+        Value::Bytes(v) => {
+            write!(f, "bytes[{}] ", v.len())?;
+            hex(f, v)
+        }
+        Value::Array(values) if values.is_empty() => f.write_str("[]"),
+        Value::Array(_) if depth == MAX_DEPTH => f.write_str("[..]"),
+        Value::Array(values) => {
+            f.write_str("[\n")?;
+            for v in values {
+                indent(f, depth + 1)?;
+                text(f, v, depth + 1)?;
+                f.write_char('\n')?;
+            }
+            indent(f, depth)?;
+            f.write_char(']')
+        }
+        Value::Struct(fields) if fields.is_empty() => f.write_str("{}"),
+        Value::Struct(_) if depth == MAX_DEPTH => f.write_str("{..}"),
+        Value::Struct(fields) => {
+            f.write_str("{\n")?;
+            for (name, v) in fields {
+                indent(f, depth + 1)?;
+                write!(f, "{}: ", name)?;
+                text(f, v, depth + 1)?;
+                f.write_char('\n')?;
+            }
+            indent(f, depth)?;
+            f.write_char('}')
+        }
+    }
+}
+
+impl<'a> fmt::Display for Text<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        text(f, self.0, 0)
+    }
+}
+
+fn json_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+// Write the items of a JSON array or object, one per line when pretty printing
+fn json_items<I, F>(
+    f: &mut fmt::Formatter,
+    (open, close): (char, char),
+    items: I,
+    depth: usize,
+    mut item: F,
+) -> fmt::Result
+where
+    I: ExactSizeIterator,
+    F: FnMut(&mut fmt::Formatter, I::Item) -> fmt::Result,
+{
+    let pretty = f.alternate() && items.len() > 0;
+    f.write_char(open)?;
+    for (i, it) in items.enumerate() {
+        if i > 0 {
+            f.write_char(',')?;
+        }
+        if pretty {
+            f.write_char('\n')?;
+            indent(f, depth + 1)?;
+        }
+        item(f, it)?;
+    }
+    if pretty {
+        f.write_char('\n')?;
+        indent(f, depth)?;
+    }
+    f.write_char(close)
+}
+
+fn json(f: &mut fmt::Formatter, value: &Value, depth: usize) -> fmt::Result {
+    match value {
+        Value::U8(v) => write!(f, "{}", v),
+        Value::U16(v) => write!(f, "{}", v),
+        Value::U32(v) => write!(f, "{}", v),
+        Value::U64(v) => write!(f, "{}", v),
+        Value::U128(v) => write!(f, "{}", v),
+        Value::Bytes(v) => {
+            f.write_char('"')?;
+            hex(f, v)?;
+            f.write_char('"')
+        }
+        Value::Array(_) | Value::Struct(_) if depth == MAX_DEPTH => f.write_str("\"..\""),
+        Value::Array(values) => json_items(f, ('[', ']'), values.iter(), depth, |f, v| {
+            json(f, v, depth + 1)
+        }),
+        Value::Struct(fields) => json_items(f, ('{', '}'), fields.iter(), depth, |f, (name, v)| {
+            json_string(f, name)?;
+            f.write_str(if f.alternate() { ": " } else { ":" })?;
+            json(f, v, depth + 1)
+        }),
+    }
+}
+
+impl<'a> fmt::Display for Json<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        json(f, self.0, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Value {
+        Value::Struct(vec![
+            ("id".to_owned(), Value::U64(1 << 40)),
+            (
+                "items".to_owned(),
+                Value::Array(vec![Value::U8(1), Value::Bytes(vec![0xab, 0x01].into())]),
+            ),
+            ("empty".to_owned(), Value::Array(vec![])),
+        ])
+    }
+
+    #[test]
+    pub fn text_tree() {
+        let expected = "\
+{
+  id: u64 1099511627776
+  items: [
+    u8 1
+    bytes[2] ab01
+  ]
+  empty: []
+}";
+        assert_eq!(sample().text().to_string(), expected);
+    }
+
+    #[test]
+    pub fn json_compact() {
+        assert_eq!(
+            sample().json().to_string(),
+            r#"{"id":1099511627776,"items":[1,"ab01"],"empty":[]}"#
+        );
+    }
+
+    fn nested(depth: usize) -> Value {
+        (0..depth).fold(Value::U8(0), |v, _| Value::Array(vec![v]))
+    }
+
+    #[test]
+    pub fn nesting_limit() {
+        let text = nested(MAX_DEPTH).text().to_string();
+        assert!(text.contains("u8 0") && !text.contains(".."));
+        let text = nested(MAX_DEPTH + 1).text().to_string();
+        assert!(text.contains("[..]") && !text.contains("u8 0"));
+
+        assert_eq!(
+            nested(MAX_DEPTH).json().to_string(),
+            format!("{}0{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH))
+        );
+        assert_eq!(
+            nested(MAX_DEPTH + 1).json().to_string(),
+            format!("{}\"..\"{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH))
+        );
+    }
+
+    #[test]
+    pub fn json_indented() {
+        let expected = r#"{
+  "id": 1099511627776,
+  "items": [
+    1,
+    "ab01"
+  ],
+  "empty": []
+}"#;
+        assert_eq!(format!("{:#}", sample().json()), expected);
+    }
+}
@@ -0,0 +1,582 @@
+//! Description of the structure of ABOR blobs.
+//!
+//! Types are defined in a small language, one definition per `;`:
+//!
+//! ```text
+//! # a comment
+//! point = struct { x: u32, y: u32 };
+//! polygon = array<point>;
+//! shape = struct { id: u16, outline: polygon, pair: tuple(u8, bytes) };
+//! ```
+//!
+//! Integers and byte strings are `u8`, `u16`, `u32`, `u64`, `u128` and
+//! `bytes`. All the other types are encoded as ABOR arrays: `array<T>`
+//! has any number of elements of type `T`, `tuple(..)` has exactly the
+//! elements listed, and `struct { .. }` the fields listed, in order.
+//! Other names refer to types defined in the registry, possibly later on.
+
+use super::{DecodeError, Decoder, Value, MAX_DEPTH};
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// The type of an ABOR element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schema {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Bytes,
+    /// An array of any number of elements of the same type
+    Array(Box<Schema>),
+    /// An array of a fixed number of elements of the given types
+    Tuple(Vec<Schema>),
+    /// An array of named fields
+    Struct(Vec<(String, Schema)>),
+    /// A type defined in the registry
+    Named(String),
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Schema::U8 => f.write_str("u8"),
+            Schema::U16 => f.write_str("u16"),
+            Schema::U32 => f.write_str("u32"),
+            Schema::U64 => f.write_str("u64"),
+            Schema::U128 => f.write_str("u128"),
+            Schema::Bytes => f.write_str("bytes"),
+            Schema::Array(elem) => write!(f, "array<{}>", elem),
+            Schema::Tuple(elems) => {
+                f.write_str("tuple(")?;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", elem)?;
+                }
+                f.write_str(")")
+            }
+            Schema::Struct(fields) => {
+                f.write_str("struct {")?;
+                for (i, (name, schema)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, " {}: {}", name, schema)?;
+                }
+                f.write_str(" }")
+            }
+            Schema::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaError {
+    /// The schema definitions are malformed
+    Syntax { line: usize, message: String },
+    /// A type is defined more than once
+    Redefined(String),
+    /// A type is referred to, but not defined
+    Undefined(String),
+    /// A type is defined as an alias of itself
+    Cycle(String),
+    /// An array does not have the number of elements of its type
+    LengthMismatch { expected: usize, got: usize },
+    /// The data does not match the schema
+    Decode(DecodeError),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Syntax { line, message } => {
+                write!(f, "syntax error at line {}: {}", line, message)
+            }
+            SchemaError::Redefined(name) => write!(f, "type '{}' is already defined", name),
+            SchemaError::Undefined(name) => write!(f, "type '{}' is not defined", name),
+            SchemaError::Cycle(name) => write!(f, "type '{}' is defined as itself", name),
+            SchemaError::LengthMismatch { expected, got } => write!(
+                f,
+                "expected an array of {} elements but got {}",
+                expected, got
+            ),
+            SchemaError::Decode(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SchemaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SchemaError::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for SchemaError {
+    fn from(e: DecodeError) -> Self {
+        SchemaError::Decode(e)
+    }
+}
+
+/// A set of named types
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    types: BTreeMap<String, Schema>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Create a registry with the types defined in `source`
+    pub fn parse(source: &str) -> Result<Self, SchemaError> {
+        let mut registry = Registry::new();
+        registry.define(source)?;
+        Ok(registry)
+    }
+
+    /// Add the types defined in `source`. On error, no type is added
+    pub fn define(&mut self, source: &str) -> Result<(), SchemaError> {
+        let definitions = Parser::new(source).definitions()?;
+        let mut types = self.types.clone();
+        for (name, schema) in definitions {
+            if types.insert(name.clone(), schema).is_some() {
+                return Err(SchemaError::Redefined(name));
+            }
+        }
+        self.types = types;
+        Ok(())
+    }
+
+    /// Add a type, replacing any previous definition with the same name
+    pub fn insert<S: Into<String>>(&mut self, name: S, schema: Schema) -> Option<Schema> {
+        self.types.insert(name.into(), schema)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Schema> {
+        self.types.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Schema)> {
+        self.types
+            .iter()
+            .map(|(name, schema)| (name.as_str(), schema))
+    }
+
+    /// Decode a blob consisting of a single element of the named type
+    pub fn decode(&self, name: &str, data: &[u8]) -> Result<Value, SchemaError> {
+        self.decode_schema(&Schema::Named(name.to_owned()), data)
+    }
+
+    /// Decode a blob consisting of a single element of the given type
+    pub fn decode_schema(&self, schema: &Schema, data: &[u8]) -> Result<Value, SchemaError> {
+        let mut decoder = Decoder::new(data);
+        let value = self.decode_value(schema, &mut decoder)?;
+        decoder.end()?;
+        Ok(value)
+    }
+
+    /// Decode the next element of the decoder with the given type.
+    ///
+    /// Fails with `DecodeError::DepthExceeded` if the element has
+    /// arrays nested deeper than `MAX_DEPTH`.
+    pub fn decode_value(
+        &self,
+        schema: &Schema,
+        decoder: &mut Decoder,
+    ) -> Result<Value, SchemaError> {
+        self.decode_nested(schema, decoder, 0)
+    }
+
+    fn decode_nested(
+        &self,
+        schema: &Schema,
+        decoder: &mut Decoder,
+        depth: usize,
+    ) -> Result<Value, SchemaError> {
+        let schema = self.resolve(schema)?;
+        let nested = matches!(
+            schema,
+            Schema::Array(_) | Schema::Tuple(_) | Schema::Struct(_)
+        );
+        if nested && depth == MAX_DEPTH {
+            return Err(DecodeError::DepthExceeded.into());
+        }
+        let value = match schema {
+            Schema::U8 => Value::U8(decoder.u8()?),
+            Schema::U16 => Value::U16(decoder.u16()?),
+            Schema::U32 => Value::U32(decoder.u32()?),
+            Schema::U64 => Value::U64(decoder.u64()?),
+            Schema::U128 => Value::U128(decoder.u128()?),
+            Schema::Bytes => Value::Bytes(decoder.bytes()?),
+            Schema::Array(elem) => {
+                let len = decoder.array()?;
+                let values = (0..len)
+                    .map(|_| self.decode_nested(elem, decoder, depth + 1))
+                    .collect::<Result<_, _>>()?;
+                Value::Array(values)
+            }
+            Schema::Tuple(elems) => {
+                expect_length(decoder, elems.len())?;
+                let values = elems
+                    .iter()
+                    .map(|elem| self.decode_nested(elem, decoder, depth + 1))
+                    .collect::<Result<_, _>>()?;
+                Value::Array(values)
+            }
+            Schema::Struct(fields) => {
+                expect_length(decoder, fields.len())?;
+                let values = fields
+                    .iter()
+                    .map(|(name, field)| {
+                        Ok((name.clone(), self.decode_nested(field, decoder, depth + 1)?))
+                    })
+                    .collect::<Result<_, SchemaError>>()?;
+                Value::Struct(values)
+            }
+            Schema::Named(_) => unreachable!("named types are resolved"),
+        };
+        Ok(value)
+    }
+
+    // Follow the names to the actual definition of a type
+    fn resolve<'a>(&'a self, mut schema: &'a Schema) -> Result<&'a Schema, SchemaError> {
+        let mut steps = 0;
+        while let Schema::Named(name) = schema {
+            if steps > self.types.len() {
+                return Err(SchemaError::Cycle(name.clone()));
+            }
+            schema = self
+                .types
+                .get(name)
+                .ok_or_else(|| SchemaError::Undefined(name.clone()))?;
+            steps += 1;
+        }
+        Ok(schema)
+    }
+}
+
+fn expect_length(decoder: &mut Decoder, expected: usize) -> Result<(), SchemaError> {
+    let got = decoder.array()?;
+    if got != expected {
+        return Err(SchemaError::LengthMismatch { expected, got });
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Punct(char),
+    End,
+}
+
+impl<'a> fmt::Display for Token<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(s) => write!(f, "'{}'", s),
+            Token::Punct(c) => write!(f, "'{}'", c),
+            Token::End => f.write_str("end of input"),
+        }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Parser {
+            source,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn error<T, S: Into<String>>(&self, message: S) -> Result<T, SchemaError> {
+        Err(SchemaError::Syntax {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn skip_blanks(&mut self) {
+        let mut chars = self.source[self.pos..].char_indices().peekable();
+        let mut in_comment = false;
+        while let Some(&(i, c)) = chars.peek() {
+            match c {
+                '\n' => {
+                    self.line += 1;
+                    in_comment = false;
+                }
+                '#' => in_comment = true,
+                c if in_comment || c.is_whitespace() => {}
+                _ => {
+                    self.pos += i;
+                    return;
+                }
+            }
+            chars.next();
+        }
+        self.pos = self.source.len();
+    }
+
+    fn peek(&mut self) -> Token<'a> {
+        self.skip_blanks();
+        let rest = &self.source[self.pos..];
+        match rest.chars().next() {
+            None => Token::End,
+            Some(c) if is_ident_char(c) => {
+                let len = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+                Token::Ident(&rest[..len])
+            }
+            Some(c) => Token::Punct(c),
+        }
+    }
+
+    fn next(&mut self) -> Token<'a> {
+        let token = self.peek();
+        self.pos += match token {
+            Token::Ident(s) => s.len(),
+            Token::Punct(c) => c.len_utf8(),
+            Token::End => 0,
+        };
+        token
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), SchemaError> {
+        match self.next() {
+            Token::Punct(p) if p == c => Ok(()),
+            token => self.error(format!("expected '{}' but got {}", c, token)),
+        }
+    }
+
+    fn ident(&mut self) -> Result<&'a str, SchemaError> {
+        match self.next() {
+            Token::Ident(s) => Ok(s),
+            token => self.error(format!("expected a name but got {}", token)),
+        }
+    }
+
+    fn definitions(mut self) -> Result<Vec<(String, Schema)>, SchemaError> {
+        let mut definitions = Vec::new();
+        while self.peek() != Token::End {
+            let name = self.ident()?;
+            if keyword(name) {
+                return self.error(format!("'{}' is a reserved type name", name));
+            }
+            self.expect('=')?;
+            let schema = self.schema()?;
+            self.expect(';')?;
+            definitions.push((name.to_owned(), schema));
+        }
+        Ok(definitions)
+    }
+
+    // Parse a comma-separated list, up to the closing delimiter
+    fn list<T, F>(&mut self, close: char, mut item: F) -> Result<Vec<T>, SchemaError>
+    where
+        F: FnMut(&mut Self) -> Result<T, SchemaError>,
+    {
+        let mut items = Vec::new();
+        loop {
+            if self.peek() == Token::Punct(close) {
+                self.next();
+                return Ok(items);
+            }
+            items.push(item(self)?);
+            match self.next() {
+                Token::Punct(',') => {}
+                Token::Punct(c) if c == close => return Ok(items),
+                token => {
+                    return self.error(format!("expected ',' or '{}' but got {}", close, token))
+                }
+            }
+        }
+    }
+
+    fn schema(&mut self) -> Result<Schema, SchemaError> {
+        let name = self.ident()?;
+        let schema = match name {
+            "u8" => Schema::U8,
+            "u16" => Schema::U16,
+            "u32" => Schema::U32,
+            "u64" => Schema::U64,
+            "u128" => Schema::U128,
+            "bytes" => Schema::Bytes,
+            "array" => {
+                self.expect('<')?;
+                let elem = self.schema()?;
+                self.expect('>')?;
+                Schema::Array(Box::new(elem))
+            }
+            "tuple" => {
+                self.expect('(')?;
+                Schema::Tuple(self.list(')', Self::schema)?)
+            }
+            "struct" => {
+                self.expect('{')?;
+                let fields = self.list('}', |p| {
+                    let name = p.ident()?;
+                    p.expect(':')?;
+                    Ok((name.to_owned(), p.schema()?))
+                })?;
+                Schema::Struct(fields)
+            }
+            _ => Schema::Named(name.to_owned()),
+        };
+        Ok(schema)
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn keyword(name: &str) -> bool {
+    matches!(
+        name,
+        "u8" | "u16" | "u32" | "u64" | "u128" | "bytes" | "array" | "tuple" | "struct"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Encoder;
+    use super::*;
+
+    const SHAPES: &str = "
+        # shapes
+        point = struct { x: u32, y: u32 };
+        polygon = array<point>;
+        shape = struct {
+            id: u16,
+            outline: polygon,
+            tag: tuple(u8, bytes),
+        };
+    ";
+
+    fn point(e: Encoder, x: u32, y: u32) -> Encoder {
+        e.struct_start().u32(x).u32(y).struct_end()
+    }
+
+    #[test]
+    pub fn parse_definitions() {
+        let registry = Registry::parse(SHAPES).unwrap();
+        assert_eq!(
+            registry.get("polygon"),
+            Some(&Schema::Array(Box::new(Schema::Named("point".to_owned()))))
+        );
+        assert_eq!(
+            registry.get("shape").unwrap().to_string(),
+            "struct { id: u16, outline: polygon, tag: tuple(u8, bytes) }"
+        );
+    }
+
+    #[test]
+    pub fn parse_errors() {
+        assert_eq!(
+            Registry::parse("a = u8;\nb = struct { x u8 };").unwrap_err(),
+            SchemaError::Syntax {
+                line: 2,
+                message: "expected ':' but got 'u8'".to_owned()
+            }
+        );
+        assert_eq!(
+            Registry::parse("a = u8; a = u16;").unwrap_err(),
+            SchemaError::Redefined("a".to_owned())
+        );
+        assert!(Registry::parse("u8 = u16;").is_err());
+        assert!(Registry::parse("a = array<u8;").is_err());
+    }
+
+    #[test]
+    pub fn decode_with_schema() {
+        let registry = Registry::parse(SHAPES).unwrap();
+        let e = Encoder::new().struct_start().u16(3).struct_start();
+        let e = point(point(e, 1, 2), 3, 4)
+            .struct_end()
+            .struct_start()
+            .u8(9)
+            .bytes(b"ab")
+            .struct_end()
+            .struct_end()
+            .finalize();
+        let value = registry.decode("shape", &e).unwrap();
+        let p = |x, y| {
+            Value::Struct(vec![
+                ("x".to_owned(), Value::U32(x)),
+                ("y".to_owned(), Value::U32(y)),
+            ])
+        };
+        assert_eq!(
+            value,
+            Value::Struct(vec![
+                ("id".to_owned(), Value::U16(3)),
+                ("outline".to_owned(), Value::Array(vec![p(1, 2), p(3, 4)])),
+                (
+                    "tag".to_owned(),
+                    Value::Array(vec![Value::U8(9), Value::Bytes(b"ab".to_vec().into())])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    pub fn decode_mismatches() {
+        let registry = Registry::parse(SHAPES).unwrap();
+        let e = point(Encoder::new(), 1, 2).finalize();
+        assert!(registry.decode("point", &e).is_ok());
+        assert_eq!(
+            registry.decode("polygon", &e).unwrap_err(),
+            SchemaError::Decode(DecodeError::TypeMismatch {
+                got: super::super::Tag::U32,
+                expected: super::super::Tag::Array
+            })
+        );
+        let e = Encoder::new().struct_start().u32(1).struct_end().finalize();
+        assert_eq!(
+            registry.decode("point", &e).unwrap_err(),
+            SchemaError::LengthMismatch {
+                expected: 2,
+                got: 1
+            }
+        );
+        assert_eq!(
+            registry.decode("circle", &e).unwrap_err(),
+            SchemaError::Undefined("circle".to_owned())
+        );
+        let cyclic = Registry::parse("a = b; b = a;").unwrap();
+        assert!(matches!(cyclic.decode("a", &e), Err(SchemaError::Cycle(_))));
+    }
+
+    #[test]
+    pub fn decode_recursive_type_nesting_limit() {
+        let registry = Registry::parse("tree = array<tree>;").unwrap();
+        let nested = |depth: usize| {
+            let mut data = [7, 1].repeat(depth);
+            data.extend_from_slice(&[7, 0]);
+            data
+        };
+        assert!(registry.decode("tree", &nested(MAX_DEPTH - 1)).is_ok());
+        assert_eq!(
+            registry.decode("tree", &nested(MAX_DEPTH)).unwrap_err(),
+            SchemaError::Decode(DecodeError::DepthExceeded)
+        );
+        assert_eq!(
+            registry.decode("tree", &nested(1_000_000)).unwrap_err(),
+            SchemaError::Decode(DecodeError::DepthExceeded)
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use super::{DecodeError, Decoder, Encoder, Tag, MAX_DEPTH};
+use super::{Json, Text};
+
+/// A decoded ABOR element
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    Bytes(Box<[u8]>),
+    Array(Vec<Value>),
+    /// An array decoded with a struct schema, along with the field names
+    Struct(Vec<(String, Value)>),
+}
+
+/// Decode all the elements of an ABOR blob, without knowledge of its structure
+pub fn decode_all(data: &[u8]) -> Result<Vec<Value>, DecodeError> {
+    let mut decoder = Decoder::new(data);
+    let mut values = Vec::new();
+    while !decoder.is_end() {
+        values.push(decoder.value()?);
+    }
+    Ok(values)
+}
+
+impl Value {
+    /// The type of the element in the ABOR encoding
+    pub fn tag(&self) -> Tag {
+        match self {
+            Value::U8(_) => Tag::U8,
+            Value::U16(_) => Tag::U16,
+            Value::U32(_) => Tag::U32,
+            Value::U64(_) => Tag::U64,
+            Value::U128(_) => Tag::U128,
+            Value::Bytes(_) => Tag::Bytes,
+            Value::Array(_) | Value::Struct(_) => Tag::Array,
+        }
+    }
+
+    /// Append the ABOR encoding of the element. Structs are encoded as arrays
+    pub fn encode(&self, encoder: Encoder) -> Encoder {
+        match self {
+            Value::U8(v) => encoder.u8(*v),
+            Value::U16(v) => encoder.u16(*v),
+            Value::U32(v) => encoder.u32(*v),
+            Value::U64(v) => encoder.u64(*v),
+            Value::U128(v) => encoder.u128(*v),
+            Value::Bytes(v) => encoder.bytes(v),
+            Value::Array(values) => values
+                .iter()
+                .fold(encoder.struct_start(), |e, v| v.encode(e))
+                .struct_end(),
+            Value::Struct(fields) => fields
+                .iter()
+                .fold(encoder.struct_start(), |e, (_, v)| v.encode(e))
+                .struct_end(),
+        }
+    }
+
+    /// Display the element as an indented text tree
+    pub fn text(&self) -> Text<'_> {
+        Text(self)
+    }
+
+    /// Display the element as JSON
+    pub fn json(&self) -> Json<'_> {
+        Json(self)
+    }
+}
+
+impl<'a> Decoder<'a> {
+    /// Decode the next element, whatever its type.
+    ///
+    /// Fails with `DecodeError::DepthExceeded` if the element has
+    /// arrays nested deeper than `MAX_DEPTH`.
+    pub fn value(&mut self) -> Result<Value, DecodeError> {
+        self.nested_value(0)
+    }
+
+    fn nested_value(&mut self, depth: usize) -> Result<Value, DecodeError> {
+        match self.peek_tag()? {
+            Tag::U8 => self.u8().map(Value::U8),
+            Tag::U16 => self.u16().map(Value::U16),
+            Tag::U32 => self.u32().map(Value::U32),
+            Tag::U64 => self.u64().map(Value::U64),
+            Tag::U128 => self.u128().map(Value::U128),
+            Tag::Bytes => self.bytes().map(Value::Bytes),
+            Tag::Array => {
+                if depth == MAX_DEPTH {
+                    return Err(DecodeError::DepthExceeded);
+                }
+                let len = self.array()?;
+                let values = (0..len)
+                    .map(|_| self.nested_value(depth + 1))
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::Array(values))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn decode_encode_roundtrip() {
+        let e = Encoder::new()
+            .u32(0x1234)
+            .struct_start()
+            .bytes(&[1, 2, 3])
+            .struct_start()
+            .struct_end()
+            .u128(7)
+            .struct_end()
+            .u16(2)
+            .finalize();
+        let values = decode_all(&e).unwrap();
+        assert_eq!(
+            values,
+            vec![
+                Value::U32(0x1234),
+                Value::Array(vec![
+                    Value::Bytes(vec![1, 2, 3].into_boxed_slice()),
+                    Value::Array(vec![]),
+                    Value::U128(7),
+                ]),
+                Value::U16(2),
+            ]
+        );
+        let reencoded = values
+            .iter()
+            .fold(Encoder::new(), |e, v| v.encode(e))
+            .finalize();
+        assert_eq!(reencoded, e);
+    }
+
+    #[test]
+    pub fn decode_unknown_tag() {
+        assert_eq!(decode_all(&[1, 5, 9]), Err(DecodeError::TypeUnknown(9)));
+    }
+
+    fn nested_arrays(depth: usize) -> Vec<u8> {
+        let mut data = [Tag::Array as u8, 1].repeat(depth);
+        data.extend_from_slice(&[Tag::U8 as u8, 0]);
+        data
+    }
+
+    #[test]
+    pub fn decode_nesting_limit() {
+        assert!(decode_all(&nested_arrays(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            decode_all(&nested_arrays(MAX_DEPTH + 1)),
+            Err(DecodeError::DepthExceeded)
+        );
+        assert_eq!(
+            decode_all(&nested_arrays(1_000_000)),
+            Err(DecodeError::DepthExceeded)
+        );
+    }
+
+    #[test]
+    pub fn decode_truncated_array() {
+        let e = Encoder::new()
+            .struct_start()
+            .u8(1)
+            .u8(2)
+            .struct_end()
+            .finalize();
+        assert_eq!(decode_all(&e[..e.len() - 2]), Err(DecodeError::EndOfStream));
+    }
+}
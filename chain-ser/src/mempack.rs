@@ -131,6 +131,11 @@ impl<'a> ReadBuf<'a> {
         Ok(s)
     }
 
+    /// Return the rest of the buffer without consuming it
+    pub fn peek_slice_end(&self) -> &'a [u8] {
+        &self.data[self.offset..]
+    }
+
     pub fn get_slice_end(&mut self) -> &'a [u8] {
         let s = &self.data[self.offset..];
         self.offset = self.data.len();
@@ -236,6 +241,12 @@ pub trait Readable: Sized {
     }
 }
 
+/// Decode a value borrowing from the data of the buffer, instead of copying
+/// it, so that the value can't outlive the data
+pub trait ReadableRef<'a>: Sized {
+    fn read_ref(buf: &mut ReadBuf<'a>) -> Result<Self, ReadError>;
+}
+
 impl Readable for () {
     fn read(_: &mut ReadBuf) -> Result<(), ReadError> {
         Ok(())
@@ -317,3 +328,11 @@ pub fn read_from_raw<T: Readable>(raw: &[u8]) -> Result<T, std::io::Error> {
         },
     }
 }
+
+/// Decode a borrowed view of a raw buffer, checking that all the data is consumed
+pub fn read_ref_from_raw<'a, T: ReadableRef<'a>>(raw: &'a [u8]) -> Result<T, ReadError> {
+    let mut rbuf = ReadBuf::from(raw);
+    let t = T::read_ref(&mut rbuf)?;
+    rbuf.expect_end()?;
+    Ok(t)
+}
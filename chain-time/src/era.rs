@@ -29,9 +29,9 @@ impl fmt::Display for EpochPosition {
 /// to a specific slot. Each epoch have a constant number of slots on a given time era.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TimeEra {
-    epoch_start: Epoch,
-    slot_start: Slot,
-    slots_per_epoch: u32,
+    pub(crate) epoch_start: Epoch,
+    pub(crate) slot_start: Slot,
+    pub(crate) slots_per_epoch: u32,
 }
 
 pub fn pack_time_era<W: std::io::Write>(
@@ -68,6 +68,16 @@ impl TimeEra {
         }
     }
 
+    /// retrieve the first epoch of the era
+    pub fn epoch_start(&self) -> Epoch {
+        self.epoch_start
+    }
+
+    /// retrieve the first slot of the era
+    pub fn slot_start(&self) -> Slot {
+        self.slot_start
+    }
+
     /// retrieve the number of slots in an epoch during a given Epoch
     pub fn slots_per_epoch(&self) -> u32 {
         self.slots_per_epoch
@@ -96,7 +106,8 @@ impl TimeEra {
         assert!(pos.epoch >= self.epoch_start);
         assert!(pos.slot.0 < self.slots_per_epoch);
 
-        let slot_offset = (pos.epoch.0 as u64) * (self.slots_per_epoch as u64) + pos.slot.0 as u64;
+        let epoch_offset = (pos.epoch.0 - self.epoch_start.0) as u64;
+        let slot_offset = epoch_offset * (self.slots_per_epoch as u64) + pos.slot.0 as u64;
         Slot(self.slot_start.0 + slot_offset)
     }
 }
@@ -181,5 +192,9 @@ mod test {
                 slot: EpochSlotOffset(2)
             }
         );
+
+        assert_eq!(era.from_era_to_slot(p1), slot1);
+        assert_eq!(era.from_era_to_slot(p2), slot2);
+        assert_eq!(era.from_era_to_slot(p3), slot3);
     }
 }
@@ -0,0 +1,394 @@
+//! Sequence of eras, each with its own slot duration and epoch length
+
+use crate::era::{pack_time_era, unpack_time_era, Epoch, EpochPosition, EpochSlotOffset, TimeEra};
+use crate::timeframe::{Slot, SlotDuration};
+use crate::timeline::Timeline;
+use chain_ser::packer::Codec;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+/// An era of a time history: the slots and epochs of the era, and the
+/// duration of each of its slots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEra {
+    era: TimeEra,
+    slot_duration: SlotDuration,
+    /// Time elapsed between the start of the history and the start of this era
+    time_start: Duration,
+}
+
+impl HistoryEra {
+    pub fn time_era(&self) -> &TimeEra {
+        &self.era
+    }
+
+    pub fn slot_duration(&self) -> SlotDuration {
+        self.slot_duration
+    }
+
+    /// The slot at an offset from the start of the history, which is
+    /// expected to be in this era
+    fn slot_at_offset(&self, offset: Duration) -> Slot {
+        let (slots, _) = self.slot_duration.slots_in(offset - self.time_start);
+        Slot(self.era.slot_start.0 + slots)
+    }
+
+    fn slot_to_offset(&self, slot: Slot) -> Duration {
+        let slots = slot.0 - self.era.slot_start.0;
+        self.time_start + self.slot_duration.of_slots(slots)
+    }
+}
+
+/// Time history, made of an ordered list of eras
+///
+/// Each era starts at the beginning of an epoch, and has its own slot
+/// duration and number of slots per epoch. The slots and epochs are
+/// numbered continuously across eras:
+///
+/// ```text
+/// epoch 0           epoch 1           epoch 2     epoch 3     epoch 4
+/// 0     1     2     3     4     5     6  7  8  9  10 11 12 13 14 15 16 17
+/// x-----x-----x-----x-----x-----x-----┳--x--x--x--x--x--x--x--x--x--x--x
+///                                     ↑
+///                                     |
+///                                     history.change_era(Epoch(2), SlotDuration::from_secs(2), 4)
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeHistory {
+    timeline: Timeline,
+    eras: Vec<HistoryEra>,
+}
+
+/// Error when adding an era to a time history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EraChangeError {
+    /// The new era does not start after the beginning of the current era
+    EpochNotAfterCurrentEra { current: Epoch, requested: Epoch },
+    /// The new era has no slots in its epochs
+    NoSlotsPerEpoch,
+}
+
+impl fmt::Display for EraChangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EraChangeError::EpochNotAfterCurrentEra { current, requested } => write!(
+                f,
+                "New era starting at epoch {} does not start after the current era starting at epoch {}",
+                requested.0, current.0
+            ),
+            EraChangeError::NoSlotsPerEpoch => write!(f, "New era has no slots per epoch"),
+        }
+    }
+}
+
+impl Error for EraChangeError {}
+
+impl TimeHistory {
+    /// Create a new history, with a first era starting at slot 0 and epoch 0
+    /// on the timeline.
+    pub fn new(timeline: Timeline, slot_duration: SlotDuration, slots_per_epoch: u32) -> Self {
+        assert!(slots_per_epoch > 0);
+        TimeHistory {
+            timeline,
+            eras: vec![HistoryEra {
+                era: TimeEra::new(Slot(0), Epoch(0), slots_per_epoch),
+                slot_duration,
+                time_start: Duration::from_secs(0),
+            }],
+        }
+    }
+
+    /// Create a new history with an additional era, starting at the
+    /// beginning of the given epoch
+    ///
+    /// The epoch must be after the beginning of the current era, which then
+    /// ends at the beginning of the new one.
+    pub fn change_era(
+        &self,
+        epoch: Epoch,
+        slot_duration: SlotDuration,
+        slots_per_epoch: u32,
+    ) -> Result<Self, EraChangeError> {
+        let current = self.current_era();
+        if epoch <= current.era.epoch_start {
+            return Err(EraChangeError::EpochNotAfterCurrentEra {
+                current: current.era.epoch_start,
+                requested: epoch,
+            });
+        }
+        if slots_per_epoch == 0 {
+            return Err(EraChangeError::NoSlotsPerEpoch);
+        }
+        let slot_start = current.era.from_era_to_slot(EpochPosition {
+            epoch,
+            slot: EpochSlotOffset(0),
+        });
+        let era = HistoryEra {
+            era: TimeEra::new(slot_start, epoch, slots_per_epoch),
+            slot_duration,
+            time_start: current.slot_to_offset(slot_start),
+        };
+        let mut eras = self.eras.clone();
+        eras.push(era);
+        Ok(TimeHistory {
+            timeline: self.timeline.clone(),
+            eras,
+        })
+    }
+
+    /// The last era of the history
+    pub fn current_era(&self) -> &HistoryEra {
+        self.eras
+            .last()
+            .expect("a time history has at least one era")
+    }
+
+    /// All the eras of the history, in order
+    pub fn eras(&self) -> &[HistoryEra] {
+        &self.eras
+    }
+
+    /// The era a slot belongs to
+    pub fn era_of_slot(&self, slot: Slot) -> &HistoryEra {
+        self.find_era(|era| era.era.slot_start <= slot)
+    }
+
+    /// The era an epoch belongs to
+    pub fn era_of_epoch(&self, epoch: Epoch) -> &HistoryEra {
+        self.find_era(|era| era.era.epoch_start <= epoch)
+    }
+
+    // the last era starting before the position tested by `started`,
+    // which is always true for the first era
+    fn find_era<F: Fn(&HistoryEra) -> bool>(&self, started: F) -> &HistoryEra {
+        let n = self.eras.iter().take_while(|era| started(era)).count();
+        &self.eras[n.max(1) - 1]
+    }
+
+    /// Get the slot associated with the given system time.
+    ///
+    /// It returns None if the system time is before the start of the history.
+    pub fn slot_at(&self, at: &SystemTime) -> Option<Slot> {
+        let offset = self.timeline.differential(at)?.0;
+        let era = self.find_era(|era| era.time_start <= offset);
+        Some(era.slot_at_offset(offset))
+    }
+
+    /// Get the system time at the beginning of a slot
+    pub fn slot_to_systemtime(&self, slot: Slot) -> SystemTime {
+        self.timeline.0 + self.era_of_slot(slot).slot_to_offset(slot)
+    }
+
+    /// Get the epoch and slot in the epoch of a slot
+    pub fn slot_to_epoch_position(&self, slot: Slot) -> EpochPosition {
+        self.era_of_slot(slot)
+            .era
+            .from_slot_to_era(slot)
+            .expect("slot in its era")
+    }
+
+    /// Get the flat slot of an epoch position
+    ///
+    /// Note this panics if the slot offset is not in the epoch.
+    pub fn epoch_position_to_slot(&self, pos: EpochPosition) -> Slot {
+        self.era_of_epoch(pos.epoch).era.from_era_to_slot(pos)
+    }
+
+    /// Get the epoch position associated with the given system time.
+    ///
+    /// It returns None if the system time is before the start of the history.
+    pub fn epoch_position_at(&self, at: &SystemTime) -> Option<EpochPosition> {
+        self.slot_at(at)
+            .map(|slot| self.slot_to_epoch_position(slot))
+    }
+
+    /// Get the system time at the beginning of an epoch position
+    pub fn epoch_position_to_systemtime(&self, pos: EpochPosition) -> SystemTime {
+        self.slot_to_systemtime(self.epoch_position_to_slot(pos))
+    }
+}
+
+pub fn pack_time_history<W: std::io::Write>(
+    time_history: &TimeHistory,
+    codec: &mut Codec<W>,
+) -> Result<(), std::io::Error> {
+    let start = time_history
+        .timeline
+        .0
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    codec.put_u64(start.as_secs())?;
+    codec.put_u32(start.subsec_nanos())?;
+    codec.put_u32(time_history.eras.len() as u32)?;
+    for era in time_history.eras.iter() {
+        pack_time_era(&era.era, codec)?;
+        codec.put_u64(era.slot_duration.as_millis())?;
+    }
+    Ok(())
+}
+
+pub fn unpack_time_history<R: std::io::BufRead>(
+    codec: &mut Codec<R>,
+) -> Result<TimeHistory, std::io::Error> {
+    fn invalid<E: Into<Box<dyn Error + Send + Sync>>>(e: E) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+
+    let secs = codec.get_u64()?;
+    let nanos = codec.get_u32()?;
+    if nanos >= 1_000_000_000 {
+        return Err(invalid("timeline start nanoseconds out of range"));
+    }
+    let start = SystemTime::UNIX_EPOCH
+        .checked_add(Duration::new(secs, nanos))
+        .ok_or_else(|| invalid("timeline start out of range"))?;
+    let timeline = Timeline::new(start);
+    let count = codec.get_u32()?;
+    let mut history: Option<TimeHistory> = None;
+    for _ in 0..count {
+        let era = unpack_time_era(codec)?;
+        let slot_duration = SlotDuration::from_duration(Duration::from_millis(codec.get_u64()?))
+            .ok_or_else(|| invalid("invalid slot duration"))?;
+        // the eras are added again, so that they are checked to follow
+        // each other the same way as when they were created
+        let next = match history {
+            None if era.slot_start == Slot(0) && era.epoch_start == Epoch(0) => {
+                if era.slots_per_epoch == 0 {
+                    return Err(invalid(EraChangeError::NoSlotsPerEpoch));
+                }
+                TimeHistory::new(timeline.clone(), slot_duration, era.slots_per_epoch)
+            }
+            None => return Err(invalid("first era not starting at slot 0 and epoch 0")),
+            Some(history) => history
+                .change_era(era.epoch_start, slot_duration, era.slots_per_epoch)
+                .map_err(invalid)?,
+        };
+        if next.current_era().era != era {
+            return Err(invalid("era not starting at the end of the previous era"));
+        }
+        history = Some(next);
+    }
+    history.ok_or_else(|| invalid("time history without any era"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use quickcheck::{quickcheck, TestResult};
+    use std::io::Cursor;
+
+    quickcheck! {
+        fn time_history_pack_unpack_bijection(time_history: TimeHistory) -> TestResult {
+            let mut c : Cursor<Vec<u8>> = Cursor::new(Vec::new());
+            let mut codec = Codec::new(c);
+            match pack_time_history(&time_history, &mut codec) {
+                Ok(_) => (),
+                Err(e) => return TestResult::error(format!("{}", e)),
+            }
+            c = codec.into_inner();
+            c.set_position(0);
+            codec = Codec::new(c);
+            match unpack_time_history(&mut codec) {
+                Ok(other_time_history) => {
+                    TestResult::from_bool(time_history == other_time_history)
+                },
+                Err(e) => TestResult::error(format!("{}", e)),
+            }
+        }
+
+        fn time_history_slot_round_trip(time_history: TimeHistory, slot: u32) -> bool {
+            let slot = Slot(slot as u64);
+            let pos = time_history.slot_to_epoch_position(slot);
+            let at = time_history.slot_to_systemtime(slot);
+            time_history.epoch_position_to_slot(pos) == slot
+                && time_history.slot_at(&at) == Some(slot)
+                && time_history.epoch_position_at(&at) == Some(pos)
+        }
+    }
+
+    fn pos(epoch: u32, slot: u32) -> EpochPosition {
+        EpochPosition {
+            epoch: Epoch(epoch),
+            slot: EpochSlotOffset(slot),
+        }
+    }
+
+    #[test]
+    pub fn it_works() {
+        let now = SystemTime::now();
+        let h0 = TimeHistory::new(Timeline::new(now), SlotDuration::from_secs(5), 3);
+        // era 1 starts at slot 6 (30s), with epochs of 4 slots of 2s
+        let h1 = h0
+            .change_era(Epoch(2), SlotDuration::from_secs(2), 4)
+            .unwrap();
+        // era 2 starts at slot 14 (46s), with epochs of 2 slots of 10s
+        let h2 = h1
+            .change_era(Epoch(4), SlotDuration::from_secs(10), 2)
+            .unwrap();
+
+        let at = |secs| now + Duration::from_secs(secs);
+
+        assert_eq!(h2.slot_at(&(now - Duration::from_secs(1))), None);
+        assert_eq!(h2.epoch_position_at(&at(0)), Some(pos(0, 0)));
+        assert_eq!(h2.epoch_position_at(&at(29)), Some(pos(1, 2)));
+        assert_eq!(h2.epoch_position_at(&at(30)), Some(pos(2, 0)));
+        assert_eq!(h2.epoch_position_at(&at(45)), Some(pos(3, 3)));
+        assert_eq!(h2.epoch_position_at(&at(46)), Some(pos(4, 0)));
+        assert_eq!(h2.epoch_position_at(&at(76)), Some(pos(5, 1)));
+
+        assert_eq!(h2.slot_at(&at(46)), Some(Slot(14)));
+        assert_eq!(h2.slot_to_systemtime(Slot(14)), at(46));
+        assert_eq!(h2.epoch_position_to_systemtime(pos(3, 1)), at(40));
+
+        // the previous history is unaffected by the changes
+        assert_eq!(h0.epoch_position_at(&at(30)), Some(pos(2, 0)));
+        assert_eq!(h0.epoch_position_at(&at(46)), Some(pos(3, 0)));
+        assert_eq!(h1.epoch_position_at(&at(46)), Some(pos(4, 0)));
+        assert_eq!(h1.epoch_position_at(&at(76)), Some(pos(7, 3)));
+    }
+
+    fn unpack_timeline_start(secs: u64, nanos: u32) -> Result<TimeHistory, std::io::Error> {
+        let mut codec = Codec::new(Vec::new());
+        codec.put_u64(secs).unwrap();
+        codec.put_u32(nanos).unwrap();
+        codec.put_u32(0).unwrap();
+        unpack_time_history(&mut Codec::new(Cursor::new(codec.into_inner())))
+    }
+
+    #[test]
+    pub fn unpack_invalid_timeline_start() {
+        for (secs, nanos) in &[(0, 1_000_000_000), (u64::MAX, 999_999_999), (u64::MAX, 0)] {
+            let err = unpack_timeline_start(*secs, *nanos).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+            assert!(err.to_string().starts_with("timeline start"));
+        }
+        // a valid start without any era gets past the timeline
+        let err = unpack_timeline_start(0, 999_999_999).unwrap_err();
+        assert_eq!(err.to_string(), "time history without any era");
+    }
+
+    #[test]
+    pub fn change_era_not_after_current() {
+        let h0 = TimeHistory::new(
+            Timeline::new(SystemTime::UNIX_EPOCH),
+            SlotDuration::from_secs(5),
+            3,
+        );
+        let h1 = h0
+            .change_era(Epoch(2), SlotDuration::from_secs(2), 4)
+            .unwrap();
+        assert_eq!(
+            h1.change_era(Epoch(2), SlotDuration::from_secs(1), 4),
+            Err(EraChangeError::EpochNotAfterCurrentEra {
+                current: Epoch(2),
+                requested: Epoch(2),
+            })
+        );
+        assert_eq!(
+            h1.change_era(Epoch(3), SlotDuration::from_secs(1), 0),
+            Err(EraChangeError::NoSlotsPerEpoch)
+        );
+    }
+}
@@ -2,11 +2,13 @@
 extern crate cfg_if;
 
 pub mod era;
+pub mod history;
 pub mod timeframe;
 pub mod timeline;
 pub mod units;
 
 pub use era::{Epoch, TimeEra};
+pub use history::{EraChangeError, TimeHistory};
 pub use timeframe::{Slot, SlotDuration, TimeFrame};
 pub use timeline::{TimeOffsetSeconds, Timeline};
 pub use units::DurationSeconds;
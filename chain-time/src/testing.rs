@@ -1,5 +1,6 @@
-use crate::{Epoch, Slot, TimeEra};
+use crate::{Epoch, Slot, SlotDuration, TimeEra, TimeHistory, Timeline};
 use quickcheck::{Arbitrary, Gen};
+use std::time::{Duration, SystemTime};
 
 impl Arbitrary for TimeEra {
     fn arbitrary<G: Gen>(g: &mut G) -> Self {
@@ -22,3 +23,32 @@ impl Arbitrary for Epoch {
         Epoch(Arbitrary::arbitrary(g))
     }
 }
+
+impl Arbitrary for SlotDuration {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        SlotDuration::from_millis(u64::arbitrary(g) % 599_999 + 1)
+    }
+}
+
+impl Arbitrary for TimeHistory {
+    fn arbitrary<G: Gen>(g: &mut G) -> Self {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(u32::arbitrary(g) as u64);
+        let mut history = TimeHistory::new(
+            Timeline::new(start),
+            Arbitrary::arbitrary(g),
+            u32::arbitrary(g) % 127 + 1,
+        );
+        for _ in 0..u8::arbitrary(g) % 4 {
+            let epoch =
+                history.current_era().time_era().epoch_start().0 + u32::arbitrary(g) % 16 + 1;
+            history = history
+                .change_era(
+                    Epoch(epoch),
+                    Arbitrary::arbitrary(g),
+                    u32::arbitrary(g) % 127 + 1,
+                )
+                .unwrap();
+        }
+        history
+    }
+}
@@ -38,18 +38,56 @@ pub struct TimeFrame {
 
 /// Duration of a slot
 ///
-/// For now we only supports duration down to the seconds
+/// The duration has a precision of a millisecond, and is less than 10 minutes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct SlotDuration(u64);
+pub struct SlotDuration(pub(crate) u64);
 
 impl SlotDuration {
+    const MAX_MILLIS: u64 = 600_000;
+
     pub fn from_secs(seconds: u32) -> Self {
         assert!(seconds < 600);
-        SlotDuration(seconds as u64)
+        SlotDuration(seconds as u64 * 1000)
+    }
+
+    pub fn from_millis(milliseconds: u64) -> Self {
+        assert!(milliseconds < Self::MAX_MILLIS);
+        SlotDuration(milliseconds)
+    }
+
+    /// Create a slot duration from a non-zero duration in milliseconds
+    ///
+    /// None is returned if the duration is zero, not a whole number of
+    /// milliseconds, or not less than 10 minutes.
+    pub fn from_duration(duration: Duration) -> Option<Self> {
+        let millis = duration.as_millis();
+        if millis == 0 || millis >= Self::MAX_MILLIS as u128 {
+            return None;
+        }
+        let slot_duration = SlotDuration(millis as u64);
+        if slot_duration.to_duration() != duration {
+            return None;
+        }
+        Some(slot_duration)
+    }
+
+    pub fn as_millis(self) -> u64 {
+        self.0
     }
 
     pub fn to_duration(self) -> Duration {
-        Duration::from_secs(self.0)
+        Duration::from_millis(self.0)
+    }
+
+    /// Duration of a number of slots
+    pub(crate) fn of_slots(self, slots: u64) -> Duration {
+        Duration::from_millis(slots * self.0)
+    }
+
+    /// Number of whole slots in a duration, and the leftover duration
+    pub(crate) fn slots_in(self, d: Duration) -> (u64, Duration) {
+        let slots = (d.as_millis() / self.0 as u128) as u64;
+        (slots, d - self.of_slots(slots))
     }
 }
 
@@ -90,7 +128,7 @@ impl TimeFrame {
     /// ```
     ///
     pub fn change_frame(&self, slot: Slot, duration_per_slot: SlotDuration) -> Self {
-        let d = Duration::from_secs(slot.0 * self.slot_duration.0);
+        let d = self.slot_duration.of_slots(slot.0);
         let new_timeline = self.timeline.advance(d);
         TimeFrame {
             timeline: new_timeline,
@@ -108,9 +146,7 @@ impl TimeFrame {
         match self.timeline.differential(at) {
             None => None,
             Some(t) => {
-                let slot_nb = t.0.as_secs() / self.slot_duration.0;
-                let e = slot_nb * self.slot_duration.0;
-                let d = t.0 - Duration::from_secs(e); // cannot wrap
+                let (slot_nb, d) = self.slot_duration.slots_in(t.0);
                 Some(SlotAndDuration {
                     slot: Slot(self.slot_offset.0 + slot_nb),
                     offset: d,
@@ -127,7 +163,7 @@ impl TimeFrame {
         match self.timeline.differential(at) {
             None => None,
             Some(t) => {
-                let slot_nb = t.0.as_secs() / self.slot_duration.0;
+                let (slot_nb, _) = self.slot_duration.slots_in(t.0);
                 Some(Slot(self.slot_offset.0 + slot_nb))
             }
         }
@@ -140,13 +176,21 @@ impl TimeFrame {
     pub fn slot_to_systemtime(&self, slot: Slot) -> Option<SystemTime> {
         match slot.0.checked_sub(self.slot_offset.0) {
             None => None,
-            Some(sd) => Some(self.timeline.0 + Duration::from_secs(sd * self.slot_duration.0)),
+            Some(sd) => Some(self.timeline.0 + self.slot_duration.of_slots(sd)),
         }
     }
 
-    /// Returns slot duration value.
+    /// Returns slot duration value, in whole seconds.
+    ///
+    /// The duration is truncated, so slots shorter than a second give 0.
+    #[deprecated(note = "truncates sub-second slots, use `slot_duration_precise`")]
     pub fn slot_duration(&self) -> u64 {
-        self.slot_duration.0
+        self.slot_duration.0 / 1000
+    }
+
+    /// Returns the precise slot duration
+    pub fn slot_duration_precise(&self) -> SlotDuration {
+        self.slot_duration
     }
 }
 
@@ -166,7 +210,7 @@ mod test {
 
         {
             let expected_slot = Slot(16);
-            let x = now + Duration::from_secs(expected_slot.0 * f0.0);
+            let x = now + f0.of_slots(expected_slot.0);
             assert_eq!(tf0.slot_at(&x), Some(expected_slot));
         }
 
@@ -186,4 +230,50 @@ mod test {
 
         assert_eq!(tf0.slot_at(&t2), Some(Slot(4)));
     }
+
+    #[test]
+    pub fn sub_second_slots() {
+        let now = SystemTime::now();
+        let tf0 = TimeFrame::new(Timeline::new(now), SlotDuration::from_millis(250));
+
+        let t1 = now + Duration::from_millis(1_100);
+        let precise = tf0.slot_at_precise(&t1).unwrap();
+        assert_eq!(precise.slot, Slot(4));
+        assert_eq!(precise.offset, Duration::from_millis(100));
+        assert_eq!(tf0.slot_at(&t1), Some(Slot(4)));
+        assert_eq!(
+            tf0.slot_to_systemtime(Slot(4)),
+            Some(now + Duration::from_secs(1))
+        );
+
+        assert_eq!(tf0.slot_duration_precise(), SlotDuration::from_millis(250));
+
+        let tf1 = tf0.change_frame(Slot(4), SlotDuration::from_millis(1_500));
+        assert_eq!(
+            tf1.slot_at(&(now + Duration::from_millis(3_999))),
+            Some(Slot(5))
+        );
+        assert_eq!(
+            tf1.slot_at(&(now + Duration::from_millis(4_000))),
+            Some(Slot(6))
+        );
+    }
+
+    #[test]
+    pub fn slot_duration_from_duration() {
+        assert_eq!(
+            SlotDuration::from_duration(Duration::from_millis(1_500)),
+            Some(SlotDuration::from_millis(1_500))
+        );
+        assert_eq!(
+            SlotDuration::from_duration(Duration::from_secs(20)),
+            Some(SlotDuration::from_secs(20))
+        );
+        assert_eq!(SlotDuration::from_duration(Duration::from_secs(0)), None);
+        assert_eq!(
+            SlotDuration::from_duration(Duration::from_micros(1_500)),
+            None
+        );
+        assert_eq!(SlotDuration::from_duration(Duration::from_secs(600)), None);
+    }
 }
@@ -2,7 +2,7 @@ use crate::units::DurationSeconds;
 use std::time::{Duration, SystemTime};
 
 /// Represent a timeline with a specific start point rooted on earth time.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Timeline(pub(crate) SystemTime);
 
 /// Represent an offset in time units in the timeline
@@ -39,6 +39,18 @@ fn bench_hamt_insert(c: &mut Criterion) {
     });
 }
 
+fn bench_hamt_mut_insert(c: &mut Criterion) {
+    c.bench_function("bench_hamt_mut_insert", |b| {
+        b.iter(|| {
+            let mut h: HamtMut<DefaultHasher, Key, u32> = HamtMut::new();
+            for k in keys() {
+                h.insert(k, 2).unwrap()
+            }
+            h.freeze()
+        })
+    });
+}
+
 fn bench_btreemap_remove(c: &mut Criterion) {
     let mut h: BTreeMap<Key, u32> = BTreeMap::new();
     for k in keys() {
@@ -74,5 +86,10 @@ criterion_group!(
     bench_btreemap_insert,
     bench_btreemap_remove,
 );
-criterion_group!(hamt, bench_hamt_insert, bench_hamt_remove);
+criterion_group!(
+    hamt,
+    bench_hamt_insert,
+    bench_hamt_mut_insert,
+    bench_hamt_remove
+);
 criterion_main!(reference_btree, hamt);
@@ -0,0 +1,106 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use imhamt::merkle::MerkleHamt;
+use imhamt::*;
+
+use std::collections::hash_map::DefaultHasher;
+
+type Key = String;
+
+const NB: usize = 1000;
+
+fn keys() -> Vec<Key> {
+    let mut v = Vec::with_capacity(NB);
+    for i in 0..NB {
+        v.push(format!("key {}", i))
+    }
+    v
+}
+
+fn bench_hamt_insert(c: &mut Criterion) {
+    c.bench_function("bench_hamt_insert", |b| {
+        b.iter(|| {
+            let mut h: Hamt<DefaultHasher, Key, u32> = Hamt::new();
+            for k in keys() {
+                h = h.insert(k, 2).unwrap()
+            }
+        })
+    });
+}
+
+fn bench_merkle_hamt_insert(c: &mut Criterion) {
+    c.bench_function("bench_merkle_hamt_insert", |b| {
+        b.iter(|| {
+            let mut h: MerkleHamt<DefaultHasher, Key, u32> = MerkleHamt::new();
+            for k in keys() {
+                h = h.insert(k, 2).unwrap()
+            }
+        })
+    });
+}
+
+fn bench_hamt_update(c: &mut Criterion) {
+    let h: Hamt<DefaultHasher, Key, u32> = keys().into_iter().map(|k| (k, 2)).collect();
+    c.bench_function("bench_hamt_update", |b| {
+        b.iter(|| {
+            let mut h2 = h.clone();
+            for k in keys() {
+                h2 = h2.replace(&k, 3).unwrap().0
+            }
+        })
+    });
+}
+
+fn bench_merkle_hamt_update(c: &mut Criterion) {
+    let h: MerkleHamt<DefaultHasher, Key, u32> = keys().into_iter().map(|k| (k, 2)).collect();
+    c.bench_function("bench_merkle_hamt_update", |b| {
+        b.iter(|| {
+            let mut h2 = h.clone();
+            for k in keys() {
+                h2 = h2.replace(&k, 3).unwrap().0
+            }
+        })
+    });
+}
+
+fn bench_hamt_remove(c: &mut Criterion) {
+    let h: Hamt<DefaultHasher, Key, u32> = keys().into_iter().map(|k| (k, 2)).collect();
+    c.bench_function("bench_hamt_remove", |b| {
+        b.iter(|| {
+            let mut h2 = h.clone();
+            for k in keys() {
+                h2 = h2.remove(&k).unwrap()
+            }
+        })
+    });
+}
+
+fn bench_merkle_hamt_remove(c: &mut Criterion) {
+    let h: MerkleHamt<DefaultHasher, Key, u32> = keys().into_iter().map(|k| (k, 2)).collect();
+    c.bench_function("bench_merkle_hamt_remove", |b| {
+        b.iter(|| {
+            let mut h2 = h.clone();
+            for k in keys() {
+                h2 = h2.remove(&k).unwrap()
+            }
+        })
+    });
+}
+
+fn bench_merkle_hamt_prove_verify(c: &mut Criterion) {
+    let h: MerkleHamt<DefaultHasher, Key, u32> = keys().into_iter().map(|k| (k, 2)).collect();
+    let root = h.root_hash();
+    c.bench_function("bench_merkle_hamt_prove_verify", |b| {
+        b.iter(|| {
+            for k in keys() {
+                h.prove(&k).verify(&root, &k).unwrap();
+            }
+        })
+    });
+}
+
+criterion_group!(insert, bench_hamt_insert, bench_merkle_hamt_insert);
+criterion_group!(update, bench_hamt_update, bench_merkle_hamt_update);
+criterion_group!(remove, bench_hamt_remove, bench_merkle_hamt_remove);
+criterion_group!(proof, bench_merkle_hamt_prove_verify);
+criterion_main!(insert, update, remove, proof);
@@ -63,6 +63,16 @@ impl SmallBitmap {
         SmallBitmap(0u32)
     }
 
+    /// Create a bitmap from its raw representation
+    pub const fn from_u32(v: u32) -> Self {
+        SmallBitmap(v)
+    }
+
+    /// Raw representation of the bitmap
+    pub const fn to_u32(self) -> u32 {
+        self.0
+    }
+
     #[inline]
     pub fn is_empty(self) -> bool {
         self.0 == 0
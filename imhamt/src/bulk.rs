@@ -0,0 +1,78 @@
+use super::bitmap::SmallBitmap;
+use super::hamt::Hamt;
+use super::hash::{Hash, HashedKey, Hasher};
+use super::node::{Collision, Entry, Node};
+use super::sharedref::SharedRef;
+use std::marker::PhantomData;
+use std::mem;
+
+// an element still to be placed in the tree, taken out once in its leaf
+type Item<K, V> = (HashedKey, Option<(K, V)>);
+
+impl<H: Hasher + Default, K: Eq + Hash, V> Hamt<H, K, V> {
+    /// Build a HAMT from all the elements of an iterator at once
+    ///
+    /// The elements are sorted by hash, so that each node is built once
+    /// from the elements under it, instead of being copied on every insert
+    /// in its subtree. The result is the same as with `collect()`: when a
+    /// key appears several times, the first value is kept.
+    pub fn from_iter_bulk<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut items: Vec<Item<K, V>> = iter
+            .into_iter()
+            .map(|(k, v)| (HashedKey::compute(PhantomData::<H>, &k), Some((k, v))))
+            .collect();
+        // the bits of the hash are reversed so that the elements under a
+        // same node, which share the lowest bits of their hash, end up
+        // contiguous. The sort is stable to keep the first duplicated key.
+        items.sort_by_key(|(h, _)| h.0.reverse_bits());
+        Self::from_root(bulk_node(&mut items, 0))
+    }
+}
+
+fn bulk_node<K: PartialEq, V>(items: &mut [Item<K, V>], lvl: usize) -> Node<K, V> {
+    let mut children = Vec::new();
+    let mut rest = items;
+    while let Some((h, _)) = rest.first() {
+        let idx = h.level_index(lvl);
+        let len = rest
+            .iter()
+            .take_while(|(h, _)| h.level_index(lvl) == idx)
+            .count();
+        let (group, tail) = mem::take(&mut rest).split_at_mut(len);
+        rest = tail;
+        children.push((idx, SharedRef::new(bulk_entry(group, lvl))));
+    }
+    // the groups follow the reversed bits of the level index
+    children.sort_by_key(|(idx, _)| idx.0);
+    Node {
+        bitmap: children
+            .iter()
+            .fold(SmallBitmap::new(), |bitmap, (idx, _)| {
+                bitmap.set_index(*idx)
+            }),
+        children: children.into_iter().map(|(_, child)| child).collect(),
+    }
+}
+
+// build the entry of a group of elements sharing the same index at `lvl`,
+// with the same structure as the successive inserts of the elements
+fn bulk_entry<K: PartialEq, V>(group: &mut [Item<K, V>], lvl: usize) -> Entry<K, V> {
+    let h = group[0].0;
+    // the group is sorted, so it has a single hash if the ends are equal
+    if group[group.len() - 1].0 != h {
+        return Entry::SubNode(bulk_node(group, lvl + 1));
+    }
+    let mut col: Vec<(K, V)> = Vec::with_capacity(group.len());
+    for (_, kv) in group.iter_mut() {
+        let (k, v) = kv.take().unwrap();
+        if !col.iter().any(|(lk, _)| *lk == k) {
+            col.push((k, v));
+        }
+    }
+    if col.len() == 1 {
+        let (k, v) = col.pop().unwrap();
+        Entry::Leaf(h, k, v)
+    } else {
+        Entry::LeafMany(h, Collision::from_vec(col))
+    }
+}
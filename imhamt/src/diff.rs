@@ -0,0 +1,192 @@
+use super::hamt::Hamt;
+use super::hash::{Hash, Hasher, LevelIndex};
+use super::node::{Entry, Node};
+use super::sharedref::SharedRef;
+
+/// Difference on a single key between two versions of a HAMT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<'a, K, V> {
+    /// The key is only present in the new version
+    Added(&'a K, &'a V),
+    /// The key is only present in the old version
+    Removed(&'a K, &'a V),
+    /// The key is present in both versions, with the old and new values
+    Changed(&'a K, &'a V, &'a V),
+}
+
+impl<'a, K, V> Change<'a, K, V> {
+    pub fn key(&self) -> &'a K {
+        match self {
+            Change::Added(k, _) | Change::Removed(k, _) | Change::Changed(k, _, _) => k,
+        }
+    }
+}
+
+// old and new version of a node at the same position
+type NodePair<'a, K, V> = (&'a Node<K, V>, &'a Node<K, V>);
+
+/// Iterator over the changes between two versions of a HAMT, in no
+/// particular order.
+///
+/// Subtrees shared by both versions are skipped without being visited,
+/// so the cost depends on the size of the difference rather than on the
+/// size of the trees.
+pub struct Diff<'a, K, V> {
+    stack: Vec<NodePair<'a, K, V>>,
+    pending: Vec<Change<'a, K, V>>,
+}
+
+/// Key modified differently by the two sides of a three-way merge
+///
+/// A value of `None` means the key is absent from the corresponding version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict<'a, K, V> {
+    pub key: &'a K,
+    pub base: Option<&'a V>,
+    pub ours: Option<&'a V>,
+    pub theirs: Option<&'a V>,
+}
+
+fn get_child<K, V>(node: &Node<K, V>, idx: LevelIndex) -> Option<&SharedRef<Entry<K, V>>> {
+    let i = node.bitmap.get_index_sparse(idx);
+    if i.is_not_found() {
+        None
+    } else {
+        Some(node.get_child(i))
+    }
+}
+
+fn entry_for_each<'a, K, V, F>(entry: &'a Entry<K, V>, f: &mut F)
+where
+    F: FnMut(&'a K, &'a V),
+{
+    match entry {
+        Entry::Leaf(_, k, v) => f(k, v),
+        Entry::LeafMany(_, col) => col.iter().for_each(|(k, v)| f(k, v)),
+        Entry::SubNode(sub) => sub.iter().for_each(|e| entry_for_each(e, f)),
+    }
+}
+
+impl<'a, K: PartialEq, V: PartialEq> Diff<'a, K, V> {
+    fn new(old: &'a Node<K, V>, new: &'a Node<K, V>) -> Self {
+        Diff {
+            stack: vec![(old, new)],
+            pending: Vec::new(),
+        }
+    }
+
+    fn diff_nodes(&mut self, old: &'a Node<K, V>, new: &'a Node<K, V>) {
+        let mut bits = old.bitmap.to_u32() | new.bitmap.to_u32();
+        while bits != 0 {
+            let idx = LevelIndex(bits.trailing_zeros() as usize);
+            bits &= bits - 1;
+            match (get_child(old, idx), get_child(new, idx)) {
+                (Some(o), Some(n)) if SharedRef::ptr_eq(o, n) => {}
+                (Some(o), Some(n)) => match (o.as_ref(), n.as_ref()) {
+                    (Entry::SubNode(osub), Entry::SubNode(nsub)) => self.stack.push((osub, nsub)),
+                    (o, n) => self.diff_entries(o, n),
+                },
+                (Some(o), None) => {
+                    let pending = &mut self.pending;
+                    entry_for_each(o, &mut |k, v| pending.push(Change::Removed(k, v)))
+                }
+                (None, Some(n)) => {
+                    let pending = &mut self.pending;
+                    entry_for_each(n, &mut |k, v| pending.push(Change::Added(k, v)))
+                }
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+
+    // compare two entries at the same position, when at most one of them
+    // is a subnode. The other one holds at most a few keys.
+    fn diff_entries(&mut self, old: &'a Entry<K, V>, new: &'a Entry<K, V>) {
+        let mut olds = Vec::new();
+        entry_for_each(old, &mut |k, v| olds.push((k, v)));
+        let mut news = Vec::new();
+        entry_for_each(new, &mut |k, v| news.push((k, v)));
+
+        for (k, v) in olds {
+            match news.iter().position(|(nk, _)| *nk == k) {
+                None => self.pending.push(Change::Removed(k, v)),
+                Some(i) => {
+                    let (_, nv) = news.swap_remove(i);
+                    if v != nv {
+                        self.pending.push(Change::Changed(k, v, nv))
+                    }
+                }
+            }
+        }
+        self.pending
+            .extend(news.into_iter().map(|(k, v)| Change::Added(k, v)));
+    }
+}
+
+impl<'a, K: PartialEq, V: PartialEq> Iterator for Diff<'a, K, V> {
+    type Item = Change<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(change) = self.pending.pop() {
+                return Some(change);
+            }
+            let (old, new) = self.stack.pop()?;
+            self.diff_nodes(old, new);
+        }
+    }
+}
+
+impl<H: Hasher + Default, K: Eq + Hash, V: PartialEq> Hamt<H, K, V> {
+    /// Enumerate the changes needed to go from this version to `other`
+    ///
+    /// Subtrees shared by the two versions are not visited, so diffing a
+    /// HAMT against one derived from it only costs the modified paths.
+    pub fn diff<'a>(&'a self, other: &'a Self) -> Diff<'a, K, V> {
+        Diff::new(&self.root, &other.root)
+    }
+}
+
+impl<H: Hasher + Default, K: Eq + Hash + Clone, V: PartialEq + Clone> Hamt<H, K, V> {
+    /// Three-way merge of two versions derived from a common `base`
+    ///
+    /// The changes from `base` to `theirs` are applied on top of `ours`.
+    /// When both sides modified a key differently, `resolve` is called
+    /// with the conflicting values and returns the merged value, or None
+    /// to leave the key out of the merged version.
+    pub fn merge<F, E>(base: &Self, ours: &Self, theirs: &Self, mut resolve: F) -> Result<Self, E>
+    where
+        F: FnMut(MergeConflict<'_, K, V>) -> Result<Option<V>, E>,
+    {
+        let mut merged = ours.clone();
+        for change in base.diff(theirs) {
+            let (key, base_value, theirs_value) = match change {
+                Change::Added(k, v) => (k, None, Some(v)),
+                Change::Removed(k, v) => (k, Some(v), None),
+                Change::Changed(k, old, new) => (k, Some(old), Some(new)),
+            };
+            let ours_value = ours.lookup(key);
+            let value = if ours_value == base_value {
+                theirs_value.cloned()
+            } else if ours_value == theirs_value {
+                continue;
+            } else {
+                resolve(MergeConflict {
+                    key,
+                    base: base_value,
+                    ours: ours_value,
+                    theirs: theirs_value,
+                })?
+            };
+            // the keys of a diff are unique, so the key in the merged
+            // version is still as in ours, and the unwraps are safe
+            merged = match (ours_value, value) {
+                (None, None) => merged,
+                (None, Some(v)) => merged.insert(key.clone(), v).unwrap(),
+                (Some(_), None) => merged.remove(key).unwrap(),
+                (Some(_), Some(v)) => merged.replace(key, v).unwrap().0,
+            };
+        }
+        Ok(merged)
+    }
+}
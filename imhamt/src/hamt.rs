@@ -4,6 +4,7 @@ use super::node::{
     update_rec, Entry, LookupRet, Node, NodeIter,
 };
 pub use super::operation::{InsertError, RemoveError, ReplaceError, UpdateError};
+use super::sharedref::SharedRef;
 use std::borrow::Borrow;
 use std::error::Error;
 use std::fmt::Debug;
@@ -12,12 +13,21 @@ use std::marker::PhantomData;
 use std::mem::swap;
 use std::slice;
 
-#[derive(Clone)]
 pub struct Hamt<H: Hasher + Default, K: PartialEq + Eq + Hash, V> {
-    root: Node<K, V>,
+    pub(crate) root: Node<K, V>,
     hasher: PhantomData<H>,
 }
 
+// not derived, as the hasher type itself does not need to be Clone
+impl<H: Hasher + Default, K: Eq + Hash + Clone, V: Clone> Clone for Hamt<H, K, V> {
+    fn clone(&self) -> Self {
+        Hamt {
+            root: self.root.clone(),
+            hasher: PhantomData,
+        }
+    }
+}
+
 pub struct HamtIter<'a, K, V> {
     stack: Vec<NodeIter<'a, K, V>>,
     content: Option<slice::Iter<'a, (K, V)>>,
@@ -31,8 +41,12 @@ impl<H: Hasher + Default, K: Eq + Hash, V> Default for Hamt<H, K, V> {
 
 impl<H: Hasher + Default, K: Eq + Hash, V> Hamt<H, K, V> {
     pub fn new() -> Self {
+        Self::from_root(Node::new())
+    }
+
+    pub(crate) fn from_root(root: Node<K, V>) -> Self {
         Hamt {
-            root: Node::new(),
+            root,
             hasher: PhantomData,
         }
     }
@@ -197,18 +211,7 @@ impl<H: Hasher + Default, K: Hash + Eq, V> Hamt<H, K, V> {
         Q: Hash + Eq,
     {
         let h = HashedKey::compute(self.hasher, k);
-        let mut n = &self.root;
-        let mut lvl = 0;
-        loop {
-            match lookup_one(n, &h, lvl, k) {
-                LookupRet::NotFound => return None,
-                LookupRet::Found(v) => return Some(v),
-                LookupRet::ContinueIn(subnode) => {
-                    lvl += 1;
-                    n = &subnode;
-                }
-            }
-        }
+        lookup_from(&self.root, h, k)
     }
 
     /// Check if the key is contained into the HAMT
@@ -221,8 +224,33 @@ impl<H: Hasher + Default, K: Hash + Eq, V> Hamt<H, K, V> {
     }
 
     pub fn iter(&self) -> HamtIter<K, V> {
+        HamtIter::from_entries(&self.root.children)
+    }
+}
+
+pub(crate) fn lookup_from<'a, Q, K, V>(root: &'a Node<K, V>, h: HashedKey, k: &Q) -> Option<&'a V>
+where
+    K: Borrow<Q> + PartialEq,
+    Q: PartialEq,
+{
+    let mut n = root;
+    let mut lvl = 0;
+    loop {
+        match lookup_one(n, &h, lvl, k) {
+            LookupRet::NotFound => return None,
+            LookupRet::Found(v) => return Some(v),
+            LookupRet::ContinueIn(subnode) => {
+                lvl += 1;
+                n = &subnode;
+            }
+        }
+    }
+}
+
+impl<'a, K, V> HamtIter<'a, K, V> {
+    pub(crate) fn from_entries(entries: &'a [SharedRef<Entry<K, V>>]) -> Self {
         HamtIter {
-            stack: vec![self.root.iter()],
+            stack: vec![entries.iter()],
             content: None,
         }
     }
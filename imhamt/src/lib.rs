@@ -6,14 +6,27 @@ extern crate quickcheck;
 extern crate quickcheck_macros;
 
 mod bitmap;
+mod bulk;
+mod diff;
 mod hamt;
 mod hash;
 mod helper;
+#[cfg(feature = "merkle")]
+pub mod merkle;
 mod node;
 mod operation;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod persist;
+#[cfg(feature = "persistent")]
+pub mod persistent;
 mod sharedref;
+mod transient;
 
+pub use diff::{Change, Diff, MergeConflict};
 pub use hamt::*;
+pub use persist::Persist;
+pub use transient::HamtMut;
 
 #[cfg(test)]
 mod tests {
@@ -26,6 +39,7 @@ mod tests {
     use std::collections::BTreeMap;
     use std::convert::Infallible;
     use std::hash::Hash;
+    use std::iter::FromIterator;
 
     #[derive(Debug, Clone)]
     enum PlanOperation<K, V> {
@@ -433,4 +447,325 @@ mod tests {
         let after_iter = BTreeMap::from_iter(h.iter().map(|(k, v)| (k.clone(), *v)));
         reference == after_iter
     }
+
+    fn transient_plan_equivalent(
+        initial: Hamt<DefaultHasher, String, u32>,
+        xs: Plan<String, u32>,
+    ) -> bool {
+        let mut h = initial.clone();
+        let mut hm = initial.clone().thaw();
+        let mut keys: Vec<String> = initial.iter().map(|(k, _)| k.clone()).collect();
+        for op in xs.0 {
+            let nth = |r: usize| keys.get(r % keys.len().max(1)).cloned();
+            let same = match op {
+                PlanOperation::Insert(k, v) => {
+                    keys.push(k.clone());
+                    let r = h.insert(k.clone(), v);
+                    let rm = hm.insert(k, v);
+                    let same = r.as_ref().map(|_| ()).map_err(|e| *e) == rm;
+                    h = r.unwrap_or(h);
+                    same
+                }
+                PlanOperation::DeleteOne(r) => match nth(r) {
+                    None => continue,
+                    Some(k) => {
+                        let r = h.remove(&k);
+                        let same = r.as_ref().map(|_| ()).map_err(|e| *e) == hm.remove(&k);
+                        h = r.unwrap_or(h);
+                        same
+                    }
+                },
+                PlanOperation::DeleteOneMatching(r) => match nth(r) {
+                    None => continue,
+                    Some(k) => {
+                        // alternate between matching and non matching values
+                        let v = h.lookup(&k).map_or(0, |v| v + (r % 2) as u32);
+                        let r = h.remove_match(&k, &v);
+                        let same =
+                            r.as_ref().map(|_| ()).map_err(|e| *e) == hm.remove_match(&k, &v);
+                        h = r.unwrap_or(h);
+                        same
+                    }
+                },
+                PlanOperation::Replace(r, v) => match nth(r) {
+                    None => continue,
+                    Some(k) => {
+                        let r = h.replace(&k, v);
+                        let same =
+                            r.as_ref().map(|(_, old)| *old).map_err(|e| *e) == hm.replace(&k, v);
+                        h = r.map(|(h, _)| h).unwrap_or(h);
+                        same
+                    }
+                },
+                PlanOperation::ReplaceWith(r) => match nth(r) {
+                    None => continue,
+                    Some(k) => {
+                        let f = |v: &u32| v.wrapping_mul(2);
+                        let r = h.replace_with(&k, f);
+                        let same = r.as_ref().map(|_| ()).map_err(|e| *e) == hm.replace_with(&k, f);
+                        h = r.unwrap_or(h);
+                        same
+                    }
+                },
+                PlanOperation::Update(r) => match nth(r) {
+                    None => continue,
+                    Some(k) => {
+                        let r = h.update(&k, next_u32);
+                        let same =
+                            r.as_ref().map(|_| ()).map_err(|e| *e) == hm.update(&k, next_u32);
+                        h = r.unwrap_or(h);
+                        same
+                    }
+                },
+                PlanOperation::UpdateRemoval(r) => match nth(r) {
+                    None => continue,
+                    Some(k) => {
+                        let remove = |_: &u32| Ok::<_, Infallible>(None);
+                        let r = h.update(&k, remove);
+                        let same = r.as_ref().map(|_| ()).map_err(|e| *e) == hm.update(&k, remove);
+                        h = r.unwrap_or(h);
+                        same
+                    }
+                },
+            };
+            if !same {
+                return false;
+            }
+        }
+        let expected = BTreeMap::from_iter(h.iter().map(|(k, v)| (k.clone(), *v)));
+        let frozen = hm.freeze();
+        frozen.size() == expected.len() && property_btreemap_eq(&expected, &frozen)
+    }
+
+    #[quickcheck]
+    fn transient_equivalent(xs: Plan<String, u32>) -> bool {
+        transient_plan_equivalent(Hamt::new(), xs)
+    }
+
+    #[quickcheck]
+    fn transient_shared_equivalent(xs: Plan<String, u32>, ys: Plan<String, u32>) -> bool {
+        // the transient version starts from nodes shared with the original
+        // tree, which must be left untouched
+        let (initial, reference) = arbitrary_hamt_and_btree(xs, next_u32, |v| v.wrapping_mul(2));
+        transient_plan_equivalent(initial.clone(), ys) && property_btreemap_eq(&reference, &initial)
+    }
+
+    #[test]
+    fn transient_insert_or_update() {
+        let mut hm: HamtMut<DefaultHasher, String, u32> = HamtMut::new();
+        for i in 0..100u32 {
+            hm.insert_or_update_simple(format!("{}", i % 10), 1, |v| Some(v + 1));
+        }
+        let h = hm.freeze();
+        assert_eq!(h.size(), 10);
+        assert!(h.iter().all(|(_, v)| *v == 10));
+    }
+
+    // hasher with only a few distinct hashes, to exercise collisions
+    #[derive(Default)]
+    struct CollidingHasher(DefaultHasher);
+
+    impl std::hash::Hasher for CollidingHasher {
+        fn finish(&self) -> u64 {
+            self.0.finish() % 100
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.write(bytes)
+        }
+    }
+
+    // hasher keeping the keys below 1000 as their hash, so that 1 and 1001
+    // collide while 33 has another hash under the same index at the root
+    #[derive(Default)]
+    struct ModuloHasher(u64);
+
+    impl std::hash::Hasher for ModuloHasher {
+        fn finish(&self) -> u64 {
+            self.0 % 1000
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for b in bytes {
+                self.0 = (self.0 << 8) | u64::from(*b);
+            }
+        }
+
+        fn write_u32(&mut self, i: u32) {
+            self.0 = u64::from(i)
+        }
+    }
+
+    #[test]
+    fn collision_next_to_another_hash() {
+        let h: Hamt<ModuloHasher, u32, u32> = vec![(1, 1), (1001, 2)].into_iter().collect();
+        assert_eq!(h.remove(&33).err(), Some(RemoveError::KeyNotFound));
+        assert_eq!(
+            h.remove_match(&33, &3).err(),
+            Some(RemoveError::KeyNotFound)
+        );
+        assert_eq!(h.replace(&33, 3).err(), Some(ReplaceError::KeyNotFound));
+        assert_eq!(
+            h.replace_with(&33, |v| *v).err(),
+            Some(ReplaceError::KeyNotFound)
+        );
+        assert_eq!(
+            h.update::<_, Infallible>(&33, |v| Ok(Some(*v))).err(),
+            Some(UpdateError::KeyNotFound)
+        );
+
+        let h = h.insert(33, 3).unwrap();
+        assert_eq!(h.lookup(&1), Some(&1));
+        assert_eq!(h.lookup(&1001), Some(&2));
+        assert_eq!(h.lookup(&33), Some(&3));
+        let h = h.remove(&1001).unwrap();
+        assert_eq!(h.size(), 2);
+        assert_eq!(h.lookup(&1), Some(&1));
+        assert_eq!(h.lookup(&33), Some(&3));
+    }
+
+    fn bulk_equivalent_with<H: std::hash::Hasher + Default>(xs: Vec<(u32, u32)>) -> bool {
+        let sequential: Hamt<H, u32, u32> = xs.iter().cloned().collect();
+        let bulk = Hamt::<H, u32, u32>::from_iter_bulk(xs.iter().cloned());
+        let mut reference = BTreeMap::new();
+        for (k, v) in xs {
+            reference.entry(k).or_insert(v);
+        }
+        bulk.size() == reference.len()
+            && reference.iter().all(|(k, v)| bulk.lookup(k) == Some(v))
+            && bulk.diff(&sequential).count() == 0
+            && (0..200)
+                .filter(|k| !reference.contains_key(k))
+                .all(|k| bulk.remove(&k).err() == Some(RemoveError::KeyNotFound))
+    }
+
+    #[quickcheck]
+    fn bulk_equivalent(xs: LargeVec<(u32, u32)>) -> bool {
+        bulk_equivalent_with::<DefaultHasher>(xs.0)
+    }
+
+    #[quickcheck]
+    fn bulk_collisions_equivalent(xs: LargeVec<(u32, u32)>) -> bool {
+        bulk_equivalent_with::<CollidingHasher>(xs.0)
+    }
+
+    #[quickcheck]
+    fn bulk_then_plan_equivalent(xs: Vec<(String, u32)>, plan: Plan<String, u32>) -> bool {
+        transient_plan_equivalent(Hamt::from_iter_bulk(xs), plan)
+    }
+
+    #[cfg(feature = "parallel")]
+    #[quickcheck]
+    fn par_iter_equivalent(xs: LargeVec<(String, u32)>) -> bool {
+        use rayon::iter::ParallelIterator;
+        let h = Hamt::<DefaultHasher, String, u32>::from_iter_bulk(xs.0);
+        let mut sequential: Vec<_> = h.iter().collect();
+        let mut parallel: Vec<_> = h.par_iter().collect();
+        sequential.sort();
+        parallel.sort();
+        sequential == parallel
+    }
+
+    fn apply_diff(
+        h: &Hamt<DefaultHasher, String, u32>,
+        other: &Hamt<DefaultHasher, String, u32>,
+    ) -> BTreeMap<String, u32> {
+        let mut applied: BTreeMap<_, _> = h.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        for change in h.diff(other) {
+            match change {
+                Change::Added(k, v) => assert!(applied.insert(k.clone(), *v).is_none()),
+                Change::Removed(k, v) => assert_eq!(applied.remove(k), Some(*v)),
+                Change::Changed(k, old, new) => {
+                    assert_ne!(old, new);
+                    assert_eq!(applied.insert(k.clone(), *new), Some(*old));
+                }
+            }
+        }
+        applied
+    }
+
+    #[quickcheck]
+    fn diff_equivalent(xs: Plan<String, u32>, ys: Plan<String, u32>) -> bool {
+        let (h1, _) = arbitrary_hamt_and_btree(xs, next_u32, |v| v.wrapping_mul(2));
+        let (h2, reference) = arbitrary_hamt_and_btree(ys, next_u32, |v| v.wrapping_mul(2));
+        apply_diff(&h1, &h2) == reference
+    }
+
+    #[test]
+    fn diff_shared_versions() {
+        let h: Hamt<DefaultHasher, String, u32> =
+            (0..10_000u32).map(|i| (i.to_string(), i)).collect();
+        assert_eq!(h.diff(&h).count(), 0);
+
+        let h2 = h
+            .insert("new".to_string(), 1)
+            .unwrap()
+            .remove(&"5".to_string())
+            .unwrap()
+            .replace(&"7".to_string(), 70)
+            .unwrap()
+            .0;
+        let mut changes: Vec<_> = h.diff(&h2).collect();
+        changes.sort_by_key(|c| c.key().clone());
+        assert_eq!(
+            changes,
+            vec![
+                Change::Removed(&"5".to_string(), &5),
+                Change::Changed(&"7".to_string(), &7, &70),
+                Change::Added(&"new".to_string(), &1),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_three_way() {
+        let base: Hamt<DefaultHasher, String, u32> =
+            (0..100u32).map(|i| (i.to_string(), i)).collect();
+        let key = |i: u32| i.to_string();
+
+        let ours = base
+            .replace(&key(1), 10)
+            .unwrap()
+            .0
+            .remove(&key(2))
+            .unwrap()
+            .replace(&key(3), 30)
+            .unwrap()
+            .0
+            .insert(key(200), 200)
+            .unwrap();
+        let theirs = base
+            .replace(&key(3), 31)
+            .unwrap()
+            .0
+            .remove(&key(4))
+            .unwrap()
+            .replace(&key(1), 10)
+            .unwrap()
+            .0
+            .insert(key(300), 300)
+            .unwrap();
+
+        let mut conflicts = Vec::new();
+        let merged = Hamt::merge(&base, &ours, &theirs, |c| {
+            conflicts.push((
+                c.key.clone(),
+                c.base.cloned(),
+                c.ours.cloned(),
+                c.theirs.cloned(),
+            ));
+            Ok::<_, Infallible>(c.theirs.cloned())
+        })
+        .unwrap();
+
+        assert_eq!(conflicts, vec![(key(3), Some(3), Some(30), Some(31))]);
+        let mut expected: BTreeMap<_, _> = base.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        expected.insert(key(1), 10);
+        expected.remove(&key(2));
+        expected.insert(key(3), 31);
+        expected.remove(&key(4));
+        expected.insert(key(200), 200);
+        expected.insert(key(300), 300);
+        assert!(property_btreemap_eq(&expected, &merged));
+    }
 }
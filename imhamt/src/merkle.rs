@@ -0,0 +1,902 @@
+//! HAMT authenticated by a Merkle hash of its nodes
+//!
+//! Every entry of a [`MerkleHamt`] carries the Blake2b-256 hash of its
+//! content, and every node is hashed from the hashes of its children, so
+//! that the hash of the root commits to the whole content of the tree.
+//! The hashes are kept up to date along the paths modified by each
+//! operation.
+//!
+//! A [`MerkleProof`] of a key holds the path from the root to the slot of
+//! the key, with the hashes of the siblings at every level. It allows
+//! anyone knowing the root hash to check the value associated with the
+//! key, or that the key is absent, without access to the tree. Proofs
+//! are serialized with [`Persist`], in the following format:
+//!
+//! ```text
+//! proof := depth:u8 step* end
+//! step  := bitmap:u32 count:u8 sibling:[u8;32]*
+//! end   := 0                         (absent)
+//!        | 1 hash:u64 kv             (leaf)
+//!        | 2 hash:u64 count:u32 kv*  (collision)
+//! kv    := klen:u32 key vlen:u32 value
+//! ```
+//!
+//! All integers are big endian. The keys and values are kept in their
+//! `Persist` encoding, so a decoded proof hashes to the same root.
+//!
+//! The tree is kept in a canonical shape: a subnode is only created for
+//! keys with different hashes, and is removed as soon as it holds a single
+//! leaf. The root hash is therefore determined by the content of the tree
+//! alone, whatever the sequence of operations that led to it.
+//!
+//! The hashes of the keys determine the layout of the tree, so the hasher
+//! must give the same results on all the parties checking proofs.
+
+use crate::bitmap::SmallBitmap;
+use crate::hash::{Hash, HashedKey, Hasher, LevelIndex};
+use crate::operation::{InsertError, RemoveError, ReplaceError, UpdateError};
+use crate::persist::{put_bytes, Persist, Reader};
+use crate::sharedref::SharedRef;
+use chain_crypto::Blake2b256;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt::Debug;
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::slice;
+use thiserror::Error;
+
+const TAG_LEAF: u8 = 0;
+const TAG_COLLISION: u8 = 1;
+const TAG_NODE: u8 = 2;
+
+fn hash_leaf(h: HashedKey, k: &[u8], v: &[u8]) -> Blake2b256 {
+    let mut buf = vec![TAG_LEAF];
+    buf.extend_from_slice(&h.0.to_be_bytes());
+    put_bytes(&mut buf, k);
+    put_bytes(&mut buf, v);
+    Blake2b256::new(&buf)
+}
+
+// the entries are hashed in the order of their keys, as the order of
+// insertion must not change the hash
+fn hash_collision(h: HashedKey, col: &[(Vec<u8>, Vec<u8>)]) -> Blake2b256 {
+    let mut sorted: Vec<_> = col.iter().collect();
+    sorted.sort();
+    let mut buf = vec![TAG_COLLISION];
+    buf.extend_from_slice(&h.0.to_be_bytes());
+    buf.extend_from_slice(&(col.len() as u32).to_be_bytes());
+    for (k, v) in sorted {
+        put_bytes(&mut buf, k);
+        put_bytes(&mut buf, v);
+    }
+    Blake2b256::new(&buf)
+}
+
+fn hash_node<'a, I>(bitmap: SmallBitmap, children: I) -> Blake2b256
+where
+    I: Iterator<Item = &'a Blake2b256>,
+{
+    let mut buf = vec![TAG_NODE];
+    buf.extend_from_slice(&bitmap.to_u32().to_be_bytes());
+    for hash in children {
+        buf.extend_from_slice(hash.as_ref());
+    }
+    Blake2b256::new(&buf)
+}
+
+fn encode_collision<K: Persist, V: Persist>(col: &[(K, V)]) -> Vec<(Vec<u8>, Vec<u8>)> {
+    col.iter()
+        .map(|(k, v)| (k.to_bytes(), v.to_bytes()))
+        .collect()
+}
+
+struct Node<K, V> {
+    bitmap: SmallBitmap,
+    children: Box<[Child<K, V>]>,
+}
+
+struct Child<K, V> {
+    hash: Blake2b256,
+    entry: SharedRef<Entry<K, V>>,
+}
+
+enum Entry<K, V> {
+    Leaf(HashedKey, K, V),
+    LeafMany(HashedKey, Box<[(K, V)]>),
+    SubNode(Node<K, V>),
+}
+
+impl<K, V> Clone for Node<K, V> {
+    fn clone(&self) -> Self {
+        Node {
+            bitmap: self.bitmap,
+            children: self.children.clone(),
+        }
+    }
+}
+
+impl<K, V> Clone for Child<K, V> {
+    fn clone(&self) -> Self {
+        Child {
+            hash: self.hash,
+            entry: SharedRef::clone(&self.entry),
+        }
+    }
+}
+
+impl<K: Persist, V: Persist> Child<K, V> {
+    fn new(entry: Entry<K, V>) -> Self {
+        let hash = match &entry {
+            Entry::Leaf(h, k, v) => hash_leaf(*h, &k.to_bytes(), &v.to_bytes()),
+            Entry::LeafMany(h, col) => hash_collision(*h, &encode_collision(col)),
+            Entry::SubNode(sub) => sub.hash(),
+        };
+        Child {
+            hash,
+            entry: SharedRef::new(entry),
+        }
+    }
+}
+
+impl<K, V> Child<K, V> {
+    fn is_subnode(&self) -> bool {
+        match self.entry.as_ref() {
+            Entry::SubNode(_) => true,
+            Entry::Leaf(..) | Entry::LeafMany(..) => false,
+        }
+    }
+}
+
+impl<K, V> Node<K, V> {
+    fn new() -> Self {
+        Node {
+            bitmap: SmallBitmap::new(),
+            children: Vec::new().into(),
+        }
+    }
+
+    fn hash(&self) -> Blake2b256 {
+        hash_node(self.bitmap, self.children.iter().map(|c| &c.hash))
+    }
+
+    fn get(&self, idx: LevelIndex) -> Option<(usize, &Child<K, V>)> {
+        let i = self.bitmap.get_index_sparse(idx);
+        if i.is_not_found() {
+            None
+        } else {
+            let pos = i.get_found();
+            Some((pos, &self.children[pos]))
+        }
+    }
+
+    fn set_at(&self, idx: LevelIndex, child: Child<K, V>) -> Self {
+        let pos = self.bitmap.get_sparse_pos(idx).get_found();
+        let mut children = self.children.to_vec();
+        children.insert(pos, child);
+        Node {
+            bitmap: self.bitmap.set_index(idx),
+            children: children.into(),
+        }
+    }
+
+    fn replace_at(&self, pos: usize, child: Child<K, V>) -> Self {
+        let mut children = self.children.to_vec();
+        children[pos] = child;
+        Node {
+            bitmap: self.bitmap,
+            children: children.into(),
+        }
+    }
+
+    fn clear_at(&self, idx: LevelIndex, pos: usize) -> Self {
+        let mut children = self.children.to_vec();
+        children.remove(pos);
+        Node {
+            bitmap: self.bitmap.clear_index(idx),
+            children: children.into(),
+        }
+    }
+}
+
+/// HAMT maintaining a Merkle hash of its content
+pub struct MerkleHamt<H, K, V> {
+    root: Node<K, V>,
+    root_hash: Blake2b256,
+    hasher: PhantomData<H>,
+}
+
+impl<H, K, V> Clone for MerkleHamt<H, K, V> {
+    fn clone(&self) -> Self {
+        MerkleHamt {
+            root: self.root.clone(),
+            root_hash: self.root_hash,
+            hasher: PhantomData,
+        }
+    }
+}
+
+pub struct MerkleHamtIter<'a, K, V> {
+    stack: Vec<slice::Iter<'a, Child<K, V>>>,
+    content: Option<slice::Iter<'a, (K, V)>>,
+}
+
+impl<'a, K, V> Iterator for MerkleHamtIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(content) = &mut self.content {
+                if let Some((k, v)) = content.next() {
+                    return Some((k, v));
+                }
+                self.content = None;
+            }
+            match self.stack.last_mut()?.next() {
+                None => {
+                    self.stack.pop();
+                }
+                Some(child) => match child.entry.as_ref() {
+                    Entry::Leaf(_, k, v) => return Some((k, v)),
+                    Entry::LeafMany(_, col) => self.content = Some(col.iter()),
+                    Entry::SubNode(sub) => self.stack.push(sub.children.iter()),
+                },
+            }
+        }
+    }
+}
+
+impl<H: Hasher + Default, K: Persist + Hash + Eq + Clone, V: Persist + Clone> Default
+    for MerkleHamt<H, K, V>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H, K, V> MerkleHamt<H, K, V>
+where
+    H: Hasher + Default,
+    K: Persist + Hash + Eq + Clone,
+    V: Persist + Clone,
+{
+    pub fn new() -> Self {
+        Self::from_root(Node::new())
+    }
+
+    fn from_root(root: Node<K, V>) -> Self {
+        MerkleHamt {
+            root_hash: root.hash(),
+            root,
+            hasher: PhantomData,
+        }
+    }
+
+    /// Hash committing to the whole content of the tree
+    pub fn root_hash(&self) -> Blake2b256 {
+        self.root_hash
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.bitmap.is_empty()
+    }
+
+    pub fn size(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn iter(&self) -> MerkleHamtIter<'_, K, V> {
+        MerkleHamtIter {
+            stack: vec![self.root.children.iter()],
+            content: None,
+        }
+    }
+
+    /// Try to get the element related to key K
+    pub fn lookup(&self, k: &K) -> Option<&V> {
+        let h = HashedKey::compute(self.hasher, k);
+        let mut node = &self.root;
+        let mut lvl = 0;
+        loop {
+            let (_, child) = node.get(h.level_index(lvl))?;
+            match child.entry.as_ref() {
+                Entry::Leaf(lh, lk, lv) => {
+                    return if *lh == h && lk == k { Some(lv) } else { None };
+                }
+                Entry::LeafMany(lh, col) => {
+                    return if *lh == h {
+                        col.iter().find(|(lk, _)| lk == k).map(|(_, v)| v)
+                    } else {
+                        None
+                    };
+                }
+                Entry::SubNode(sub) => {
+                    node = sub;
+                    lvl += 1;
+                }
+            }
+        }
+    }
+
+    pub fn contains_key(&self, k: &K) -> bool {
+        self.lookup(k).is_some()
+    }
+
+    pub fn insert(&self, k: K, v: V) -> Result<Self, InsertError> {
+        let h = HashedKey::compute(self.hasher, &k);
+        let root = insert_rec(&self.root, h, 0, k, v)?;
+        Ok(Self::from_root(root))
+    }
+
+    pub fn remove(&self, k: &K) -> Result<Self, RemoveError> {
+        self.modify::<_, Infallible>(k, |_| Ok(None))
+            .map_err(|_| RemoveError::KeyNotFound)
+    }
+
+    /// Remove the key only if it is associated with the value `v`
+    pub fn remove_match(&self, k: &K, v: &V) -> Result<Self, RemoveError>
+    where
+        V: PartialEq,
+    {
+        self.modify(k, |lv| {
+            if lv == v {
+                Ok(None)
+            } else {
+                Err(RemoveError::ValueNotMatching)
+            }
+        })
+        .map_err(|e| match e {
+            UpdateError::KeyNotFound => RemoveError::KeyNotFound,
+            UpdateError::ValueCallbackError(e) => e,
+        })
+    }
+
+    /// Replace the element at the key by the v and return the new tree
+    /// and the old value.
+    pub fn replace(&self, k: &K, v: V) -> Result<(Self, V), ReplaceError> {
+        let mut old = None;
+        let new = self
+            .modify::<_, Infallible>(k, |lv| {
+                old = Some(lv.clone());
+                Ok(Some(v))
+            })
+            .map_err(|_| ReplaceError::KeyNotFound)?;
+        Ok((new, old.expect("replaced value")))
+    }
+
+    /// Update the element at the key K.
+    ///
+    /// If the closure F in parameter returns None, then the key is deleted.
+    ///
+    /// If the key is not present then UpdateError::KeyNotFound is returned
+    pub fn update<F, U>(&self, k: &K, f: F) -> Result<Self, UpdateError<U>>
+    where
+        F: FnOnce(&V) -> Result<Option<V>, U>,
+        U: Error + Debug + 'static,
+    {
+        self.modify(k, f)
+    }
+
+    fn modify<F, U>(&self, k: &K, f: F) -> Result<Self, UpdateError<U>>
+    where
+        F: FnOnce(&V) -> Result<Option<V>, U>,
+        U: Error + Debug + 'static,
+    {
+        let h = HashedKey::compute(self.hasher, k);
+        let root = modify_rec(&self.root, h, 0, k, f)?;
+        Ok(Self::from_root(root))
+    }
+
+    /// Produce a proof of the value associated with the key, or of its
+    /// absence, against the current root hash
+    pub fn prove(&self, k: &K) -> MerkleProof<H, K, V> {
+        let h = HashedKey::compute(self.hasher, k);
+        let mut node = &self.root;
+        let mut path = Vec::new();
+        let mut lvl = 0;
+        let end = loop {
+            let found = node.get(h.level_index(lvl));
+            let pos = found.map(|(pos, _)| pos);
+            path.push(ProofStep {
+                bitmap: node.bitmap,
+                siblings: node
+                    .children
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| Some(*i) != pos)
+                    .map(|(_, c)| c.hash)
+                    .collect(),
+            });
+            match found.map(|(_, child)| child.entry.as_ref()) {
+                None => break ProofEnd::Absent,
+                Some(Entry::Leaf(lh, lk, lv)) => {
+                    break ProofEnd::Leaf(*lh, lk.to_bytes(), lv.to_bytes())
+                }
+                Some(Entry::LeafMany(lh, col)) => {
+                    break ProofEnd::Collision(*lh, encode_collision(col))
+                }
+                Some(Entry::SubNode(sub)) => {
+                    node = sub;
+                    lvl += 1;
+                }
+            }
+        };
+        MerkleProof {
+            path,
+            end,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<H, K, V> FromIterator<(K, V)> for MerkleHamt<H, K, V>
+where
+    H: Hasher + Default,
+    K: Persist + Hash + Eq + Clone,
+    V: Persist + Clone,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut h = MerkleHamt::new();
+        for (k, v) in iter {
+            if let Ok(newh) = h.insert(k, v) {
+                h = newh
+            }
+        }
+        h
+    }
+}
+
+fn insert_rec<K, V>(
+    node: &Node<K, V>,
+    h: HashedKey,
+    lvl: usize,
+    k: K,
+    v: V,
+) -> Result<Node<K, V>, InsertError>
+where
+    K: Persist + Eq + Clone,
+    V: Persist + Clone,
+{
+    let idx = h.level_index(lvl);
+    let (pos, child) = match node.get(idx) {
+        None => return Ok(node.set_at(idx, Child::new(Entry::Leaf(h, k, v)))),
+        Some(found) => found,
+    };
+    let entry = match child.entry.as_ref() {
+        Entry::Leaf(lh, lk, lv) if *lh == h => {
+            if *lk == k {
+                return Err(InsertError::EntryExists);
+            }
+            Entry::LeafMany(h, vec![(lk.clone(), lv.clone()), (k, v)].into())
+        }
+        Entry::LeafMany(lh, col) if *lh == h => {
+            if col.iter().any(|(lk, _)| *lk == k) {
+                return Err(InsertError::EntryExists);
+            }
+            let mut col = col.to_vec();
+            col.push((k, v));
+            Entry::LeafMany(h, col.into())
+        }
+        Entry::SubNode(sub) => Entry::SubNode(insert_rec(sub, h, lvl + 1, k, v)?),
+        // a different hash with the same index at this level: move the
+        // existing entry one level down, along with the new one
+        Entry::Leaf(lh, _, _) | Entry::LeafMany(lh, _) => {
+            let sub = Node::new().set_at(lh.level_index(lvl + 1), child.clone());
+            Entry::SubNode(insert_rec(&sub, h, lvl + 1, k, v)?)
+        }
+    };
+    Ok(node.replace_at(pos, Child::new(entry)))
+}
+
+// the returned node may be empty, or hold a single leaf
+fn modify_rec<K, V, F, U>(
+    node: &Node<K, V>,
+    h: HashedKey,
+    lvl: usize,
+    k: &K,
+    f: F,
+) -> Result<Node<K, V>, UpdateError<U>>
+where
+    K: Persist + Eq + Clone,
+    V: Persist + Clone,
+    F: FnOnce(&V) -> Result<Option<V>, U>,
+    U: Error + Debug + 'static,
+{
+    let idx = h.level_index(lvl);
+    let (pos, child) = node.get(idx).ok_or(UpdateError::KeyNotFound)?;
+    let child = match child.entry.as_ref() {
+        Entry::Leaf(lh, lk, lv) => {
+            if *lh != h || lk != k {
+                return Err(UpdateError::KeyNotFound);
+            }
+            f(lv)
+                .map_err(UpdateError::ValueCallbackError)?
+                .map(|v| Child::new(Entry::Leaf(h, lk.clone(), v)))
+        }
+        Entry::LeafMany(lh, col) => {
+            if *lh != h {
+                return Err(UpdateError::KeyNotFound);
+            }
+            let i = col
+                .iter()
+                .position(|(lk, _)| lk == k)
+                .ok_or(UpdateError::KeyNotFound)?;
+            let mut col = col.to_vec();
+            match f(&col[i].1).map_err(UpdateError::ValueCallbackError)? {
+                None => {
+                    col.remove(i);
+                }
+                Some(v) => col[i].1 = v,
+            }
+            let entry = if col.len() == 1 {
+                let (k, v) = col.pop().unwrap();
+                Entry::Leaf(h, k, v)
+            } else {
+                Entry::LeafMany(h, col.into())
+            };
+            Some(Child::new(entry))
+        }
+        Entry::SubNode(sub) => {
+            let sub = modify_rec(sub, h, lvl + 1, k, f)?;
+            // a subnode left with a single leaf is replaced by the leaf, to
+            // keep the shape of the tree canonical
+            match sub.children.as_ref() {
+                [] => None,
+                [single] if !single.is_subnode() => Some(single.clone()),
+                _ => Some(Child::new(Entry::SubNode(sub))),
+            }
+        }
+    };
+    Ok(match child {
+        None => node.clear_at(idx, pos),
+        Some(child) => node.replace_at(pos, child),
+    })
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum ProofError {
+    #[error("the proof does not match the structure of a HAMT")]
+    Malformed,
+    #[error("the proof does not lead to the expected root hash")]
+    RootMismatch,
+    #[error("the value in the proof cannot be decoded")]
+    InvalidValue,
+}
+
+/// Proof of the value associated with a key in a [`MerkleHamt`], or of
+/// the absence of the key
+pub struct MerkleProof<H, K, V> {
+    path: Vec<ProofStep>,
+    end: ProofEnd,
+    phantom: PhantomData<(H, K, V)>,
+}
+
+// a node on the path to the key, with the hashes of all its children but
+// the one on the path
+#[derive(Debug, Clone)]
+struct ProofStep {
+    bitmap: SmallBitmap,
+    siblings: Vec<Blake2b256>,
+}
+
+// the entry found in the slot of the key, at the end of the path
+#[derive(Debug, Clone)]
+enum ProofEnd {
+    Absent,
+    Leaf(HashedKey, Vec<u8>, Vec<u8>),
+    Collision(HashedKey, Vec<(Vec<u8>, Vec<u8>)>),
+}
+
+impl<H, K, V> Clone for MerkleProof<H, K, V> {
+    fn clone(&self) -> Self {
+        MerkleProof {
+            path: self.path.clone(),
+            end: self.end.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<H, K, V> Debug for MerkleProof<H, K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MerkleProof")
+            .field("path", &self.path)
+            .field("end", &self.end)
+            .finish()
+    }
+}
+
+const PROOF_ABSENT: u8 = 0;
+const PROOF_LEAF: u8 = 1;
+const PROOF_COLLISION: u8 = 2;
+
+impl<H, K, V> MerkleProof<H, K, V> {
+    fn decode_opt(data: &[u8]) -> Option<Self> {
+        let mut r = Reader(data);
+        let depth = r.u8()?;
+        let path = (0..depth)
+            .map(|_| {
+                let bitmap = SmallBitmap::from_u32(r.u32()?);
+                let count = r.u8()?;
+                let siblings = (0..count)
+                    .map(|_| r.take(Blake2b256::HASH_SIZE))
+                    .map(|h| Blake2b256::try_from_slice(h?).ok())
+                    .collect::<Option<_>>()?;
+                Some(ProofStep { bitmap, siblings })
+            })
+            .collect::<Option<_>>()?;
+        let end = match r.u8()? {
+            PROOF_ABSENT => ProofEnd::Absent,
+            PROOF_LEAF => {
+                let h = HashedKey(r.u64()?);
+                ProofEnd::Leaf(h, r.bytes()?.to_vec(), r.bytes()?.to_vec())
+            }
+            PROOF_COLLISION => {
+                let h = HashedKey(r.u64()?);
+                let count = r.u32()?;
+                let col = (0..count)
+                    .map(|_| Some((r.bytes()?.to_vec(), r.bytes()?.to_vec())))
+                    .collect::<Option<_>>()?;
+                ProofEnd::Collision(h, col)
+            }
+            _ => return None,
+        };
+        if !r.is_end() {
+            return None;
+        }
+        Some(MerkleProof {
+            path,
+            end,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<H, K, V> Persist for MerkleProof<H, K, V> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![self.path.len() as u8];
+        for step in self.path.iter() {
+            out.extend_from_slice(&step.bitmap.to_u32().to_be_bytes());
+            out.push(step.siblings.len() as u8);
+            for hash in step.siblings.iter() {
+                out.extend_from_slice(hash.as_ref());
+            }
+        }
+        match &self.end {
+            ProofEnd::Absent => out.push(PROOF_ABSENT),
+            ProofEnd::Leaf(h, k, v) => {
+                out.push(PROOF_LEAF);
+                out.extend_from_slice(&h.0.to_be_bytes());
+                put_bytes(&mut out, k);
+                put_bytes(&mut out, v);
+            }
+            ProofEnd::Collision(h, col) => {
+                out.push(PROOF_COLLISION);
+                out.extend_from_slice(&h.0.to_be_bytes());
+                out.extend_from_slice(&(col.len() as u32).to_be_bytes());
+                for (k, v) in col.iter() {
+                    put_bytes(&mut out, k);
+                    put_bytes(&mut out, v);
+                }
+            }
+        }
+        out
+    }
+
+    /// Decode a proof received from another party. The proof is only
+    /// checked for structure, and must be verified against a root hash
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Self::decode_opt(bytes)
+    }
+}
+
+impl<H: Hasher + Default, K: Persist + Hash, V: Persist> MerkleProof<H, K, V> {
+    /// Check the proof against a root hash, returning the value associated
+    /// with the key, or None if the proof shows that the key is absent
+    pub fn verify(&self, root: &Blake2b256, k: &K) -> Result<Option<V>, ProofError> {
+        let h = HashedKey::compute(PhantomData::<H>, k);
+        let kb = k.to_bytes();
+        let (mut hash, value) = match &self.end {
+            ProofEnd::Absent => (None, None),
+            ProofEnd::Leaf(lh, lk, lv) => {
+                let value = if *lh == h && *lk == kb {
+                    Some(lv)
+                } else {
+                    None
+                };
+                (Some(hash_leaf(*lh, lk, lv)), value)
+            }
+            ProofEnd::Collision(lh, col) => {
+                let value = if *lh == h {
+                    col.iter().find(|(lk, _)| *lk == kb).map(|(_, v)| v)
+                } else {
+                    None
+                };
+                (Some(hash_collision(*lh, col)), value)
+            }
+        };
+
+        for (lvl, step) in self.path.iter().enumerate().rev() {
+            let idx = h.level_index(lvl);
+            let mut children = step.siblings.clone();
+            match hash {
+                None if !step.bitmap.is_set(idx) => {}
+                Some(hash) if step.bitmap.is_set(idx) => {
+                    children.insert(step.bitmap.get_index_sparse(idx).get_found(), hash)
+                }
+                _ => return Err(ProofError::Malformed),
+            }
+            if children.len() != step.bitmap.present() {
+                return Err(ProofError::Malformed);
+            }
+            hash = Some(hash_node(step.bitmap, children.iter()));
+        }
+
+        match hash {
+            None => Err(ProofError::Malformed),
+            Some(hash) if hash != *root => Err(ProofError::RootMismatch),
+            Some(_) => value
+                .map(|v| V::from_bytes(v).ok_or(ProofError::InvalidValue))
+                .transpose(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::BTreeMap;
+
+    type TestHamt = MerkleHamt<DefaultHasher, u32, u32>;
+
+    #[quickcheck]
+    fn insert_remove_equivalent(xs: Vec<(u32, u32)>, removed: Vec<u32>) -> bool {
+        let mut reference = BTreeMap::new();
+        let mut h = TestHamt::new();
+        for (k, v) in xs {
+            if reference.contains_key(&k) {
+                continue;
+            }
+            reference.insert(k, v);
+            h = h.insert(k, v).unwrap();
+        }
+        for k in removed {
+            if reference.remove(&k).is_some() {
+                h = h.remove(&k).unwrap();
+            }
+        }
+        let entries: BTreeMap<_, _> = h.iter().map(|(k, v)| (*k, *v)).collect();
+        entries == reference && reference.iter().all(|(k, v)| h.lookup(k) == Some(v))
+    }
+
+    #[quickcheck]
+    fn root_hash_is_canonical(xs: Vec<(u32, u32)>, removed: Vec<u32>) -> bool {
+        let xs: BTreeMap<_, _> = xs.into_iter().collect();
+        let mut h = TestHamt::new();
+        for (k, v) in xs.iter().rev() {
+            h = h.insert(*k, *v).unwrap();
+        }
+        for k in removed.iter() {
+            if let Ok(newh) = h.remove(k) {
+                h = newh;
+            }
+        }
+        let remaining = xs.into_iter().filter(|(k, _)| !removed.contains(k));
+        h.root_hash() == remaining.collect::<TestHamt>().root_hash()
+    }
+
+    #[test]
+    fn root_hash_changes_with_content() {
+        let empty = TestHamt::new();
+        let h1 = empty.insert(1, 10).unwrap();
+        let h2 = h1.replace(&1, 11).unwrap().0;
+        assert_ne!(empty.root_hash(), h1.root_hash());
+        assert_ne!(h1.root_hash(), h2.root_hash());
+        assert_eq!(h2.remove(&1).unwrap().root_hash(), empty.root_hash());
+    }
+
+    #[quickcheck]
+    fn proofs_verify(xs: Vec<(u32, u32)>, absent: Vec<u32>) -> bool {
+        let h: TestHamt = xs.iter().cloned().collect();
+        let root = h.root_hash();
+        let included = h
+            .iter()
+            .all(|(k, v)| h.prove(k).verify(&root, k) == Ok(Some(*v)));
+        let excluded = absent
+            .iter()
+            .filter(|k| !h.contains_key(k))
+            .all(|k| h.prove(k).verify(&root, k) == Ok(None));
+        included && excluded
+    }
+
+    #[test]
+    fn proofs_are_bound_to_the_root_and_key() {
+        let h: TestHamt = (0..1000).map(|i| (i, i * 2)).collect();
+        let root = h.root_hash();
+        let proof = h.prove(&7);
+        assert_eq!(proof.verify(&root, &7), Ok(Some(14)));
+
+        let h2 = h.replace(&7, 15).unwrap().0;
+        assert_eq!(
+            proof.verify(&h2.root_hash(), &7),
+            Err(ProofError::RootMismatch)
+        );
+        assert_eq!(h2.prove(&7).verify(&h2.root_hash(), &7), Ok(Some(15)));
+
+        // the proof of another key leads elsewhere in the tree
+        assert!(proof.verify(&root, &8).is_err());
+
+        let mut forged = proof.clone();
+        if let ProofEnd::Leaf(_, _, v) = &mut forged.end {
+            *v = 15u32.to_bytes();
+        }
+        assert_eq!(forged.verify(&root, &7), Err(ProofError::RootMismatch));
+    }
+
+    #[quickcheck]
+    fn proofs_serialization_roundtrip(xs: Vec<(u32, u32)>, k: u32) -> bool {
+        let h: TestHamt = xs.into_iter().collect();
+        let bytes = h.prove(&k).to_bytes();
+        let proof = MerkleProof::<DefaultHasher, u32, u32>::from_bytes(&bytes).unwrap();
+        proof.to_bytes() == bytes && proof.verify(&h.root_hash(), &k) == Ok(h.lookup(&k).cloned())
+    }
+
+    #[test]
+    fn proofs_decoding_rejects_malformed_data() {
+        let h: TestHamt = (0..1000).map(|i| (i, i * 2)).collect();
+        let bytes = h.prove(&7).to_bytes();
+        let decode = MerkleProof::<DefaultHasher, u32, u32>::from_bytes;
+        assert!(decode(&bytes).is_some());
+        assert!(decode(&[]).is_none());
+        assert!(decode(&bytes[..bytes.len() - 1]).is_none());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(decode(&trailing).is_none());
+        let mut bad_tag = bytes;
+        let end = bad_tag.len() - (1 + 8 + 4 + 4 + 4 + 4);
+        bad_tag[end] = 3;
+        assert!(decode(&bad_tag).is_none());
+    }
+
+    // hasher with only a few distinct hashes, to exercise collisions
+    #[derive(Default)]
+    struct CollidingHasher(DefaultHasher);
+
+    impl Hasher for CollidingHasher {
+        fn finish(&self) -> u64 {
+            self.0.finish() % 40
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            self.0.write(bytes)
+        }
+    }
+
+    #[quickcheck]
+    fn collisions(xs: Vec<(u32, u32)>, removed: Vec<u32>) -> bool {
+        let xs: BTreeMap<_, _> = xs.into_iter().collect();
+        let mut h: MerkleHamt<CollidingHasher, u32, u32> = xs.clone().into_iter().collect();
+        for k in removed.iter() {
+            if let Ok(newh) = h.remove(k) {
+                h = newh;
+            }
+        }
+        let remaining: BTreeMap<_, _> = xs
+            .into_iter()
+            .filter(|(k, _)| !removed.contains(k))
+            .collect();
+        let rebuilt: MerkleHamt<CollidingHasher, u32, u32> =
+            remaining.clone().into_iter().rev().collect();
+        let root = h.root_hash();
+        h.size() == remaining.len()
+            && rebuilt.root_hash() == root
+            && remaining
+                .iter()
+                .all(|(k, v)| h.prove(k).verify(&root, k) == Ok(Some(*v)))
+            && removed
+                .iter()
+                .filter(|k| !remaining.contains_key(k))
+                .all(|k| h.prove(k).verify(&root, k) == Ok(None))
+    }
+}
@@ -1,2 +1,4 @@
+mod mutable;
 mod reference;
+pub use mutable::*;
 pub use reference::*;
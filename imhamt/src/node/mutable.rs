@@ -0,0 +1,170 @@
+//! In place operations on uniquely owned nodes
+//!
+//! An entry is modified in place when its reference is not shared, and
+//! copied first otherwise (see `SharedRef::make_mut`), so that the nodes
+//! shared with other versions of the tree are never modified. Once copied,
+//! the entries of a path are uniquely owned, and the following operations
+//! on the same path do not allocate anymore.
+
+use super::super::hash::{HashedKey, LevelIndex};
+use super::super::operation::*;
+use super::super::sharedref::SharedRef;
+use super::reference::{Collision, Entry, Node};
+use std::borrow::Borrow;
+use std::error::Error;
+use std::fmt::Debug;
+use std::mem;
+
+impl<K, V> Node<K, V> {
+    fn children_mut<F: FnOnce(&mut Vec<SharedRef<Entry<K, V>>>)>(&mut self, f: F) {
+        let mut children = mem::take(&mut self.children).into_vec();
+        f(&mut children);
+        self.children = children.into();
+    }
+
+    pub fn set_at_mut(&mut self, idx: LevelIndex, child: SharedRef<Entry<K, V>>) {
+        assert!(!self.bitmap.is_set(idx));
+        let pos = self.bitmap.get_sparse_pos(idx).get_found();
+        self.bitmap = self.bitmap.set_index(idx);
+        self.children_mut(|children| children.insert(pos, child));
+    }
+
+    pub fn clear_at_mut(&mut self, idx: LevelIndex) {
+        assert!(self.bitmap.is_set(idx));
+        // use the old bitmap to locate the element
+        let pos = self.bitmap.get_sparse_pos(idx).get_found();
+        self.bitmap = self.bitmap.clear_index(idx);
+        self.children_mut(|children| {
+            children.remove(pos);
+        });
+    }
+}
+
+impl<K, V> Collision<K, V> {
+    fn position<Q>(&self, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: PartialEq,
+    {
+        self.0.iter().position(|(lk, _)| lk.borrow() == k)
+    }
+
+    fn items_mut<F: FnOnce(&mut Vec<(K, V)>)>(&mut self, f: F) {
+        let mut items = mem::take(&mut *self.0).into_vec();
+        f(&mut items);
+        *self.0 = items.into();
+    }
+}
+
+// Insert a leaf in place, with the same resulting structure as `insert_rec`
+pub fn insert_mut<K: Clone + PartialEq, V: Clone>(
+    node: &mut Node<K, V>,
+    hash: HashedKey,
+    lvl: usize,
+    key: K,
+    value: V,
+) -> Result<(), InsertError> {
+    let level_hash = hash.level_index(lvl);
+    let idx = node.bitmap.get_index_sparse(level_hash);
+    if idx.is_not_found() {
+        node.set_at_mut(level_hash, SharedRef::new(Entry::Leaf(hash, key, value)));
+        return Ok(());
+    }
+    let child = &mut node.children[idx.get_found()];
+    match child.as_ref() {
+        Entry::Leaf(lh, lk, lv) if *lh == hash => {
+            if lk == &key {
+                return Err(InsertError::EntryExists);
+            }
+            let dat = vec![(lk.clone(), lv.clone()), (key, value)];
+            *child = SharedRef::new(Entry::LeafMany(*lh, Collision::from_vec(dat)));
+        }
+        Entry::LeafMany(lh, col) if *lh == hash => {
+            if col.position(&key).is_some() {
+                return Err(InsertError::EntryExists);
+            }
+            if let Entry::LeafMany(_, col) = SharedRef::make_mut(child) {
+                col.items_mut(|items| items.push((key, value)));
+            }
+        }
+        Entry::SubNode(_) => {
+            if let Entry::SubNode(sub) = SharedRef::make_mut(child) {
+                insert_mut(sub, hash, lvl + 1, key, value)?;
+            }
+        }
+        // a different hash with the same index at this level: move the
+        // existing entry one level down, along with the new one
+        Entry::Leaf(lh, _, _) | Entry::LeafMany(lh, _) => {
+            let mut subnode = Node::singleton(lh.level_index(lvl + 1), SharedRef::clone(child));
+            insert_mut(&mut subnode, hash, lvl + 1, key, value)?;
+            *child = SharedRef::new(Entry::SubNode(subnode));
+        }
+    }
+    Ok(())
+}
+
+// Update a value in place, removing it if the closure returns None.
+//
+// As with `update_rec`, a subnode left empty is removed from its parent.
+pub fn update_mut<Q, K, V, F, U>(
+    node: &mut Node<K, V>,
+    h: HashedKey,
+    lvl: usize,
+    k: &Q,
+    f: F,
+) -> Result<(), UpdateError<U>>
+where
+    Q: PartialEq,
+    K: Borrow<Q> + Clone,
+    V: Clone,
+    F: FnOnce(&V) -> Result<Option<V>, U>,
+    U: Error + Debug + 'static,
+{
+    let level_hash = h.level_index(lvl);
+    let idx = node.bitmap.get_index_sparse(level_hash);
+    if idx.is_not_found() {
+        return Err(UpdateError::KeyNotFound);
+    }
+    let child = &mut node.children[idx.get_found()];
+    match child.as_ref() {
+        Entry::Leaf(lh, lk, _) if *lh != h || lk.borrow() != k => {
+            return Err(UpdateError::KeyNotFound)
+        }
+        Entry::LeafMany(lh, col) if *lh != h || col.position(k).is_none() => {
+            return Err(UpdateError::KeyNotFound)
+        }
+        _ => {}
+    }
+    let entry = SharedRef::make_mut(child);
+    let remove = match entry {
+        Entry::Leaf(_, _, lv) => match f(lv).map_err(UpdateError::ValueCallbackError)? {
+            None => true,
+            Some(newv) => {
+                *lv = newv;
+                false
+            }
+        },
+        Entry::LeafMany(lh, col) => {
+            let pos = col.position(k).unwrap();
+            match f(&col.0[pos].1).map_err(UpdateError::ValueCallbackError)? {
+                None if col.len() == 2 => {
+                    let (lk, lv) = col.0[1 - pos].clone();
+                    *entry = Entry::Leaf(*lh, lk, lv);
+                }
+                None => col.items_mut(|items| {
+                    items.remove(pos);
+                }),
+                Some(newv) => col.0[pos].1 = newv,
+            }
+            false
+        }
+        Entry::SubNode(sub) => {
+            update_mut(sub, h, lvl + 1, k, f)?;
+            sub.is_empty()
+        }
+    };
+    if remove {
+        node.clear_at_mut(level_hash);
+    }
+    Ok(())
+}
@@ -21,7 +21,8 @@ pub struct Node<K, V> {
 
 pub type NodeIter<'a, K, V> = slice::Iter<'a, SharedRef<Entry<K, V>>>;
 
-pub struct Collision<K, V>(Box<Box<[(K, V)]>>);
+#[derive(Clone)]
+pub struct Collision<K, V>(pub(super) Box<Box<[(K, V)]>>);
 
 impl<K, V> Collision<K, V> {
     pub fn from_vec(vec: Vec<(K, V)>) -> Self {
@@ -153,6 +154,7 @@ impl<K: Clone + PartialEq, V: Clone> Collision<K, V> {
     }
 }
 
+#[derive(Clone)]
 pub enum Entry<K, V> {
     Leaf(HashedKey, K, V),
     LeafMany(HashedKey, Collision<K, V>),
@@ -297,9 +299,17 @@ pub fn insert_rec<K: Clone + PartialEq, V: Clone>(
                 }
             }
             Entry::LeafMany(lh, col) => {
-                assert_eq!(*lh, hash);
-                let col = col.insert(key, value)?;
-                Ok(node.replace_at(idx, SharedRef::new(Entry::LeafMany(*lh, col))))
+                if *lh == hash {
+                    let col = col.insert(key, value)?;
+                    Ok(node.replace_at(idx, SharedRef::new(Entry::LeafMany(*lh, col))))
+                } else {
+                    // as with a leaf, move the collision one level down
+                    let leaf_idx = lh.level_index(lvl + 1);
+                    let subnode = Node::singleton(leaf_idx, SharedRef::clone(node.get_child(idx)));
+                    let r = insert_rec(&subnode, hash, lvl + 1, key, value)?;
+                    let e = SharedRef::new(Entry::SubNode(r));
+                    Ok(node.replace_at(idx, e))
+                }
             }
             Entry::SubNode(sub) => {
                 if lvl > 13 {
@@ -387,7 +397,9 @@ where
                 }
             }
             Entry::LeafMany(lh, col) => {
-                assert_eq!(*lh, h);
+                if *lh != h {
+                    return Err(RemoveError::KeyNotFound);
+                }
                 let replacement = col.remove_match(h, k, v)?;
                 Ok(Some(node.replace_at(idx, SharedRef::new(replacement))))
             }
@@ -428,7 +440,9 @@ where
                 }
             }
             Entry::LeafMany(lh, col) => {
-                assert_eq!(*lh, h);
+                if *lh != h {
+                    return Err(RemoveError::KeyNotFound);
+                }
                 let replacement = col.remove(h, k)?;
                 Ok(Some(node.replace_at(idx, SharedRef::new(replacement))))
             }
@@ -477,7 +491,9 @@ where
                 }
             }
             Entry::LeafMany(lh, col) => {
-                assert_eq!(*lh, h);
+                if *lh != h {
+                    return Err(UpdateError::KeyNotFound);
+                }
                 let replacement = col.update(h, k, f)?;
                 Ok(Some(node.replace_at(idx, SharedRef::new(replacement))))
             }
@@ -515,7 +531,9 @@ pub fn replace_rec<K: PartialEq + Clone, V: Clone>(
                 }
             }
             Entry::LeafMany(lh, col) => {
-                assert_eq!(*lh, h);
+                if *lh != h {
+                    return Err(ReplaceError::KeyNotFound);
+                }
                 let (replacement, old_value) = col.replace(k, v)?;
                 Ok((
                     node.replace_at(idx, SharedRef::new(Entry::LeafMany(*lh, replacement))),
@@ -557,7 +575,9 @@ where
                 }
             }
             Entry::LeafMany(lh, col) => {
-                assert_eq!(*lh, h);
+                if *lh != h {
+                    return Err(ReplaceError::KeyNotFound);
+                }
                 let replacement = col.replace_with(k, f)?;
                 Ok(node.replace_at(idx, SharedRef::new(Entry::LeafMany(*lh, replacement))))
             }
@@ -0,0 +1,17 @@
+use super::hamt::{Hamt, HamtIter};
+use super::hash::{Hash, Hasher};
+use rayon::prelude::*;
+use std::slice;
+
+impl<H: Hasher + Default, K: Eq + Hash + Sync + Send, V: Sync + Send> Hamt<H, K, V> {
+    /// Parallel iterator over the elements, in no particular order
+    ///
+    /// The subtrees under the root are iterated concurrently, each of them
+    /// sequentially.
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (&K, &V)> {
+        self.root
+            .children
+            .par_iter()
+            .flat_map_iter(|child| HamtIter::from_entries(slice::from_ref(child)))
+    }
+}
@@ -0,0 +1,112 @@
+use std::convert::TryInto;
+
+/// Serialization of the keys and values of a persistent or Merkle HAMT
+///
+/// The encoding of a value must not change across versions of the
+/// program, as it determines the stored data and the hashes.
+pub trait Persist: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! persist_int_impl {
+    ($($Ty: ty)+) => {
+        $(
+        impl Persist for $Ty {
+            fn to_bytes(&self) -> Vec<u8> {
+                self.to_be_bytes().to_vec()
+            }
+            fn from_bytes(bytes: &[u8]) -> Option<Self> {
+                bytes.try_into().ok().map(<$Ty>::from_be_bytes)
+            }
+        }
+        )+
+    };
+}
+
+persist_int_impl! { u8 u16 u32 u64 u128 }
+
+impl Persist for Vec<u8> {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl Persist for String {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl Persist for [u8; 32] {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok()
+    }
+}
+
+pub(crate) fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+pub(crate) fn put_kv<K: Persist, V: Persist>(out: &mut Vec<u8>, k: &K, v: &V) {
+    put_bytes(out, &k.to_bytes());
+    put_bytes(out, &v.to_bytes());
+}
+
+/// Reader of the big endian integers and length prefixed byte strings
+/// written with `put_bytes` and `put_kv`
+pub(crate) struct Reader<'a>(pub &'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub fn is_end(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (s, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(s)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|s| s[0])
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.take(4).and_then(u32::from_bytes)
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.take(8).and_then(u64::from_bytes)
+    }
+
+    pub fn bytes(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    pub fn skip_kv(&mut self) -> Option<()> {
+        self.bytes()?;
+        self.bytes()?;
+        Some(())
+    }
+
+    pub fn kv<K: Persist, V: Persist>(&mut self) -> Option<(K, V)> {
+        let k = K::from_bytes(self.bytes()?)?;
+        let v = V::from_bytes(self.bytes()?)?;
+        Some((k, v))
+    }
+}
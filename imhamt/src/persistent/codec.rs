@@ -0,0 +1,116 @@
+//! On-disk format of the nodes
+//!
+//! ```text
+//! node    := bitmap:u32 child*         (one child per bit set in bitmap)
+//! child   := 0 hash:u64 kv             (leaf)
+//!          | 1 hash:u64 count:u32 kv*  (collision)
+//!          | 2 id:[u8;32]              (subnode)
+//! kv      := klen:u32 key vlen:u32 value
+//! ```
+//!
+//! All integers are big endian. Keys and values are length prefixed so
+//! that the structure of a node can be walked without knowing their types.
+
+use super::store::{NodeId, StoreError};
+use super::{StoredEntry, StoredNode};
+use crate::bitmap::SmallBitmap;
+use crate::hash::HashedKey;
+use crate::persist::{put_kv, Persist, Reader};
+
+const TAG_LEAF: u8 = 0;
+const TAG_COLLISION: u8 = 1;
+const TAG_SUBNODE: u8 = 2;
+
+pub(super) fn encode<K: Persist, V: Persist>(node: &StoredNode<K, V>) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&node.bitmap.to_u32().to_be_bytes());
+    for child in node.children.iter() {
+        match child {
+            StoredEntry::Leaf(h, k, v) => {
+                out.push(TAG_LEAF);
+                out.extend_from_slice(&h.0.to_be_bytes());
+                put_kv(&mut out, k, v);
+            }
+            StoredEntry::LeafMany(h, col) => {
+                out.push(TAG_COLLISION);
+                out.extend_from_slice(&h.0.to_be_bytes());
+                out.extend_from_slice(&(col.len() as u32).to_be_bytes());
+                for (k, v) in col.iter() {
+                    put_kv(&mut out, k, v);
+                }
+            }
+            StoredEntry::SubNode(id) => {
+                out.push(TAG_SUBNODE);
+                out.extend_from_slice(id.as_ref());
+            }
+        }
+    }
+    out
+}
+
+fn node_id(r: &mut Reader) -> Option<NodeId> {
+    r.take(NodeId::SIZE).and_then(NodeId::from_bytes)
+}
+
+fn decode_opt<K: Persist, V: Persist>(data: &[u8]) -> Option<StoredNode<K, V>> {
+    let mut r = Reader(data);
+    let bitmap = SmallBitmap::from_u32(r.u32()?);
+    let mut children = Vec::with_capacity(bitmap.present());
+    for _ in 0..bitmap.present() {
+        let child = match r.u8()? {
+            TAG_LEAF => {
+                let h = HashedKey(r.u64()?);
+                let (k, v) = r.kv()?;
+                StoredEntry::Leaf(h, k, v)
+            }
+            TAG_COLLISION => {
+                let h = HashedKey(r.u64()?);
+                let count = r.u32()? as usize;
+                let col = (0..count).map(|_| r.kv()).collect::<Option<_>>()?;
+                StoredEntry::LeafMany(h, col)
+            }
+            TAG_SUBNODE => StoredEntry::SubNode(node_id(&mut r)?),
+            _ => return None,
+        };
+        children.push(child);
+    }
+    if !r.is_end() {
+        return None;
+    }
+    Some(StoredNode { bitmap, children })
+}
+
+pub(super) fn decode<K: Persist, V: Persist>(
+    id: &NodeId,
+    data: &[u8],
+) -> Result<StoredNode<K, V>, StoreError> {
+    decode_opt(data).ok_or_else(|| StoreError::Corrupted(*id))
+}
+
+fn subnodes_opt(data: &[u8]) -> Option<Vec<NodeId>> {
+    let mut r = Reader(data);
+    let bitmap = SmallBitmap::from_u32(r.u32()?);
+    let mut ids = Vec::new();
+    for _ in 0..bitmap.present() {
+        match r.u8()? {
+            TAG_LEAF => {
+                r.u64()?;
+                r.skip_kv()?;
+            }
+            TAG_COLLISION => {
+                r.u64()?;
+                for _ in 0..r.u32()? {
+                    r.skip_kv()?;
+                }
+            }
+            TAG_SUBNODE => ids.push(node_id(&mut r)?),
+            _ => return None,
+        }
+    }
+    Some(ids)
+}
+
+/// Return the subnodes referenced by an encoded node
+pub(super) fn subnodes(id: &NodeId, data: &[u8]) -> Result<Vec<NodeId>, StoreError> {
+    subnodes_opt(data).ok_or_else(|| StoreError::Corrupted(*id))
+}
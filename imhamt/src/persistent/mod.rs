@@ -0,0 +1,617 @@
+//! HAMT whose nodes live in a key-value store
+//!
+//! A [`PersistentHamt`] is a handle on a root node in a [`NodeStore`].
+//! Nodes are content addressed: their identifier is the hash of their
+//! encoding, and they are loaded from the store only along the paths
+//! visited by an operation. As with [`Hamt`], operations return a new
+//! version of the tree that shares all the untouched nodes with the
+//! previous one, so keeping many versions costs little storage.
+//!
+//! The store accumulates the nodes of all the versions ever created. Once
+//! some versions are not needed anymore, [`collect_garbage`] removes the
+//! nodes that are not reachable from the roots of the versions to keep.
+//!
+//! The layout of the tree depends on the hashes of the keys, so the hasher
+//! must give the same results across runs of the program to reopen a tree.
+
+mod codec;
+mod store;
+
+pub use crate::persist::Persist;
+#[cfg(feature = "sled-store")]
+pub use store::SledStore;
+pub use store::{MemoryStore, NodeId, NodeStore, StoreError};
+
+use crate::bitmap::SmallBitmap;
+use crate::hash::{Hash, HashedKey, Hasher};
+use crate::node::{Entry, Node};
+use crate::operation::{InsertError, RemoveError, ReplaceError, UpdateError};
+use crate::Hamt;
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use thiserror::Error;
+
+/// Error of an operation on a persistent HAMT: either the error of the
+/// operation itself, or a failure to access the store
+#[derive(Debug, Error)]
+pub enum PersistentError<T: Error + 'static> {
+    #[error(transparent)]
+    Operation(T),
+    #[error(transparent)]
+    Store(#[from] StoreError),
+}
+
+// Decoded node, with the subnodes referenced by identifier
+pub(crate) struct StoredNode<K, V> {
+    bitmap: SmallBitmap,
+    children: Vec<StoredEntry<K, V>>,
+}
+
+pub(crate) enum StoredEntry<K, V> {
+    Leaf(HashedKey, K, V),
+    LeafMany(HashedKey, Vec<(K, V)>),
+    SubNode(NodeId),
+}
+
+impl<K, V> StoredNode<K, V> {
+    fn new() -> Self {
+        StoredNode {
+            bitmap: SmallBitmap::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn get(&self, h: HashedKey, lvl: usize) -> Option<(usize, &StoredEntry<K, V>)> {
+        let idx = self.bitmap.get_index_sparse(h.level_index(lvl));
+        if idx.is_not_found() {
+            None
+        } else {
+            let pos = idx.get_found();
+            Some((pos, &self.children[pos]))
+        }
+    }
+
+    fn set_at(&mut self, h: HashedKey, lvl: usize, entry: StoredEntry<K, V>) {
+        let level_hash = h.level_index(lvl);
+        let pos = self.bitmap.get_sparse_pos(level_hash).get_found();
+        self.bitmap = self.bitmap.set_index(level_hash);
+        self.children.insert(pos, entry);
+    }
+
+    fn clear_at(&mut self, h: HashedKey, lvl: usize, pos: usize) {
+        self.bitmap = self.bitmap.clear_index(h.level_index(lvl));
+        self.children.remove(pos);
+    }
+}
+
+/// Version of a HAMT stored in a [`NodeStore`]
+pub struct PersistentHamt<H, K, V, S> {
+    root: NodeId,
+    store: S,
+    phantom: PhantomData<(H, K, V)>,
+}
+
+impl<H, K, V, S: Clone> Clone for PersistentHamt<H, K, V, S> {
+    fn clone(&self) -> Self {
+        PersistentHamt {
+            root: self.root,
+            store: self.store.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<H, K, V, S> PersistentHamt<H, K, V, S>
+where
+    H: Hasher + Default,
+    K: Persist + Hash + Eq,
+    V: Persist,
+    S: NodeStore + Clone,
+{
+    /// Create an empty HAMT in the store
+    pub fn new(store: S) -> Result<Self, StoreError> {
+        let root = save(&store, &StoredNode::<K, V>::new())?;
+        Ok(Self::open(store, root))
+    }
+
+    /// Open a version of a HAMT previously created in the store
+    pub fn open(store: S, root: NodeId) -> Self {
+        PersistentHamt {
+            root,
+            store,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Identifier of the root node, from which this version can be reopened
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    fn with_root(&self, root: NodeId) -> Self {
+        Self::open(self.store.clone(), root)
+    }
+
+    fn load(&self, id: &NodeId) -> Result<StoredNode<K, V>, StoreError> {
+        load(&self.store, id)
+    }
+
+    fn save(&self, node: &StoredNode<K, V>) -> Result<NodeId, StoreError> {
+        save(&self.store, node)
+    }
+
+    pub fn is_empty(&self) -> Result<bool, StoreError> {
+        Ok(self.load(&self.root)?.bitmap.is_empty())
+    }
+
+    /// Count the entries, loading the whole tree
+    pub fn size(&self) -> Result<usize, StoreError> {
+        let mut size = 0;
+        self.for_each(|_, _| size += 1)?;
+        Ok(size)
+    }
+
+    /// Call `f` on every entry, loading the whole tree
+    pub fn for_each<F>(&self, mut f: F) -> Result<(), StoreError>
+    where
+        F: FnMut(K, V),
+    {
+        let mut stack = vec![self.root];
+        while let Some(id) = stack.pop() {
+            for child in self.load(&id)?.children {
+                match child {
+                    StoredEntry::Leaf(_, k, v) => f(k, v),
+                    StoredEntry::LeafMany(_, col) => col.into_iter().for_each(|(k, v)| f(k, v)),
+                    StoredEntry::SubNode(sub) => stack.push(sub),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Try to get the value associated with the key
+    pub fn lookup(&self, k: &K) -> Result<Option<V>, StoreError> {
+        let h = HashedKey::compute(PhantomData::<H>, k);
+        let mut node = self.load(&self.root)?;
+        let mut lvl = 0;
+        loop {
+            let pos = match node.get(h, lvl) {
+                None => return Ok(None),
+                Some((pos, _)) => pos,
+            };
+            match node.children.swap_remove(pos) {
+                StoredEntry::Leaf(lh, lk, lv) => {
+                    return Ok(if lh == h && &lk == k { Some(lv) } else { None })
+                }
+                StoredEntry::LeafMany(lh, col) => {
+                    return Ok(if lh == h {
+                        col.into_iter().find(|(lk, _)| lk == k).map(|(_, v)| v)
+                    } else {
+                        None
+                    })
+                }
+                StoredEntry::SubNode(sub) => {
+                    node = self.load(&sub)?;
+                    lvl += 1;
+                }
+            }
+        }
+    }
+
+    pub fn contains_key(&self, k: &K) -> Result<bool, StoreError> {
+        self.lookup(k).map(|v| v.is_some())
+    }
+
+    pub fn insert(&self, k: K, v: V) -> Result<Self, PersistentError<InsertError>> {
+        let h = HashedKey::compute(PhantomData::<H>, &k);
+        let root = self.load(&self.root)?;
+        let root = self.insert_rec(root, h, 0, k, v)?;
+        Ok(self.with_root(self.save(&root)?))
+    }
+
+    fn insert_rec(
+        &self,
+        mut node: StoredNode<K, V>,
+        h: HashedKey,
+        lvl: usize,
+        k: K,
+        v: V,
+    ) -> Result<StoredNode<K, V>, PersistentError<InsertError>> {
+        let exists = Err(PersistentError::Operation(InsertError::EntryExists));
+        match node.get(h, lvl) {
+            None => node.set_at(h, lvl, StoredEntry::Leaf(h, k, v)),
+            Some((_, StoredEntry::Leaf(_, lk, _))) if *lk == k => return exists,
+            Some((_, StoredEntry::LeafMany(_, col))) if col.iter().any(|(lk, _)| *lk == k) => {
+                return exists
+            }
+            Some((pos, _)) => {
+                let entry = node.children.remove(pos);
+                let entry = self.insert_entry(entry, h, lvl, k, v)?;
+                node.children.insert(pos, entry);
+            }
+        }
+        Ok(node)
+    }
+
+    // insert in the slot of an existing entry, which does not hold the key
+    fn insert_entry(
+        &self,
+        entry: StoredEntry<K, V>,
+        h: HashedKey,
+        lvl: usize,
+        k: K,
+        v: V,
+    ) -> Result<StoredEntry<K, V>, PersistentError<InsertError>> {
+        let sub = match entry {
+            StoredEntry::Leaf(lh, lk, lv) if lh == h => {
+                return Ok(StoredEntry::LeafMany(lh, vec![(lk, lv), (k, v)]))
+            }
+            StoredEntry::LeafMany(lh, mut col) if lh == h => {
+                col.push((k, v));
+                return Ok(StoredEntry::LeafMany(lh, col));
+            }
+            StoredEntry::SubNode(sub) => self.load(&sub)?,
+            // a different hash with the same index at this level: move the
+            // existing entry one level down, along with the new one
+            StoredEntry::Leaf(lh, _, _) | StoredEntry::LeafMany(lh, _) => {
+                let mut sub = StoredNode::new();
+                sub.set_at(lh, lvl + 1, entry);
+                sub
+            }
+        };
+        let sub = self.insert_rec(sub, h, lvl + 1, k, v)?;
+        Ok(StoredEntry::SubNode(self.save(&sub)?))
+    }
+
+    pub fn remove(&self, k: &K) -> Result<Self, PersistentError<RemoveError>> {
+        self.remove_with(k, |_| Ok(()))
+    }
+
+    /// Remove the key only if it is associated with the value `v`
+    pub fn remove_match(&self, k: &K, v: &V) -> Result<Self, PersistentError<RemoveError>>
+    where
+        V: PartialEq,
+    {
+        self.remove_with(k, |lv| {
+            if lv == v {
+                Ok(())
+            } else {
+                Err(RemoveError::ValueNotMatching)
+            }
+        })
+    }
+
+    fn remove_with<F>(&self, k: &K, check: F) -> Result<Self, PersistentError<RemoveError>>
+    where
+        F: FnOnce(&V) -> Result<(), RemoveError>,
+    {
+        let f = |v: &V| match check(v) {
+            Ok(()) => Ok(None),
+            Err(e) => Err(e),
+        };
+        self.modify(k, f).map_err(|e| match e {
+            PersistentError::Operation(UpdateError::KeyNotFound) => {
+                PersistentError::Operation(RemoveError::KeyNotFound)
+            }
+            PersistentError::Operation(UpdateError::ValueCallbackError(e)) => {
+                PersistentError::Operation(e)
+            }
+            PersistentError::Store(e) => PersistentError::Store(e),
+        })
+    }
+
+    /// Replace the value associated with the key, returning the new tree
+    /// and the old value
+    pub fn replace(&self, k: &K, v: V) -> Result<(Self, V), PersistentError<ReplaceError>>
+    where
+        V: Clone,
+    {
+        let mut old = None;
+        let new = self
+            .modify::<_, Infallible>(k, |lv| {
+                old = Some(lv.clone());
+                Ok(Some(v))
+            })
+            .map_err(|e| match e {
+                PersistentError::Store(e) => PersistentError::Store(e),
+                PersistentError::Operation(_) => {
+                    PersistentError::Operation(ReplaceError::KeyNotFound)
+                }
+            })?;
+        Ok((new, old.expect("replaced value")))
+    }
+
+    /// Update the value associated with the key.
+    ///
+    /// If the closure F returns None, then the key is deleted.
+    pub fn update<F, U>(&self, k: &K, f: F) -> Result<Self, PersistentError<UpdateError<U>>>
+    where
+        F: FnOnce(&V) -> Result<Option<V>, U>,
+        U: Error + Debug + 'static,
+    {
+        self.modify(k, f)
+    }
+
+    fn modify<F, U>(&self, k: &K, f: F) -> Result<Self, PersistentError<UpdateError<U>>>
+    where
+        F: FnOnce(&V) -> Result<Option<V>, U>,
+        U: Error + Debug + 'static,
+    {
+        let h = HashedKey::compute(PhantomData::<H>, k);
+        let root = self.load(&self.root)?;
+        let root = self
+            .modify_rec(root, h, 0, k, f)?
+            .unwrap_or_else(StoredNode::new);
+        Ok(self.with_root(self.save(&root)?))
+    }
+
+    fn modify_rec<F, U>(
+        &self,
+        mut node: StoredNode<K, V>,
+        h: HashedKey,
+        lvl: usize,
+        k: &K,
+        f: F,
+    ) -> Result<Option<StoredNode<K, V>>, PersistentError<UpdateError<U>>>
+    where
+        F: FnOnce(&V) -> Result<Option<V>, U>,
+        U: Error + Debug + 'static,
+    {
+        let not_found = || PersistentError::Operation(UpdateError::KeyNotFound);
+        let callback_error = |e| PersistentError::Operation(UpdateError::ValueCallbackError(e));
+        let pos = node.get(h, lvl).ok_or_else(not_found)?.0;
+        let remove = match &mut node.children[pos] {
+            StoredEntry::Leaf(lh, lk, lv) => {
+                if *lh != h || lk != k {
+                    return Err(not_found());
+                }
+                match f(lv).map_err(callback_error)? {
+                    None => true,
+                    Some(newv) => {
+                        *lv = newv;
+                        false
+                    }
+                }
+            }
+            StoredEntry::LeafMany(lh, col) => {
+                if *lh != h {
+                    return Err(not_found());
+                }
+                let i = col
+                    .iter()
+                    .position(|(lk, _)| lk == k)
+                    .ok_or_else(not_found)?;
+                match f(&col[i].1).map_err(callback_error)? {
+                    None => {
+                        col.remove(i);
+                        if col.len() == 1 {
+                            let (lk, lv) = col.pop().unwrap();
+                            node.children[pos] = StoredEntry::Leaf(h, lk, lv);
+                        }
+                    }
+                    Some(newv) => col[i].1 = newv,
+                }
+                false
+            }
+            StoredEntry::SubNode(sub) => {
+                let sub = self.load(sub)?;
+                match self.modify_rec(sub, h, lvl + 1, k, f)? {
+                    None => true,
+                    Some(sub) => {
+                        node.children[pos] = StoredEntry::SubNode(self.save(&sub)?);
+                        false
+                    }
+                }
+            }
+        };
+        if remove {
+            node.clear_at(h, lvl, pos);
+        }
+        Ok(if node.bitmap.is_empty() {
+            None
+        } else {
+            Some(node)
+        })
+    }
+
+    /// Store all the entries of an in-memory HAMT
+    pub fn from_hamt(store: S, hamt: &Hamt<H, K, V>) -> Result<Self, StoreError>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let root = save_node(&store, &hamt.root)?;
+        Ok(Self::open(store, root))
+    }
+
+    /// Load all the entries in an in-memory HAMT
+    pub fn to_hamt(&self) -> Result<Hamt<H, K, V>, StoreError>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut entries = Vec::new();
+        self.for_each(|k, v| entries.push((k, v)))?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+fn load<K: Persist, V: Persist, S: NodeStore>(
+    store: &S,
+    id: &NodeId,
+) -> Result<StoredNode<K, V>, StoreError> {
+    let data = store.get(id)?.ok_or(StoreError::MissingNode(*id))?;
+    codec::decode(id, &data)
+}
+
+fn save<K: Persist, V: Persist, S: NodeStore>(
+    store: &S,
+    node: &StoredNode<K, V>,
+) -> Result<NodeId, StoreError> {
+    let data = codec::encode(node);
+    let id = NodeId::hash_bytes(&data);
+    store.put(&id, &data)?;
+    Ok(id)
+}
+
+fn save_node<K, V, S>(store: &S, node: &Node<K, V>) -> Result<NodeId, StoreError>
+where
+    K: Persist + Clone,
+    V: Persist + Clone,
+    S: NodeStore,
+{
+    let mut children = Vec::with_capacity(node.children.len());
+    for child in node.children.iter() {
+        children.push(match child.as_ref() {
+            Entry::Leaf(h, k, v) => StoredEntry::Leaf(*h, k.clone(), v.clone()),
+            Entry::LeafMany(h, col) => StoredEntry::LeafMany(*h, col.iter().cloned().collect()),
+            Entry::SubNode(sub) => StoredEntry::SubNode(save_node(store, sub)?),
+        });
+    }
+    save(
+        store,
+        &StoredNode {
+            bitmap: node.bitmap,
+            children,
+        },
+    )
+}
+
+/// Remove from the store all the nodes that are not reachable from the
+/// given roots, returning the number of nodes removed.
+///
+/// The roots must include every version still in use, including those
+/// of other HAMTs sharing the store.
+pub fn collect_garbage<S: NodeStore>(store: &S, roots: &[NodeId]) -> Result<usize, StoreError> {
+    let mut reachable = HashSet::new();
+    let mut stack = roots.to_vec();
+    while let Some(id) = stack.pop() {
+        if !reachable.insert(id) {
+            continue;
+        }
+        let data = store.get(&id)?.ok_or(StoreError::MissingNode(id))?;
+        stack.extend(codec::subnodes(&id, &data)?);
+    }
+    let mut removed = 0;
+    for id in store.ids()? {
+        if !reachable.contains(&id) {
+            store.remove(&id)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::BTreeMap;
+
+    type TestHamt = PersistentHamt<DefaultHasher, u32, u32, MemoryStore>;
+
+    #[quickcheck]
+    fn insert_lookup_remove(xs: Vec<(u32, u32)>, removed: Vec<u32>) -> bool {
+        let mut reference = BTreeMap::new();
+        let mut h = TestHamt::new(MemoryStore::new()).unwrap();
+        for (k, v) in xs {
+            if reference.contains_key(&k) {
+                continue;
+            }
+            reference.insert(k, v);
+            h = h.insert(k, v).unwrap();
+        }
+        for k in removed {
+            if reference.remove(&k).is_some() {
+                h = h.remove(&k).unwrap();
+            }
+        }
+        let mut entries = BTreeMap::new();
+        h.for_each(|k, v| {
+            entries.insert(k, v);
+        })
+        .unwrap();
+        entries == reference
+            && reference
+                .iter()
+                .all(|(k, v)| h.lookup(k).unwrap() == Some(*v))
+    }
+
+    #[test]
+    fn operation_errors() {
+        let h = TestHamt::new(MemoryStore::new()).unwrap();
+        let h = h.insert(1, 10).unwrap();
+        assert!(matches!(
+            h.insert(1, 11),
+            Err(PersistentError::Operation(InsertError::EntryExists))
+        ));
+        assert!(matches!(
+            h.remove(&2),
+            Err(PersistentError::Operation(RemoveError::KeyNotFound))
+        ));
+        assert!(matches!(
+            h.remove_match(&1, &11),
+            Err(PersistentError::Operation(RemoveError::ValueNotMatching))
+        ));
+        let (h, old) = h.replace(&1, 12).unwrap();
+        assert_eq!(old, 10);
+        assert_eq!(h.lookup(&1).unwrap(), Some(12));
+        let h = h.update::<_, Infallible>(&1, |_| Ok(None)).unwrap();
+        assert!(h.is_empty().unwrap());
+    }
+
+    #[test]
+    fn versions_share_nodes() {
+        let store = MemoryStore::new();
+        let mut h = TestHamt::new(store.clone()).unwrap();
+        for i in 0..10_000 {
+            h = h.insert(i, i).unwrap();
+        }
+        let reopened = TestHamt::open(store.clone(), h.root());
+        assert_eq!(reopened.size().unwrap(), 10_000);
+
+        collect_garbage(&store, &[h.root()]).unwrap();
+        let before = store.len();
+        let h2 = h.insert(10_000, 0).unwrap();
+        // only the nodes on the path to the new entry are written
+        assert!(store.len() - before <= 4);
+        assert_eq!(h.lookup(&10_000).unwrap(), None);
+        assert_eq!(h2.lookup(&10_000).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn garbage_collection_keeps_reachable_nodes() {
+        let store = MemoryStore::new();
+        let mut versions = vec![TestHamt::new(store.clone()).unwrap()];
+        for i in 0..1000 {
+            let h = versions.last().unwrap().insert(i, i).unwrap();
+            versions.push(h);
+        }
+        let kept = &versions[500];
+        let removed = collect_garbage(&store, &[kept.root()]).unwrap();
+        assert!(removed > 0);
+        assert_eq!(kept.size().unwrap(), 500);
+        assert!(matches!(
+            versions[1000].size(),
+            Err(StoreError::MissingNode(_))
+        ));
+        assert_eq!(collect_garbage(&store, &[kept.root()]).unwrap(), 0);
+    }
+
+    #[test]
+    fn hamt_roundtrip() {
+        let hamt: Hamt<DefaultHasher, u32, u32> = (0..5000).map(|i| (i, i * 2)).collect();
+        let h = TestHamt::from_hamt(MemoryStore::new(), &hamt).unwrap();
+        assert_eq!(h.lookup(&1234).unwrap(), Some(2468));
+        let back = h.to_hamt().unwrap();
+        assert_eq!(back.size(), 5000);
+        assert!(hamt.iter().all(|(k, v)| back.lookup(k) == Some(v)));
+    }
+}
@@ -0,0 +1,174 @@
+use cryptoxide::blake2b::Blake2b;
+use cryptoxide::digest::Digest;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Identifier of a stored node: the Blake2b-256 hash of its encoding
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId([u8; NodeId::SIZE]);
+
+impl NodeId {
+    pub const SIZE: usize = 32;
+
+    pub fn hash_bytes(data: &[u8]) -> Self {
+        let mut b2b = Blake2b::new(Self::SIZE);
+        b2b.input(data);
+        let mut out = [0u8; Self::SIZE];
+        b2b.result(&mut out);
+        NodeId(out)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(NodeId)
+    }
+}
+
+impl AsRef<[u8]> for NodeId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0.iter() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NodeId({})", self)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("node {0} is missing from the store")]
+    MissingNode(NodeId),
+    #[error("node {0} cannot be decoded")]
+    Corrupted(NodeId),
+    #[error("storage backend error")]
+    Backend(#[source] Box<dyn Error + Send + Sync>),
+}
+
+/// Key-value store holding the encoded nodes of persistent HAMTs
+///
+/// Nodes are keyed by their content hash, so a node shared by several
+/// versions of a HAMT, or by several HAMTs, is stored once. Writing a
+/// node that is already present must succeed and leave it unchanged.
+pub trait NodeStore {
+    fn get(&self, id: &NodeId) -> Result<Option<Vec<u8>>, StoreError>;
+
+    fn put(&self, id: &NodeId, data: &[u8]) -> Result<(), StoreError>;
+
+    fn remove(&self, id: &NodeId) -> Result<(), StoreError>;
+
+    /// Return the identifiers of all the stored nodes
+    fn ids(&self) -> Result<Vec<NodeId>, StoreError>;
+}
+
+/// Node store kept in memory, sharing its content between its clones
+#[derive(Clone, Default)]
+pub struct MemoryStore(Arc<Mutex<HashMap<NodeId, Box<[u8]>>>>);
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl NodeStore for MemoryStore {
+    fn get(&self, id: &NodeId) -> Result<Option<Vec<u8>>, StoreError> {
+        Ok(self.0.lock().unwrap().get(id).map(|data| data.to_vec()))
+    }
+
+    fn put(&self, id: &NodeId, data: &[u8]) -> Result<(), StoreError> {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(*id)
+            .or_insert_with(|| data.into());
+        Ok(())
+    }
+
+    fn remove(&self, id: &NodeId) -> Result<(), StoreError> {
+        self.0.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn ids(&self) -> Result<Vec<NodeId>, StoreError> {
+        Ok(self.0.lock().unwrap().keys().copied().collect())
+    }
+}
+
+#[cfg(feature = "sled-store")]
+mod sled_store {
+    use super::{NodeId, NodeStore, StoreError};
+
+    fn backend(e: sled::Error) -> StoreError {
+        StoreError::Backend(Box::new(e))
+    }
+
+    /// Node store backed by a sled tree, which should be used exclusively
+    /// for nodes as garbage collection removes any unreachable entry
+    #[derive(Clone)]
+    pub struct SledStore(sled::Tree);
+
+    impl SledStore {
+        pub fn new(tree: sled::Tree) -> Self {
+            SledStore(tree)
+        }
+
+        pub fn flush(&self) -> Result<(), StoreError> {
+            self.0.flush().map(|_| ()).map_err(backend)
+        }
+    }
+
+    impl NodeStore for SledStore {
+        fn get(&self, id: &NodeId) -> Result<Option<Vec<u8>>, StoreError> {
+            self.0
+                .get(id)
+                .map(|data| data.map(|ivec| ivec.to_vec()))
+                .map_err(backend)
+        }
+
+        fn put(&self, id: &NodeId, data: &[u8]) -> Result<(), StoreError> {
+            self.0.insert(id, data).map(|_| ()).map_err(backend)
+        }
+
+        fn remove(&self, id: &NodeId) -> Result<(), StoreError> {
+            self.0.remove(id).map(|_| ()).map_err(backend)
+        }
+
+        fn ids(&self) -> Result<Vec<NodeId>, StoreError> {
+            self.0
+                .iter()
+                .keys()
+                .map(|key| {
+                    let key = key.map_err(backend)?;
+                    NodeId::from_bytes(&key).ok_or_else(|| {
+                        StoreError::Backend("invalid node identifier in the store".into())
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(feature = "sled-store")]
+pub use sled_store::SledStore;
@@ -0,0 +1,182 @@
+use super::hamt::Hamt;
+use super::hash::{Hash, HashedKey, Hasher};
+use super::node::{insert_mut, update_mut, Node};
+use super::operation::{InsertError, RemoveError, ReplaceError, UpdateError};
+use std::borrow::Borrow;
+use std::convert::Infallible;
+use std::error::Error;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+/// Transient mutable version of a `Hamt`, to apply a batch of operations
+///
+/// Each operation on a `Hamt` copies the nodes on the path from the root
+/// to the modified entry. A `HamtMut` instead modifies in place the nodes
+/// it owns exclusively, and only copies the nodes still shared with other
+/// versions of the tree, the first time they are modified. The versions
+/// sharing nodes with it are never affected.
+///
+/// It is obtained with `Hamt::thaw` or `HamtMut::new`, and turned back
+/// into an immutable `Hamt` with `freeze`, none of which copy any node.
+///
+/// The result of a failed operation is the same as with `Hamt`: an error
+/// is returned and the content is unchanged.
+pub struct HamtMut<H: Hasher + Default, K: PartialEq + Eq + Hash, V> {
+    root: Node<K, V>,
+    hasher: PhantomData<H>,
+}
+
+impl<H: Hasher + Default, K: Eq + Hash, V> Default for HamtMut<H, K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Hasher + Default, K: Eq + Hash, V> Hamt<H, K, V> {
+    /// Turn the HAMT into a mutable one, sharing all the nodes
+    pub fn thaw(self) -> HamtMut<H, K, V> {
+        HamtMut {
+            root: self.root,
+            hasher: PhantomData,
+        }
+    }
+}
+
+impl<H: Hasher + Default, K: Eq + Hash, V> HamtMut<H, K, V> {
+    pub fn new() -> Self {
+        Hamt::new().thaw()
+    }
+
+    /// Turn back into an immutable HAMT, without copying any node
+    pub fn freeze(self) -> Hamt<H, K, V> {
+        Hamt::from_root(self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty()
+    }
+
+    /// Try to get the element related to key K
+    pub fn lookup<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        let h = HashedKey::compute(self.hasher, k);
+        super::hamt::lookup_from(&self.root, h, k)
+    }
+
+    /// Check if the key is contained into the HAMT
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.lookup(k).is_some()
+    }
+}
+
+impl<H: Hasher + Default, K: Clone + Eq + Hash, V: Clone> HamtMut<H, K, V> {
+    pub fn insert(&mut self, k: K, v: V) -> Result<(), InsertError> {
+        let h = HashedKey::compute(self.hasher, &k);
+        insert_mut(&mut self.root, h, 0, k, v)
+    }
+
+    pub fn remove<Q>(&mut self, k: &Q) -> Result<(), RemoveError>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        let h = HashedKey::compute(self.hasher, k);
+        update_mut::<_, _, _, _, Infallible>(&mut self.root, h, 0, k, |_| Ok(None))
+            .map_err(|_| RemoveError::KeyNotFound)
+    }
+
+    pub fn remove_match<Q>(&mut self, k: &Q, v: &V) -> Result<(), RemoveError>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+        V: PartialEq,
+    {
+        let h = HashedKey::compute(self.hasher, k);
+        let check = |lv: &V| {
+            if lv == v {
+                Ok(None)
+            } else {
+                Err(RemoveError::ValueNotMatching)
+            }
+        };
+        update_mut(&mut self.root, h, 0, k, check).map_err(|e| match e {
+            UpdateError::KeyNotFound => RemoveError::KeyNotFound,
+            UpdateError::ValueCallbackError(e) => e,
+        })
+    }
+
+    /// Replace the element at the key by the v and return the old value.
+    pub fn replace(&mut self, k: &K, v: V) -> Result<V, ReplaceError> {
+        let mut old = None;
+        self.update::<_, Infallible>(k, |lv| {
+            old = Some(lv.clone());
+            Ok(Some(v))
+        })
+        .map_err(|_| ReplaceError::KeyNotFound)?;
+        Ok(old.expect("replaced value"))
+    }
+
+    /// Replace the element at the key by the result of f applied to the
+    /// current value.
+    pub fn replace_with<F>(&mut self, k: &K, f: F) -> Result<(), ReplaceError>
+    where
+        F: FnOnce(&V) -> V,
+    {
+        self.update::<_, Infallible>(k, |lv| Ok(Some(f(lv))))
+            .map_err(|_| ReplaceError::KeyNotFound)
+    }
+
+    /// Update the element at the key K.
+    ///
+    /// If the closure F in parameter returns None, then the key is deleted.
+    ///
+    /// If the key is not present then UpdateError::KeyNotFound is returned
+    pub fn update<F, U>(&mut self, k: &K, f: F) -> Result<(), UpdateError<U>>
+    where
+        F: FnOnce(&V) -> Result<Option<V>, U>,
+        U: Error + Debug + 'static,
+    {
+        let h = HashedKey::compute(self.hasher, k);
+        update_mut(&mut self.root, h, 0, k, f)
+    }
+
+    /// Update or insert the element at the key K
+    ///
+    /// If the element is not present, then V is added, otherwise the closure F is apply
+    /// to the found element. If the closure returns None, then the key is deleted
+    pub fn insert_or_update<F, E>(&mut self, k: K, v: V, f: F) -> Result<(), E>
+    where
+        F: FnOnce(&V) -> Result<Option<V>, E>,
+        E: Error + Debug + 'static,
+    {
+        match self.update(&k, f) {
+            Ok(()) => Ok(()),
+            // unwrap is safe: the key is not present
+            Err(UpdateError::KeyNotFound) => {
+                self.insert(k, v).unwrap();
+                Ok(())
+            }
+            Err(UpdateError::ValueCallbackError(x)) => Err(x),
+        }
+    }
+
+    /// Update or insert the element at the key K
+    ///
+    /// This is similar to 'insert_or_update' except the closure shouldn't be failing
+    pub fn insert_or_update_simple<F>(&mut self, k: K, v: V, f: F)
+    where
+        F: for<'a> FnOnce(&'a V) -> Option<V>,
+    {
+        match self.insert_or_update(k, v, |x| Ok::<_, Infallible>(f(x))) {
+            Ok(()) => {}
+            Err(_) => unreachable!(), // callback always wrapped in Ok
+        }
+    }
+}